@@ -2,9 +2,12 @@
 in vec2 texCoord;
 
 uniform sampler2D inputTexture;
+uniform float opacity;
+uniform vec4 tint;
 
 out vec4 outputColor;
 
 void main() {
-    outputColor = texture(inputTexture, texCoord).rgba;
+    vec4 color = texture(inputTexture, texCoord).rgba;
+    outputColor = vec4(mix(color.rgb, tint.rgb, tint.a), color.a * opacity);
 }
\ No newline at end of file
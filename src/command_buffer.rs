@@ -1,10 +1,13 @@
 use std::collections::VecDeque;
 use std::path::PathBuf;
 
-use crate::editor::image_operation::ImageOperation;
+use crate::editor::image_operation::{ImageOperation, Symmetry};
+use crate::editor::editor::ImageFormat;
 use crate::editor::tools::Tools;
 use crate::editor::tools::selection::Selection;
 use crate::program::{ProgramAction, ProgramActionData};
+use crate::ui::UiTheme;
+use crate::rendering::prelude::Position;
 
 #[derive(Debug)]
 pub enum BackgroundType {
@@ -12,30 +15,84 @@ pub enum BackgroundType {
     Color(image::Rgba<u8>)
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum FilterKind {
+    Grayscale
+}
+
 #[derive(Debug)]
 pub enum Command {
     SetImageSize(u32, u32),
     NewImage(u32, u32, BackgroundType),
     SwitchImage(PathBuf, image::RgbaImage),
+    RecoverProject(PathBuf),
+    OpenProject(PathBuf),
+    ExportImageGpu(PathBuf, ImageFormat),
     SetTool(Tools),
     SwitchToPrevTool,
     SwitchedTool(Tools),
     SetPrimaryColor(image::Rgba<u8>),
+    SetFillTolerance(f32),
+    SetRecentColors(Vec<image::Rgba<u8>>),
+    CommitPrimaryColor,
+    PickMergedColor { x: u32, y: u32, half_size: i32, secondary: bool },
     SetSecondaryColor(image::Rgba<u8>),
     SetSelection(Option<Selection>),
+    SetSelectionFeather(i32),
     SetClipboard(image::RgbaImage),
     SetCopiedImage(image::RgbaImage),
+    Paste(image::RgbaImage),
+    PlacePaste(image::RgbaImage, i32, i32),
     ApplyImageOp(ImageOperation),
+    PreviewImageOp(ImageOperation),
+    ClearPreviewImageOp,
     UndoImageOp,
     RedoImageOp,
+    JumpToHistory(usize),
+    SaveSnapshot(String),
+    RestoreSnapshot(String),
     NewLayer,
     DuplicateLayer,
+    ImportAsLayer(PathBuf, image::RgbaImage),
     DeleteLayer,
+    MoveLayerUp,
+    MoveLayerDown,
+    SetLayerName(usize, String),
+    MergeDown,
+    LoadPalette(PathBuf),
+    SavePalette(PathBuf),
     SelectAll,
+    TransformSelection { offset_x: i32, offset_y: i32, scale: f32, rotation: f32 },
     ResizeImage(u32, u32),
     ResizeCanvas(u32, u32),
-    RequestResizeCanvas(u32, u32),
+    RotateCanvas(u8),
+    CropToSelection,
+    FlattenImage,
+    ApplyFilter(FilterKind),
+    SetBrushSize(i32),
+    ChangeBrushSize(i32),
+    SetStrokeWidth(i32),
     AbortedResizeCanvas,
+    AbortedText,
+    AddGuide { vertical: bool, position: i32 },
+    ClearGuides,
+    ToggleSnapToGrid,
+    SetGridSize(u32),
+    ToggleRulers,
+    ToggleOnionSkin,
+    ToggleFitToWindowOnOpen,
+    ZoomToFit,
+    SetZoom(f32),
+    ZoomToActualSize,
+    ToggleCompositionOverlay,
+    ToggleTiledPreview,
+    SetSymmetry(Symmetry),
+    SetSymmetryAxis(Position),
+    FlipHorizontal,
+    FlipVertical,
+    SetTheme(UiTheme),
+    SetStampImage(image::RgbaImage),
+    SetText { text: String, size: u32, color: image::Rgba<u8> },
     TriggerProgramAction(ProgramAction, ProgramActionData)
 }
 
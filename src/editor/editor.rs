@@ -1,31 +1,292 @@
 use std::path::{Path, PathBuf};
+use std::cell::{RefCell, Ref};
+use std::collections::HashMap;
+use std::io::{Read, Write};
 use std::fmt::{Display};
 
 use itertools::Itertools;
 
-use image::{GenericImage, FilterType};
+use image::{FilterType, Pixel};
 
 use crate::editor::image_operation::{ImageOperation, ImageOperationMarker, ImageSource};
 use crate::editor::{Image, Region};
+use crate::editor::image_operation_helpers::sub_image;
+use crate::rendering::ShaderAndRender;
+use crate::rendering::texture_render::TextureRender;
+use crate::rendering::framebuffer::FrameBuffer;
+use crate::rendering::helpers::has_current_context;
+use crate::rendering::prelude::Position;
 
 #[derive(Debug, Clone)]
 pub enum ImageFormat {
     Png,
     Jpeg(u8),
     Bmp,
-    Tiff
+    Tiff,
+    Gif,
+    Ico,
+    Project
 }
 
+pub const MAX_ICO_SIZE: u32 = 256;
+pub const PROJECT_EXTENSION: &str = "iep";
+const PROJECT_MAGIC: &[u8; 4] = b"IEPF";
+const PROJECT_VERSION: u32 = 1;
+pub const DEFAULT_MAX_HISTORY_LENGTH: usize = 100;
+
 impl ImageFormat {
     pub fn from_extension(extension: &str) -> Option<ImageFormat> {
-        match extension {
+        match extension.to_lowercase().as_str() {
             "png" => Some(ImageFormat::Png),
             "jpg" | "jpeg" => Some(ImageFormat::Jpeg(80)),
             "bmp" => Some(ImageFormat::Bmp),
             "tif" | "tiff" => Some(ImageFormat::Tiff),
+            "gif" => Some(ImageFormat::Gif),
+            "ico" => Some(ImageFormat::Ico),
+            PROJECT_EXTENSION => Some(ImageFormat::Project),
             _ => None
         }
     }
+
+    pub fn has_alpha(&self) -> bool {
+        match self {
+            ImageFormat::Png | ImageFormat::Tiff | ImageFormat::Gif | ImageFormat::Ico | ImageFormat::Project => true,
+            ImageFormat::Jpeg(_) | ImageFormat::Bmp => false
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveWarning {
+    IcoDownscaled { width: u32, height: u32, new_width: u32, new_height: u32 }
+}
+
+impl std::fmt::Display for SaveWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveWarning::IcoDownscaled { width, height, new_width, new_height } => {
+                write!(
+                    f,
+                    "ICO images can be at most {}x{}, the image was downscaled from {}x{} to {}x{}.",
+                    MAX_ICO_SIZE, MAX_ICO_SIZE,
+                    width, height,
+                    new_width, new_height
+                )
+            }
+        }
+    }
+}
+
+pub struct ProjectContent {
+    pub width: u32,
+    pub height: u32,
+    pub matte_color: image::Rgba<u8>,
+    // The name, visibility, opacity and image of each layer
+    pub layers: Vec<(String, bool, f32, image::RgbaImage)>
+}
+
+// Layout: magic, version, width, height, matte color, number of layers and then for each layer
+// its name, state, opacity and raw RGBA pixels. Numbers are stored as little endian.
+pub fn write_project(writer: &mut impl Write,
+                     width: u32,
+                     height: u32,
+                     matte_color: image::Rgba<u8>,
+                     layers: &[(&str, bool, f32, &image::RgbaImage)]) -> std::io::Result<()> {
+    writer.write_all(PROJECT_MAGIC)?;
+    writer.write_all(&PROJECT_VERSION.to_le_bytes())?;
+    writer.write_all(&width.to_le_bytes())?;
+    writer.write_all(&height.to_le_bytes())?;
+    writer.write_all(&matte_color.0)?;
+    writer.write_all(&(layers.len() as u32).to_le_bytes())?;
+
+    for (name, visible, opacity, image) in layers {
+        writer.write_all(&(name.len() as u32).to_le_bytes())?;
+        writer.write_all(name.as_bytes())?;
+        writer.write_all(&[if *visible { 0 } else { 1 }])?;
+        writer.write_all(&opacity.to_le_bytes())?;
+        writer.write_all(image.as_ref())?;
+    }
+
+    Ok(())
+}
+
+pub fn read_project(reader: &mut impl Read, length: u64) -> std::io::Result<ProjectContent> {
+    let invalid_data = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_owned());
+
+    // The sizes in the header are checked against what is left of the file before anything is allocated
+    let mut reader = reader.take(length);
+
+    fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+        let mut buffer = [0; 4];
+        reader.read_exact(&mut buffer)?;
+        Ok(u32::from_le_bytes(buffer))
+    }
+
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != PROJECT_MAGIC {
+        return Err(invalid_data("Not a project file"));
+    }
+
+    let version = read_u32(&mut reader)?;
+    if version != PROJECT_VERSION {
+        return Err(invalid_data(&format!("Unsupported project version: {}", version)));
+    }
+
+    let width = read_u32(&mut reader)?;
+    let height = read_u32(&mut reader)?;
+
+    let mut matte_color = [0; 4];
+    reader.read_exact(&mut matte_color)?;
+
+    let num_layers = read_u32(&mut reader)?;
+    if num_layers == 0 {
+        return Err(invalid_data("Project without layers"));
+    }
+
+    let layer_size = (width as u64)
+        .checked_mul(height as u64)
+        .and_then(|size| size.checked_mul(4))
+        .ok_or_else(|| invalid_data("Invalid layer size"))?;
+
+    let mut layers = Vec::new();
+    for _ in 0..num_layers {
+        let name_length = read_u32(&mut reader)? as u64;
+        if name_length > reader.limit() {
+            return Err(invalid_data("Truncated project file"));
+        }
+
+        let mut name = vec![0; name_length as usize];
+        reader.read_exact(&mut name)?;
+        let name = String::from_utf8(name).map_err(|_| invalid_data("Invalid layer name"))?;
+
+        let mut state = [0; 1];
+        reader.read_exact(&mut state)?;
+
+        let opacity = f32::from_bits(read_u32(&mut reader)?);
+
+        if layer_size > reader.limit() {
+            return Err(invalid_data("Truncated project file"));
+        }
+
+        let mut pixels = vec![0; layer_size as usize];
+        reader.read_exact(&mut pixels)?;
+        let image = image::RgbaImage::from_raw(width, height, pixels).ok_or_else(|| invalid_data("Invalid layer size"))?;
+
+        layers.push((name, state[0] == 0, opacity, image));
+    }
+
+    Ok(
+        ProjectContent {
+            width,
+            height,
+            matte_color: image::Rgba(matte_color),
+            layers
+        }
+    )
+}
+
+pub fn flatten_on_matte(image: &image::RgbaImage, matte_color: image::Rgba<u8>) -> image::RgbaImage {
+    image::RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let pixel = image.get_pixel(x, y);
+        let alpha = pixel[3] as f32 / 255.0;
+
+        let mut flattened = image::Rgba([0, 0, 0, 255]);
+        for channel in 0..3 {
+            flattened[channel] = (pixel[channel] as f32 * alpha + matte_color[channel] as f32 * (1.0 - alpha)).round() as u8;
+        }
+
+        flattened
+    })
+}
+
+pub fn encode_image(image: &image::RgbaImage, path: &Path, format: &ImageFormat, matte_color: image::Rgba<u8>) -> std::io::Result<Option<SaveWarning>> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+    let flattened_image;
+    let image = if format.has_alpha() {
+        image
+    } else {
+        flattened_image = flatten_on_matte(image, matte_color);
+        &flattened_image
+    };
+
+    let mut warning = None;
+    match format {
+        ImageFormat::Png => {
+            let encoder = image::png::PNGEncoder::new(writer);
+            encoder.encode(
+                image,
+                image.width(),
+                image.height(),
+                image::ColorType::RGBA(8)
+            )?;
+        }
+        ImageFormat::Jpeg(quality) => {
+            let mut encoder = image::jpeg::JPEGEncoder::new_with_quality(&mut writer, *quality);
+            encoder.encode(
+                image,
+                image.width(),
+                image.height(),
+                image::ColorType::RGBA(8)
+            )?;
+        }
+        ImageFormat::Bmp => {
+            let mut encoder = image::bmp::BMPEncoder::new(&mut writer);
+            encoder.encode(
+                image,
+                image.width(),
+                image.height(),
+                image::ColorType::RGBA(8)
+            )?;
+        }
+        ImageFormat::Tiff => {
+            let encoder = image::tiff::TiffEncoder::new(&mut writer);
+            encoder.encode(
+                image,
+                image.width(),
+                image.height(),
+                image::ColorType::RGBA(8)
+            ).map_err(|_| std::io::Error::new(std::io::ErrorKind::Other, "decode error"))?;
+        }
+        ImageFormat::Gif => {
+            let mut encoder = image::gif::Encoder::new(&mut writer);
+            encoder.encode(&image::Frame::new(image.clone()))
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", err)))?;
+        }
+        ImageFormat::Ico => {
+            let resized_image;
+            let image = if image.width() > MAX_ICO_SIZE || image.height() > MAX_ICO_SIZE {
+                let scale = MAX_ICO_SIZE as f32 / image.width().max(image.height()) as f32;
+                let new_width = ((image.width() as f32 * scale).round() as u32).max(1);
+                let new_height = ((image.height() as f32 * scale).round() as u32).max(1);
+                warning = Some(SaveWarning::IcoDownscaled {
+                    width: image.width(),
+                    height: image.height(),
+                    new_width,
+                    new_height
+                });
+
+                resized_image = image::imageops::resize(image, new_width, new_height, FilterType::Triangle);
+                &resized_image
+            } else {
+                image
+            };
+
+            let encoder = image::ico::ICOEncoder::new(&mut writer);
+            encoder.encode(
+                image,
+                image.width(),
+                image.height(),
+                image::ColorType::RGBA(8)
+            )?;
+        }
+        ImageFormat::Project => {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Project files store layers and cannot be created from a single image"));
+        }
+    }
+
+    Ok(warning)
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -35,28 +296,130 @@ pub enum LayerState {
     Deleted
 }
 
+#[derive(Clone, Debug)]
+pub struct Layer {
+    pub name: String,
+    pub state: LayerState,
+    pub image: Image,
+    pub opacity: f32
+}
+
+impl Layer {
+    pub fn new(name: String, image: Image) -> Layer {
+        Layer {
+            name,
+            state: LayerState::Visible,
+            image,
+            opacity: 1.0
+        }
+    }
+
+    pub fn blend_onto(&self, image: &mut image::RgbaImage) {
+        let layer = self.image.get_image();
+
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            blend_layer_pixel(pixel, *layer.get_pixel(x, y), self.opacity);
+        }
+    }
+
+    pub fn blend_row_onto(&self, y: u32, row: &mut [image::Rgba<u8>]) {
+        blend_row(self.image.get_image(), self.opacity, y, row);
+    }
+}
+
+// Blends a pixel of a layer with the opacity of the layer onto the composite below it.
+// All composites use this, such that single pixels, rows and whole images agree.
+fn blend_layer_pixel(below: &mut image::Rgba<u8>, mut pixel: image::Rgba<u8>, opacity: f32) {
+    pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    below.blend(&pixel);
+}
+
+fn blend_row(layer: &image::RgbaImage, opacity: f32, y: u32, row: &mut [image::Rgba<u8>]) {
+    for (x, row_pixel) in row.iter_mut().enumerate() {
+        blend_layer_pixel(row_pixel, *layer.get_pixel(x as u32, y), opacity);
+    }
+}
+
+// Blends the images in order, each with its opacity, onto a transparent image
+fn composite_layer_images(width: u32, height: u32, layers: &[(&image::RgbaImage, f32)]) -> image::RgbaImage {
+    let mut image = image::RgbaImage::new(width, height);
+    for (layer, opacity) in layers {
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            blend_layer_pixel(pixel, *layer.get_pixel(x, y), *opacity);
+        }
+    }
+
+    image
+}
+
+// Writes the PNG one row at a time, so that the full image is never in memory
+fn write_png_streamed<W: Write, F: FnMut(u32, &mut [image::Rgba<u8>])>(writer: W,
+                                                                        width: u32,
+                                                                        height: u32,
+                                                                        mut compute_row: F) -> std::io::Result<()> {
+    let to_io_error = |err: png::EncodingError| std::io::Error::new(std::io::ErrorKind::Other, err);
+
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut stream_writer = encoder
+        .write_header()
+        .and_then(|writer| writer.into_stream_writer())
+        .map_err(to_io_error)?;
+
+    let mut row = vec![image::Rgba([0, 0, 0, 0]); width as usize];
+    let mut row_bytes = Vec::with_capacity(width as usize * 4);
+    for y in 0..height {
+        for pixel in row.iter_mut() {
+            *pixel = image::Rgba([0, 0, 0, 0]);
+        }
+
+        compute_row(y, &mut row);
+
+        row_bytes.clear();
+        for pixel in &row {
+            row_bytes.extend_from_slice(&pixel.0);
+        }
+
+        stream_writer.write_all(&row_bytes)?;
+    }
+
+    // Finishing explicitly writes the end of the image, where errors would otherwise be lost when dropped
+    stream_writer.finish().map_err(to_io_error)
+}
+
+fn image_format_of_path(path: Option<&PathBuf>) -> Option<ImageFormat> {
+    path
+        .map(|path| path.extension()).flatten()
+        .map(|extension| extension.to_str()).flatten()
+        .map(|extension| ImageFormat::from_extension(extension)).flatten()
+}
+
 #[derive(Clone, Debug)]
 pub struct EditorImage {
     path: Option<PathBuf>,
     image_format: Option<ImageFormat>,
+    matte_color: image::Rgba<u8>,
     width: u32,
     height: u32,
-    layers: Vec<(LayerState, Image)>
+    layers: Vec<Layer>,
+    // The flattened layers, computed on demand and invalidated on any mutable access to the layers
+    composite: RefCell<Option<image::RgbaImage>>
 }
 
 impl EditorImage {
     pub fn new(path: Option<PathBuf>, image: Image) -> EditorImage {
-        let image_format = path
-            .as_ref().map(|path| path.extension()).flatten()
-            .map(|extension| extension.to_str()).flatten()
-            .map(|extension| ImageFormat::from_extension(extension)).flatten();
+        let image_format = image_format_of_path(path.as_ref());
 
         EditorImage {
             path,
             image_format,
+            matte_color: image::Rgba([255, 255, 255, 255]),
             width: image.width(),
             height: image.height(),
-            layers: vec![(LayerState::Visible, image)]
+            layers: vec![Layer::new("Layer 1".to_owned(), image)],
+            composite: RefCell::new(None)
         }
     }
 
@@ -68,10 +431,23 @@ impl EditorImage {
         self.path.as_ref().map(|path| path.as_path())
     }
 
+    pub fn set_path(&mut self, path: Option<PathBuf>) {
+        self.image_format = image_format_of_path(path.as_ref());
+        self.path = path;
+    }
+
     pub fn image_format(&self) -> Option<&ImageFormat> {
         self.image_format.as_ref()
     }
 
+    pub fn matte_color(&self) -> image::Rgba<u8> {
+        self.matte_color
+    }
+
+    pub fn set_matte_color(&mut self, matte_color: image::Rgba<u8>) {
+        self.matte_color = matte_color;
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
@@ -80,102 +456,156 @@ impl EditorImage {
         self.height
     }
 
-    pub fn layers(&self) -> &Vec<(LayerState, Image)> {
+    pub fn layers(&self) -> &Vec<Layer> {
         &self.layers
     }
 
-    pub fn layers_mut(&mut self) -> &mut Vec<(LayerState, Image)> {
+    pub fn layers_mut(&mut self) -> &mut Vec<Layer> {
+        self.invalidate_composite();
         &mut self.layers
     }
 
     pub fn get_layer(&self, layer: usize) -> Option<&Image> {
-        self.layers.get(layer).map(|(_, layer)| layer)
+        self.layers.get(layer).map(|layer| &layer.image)
     }
 
     pub fn get_layer_mut(&mut self, layer: usize) -> Option<&mut Image> {
-        self.layers.get_mut(layer).map(|(_, layer)| layer)
+        self.invalidate_composite();
+        self.layers.get_mut(layer).map(|layer| &mut layer.image)
+    }
+
+    fn invalidate_composite(&mut self) {
+        *self.composite.get_mut() = None;
+    }
+
+    fn next_layer_name(&self) -> String {
+        format!("Layer {}", self.layers.len() + 1)
     }
 
     pub fn add_layer(&mut self) {
-        self.layers.push((LayerState::Visible, Image::new(image::RgbaImage::new(self.width(), self.height()))));
+        self.invalidate_composite();
+        self.layers.push(Layer::new(self.next_layer_name(), Image::new(image::RgbaImage::new(self.width(), self.height()))));
     }
 
     pub fn add_layer_with_image(&mut self, image: image::RgbaImage) {
         assert_eq!(self.width, image.width());
         assert_eq!(self.height, image.height());
-        self.layers.push((LayerState::Visible, Image::new(image)));
+        self.invalidate_composite();
+        self.layers.push(Layer::new(self.next_layer_name(), Image::new(image)));
     }
 
-    pub fn save(&self, path: &Path, format: &ImageFormat) -> std::io::Result<()> {
-        let mut image: image::RgbaImage = image::RgbaImage::new(self.width(), self.height());
-        for (state, layer) in &self.layers {
-            if state == &LayerState::Visible {
-                let layer = layer.get_image();
-
-                for y in 0..image.height() {
-                    for x in 0..image.width() {
-                        image.blend_pixel(x, y, *layer.get_pixel(x, y));
-                    }
+    pub fn composited_image(&self) -> Ref<image::RgbaImage> {
+        if self.composite.borrow().is_none() {
+            let mut image: image::RgbaImage = image::RgbaImage::new(self.width(), self.height());
+            for layer in &self.layers {
+                if layer.state == LayerState::Visible {
+                    layer.blend_onto(&mut image);
                 }
             }
+
+            *self.composite.borrow_mut() = Some(image);
         }
 
-        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        Ref::map(self.composite.borrow(), |composite| composite.as_ref().unwrap())
+    }
+
+    pub fn composited_pixel(&self, x: u32, y: u32) -> Option<image::Rgba<u8>> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        // The composite is not computed here, as that would happen on every change while drawing
+        if let Some(image) = self.composite.borrow().as_ref() {
+            return Some(*image.get_pixel(x, y));
+        }
 
+        let mut pixel = image::Rgba([0, 0, 0, 0]);
+        for layer in &self.layers {
+            if layer.state == LayerState::Visible {
+                blend_layer_pixel(&mut pixel, layer.image.get_pixel(x, y), layer.opacity);
+            }
+        }
+
+        Some(pixel)
+    }
+
+    pub fn save(&self, path: &Path, format: &ImageFormat) -> std::io::Result<Option<SaveWarning>> {
         match format {
-            ImageFormat::Png => {
-                let encoder = image::png::PNGEncoder::new(writer);
-                encoder.encode(
-                    &image,
-                    image.width(),
-                    image.height(),
-                    image::ColorType::RGBA(8)
-                )?;
-            }
-            ImageFormat::Jpeg(quality) => {
-                let mut encoder = image::jpeg::JPEGEncoder::new_with_quality(&mut writer, *quality);
-                encoder.encode(
-                    &image,
-                    image.width(),
-                    image.height(),
-                    image::ColorType::RGBA(8)
-                )?;
-            }
-            ImageFormat::Bmp => {
-                let mut encoder = image::bmp::BMPEncoder::new(&mut writer);
-                encoder.encode(
-                    &image,
-                    image.width(),
-                    image.height(),
-                    image::ColorType::RGBA(8)
-                )?;
-            }
-            ImageFormat::Tiff => {
-                let encoder = image::tiff::TiffEncoder::new(&mut writer);
-                encoder.encode(
-                    &image,
-                    image.width(),
-                    image.height(),
-                    image::ColorType::RGBA(8)
-                ).map_err(|_| std::io::Error::new(std::io::ErrorKind::Other, "decode error"))?;
-            }
-        }
-
-        Ok(())
-    }
-
-    pub fn save_as(&mut self, path: &Path, format: &ImageFormat) -> std::io::Result<()> {
-        self.save(path, format)?;
+            ImageFormat::Project => self.save_project(path).map(|_| None),
+            // Unless the composite is already computed, large images are cheaper to composite one row at a time
+            ImageFormat::Png if self.composite.borrow().is_none() => self.save_png_streamed(path).map(|_| None),
+            format => encode_image(&self.composited_image(), path, format, self.matte_color)
+        }
+    }
+
+    fn save_png_streamed(&self, path: &Path) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+        write_png_streamed(&mut writer, self.width, self.height, |y, row| {
+            for layer in &self.layers {
+                if layer.state == LayerState::Visible {
+                    layer.blend_row_onto(y, row);
+                }
+            }
+        })?;
+
+        writer.flush()
+    }
+
+    pub fn save_project(&self, path: &Path) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+        let layers = self.layers
+            .iter()
+            .filter(|layer| layer.state != LayerState::Deleted)
+            .map(|layer| (layer.name.as_str(), layer.state == LayerState::Visible, layer.opacity, layer.image.get_image()))
+            .collect::<Vec<_>>();
+
+        write_project(&mut writer, self.width, self.height, self.matte_color, &layers)?;
+        writer.flush()
+    }
+
+    pub fn load_project(path: &Path) -> std::io::Result<EditorImage> {
+        let file = std::fs::File::open(path)?;
+        let file_length = file.metadata()?.len();
+        let project = read_project(&mut std::io::BufReader::new(file), file_length)?;
+
+        let layers = project.layers
+            .into_iter()
+            .map(|(name, visible, opacity, image)| {
+                let mut layer = Layer::new(name, Image::new(image));
+                layer.state = if visible { LayerState::Visible } else { LayerState::Hidden };
+                layer.opacity = opacity.max(0.0).min(1.0);
+                layer
+            })
+            .collect();
+
+        Ok(
+            EditorImage {
+                path: Some(path.to_path_buf()),
+                image_format: Some(ImageFormat::Project),
+                matte_color: project.matte_color,
+                width: project.width,
+                height: project.height,
+                layers,
+                composite: RefCell::new(None)
+            }
+        )
+    }
+
+    pub fn save_as(&mut self, path: &Path, format: &ImageFormat) -> std::io::Result<Option<SaveWarning>> {
+        let warning = self.save(path, format)?;
         self.path = Some(path.to_path_buf());
         self.image_format = Some(format.clone());
-        Ok(())
+        Ok(warning)
     }
 
     pub fn resize(&mut self, new_width: u32, new_height: u32) {
+        self.invalidate_composite();
         self.width = new_width;
         self.height = new_height;
 
-        for (_, layer) in &mut self.layers {
+        for Layer { image: layer, .. } in &mut self.layers {
             let resized_image = image::imageops::resize(
                 layer.get_image(),
                 new_width,
@@ -188,10 +618,11 @@ impl EditorImage {
     }
 
     pub fn resize_canvas(&mut self, new_width: u32, new_height: u32) {
+        self.invalidate_composite();
         self.width = new_width;
         self.height = new_height;
 
-        for (_, layer) in &mut self.layers {
+        for Layer { image: layer, .. } in &mut self.layers {
             let mut resized_image: image::RgbaImage = image::RgbaImage::new(new_width, new_height);
             for y in 0..layer.height().min(new_height) {
                 for x in 0..layer.width().min(new_width) {
@@ -202,12 +633,76 @@ impl EditorImage {
             *layer = Image::new(resized_image);
         }
     }
+
+    pub fn rotate_canvas(&mut self, quarter_turns: u8) {
+        self.invalidate_composite();
+        let quarter_turns = quarter_turns % 4;
+        if quarter_turns % 2 == 1 {
+            std::mem::swap(&mut self.width, &mut self.height);
+        }
+
+        for Layer { image: layer, .. } in &mut self.layers {
+            let rotated_image = match quarter_turns {
+                1 => image::imageops::rotate90(layer.get_image()),
+                2 => image::imageops::rotate180(layer.get_image()),
+                3 => image::imageops::rotate270(layer.get_image()),
+                _ => continue
+            };
+
+            *layer = Image::new(rotated_image);
+        }
+    }
+
+    pub fn crop(&mut self, region: &Region) {
+        let min_x = region.left().min(region.right()).max(0);
+        let min_y = region.top().min(region.bottom()).max(0);
+        let max_x = region.left().max(region.right()).min(self.width as i32);
+        let max_y = region.top().max(region.bottom()).min(self.height as i32);
+        if max_x <= min_x || max_y <= min_y {
+            return;
+        }
+
+        self.invalidate_composite();
+        self.width = (max_x - min_x) as u32;
+        self.height = (max_y - min_y) as u32;
+
+        for Layer { image: layer, .. } in &mut self.layers {
+            let cropped_image = sub_image(&*layer, min_x, min_y, max_x, max_y);
+            *layer = Image::new(cropped_image);
+        }
+    }
+
+    pub fn flatten(&mut self) {
+        // The composite becomes the only layer, so it is moved out of the cache instead of copied
+        self.composited_image();
+        let image = self.composite.get_mut().take().unwrap();
+        self.layers = vec![Layer::new("Layer 1".to_owned(), Image::new(image))];
+    }
+}
+
+impl ImageSource for EditorImage {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> image::Rgba<u8> {
+        self.composited_pixel(x, y).unwrap_or(image::Rgba([0, 0, 0, 0]))
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum EditorOperation {
     Sequential(Vec<EditorOperation>),
     SetLayerState(usize, LayerState),
+    SetLayerOpacity(usize, f32),
+    ReorderLayers(usize, usize),
+    SetLayerName(usize, String),
+    SetLayerImage(usize, image::RgbaImage),
+    MergeDown(usize),
     SetActiveLayer(usize),
     SetImage(EditorImage),
     ImageOp(usize, ImageOperation)
@@ -234,6 +729,11 @@ impl Display for EditorOperation {
         match self {
             EditorOperation::Sequential(ops) => write!(f, "{}", ops.iter().map(|op| format!("{}", op)).join(", ")),
             EditorOperation::SetLayerState(_, _) => write!(f, "Set layer state"),
+            EditorOperation::SetLayerOpacity(_, _) => write!(f, "Set layer opacity"),
+            EditorOperation::ReorderLayers(_, _) => write!(f, "Reorder layers"),
+            EditorOperation::SetLayerName(_, _) => write!(f, "Rename layer"),
+            EditorOperation::SetLayerImage(_, _) => write!(f, "Set layer image"),
+            EditorOperation::MergeDown(_) => write!(f, "Merge down"),
             EditorOperation::SetActiveLayer(_) => write!(f, "Set active layer"),
             EditorOperation::SetImage(image) => {
                 match &image.path {
@@ -246,12 +746,30 @@ impl Display for EditorOperation {
     }
 }
 
+// The given layer index if that layer is alive, otherwise the first alive layer
+fn alive_layer_index(layer_states: &[LayerState], layer_index: usize) -> usize {
+    match layer_states.get(layer_index) {
+        Some(state) if state != &LayerState::Deleted => layer_index,
+        _ => layer_states.iter().position(|state| state != &LayerState::Deleted).unwrap_or(0)
+    }
+}
+
+fn merge_history_entries(entries: Vec<(EditorOperation, EditorOperation)>) -> (EditorOperation, EditorOperation) {
+    let (ops, mut undo_ops): (Vec<EditorOperation>, Vec<EditorOperation>) = entries.into_iter().unzip();
+    undo_ops.reverse();
+    (EditorOperation::Sequential(ops), EditorOperation::Sequential(undo_ops))
+}
+
 pub struct Editor {
     image: EditorImage,
     active_layer_index: usize,
     undo_stack: Vec<(EditorOperation, EditorOperation)>,
     redo_stack: Vec<EditorOperation>,
-    valid_region: Option<Region>
+    valid_region: Option<Region>,
+    preview_undo_op: Option<(usize, ImageOperation)>,
+    max_history_length: usize,
+    snapshots: HashMap<String, EditorImage>,
+    revision: u64
 }
 
 impl Editor {
@@ -261,7 +779,11 @@ impl Editor {
             active_layer_index: 0,
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
-            valid_region: None
+            valid_region: None,
+            preview_undo_op: None,
+            max_history_length: DEFAULT_MAX_HISTORY_LENGTH,
+            snapshots: HashMap::new(),
+            revision: 0
         }
     }
 
@@ -277,6 +799,22 @@ impl Editor {
         self.valid_region = region;
     }
 
+    pub fn valid_region(&self) -> Option<&Region> {
+        self.valid_region.as_ref()
+    }
+
+    // Changes whenever an operation is applied, undone or redone, unlike the length of the history
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    // Each history entry can hold a copy of a whole layer (or image), so a longer history uses more memory.
+    // Entries beyond the limit are dropped oldest first and can no longer be undone.
+    pub fn set_max_history_length(&mut self, max_history_length: usize) {
+        self.max_history_length = max_history_length.max(1);
+        self.truncate_history();
+    }
+
     pub fn new_image_same(&self) -> Image {
         Image::new(image::RgbaImage::new(self.image.width(), self.image.height()))
     }
@@ -286,8 +824,30 @@ impl Editor {
     }
 
     pub fn apply_editor_op(&mut self, op: EditorOperation) {
+        self.clear_preview_image_op();
         self.internal_apply_op(op);
         self.redo_stack.clear();
+        self.truncate_history();
+        self.revision += 1;
+    }
+
+    pub fn preview_image_op(&mut self, op: ImageOperation) {
+        self.clear_preview_image_op();
+
+        let layer_index = self.active_layer_index;
+        let mut update_op = self.image.get_layer_mut(layer_index).unwrap().update_operation_with_region(self.valid_region.clone());
+        if let Some(undo_op) = op.apply(&mut update_op, true) {
+            self.preview_undo_op = Some((layer_index, undo_op));
+        }
+    }
+
+    pub fn clear_preview_image_op(&mut self) {
+        if let Some((layer_index, undo_op)) = self.preview_undo_op.take() {
+            if let Some(layer) = self.image.get_layer_mut(layer_index) {
+                let mut update_op = layer.update_operation_with_region(self.valid_region.clone());
+                undo_op.apply(&mut update_op, false);
+            }
+        }
     }
 
     pub fn undo_op(&mut self) {
@@ -304,19 +864,145 @@ impl Editor {
                     self.redo_stack.push(orig_op);
                 }
             }
+
+            self.active_layer_index = alive_layer_index(&self.layer_states(), self.active_layer_index);
+            self.revision += 1;
         }
     }
 
     pub fn redo_op(&mut self) {
         if let Some(op) = self.redo_stack.pop() {
             self.internal_apply_op(op);
+            self.active_layer_index = alive_layer_index(&self.layer_states(), self.active_layer_index);
+            self.revision += 1;
+        }
+    }
+
+    fn layer_states(&self) -> Vec<LayerState> {
+        self.image.layers().iter().map(|layer| layer.state.clone()).collect()
+    }
+
+    pub fn goto_history(&mut self, index: usize) {
+        self.clear_preview_image_op();
+
+        let target_length = index + 1;
+        while self.undo_stack.len() > target_length {
+            self.undo_op();
+        }
+
+        while self.undo_stack.len() < target_length && !self.redo_stack.is_empty() {
+            self.redo_op();
         }
     }
 
+    pub fn save_snapshot(&mut self, name: String) {
+        self.snapshots.insert(name, self.image.clone());
+    }
+
+    pub fn restore_snapshot(&mut self, name: &str) -> bool {
+        match self.snapshots.get(name) {
+            Some(snapshot) => {
+                let snapshot = snapshot.clone();
+                self.apply_editor_op(EditorOperation::SetImage(snapshot));
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn snapshot_names(&self) -> Vec<String> {
+        self.snapshots.keys().cloned().sorted().collect()
+    }
+
+    pub fn export_gpu(&self,
+                      texture_render: &ShaderAndRender<TextureRender>,
+                      path: &Path,
+                      format: &ImageFormat) -> std::io::Result<Option<SaveWarning>> {
+        if !has_current_context() {
+            return Err(std::io::Error::new(std::io::ErrorKind::Other, "GPU export requires a current OpenGL context"));
+        }
+
+        let width = self.image.width();
+        let height = self.image.height();
+        let frame_buffer = FrameBuffer::new(width, height, 4);
+
+        let transform = cgmath::ortho(
+            0.0,
+            width as f32,
+            height as f32,
+            0.0,
+            0.0,
+            1.0
+        );
+
+        let mut image = {
+            let mut viewport = [0; 4];
+            let mut blend_func = [0; 4];
+            let mut clear_color = [0.0; 4];
+            unsafe {
+                gl::GetIntegerv(gl::VIEWPORT, &mut viewport[0]);
+                gl::GetIntegerv(gl::BLEND_SRC_RGB, &mut blend_func[0]);
+                gl::GetIntegerv(gl::BLEND_DST_RGB, &mut blend_func[1]);
+                gl::GetIntegerv(gl::BLEND_SRC_ALPHA, &mut blend_func[2]);
+                gl::GetIntegerv(gl::BLEND_DST_ALPHA, &mut blend_func[3]);
+                gl::GetFloatv(gl::COLOR_CLEAR_VALUE, &mut clear_color[0]);
+            }
+
+            let binding = frame_buffer.bind();
+
+            unsafe {
+                gl::Viewport(0, 0, width as i32, height as i32);
+                gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+                gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+            }
+
+            for layer in self.image.layers() {
+                if layer.state == LayerState::Visible {
+                    texture_render.render_sub_with_opacity(
+                        texture_render.shader(),
+                        &transform,
+                        layer.image.get_texture(),
+                        Position::new(0.0, 0.0),
+                        1.0,
+                        None,
+                        layer.opacity
+                    );
+                }
+            }
+
+            let image = binding.read_to_image();
+
+            unsafe {
+                gl::BlendFuncSeparate(blend_func[0] as u32, blend_func[1] as u32, blend_func[2] as u32, blend_func[3] as u32);
+                gl::ClearColor(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+                gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            }
+
+            image
+        };
+
+        // The color channels are premultiplied with alpha after blending onto the transparent framebuffer
+        for pixel in image.pixels_mut() {
+            let alpha = pixel[3] as f32 / 255.0;
+            if alpha > 0.0 {
+                for i in 0..3 {
+                    pixel[i] = (pixel[i] as f32 / alpha).round().min(255.0) as u8;
+                }
+            }
+        }
+
+        encode_image(&image, path, format, self.image().matte_color())
+    }
+
     pub fn history(&self) -> impl Iterator<Item=&EditorOperation> {
         self.undo_stack.iter().map(|(op, _)| op)
     }
 
+    pub fn redo_history(&self) -> impl Iterator<Item=&EditorOperation> {
+        self.redo_stack.iter().rev()
+    }
+
     pub fn active_layer(&self) -> &Image {
         self.image.get_layer(self.active_layer_index).unwrap()
     }
@@ -326,7 +1012,7 @@ impl Editor {
     }
 
     pub fn num_alive_layers(&self) -> usize {
-        self.image.layers.iter().map(|(state, _)| state != &LayerState::Deleted).count()
+        self.image.layers.iter().filter(|layer| layer.state != LayerState::Deleted).count()
     }
 
     pub fn add_layer(&mut self) {
@@ -335,13 +1021,28 @@ impl Editor {
 
     pub fn duplicate_active_layer(&mut self) {
         if let Some(layer) = self.image.layers.get(self.active_layer_index) {
-            if layer.0 == LayerState::Visible {
-                let layer_image = layer.1.get_image().clone();
+            if layer.state == LayerState::Visible {
+                let layer_image = layer.image.get_image().clone();
                 self.image_mut().add_layer_with_image(layer_image);
             }
         }
     }
 
+    pub fn import_layer(&mut self, name: Option<String>, image: &image::RgbaImage) {
+        // The image is placed in the top-left corner, cropped if it is larger than the canvas
+        let mut layer_image = image::RgbaImage::new(self.image.width(), self.image.height());
+        for y in 0..image.height().min(layer_image.height()) {
+            for x in 0..image.width().min(layer_image.width()) {
+                layer_image.put_pixel(x, y, *image.get_pixel(x, y));
+            }
+        }
+
+        self.image_mut().add_layer_with_image(layer_image);
+        if let (Some(name), Some(layer)) = (name, self.image.layers_mut().last_mut()) {
+            layer.name = name;
+        }
+    }
+
     pub fn delete_active_layer(&mut self) {
         if self.num_alive_layers() > 1 {
             self.apply_editor_op(
@@ -353,6 +1054,60 @@ impl Editor {
         }
     }
 
+    pub fn move_layer_up(&mut self, layer_index: usize) {
+        let other_layer_index = self.image.layers()
+            .iter()
+            .enumerate()
+            .skip(layer_index + 1)
+            .find(|(_, layer)| layer.state != LayerState::Deleted)
+            .map(|(index, _)| index);
+
+        if let Some(other_layer_index) = other_layer_index {
+            self.apply_editor_op(EditorOperation::ReorderLayers(layer_index, other_layer_index));
+        }
+    }
+
+    pub fn move_layer_down(&mut self, layer_index: usize) {
+        if let Some(other_layer_index) = self.layer_below(layer_index) {
+            self.apply_editor_op(EditorOperation::ReorderLayers(layer_index, other_layer_index));
+        }
+    }
+
+    fn layer_below(&self, layer_index: usize) -> Option<usize> {
+        self.image.layers()
+            .iter()
+            .enumerate()
+            .take(layer_index)
+            .rev()
+            .find(|(_, layer)| layer.state != LayerState::Deleted)
+            .map(|(index, _)| index)
+    }
+
+    pub fn merge_down_active_layer(&mut self) {
+        let layer_index = self.active_layer_index;
+        if self.image.layers()[layer_index].state == LayerState::Visible && self.layer_below(layer_index).is_some() {
+            self.apply_editor_op(EditorOperation::MergeDown(layer_index));
+        }
+    }
+
+    fn truncate_history(&mut self) {
+        // The operations of an unfinished draw are merged when it ends, so they are never dropped
+        let open_draw_index = self.undo_stack
+            .iter()
+            .position(|(op, _)| {
+                match op {
+                    EditorOperation::ImageOp(_, op) => op.is_marker(ImageOperationMarker::BeginDraw),
+                    _ => false
+                }
+            })
+            .unwrap_or(self.undo_stack.len());
+
+        let num_excess = self.undo_stack.len().saturating_sub(self.max_history_length).min(open_draw_index);
+        if num_excess > 0 {
+            self.undo_stack.drain(0..num_excess);
+        }
+    }
+
     fn merge_draw_operations(&mut self) {
         for i in (0..self.undo_stack.len()).rev() {
             match &self.undo_stack[i].0 {
@@ -409,22 +1164,25 @@ impl Editor {
     fn internal_apply_other_op(&mut self, op: EditorOperation, push_undo: bool) {
         match op {
             EditorOperation::Sequential(ops) => {
+                let undo_stack_length = self.undo_stack.len();
                 for op in ops {
                     self.internal_apply_other_op(op, push_undo);
                 }
+
+                // The sequence is a single history entry, otherwise a redone sequence would add one entry per operation
+                if push_undo && self.undo_stack.len() > undo_stack_length {
+                    let entries = self.undo_stack.drain(undo_stack_length..).collect();
+                    self.undo_stack.push(merge_history_entries(entries));
+                }
             }
             EditorOperation::SetLayerState(index, state) => {
-                let current_state = self.image.layers_mut()[index].0.clone();
-                self.image.layers_mut()[index].0 = state.clone();
-
-                let change_active_layer_index = if state == LayerState::Deleted && self.active_layer_index == index {
-                    if let Some((new_active_layer_index, _)) = self.image.layers().iter().enumerate().find(|(_, (state, _))| state != &LayerState::Deleted) {
-                        let current_active_layer_index = self.active_layer_index;
-                        self.active_layer_index = new_active_layer_index;
-                        Some((current_active_layer_index, new_active_layer_index))
-                    } else {
-                        None
-                    }
+                let current_state = self.image.layers_mut()[index].state.clone();
+                self.image.layers_mut()[index].state = state.clone();
+
+                let current_active_layer_index = self.active_layer_index;
+                self.active_layer_index = alive_layer_index(&self.layer_states(), current_active_layer_index);
+                let change_active_layer_index = if self.active_layer_index != current_active_layer_index {
+                    Some((current_active_layer_index, self.active_layer_index))
                 } else {
                     None
                 };
@@ -444,6 +1202,99 @@ impl Editor {
                     ));
                 }
             }
+            EditorOperation::SetLayerOpacity(index, opacity) => {
+                let opacity = opacity.max(0.0).min(1.0);
+                let current_opacity = self.image.layers()[index].opacity;
+                self.image.layers_mut()[index].opacity = opacity;
+
+                if push_undo {
+                    self.undo_stack.push((
+                        EditorOperation::SetLayerOpacity(index, opacity),
+                        EditorOperation::SetLayerOpacity(index, current_opacity)
+                    ));
+                }
+            }
+            EditorOperation::ReorderLayers(index1, index2) => {
+                self.image.layers_mut().swap(index1, index2);
+
+                if self.active_layer_index == index1 {
+                    self.active_layer_index = index2;
+                } else if self.active_layer_index == index2 {
+                    self.active_layer_index = index1;
+                }
+
+                if push_undo {
+                    self.undo_stack.push((
+                        EditorOperation::ReorderLayers(index1, index2),
+                        EditorOperation::ReorderLayers(index1, index2)
+                    ));
+                }
+            }
+            EditorOperation::SetLayerName(index, name) => {
+                let current_name = std::mem::replace(&mut self.image.layers_mut()[index].name, name.clone());
+
+                if push_undo {
+                    self.undo_stack.push((
+                        EditorOperation::SetLayerName(index, name),
+                        EditorOperation::SetLayerName(index, current_name)
+                    ));
+                }
+            }
+            EditorOperation::SetLayerImage(index, image) => {
+                let current_image = self.image.layers()[index].image.get_image().clone();
+                self.image.layers_mut()[index].image = Image::new(image.clone());
+
+                if push_undo {
+                    self.undo_stack.push((
+                        EditorOperation::SetLayerImage(index, image),
+                        EditorOperation::SetLayerImage(index, current_image)
+                    ));
+                }
+            }
+            EditorOperation::MergeDown(index) => {
+                let below_index = match self.layer_below(index) {
+                    Some(below_index) => below_index,
+                    None => return
+                };
+
+                let below_layer = &self.image.layers()[below_index];
+                let below_image = below_layer.image.get_image().clone();
+                let below_state = below_layer.state.clone();
+                let below_opacity = below_layer.opacity;
+
+                let visible_layers = [below_index, index]
+                    .iter()
+                    .map(|&layer_index| &self.image.layers()[layer_index])
+                    .filter(|layer| layer.state == LayerState::Visible)
+                    .map(|layer| (layer.image.get_image(), layer.opacity))
+                    .collect::<Vec<_>>();
+                let merged_image = composite_layer_images(self.image.width(), self.image.height(), &visible_layers);
+
+                let current_state = self.image.layers()[index].state.clone();
+                let current_active_layer_index = self.active_layer_index;
+
+                // The opacities are baked into the merged image
+                let merged_layer = &mut self.image.layers_mut()[below_index];
+                merged_layer.image = Image::new(merged_image);
+                merged_layer.state = LayerState::Visible;
+                merged_layer.opacity = 1.0;
+
+                self.image.layers_mut()[index].state = LayerState::Deleted;
+                self.active_layer_index = below_index;
+
+                if push_undo {
+                    self.undo_stack.push((
+                        EditorOperation::MergeDown(index),
+                        EditorOperation::Sequential(vec![
+                            EditorOperation::SetLayerImage(below_index, below_image),
+                            EditorOperation::SetLayerOpacity(below_index, below_opacity),
+                            EditorOperation::SetLayerState(below_index, below_state),
+                            EditorOperation::SetLayerState(index, current_state),
+                            EditorOperation::SetActiveLayer(current_active_layer_index)
+                        ])
+                    ));
+                }
+            }
             EditorOperation::SetActiveLayer(layer_index) => {
                 let current_active_layer_index = self.active_layer_index;
                 self.active_layer_index = layer_index;
@@ -458,16 +1309,207 @@ impl Editor {
             EditorOperation::SetImage(image) => {
                 let mut current_image = image;
                 std::mem::swap(&mut current_image, &mut self.image);
-                self.active_layer_index = 0;
+
+                // The first layer of the new image might be deleted
+                let current_active_layer_index = self.active_layer_index;
+                self.active_layer_index = alive_layer_index(&self.layer_states(), 0);
 
                 if push_undo {
                     self.undo_stack.push((
                         EditorOperation::SetImage(self.image.clone()),
-                        EditorOperation::SetImage(current_image)
+                        EditorOperation::Sequential(vec![
+                            EditorOperation::SetImage(current_image),
+                            EditorOperation::SetActiveLayer(current_active_layer_index)
+                        ])
                     ));
                 }
             }
             EditorOperation::ImageOp(_, _) => panic!("Should not be used in this way.")
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_test_project(width: u32, height: u32, layers: &[(&str, bool, f32, &image::RgbaImage)]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        write_project(&mut buffer, width, height, image::Rgba([255, 255, 255, 255]), layers).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_project_round_trip() {
+        let first = image::RgbaImage::from_fn(3, 2, |x, y| image::Rgba([x as u8, y as u8, 100, 255]));
+        let second = image::RgbaImage::from_pixel(3, 2, image::Rgba([10, 20, 30, 40]));
+        let buffer = write_test_project(3, 2, &[("Background", true, 1.0, &first), ("Layer 2", false, 0.5, &second)]);
+
+        let project = read_project(&mut buffer.as_slice(), buffer.len() as u64).unwrap();
+        assert_eq!(3, project.width);
+        assert_eq!(2, project.height);
+        assert_eq!(image::Rgba([255, 255, 255, 255]), project.matte_color);
+        assert_eq!(2, project.layers.len());
+        assert_eq!(("Background".to_owned(), true, 1.0, first), project.layers[0]);
+        assert_eq!(("Layer 2".to_owned(), false, 0.5, second), project.layers[1]);
+    }
+
+    #[test]
+    fn test_project_truncated() {
+        let image = image::RgbaImage::from_pixel(3, 2, image::Rgba([10, 20, 30, 40]));
+        let buffer = write_test_project(3, 2, &[("Background", true, 1.0, &image)]);
+
+        let truncated = &buffer[..buffer.len() - 1];
+        assert!(read_project(&mut &truncated[..], truncated.len() as u64).is_err());
+    }
+
+    #[test]
+    fn test_project_too_large_layer() {
+        // The header claims a huge layer that the file does not contain
+        let image = image::RgbaImage::new(1, 1);
+        let mut buffer = write_test_project(1, 1, &[("Background", true, 1.0, &image)]);
+        buffer[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        buffer[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let err = read_project(&mut buffer.as_slice(), buffer.len() as u64).err().unwrap();
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+    }
+
+    #[test]
+    fn test_jpeg_export_flattens_on_matte() {
+        let image = image::RgbaImage::from_pixel(16, 16, image::Rgba([255, 0, 0, 128]));
+        let path = std::env::temp_dir().join(format!("imageeditor_matte_{}.jpg", std::process::id()));
+
+        encode_image(&image, &path, &ImageFormat::Jpeg(100), image::Rgba([255, 255, 255, 255])).unwrap();
+        let decoded = image::open(&path).unwrap().to_rgba();
+        std::fs::remove_file(&path).unwrap();
+
+        // Half of the red is replaced by the white matte
+        for pixel in decoded.pixels() {
+            assert!(pixel[0] >= 250, "{:?}", pixel);
+            assert!((pixel[1] as i32 - 127).abs() <= 3, "{:?}", pixel);
+            assert!((pixel[2] as i32 - 127).abs() <= 3, "{:?}", pixel);
+            assert_eq!(255, pixel[3]);
+        }
+    }
+
+    #[test]
+    fn test_ico_export_warns_when_downscaling() {
+        let image = image::RgbaImage::from_pixel(512, 128, image::Rgba([255, 0, 0, 255]));
+        let path = std::env::temp_dir().join(format!("imageeditor_downscale_{}.ico", std::process::id()));
+
+        let warning = encode_image(&image, &path, &ImageFormat::Ico, image::Rgba([255, 255, 255, 255])).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            Some(SaveWarning::IcoDownscaled { width: 512, height: 128, new_width: 256, new_height: 64 }),
+            warning
+        );
+    }
+
+    #[test]
+    fn test_png_streamed_matches_composite() {
+        let layers = vec![
+            (image::RgbaImage::from_fn(5, 3, |x, y| image::Rgba([x as u8 * 40, y as u8 * 60, 10, 255])), 1.0),
+            (image::RgbaImage::from_fn(5, 3, |x, _| image::Rgba([200, 100, 50, x as u8 * 50])), 0.5)
+        ];
+
+        // Composited the same way as the composite image of the editor
+        let mut composite = image::RgbaImage::new(5, 3);
+        for (layer, opacity) in &layers {
+            for (x, y, pixel) in layer.enumerate_pixels() {
+                let mut pixel = *pixel;
+                pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+                composite.get_pixel_mut(x, y).blend(&pixel);
+            }
+        }
+
+        let mut buffer = Vec::new();
+        write_png_streamed(&mut buffer, 5, 3, |y, row| {
+            for (layer, opacity) in &layers {
+                blend_row(layer, *opacity, y, row);
+            }
+        }).unwrap();
+
+        let decoded = image::load_from_memory_with_format(&buffer, image::ImageFormat::PNG).unwrap().to_rgba();
+        assert_eq!(composite, decoded);
+    }
+
+    #[test]
+    fn test_alive_layer_index_delete_undo_redo() {
+        // Delete the active layer
+        let states = vec![LayerState::Visible, LayerState::Deleted, LayerState::Visible];
+        assert_eq!(0, alive_layer_index(&states, 1));
+
+        // Undo restores the layer, which keeps the restored index
+        let states = vec![LayerState::Visible, LayerState::Visible, LayerState::Visible];
+        assert_eq!(1, alive_layer_index(&states, 1));
+
+        // Redo where the first layers are deleted as well
+        let states = vec![LayerState::Deleted, LayerState::Deleted, LayerState::Hidden];
+        assert_eq!(2, alive_layer_index(&states, 1));
+        assert_eq!(2, alive_layer_index(&states, 5));
+    }
+
+    #[test]
+    fn test_composite_layer_images_merge_down() {
+        let below = image::RgbaImage::from_fn(8, 8, |x, y| image::Rgba([(x * 30) as u8, 200, (y * 30) as u8, 255]));
+        let above = image::RgbaImage::from_fn(8, 8, |x, y| image::Rgba([250, (x * y) as u8, 10, (x * 32) as u8]));
+
+        let background = image::RgbaImage::from_pixel(8, 8, image::Rgba([40, 90, 160, 255]));
+
+        let assert_similar = |expected: &image::RgbaImage, actual: &image::RgbaImage| {
+            for (expected, actual) in expected.pixels().zip(actual.pixels()) {
+                for channel in 0..4 {
+                    assert!((expected[channel] as i32 - actual[channel] as i32).abs() <= 2, "{:?} != {:?}", expected, actual);
+                }
+            }
+        };
+
+        // With full opacity, the merged layer looks like the two layers did with their own opacities
+        let merged = composite_layer_images(8, 8, &[(&below, 0.5), (&above, 0.75)]);
+        assert_similar(
+            &composite_layer_images(8, 8, &[(&background, 1.0), (&below, 0.5), (&above, 0.75)]),
+            &composite_layer_images(8, 8, &[(&background, 1.0), (&merged, 1.0)])
+        );
+
+        for (x, y, pixel) in merged.enumerate_pixels() {
+            let above_alpha = above.get_pixel(x, y)[3] as f32 / 255.0 * 0.75;
+            let expected_alpha = above_alpha + 0.5 * (1.0 - above_alpha);
+            assert!((pixel[3] as f32 - expected_alpha * 255.0).abs() <= 1.0, "{:?}", pixel);
+        }
+
+        // A hidden layer below is left out of the merge
+        let merged = composite_layer_images(8, 8, &[(&above, 0.75)]);
+        assert_similar(
+            &composite_layer_images(8, 8, &[(&background, 1.0), (&above, 0.75)]),
+            &composite_layer_images(8, 8, &[(&background, 1.0), (&merged, 1.0)])
+        );
+    }
+
+    #[test]
+    fn test_merge_history_entries() {
+        let (op, undo_op) = merge_history_entries(vec![
+            (EditorOperation::SetLayerState(1, LayerState::Deleted), EditorOperation::SetLayerState(1, LayerState::Visible)),
+            (EditorOperation::SetActiveLayer(0), EditorOperation::SetActiveLayer(1))
+        ]);
+
+        match op {
+            EditorOperation::Sequential(ops) => {
+                assert_eq!(2, ops.len());
+                assert!(matches!(ops[0], EditorOperation::SetLayerState(1, LayerState::Deleted)));
+                assert!(matches!(ops[1], EditorOperation::SetActiveLayer(0)));
+            }
+            _ => panic!("Expected a sequential operation.")
+        }
+
+        // The undo operations are applied in reverse order
+        match undo_op {
+            EditorOperation::Sequential(ops) => {
+                assert_eq!(2, ops.len());
+                assert!(matches!(ops[0], EditorOperation::SetActiveLayer(1)));
+                assert!(matches!(ops[1], EditorOperation::SetLayerState(1, LayerState::Visible)));
+            }
+            _ => panic!("Expected a sequential operation.")
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::rendering::prelude::Position;
+
+pub const SNAP_DISTANCE: f32 = 6.0;
+pub const DEFAULT_GRID_SIZE: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Guide {
+    pub vertical: bool,
+    pub position: i32
+}
+
+impl Guide {
+    pub fn new(vertical: bool, position: i32) -> Guide {
+        Guide {
+            vertical,
+            position
+        }
+    }
+
+    // The center of the pixel, such that truncating tools lands on the guide
+    pub fn image_position(&self) -> f32 {
+        self.position as f32 + 0.5
+    }
+}
+
+pub fn snap_position(guides: &[Guide], position: Position, max_distance: f32) -> Position {
+    let mut snapped_position = position;
+    let mut best_distance_x = max_distance;
+    let mut best_distance_y = max_distance;
+
+    for guide in guides {
+        let guide_position = guide.image_position();
+
+        if guide.vertical {
+            let distance = (position.x - guide_position).abs();
+            if distance <= best_distance_x {
+                best_distance_x = distance;
+                snapped_position.x = guide_position;
+            }
+        } else {
+            let distance = (position.y - guide_position).abs();
+            if distance <= best_distance_y {
+                best_distance_y = distance;
+                snapped_position.y = guide_position;
+            }
+        }
+    }
+
+    snapped_position
+}
+
+pub fn snap_to_grid(position: Position, grid_size: u32) -> Position {
+    let grid_size = grid_size.max(1) as f32;
+
+    // Same as guides, snap to the center of the pixel at the grid intersection
+    Position::new(
+        (position.x / grid_size).round() * grid_size + 0.5,
+        (position.y / grid_size).round() * grid_size + 0.5
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snap_position_on_guide() {
+        let guides = [Guide::new(true, 10), Guide::new(false, 20)];
+        let snapped_position = snap_position(&guides, Position::new(12.0, 18.0), SNAP_DISTANCE);
+        assert_eq!(guides[0].image_position(), snapped_position.x);
+        assert_eq!(guides[1].image_position(), snapped_position.y);
+
+        let snapped_position = snap_position(&guides, Position::new(30.0, 40.0), SNAP_DISTANCE);
+        assert_eq!(Position::new(30.0, 40.0), snapped_position);
+    }
+}
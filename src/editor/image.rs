@@ -6,19 +6,65 @@ use crate::editor::Region;
 
 pub type Color = Rgba<u8>;
 
+// Inclusive pixel bounds of a changed area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChangedArea {
+    min_x: u32,
+    min_y: u32,
+    max_x: u32,
+    max_y: u32
+}
+
+impl ChangedArea {
+    fn new(min_x: u32, min_y: u32, max_x: u32, max_y: u32) -> ChangedArea {
+        ChangedArea {
+            min_x,
+            min_y,
+            max_x,
+            max_y
+        }
+    }
+
+    fn union(area: Option<ChangedArea>, other: ChangedArea) -> ChangedArea {
+        match area {
+            Some(area) => ChangedArea::new(
+                area.min_x.min(other.min_x),
+                area.min_y.min(other.min_y),
+                area.max_x.max(other.max_x),
+                area.max_y.max(other.max_y)
+            ),
+            None => other
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Image {
     underlying_image: image::RgbaImage,
-    texture: Texture
+    texture: Texture,
+    // Changed since the last upload to the GPU
+    dirty_area: Option<ChangedArea>,
+    // Changed since the last clear, which is what needs to be cleared
+    drawn_area: Option<ChangedArea>
 }
 
 impl Image {
     pub fn new(image: image::RgbaImage) -> Image {
         let texture = Texture::new(image.width(), image.height(), 4);
         texture.upload(image.as_ref());
+
+        // The content of the given image is unknown, so all of it needs to be cleared
+        let drawn_area = if image.width() > 0 && image.height() > 0 {
+            Some(ChangedArea::new(0, 0, image.width() - 1, image.height() - 1))
+        } else {
+            None
+        };
+
         Image {
             underlying_image: image,
-            texture
+            texture,
+            dirty_area: None,
+            drawn_area
         }
     }
 
@@ -31,7 +77,26 @@ impl Image {
     }
 
     fn upload_to_gpu(&mut self) {
-        self.texture.upload(self.underlying_image.as_ref());
+        if let Some(area) = self.dirty_area.take() {
+            self.texture.upload_region(
+                self.underlying_image.as_ref(),
+                area.min_x,
+                area.min_y,
+                area.max_x - area.min_x + 1,
+                area.max_y - area.min_y + 1
+            );
+        }
+    }
+
+    fn mark_changed(&mut self, area: ChangedArea) {
+        self.dirty_area = Some(ChangedArea::union(self.dirty_area, area));
+        self.drawn_area = Some(ChangedArea::union(self.drawn_area, area));
+    }
+
+    fn mark_all_changed(&mut self) {
+        if self.width() > 0 && self.height() > 0 {
+            self.mark_changed(ChangedArea::new(0, 0, self.width() - 1, self.height() - 1));
+        }
     }
 
     pub fn update_operation(&mut self) -> ImageUpdateOperation {
@@ -43,8 +108,15 @@ impl Image {
     }
 
     pub fn clear_cpu(&mut self) {
-        for pixel in self.underlying_image.pixels_mut() {
-            *pixel = image::Rgba([0, 0, 0, 0]);
+        // Only the drawn area can contain anything, and the cleared area is uploaded on the next update
+        if let Some(area) = self.drawn_area.take() {
+            for y in area.min_y..=area.max_y {
+                for x in area.min_x..=area.max_x {
+                    self.underlying_image.put_pixel(x, y, image::Rgba([0, 0, 0, 0]));
+                }
+            }
+
+            self.dirty_area = Some(ChangedArea::union(self.dirty_area, area));
         }
     }
 }
@@ -87,6 +159,8 @@ impl<'a> ImageUpdateOperation<'a> {
     }
 
     pub fn raw_pixels_mut(&mut self) -> &mut [u8] {
+        // Any pixel can be changed through the raw buffer
+        self.image.mark_all_changed();
         self.image.underlying_image.as_mut()
     }
 
@@ -110,14 +184,34 @@ impl<'a> ImageSource for ImageUpdateOperation<'a> {
 }
 
 impl<'a> ImageOperationSource for ImageUpdateOperation<'a> {
+    fn is_valid_pixel(&self, x: i32, y: i32) -> bool {
+        match self.valid_region.as_ref() {
+            Some(valid_region) => valid_region.contains(x, y),
+            None => true
+        }
+    }
+
+    fn valid_region(&self) -> Option<&Region> {
+        self.valid_region.as_ref()
+    }
+
+    fn unclipped_pixels_mut(&mut self) -> Option<&mut [u8]> {
+        if self.valid_region.is_none() {
+            Some(self.raw_pixels_mut())
+        } else {
+            None
+        }
+    }
+
     fn put_pixel(&mut self, x: u32, y: u32, pixel: Color) {
         if let Some(valid_region) = self.valid_region.as_ref() {
-            if valid_region.contains(x as i32, y as i32) {
-                self.image.underlying_image.put_pixel(x, y, pixel);
+            if !valid_region.contains(x as i32, y as i32) {
+                return;
             }
-        } else {
-            self.image.underlying_image.put_pixel(x, y, pixel);
         }
+
+        self.image.underlying_image.put_pixel(x, y, pixel);
+        self.image.mark_changed(ChangedArea::new(x, y, x, y));
     }
 }
 
@@ -6,7 +6,9 @@ use itertools::Itertools;
 use image::{Pixel, FilterType};
 
 use crate::editor::image::{Color};
-use crate::editor::image_operation_helpers::{sub_image, draw_block, draw_line, draw_circle, fill_rectangle, bucket_fill, draw_line_anti_aliased_thick, draw_circle_anti_aliased_thick, color_gradient, pencil_stroke_anti_aliased, rotate_image, draw_line_thick, draw_pixel};
+use crate::editor::Region;
+use crate::rendering::prelude::Position;
+use crate::editor::image_operation_helpers::{sub_image, draw_block, draw_line, draw_circle, fill_rectangle, bucket_fill, draw_line_anti_aliased_thick, draw_circle_anti_aliased_thick, color_gradient, pencil_stroke_anti_aliased, rotate_image, draw_line_thick, draw_pixel, draw_changed_pixel, apply_lut, fill_region, draw_ellipse, draw_ellipse_anti_aliased, dash_segments, draw_rounded_rectangle, replace_color_line, erase_pixels, flip, grayscale, brightness_contrast, gaussian_blur, hue_saturation, posterize, threshold, levels};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ImageOperationMarker {
@@ -20,6 +22,51 @@ pub enum ColorGradientType {
     Radial
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelSelector {
+    All,
+    Red,
+    Green,
+    Blue
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    None,
+    Vertical,
+    Horizontal,
+    Both
+}
+
+impl Symmetry {
+    pub fn apply(&self, op: ImageOperation, axis: Position) -> ImageOperation {
+        // Mirroring around an axis at a / 2 maps the pixel x to a - 1 - x
+        let width = (axis.x * 2.0).round() as i32;
+        let height = (axis.y * 2.0).round() as i32;
+
+        let mirrors = match self {
+            Symmetry::None => { return op; }
+            Symmetry::Vertical => vec![(Some(width), None)],
+            Symmetry::Horizontal => vec![(None, Some(height))],
+            Symmetry::Both => vec![(Some(width), None), (None, Some(height)), (Some(width), Some(height))]
+        };
+
+        let mut ops = Vec::new();
+        for (mirror_width, mirror_height) in mirrors {
+            match op.mirrored(mirror_width, mirror_height) {
+                Some(mirrored_op) => ops.push(mirrored_op),
+                None => {
+                    println!("Warning: symmetry is not supported for '{}', applying it without symmetry.", op);
+                    return op;
+                }
+            }
+        }
+        ops.insert(0, op);
+
+        ImageOperation::Sequential(Some(ops[0].to_string()), ops)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ImageOperation {
     Empty,
@@ -32,14 +79,29 @@ pub enum ImageOperation {
     SetRotatedImage { image: image::RgbaImage, start_x: i32, start_y: i32, end_x: i32, end_y: i32, rotation: f32 },
     SetPixel { x: i32, y: i32, color: Color },
     Block { x: i32, y: i32, color: Color, blend: bool, side_half_width: i32 },
-    Line { start_x: i32, start_y: i32, end_x: i32, end_y: i32, color: Color, blend: bool, anti_aliased: Option<bool>, side_half_width: i32 },
+    Line { start_x: i32, start_y: i32, end_x: i32, end_y: i32, color: Color, blend: bool, anti_aliased: Option<bool>, side_half_width: i32, dash: Option<(u32, u32)> },
     PencilStroke { start_x: i32, start_y: i32, end_x: i32, end_y: i32, prev_start_x: Option<i32>, prev_start_y: Option<i32>, color: Color, blend: bool, anti_aliased: Option<bool>, side_half_width: i32 },
     Rectangle { start_x: i32, start_y: i32, end_x: i32, end_y: i32, border_half_width: i32, color: Color, blend: bool },
     FillRectangle { start_x: i32, start_y: i32, end_x: i32, end_y: i32, color: Color, blend: bool },
+    RoundedRectangle { start_x: i32, start_y: i32, end_x: i32, end_y: i32, corner_radius: i32, border_half_width: i32, color: Color, blend: bool },
+    FillRegion { region: Region, color: Color, blend: bool },
     Circle { center_x: i32, center_y: i32, radius: i32, border_half_width: i32, color: Color, blend: bool, anti_aliased: Option<bool> },
     FillCircle { center_x: i32, center_y: i32, radius: i32, color: Color, blend: bool },
-    BucketFill { start_x: i32, start_y: i32, fill_color: Color, tolerance: f32 },
-    ColorGradient { start_x: i32, start_y: i32, end_x: i32, end_y: i32, first_color: Color, second_color: Color, gradient_type: ColorGradientType }
+    Ellipse { center_x: i32, center_y: i32, radius_x: i32, radius_y: i32, border_half_width: i32, color: Color, blend: bool, anti_aliased: Option<bool> },
+    FillEllipse { center_x: i32, center_y: i32, radius_x: i32, radius_y: i32, color: Color, blend: bool },
+    BucketFill { start_x: i32, start_y: i32, fill_color: Color, tolerance: f32, contiguous: bool, anti_aliased: bool },
+    ReplaceColor { start_x: i32, start_y: i32, end_x: i32, end_y: i32, side_half_width: i32, from_color: Color, to_color: Color, tolerance: f32 },
+    Erase { alpha_scales: Vec<((i32, i32), f32)>, color_tolerance: Option<(Color, f32)> },
+    ColorGradient { start_x: i32, start_y: i32, end_x: i32, end_y: i32, first_color: Color, second_color: Color, gradient_type: ColorGradientType, dither: bool },
+    Curves { lut: [u8; 256], channel: ChannelSelector },
+    Flip { horizontal: bool, start_x: i32, start_y: i32, end_x: i32, end_y: i32 },
+    Grayscale { luminance_weights: Option<(f32, f32, f32)> },
+    BrightnessContrast { brightness: i32, contrast: f32 },
+    GaussianBlur { radius: f32 },
+    HueSaturation { hue_shift: f64, saturation_scale: f64, value_scale: f64 },
+    Posterize { levels: u8 },
+    Threshold { level: u8 },
+    Levels { in_black: u8, in_white: u8, out_black: u8, out_white: u8, gamma: f32 }
 }
 
 pub trait ImageSource {
@@ -51,6 +113,19 @@ pub trait ImageSource {
 pub trait ImageOperationSource : ImageSource {
     fn put_pixel(&mut self, x: u32, y: u32, pixel: Color);
 
+    fn is_valid_pixel(&self, _x: i32, _y: i32) -> bool {
+        true
+    }
+
+    fn valid_region(&self) -> Option<&Region> {
+        None
+    }
+
+    // The raw RGBA buffer when every pixel can be changed, which allows rows to be written in parallel
+    fn unclipped_pixels_mut(&mut self) -> Option<&mut [u8]> {
+        None
+    }
+
     fn put_pixel_with_blend(&mut self, x: u32, y: u32, pixel: Color) {
         let mut current = self.get_pixel(x, y);
         current.blend(&pixel);
@@ -193,34 +268,44 @@ impl ImageOperation {
                     None
                 }
             }
-            ImageOperation::Line { start_x, start_y, end_x, end_y, color, blend, anti_aliased, side_half_width } => {
+            ImageOperation::Line { start_x, start_y, end_x, end_y, color, blend, anti_aliased, side_half_width, dash } => {
                 let mut undo_image = SparseImage::new();
 
-                if anti_aliased.unwrap_or(true) {
-                    draw_line_anti_aliased_thick(
-                        update_op,
-                        *start_x,
-                        *start_y,
-                        *end_x,
-                        *end_y,
-                        *side_half_width,
-                        *color,
-                        undo,
-                        &mut undo_image
-                    );
-                } else {
-                    draw_line_thick(
-                        update_op,
-                        *start_x,
-                        *start_y,
-                        *end_x,
-                        *end_y,
-                        *side_half_width,
-                        *color,
-                        *blend,
-                        undo,
-                        &mut undo_image
-                    );
+                let segments = match dash {
+                    Some((on_length, off_length)) => dash_segments(*start_x, *start_y, *end_x, *end_y, *on_length, *off_length),
+                    None => vec![(*start_x, *start_y, *end_x, *end_y)]
+                };
+
+                for (start_x, start_y, end_x, end_y) in segments {
+                    // The thick line drawing needs a direction, which a single pixel dash does not have
+                    if (start_x, start_y) == (end_x, end_y) {
+                        draw_block(update_op, start_x, start_y, *side_half_width, *color, *blend, undo, &mut undo_image);
+                    } else if anti_aliased.unwrap_or(true) {
+                        draw_line_anti_aliased_thick(
+                            update_op,
+                            start_x,
+                            start_y,
+                            end_x,
+                            end_y,
+                            *side_half_width,
+                            *color,
+                            undo,
+                            &mut undo_image
+                        );
+                    } else {
+                        draw_line_thick(
+                            update_op,
+                            start_x,
+                            start_y,
+                            end_x,
+                            end_y,
+                            *side_half_width,
+                            *color,
+                            *blend,
+                            undo,
+                            &mut undo_image
+                        );
+                    }
                 }
 
                 if undo {
@@ -281,25 +366,55 @@ impl ImageOperation {
                 let max_x = std::cmp::min(width, *end_x);
                 let max_y = std::cmp::min(height, *end_y);
 
-                let undo_image = if undo {
-                    Some(sub_image(update_op, min_x, min_y, max_x + 1, max_y + 1))
-                } else {
-                    None
-                };
+                let mut undo_image = SparseImage::new();
 
                 fill_rectangle(
                     min_x, min_y,
                     max_x, max_y,
                     |x, y| {
-                        if *blend {
-                            update_op.put_pixel_with_blend(x as u32, y as u32, *color)
-                        } else {
-                            update_op.put_pixel(x as u32, y as u32, *color)
-                        }
+                        draw_changed_pixel(update_op, x, y, *color, *blend, undo, &mut undo_image);
                     }
                 );
 
-                undo_image.map(|image| ImageOperation::SetImage { start_x: min_x, start_y: min_y, image, blend: false })
+                if undo {
+                    Some(compact_undo_image(update_op, undo_image))
+                } else {
+                    None
+                }
+            }
+            ImageOperation::RoundedRectangle { start_x, start_y, end_x, end_y, corner_radius, border_half_width, color, blend } => {
+                let mut undo_image = SparseImage::new();
+
+                draw_rounded_rectangle(
+                    update_op,
+                    *start_x,
+                    *start_y,
+                    *end_x,
+                    *end_y,
+                    *corner_radius,
+                    *border_half_width,
+                    *color,
+                    *blend,
+                    undo,
+                    &mut undo_image
+                );
+
+                if undo {
+                    Some(ImageOperation::SetSparseImage { image: undo_image })
+                } else {
+                    None
+                }
+            }
+            ImageOperation::FillRegion { region, color, blend } => {
+                let undo_image = if undo {
+                    Some(sub_image(update_op, region.left(), region.top(), region.right(), region.bottom()))
+                } else {
+                    None
+                };
+
+                fill_region(update_op, region, *color, *blend);
+
+                undo_image.map(|image| ImageOperation::SetImage { start_x: region.left(), start_y: region.top(), image, blend: false })
             }
             ImageOperation::Rectangle { start_x, start_y, end_x, end_y, border_half_width: side_half_width, color, blend } => {
                 let mut undo_ops = Vec::new();
@@ -313,7 +428,8 @@ impl ImageOperation {
                         color: color.clone(),
                         blend: *blend,
                         anti_aliased: Some(false),
-                        side_half_width: *side_half_width
+                        side_half_width: *side_half_width,
+                        dash: None
                     }.apply(update_op, undo)
                 );
 
@@ -326,7 +442,8 @@ impl ImageOperation {
                         color: color.clone(),
                         blend: *blend,
                         anti_aliased: Some(false),
-                        side_half_width: *side_half_width
+                        side_half_width: *side_half_width,
+                        dash: None
                     }.apply(update_op, undo)
                 );
 
@@ -339,7 +456,8 @@ impl ImageOperation {
                         color: color.clone(),
                         blend: *blend,
                         anti_aliased: Some(false),
-                        side_half_width: *side_half_width
+                        side_half_width: *side_half_width,
+                        dash: None
                     }.apply(update_op, undo)
                 );
 
@@ -352,7 +470,8 @@ impl ImageOperation {
                         color: color.clone(),
                         blend: *blend,
                         anti_aliased: Some(false),
-                        side_half_width: *side_half_width
+                        side_half_width: *side_half_width,
+                        dash: None
                     }.apply(update_op, undo)
                 );
 
@@ -415,7 +534,62 @@ impl ImageOperation {
                     None
                 }
             }
-            ImageOperation::BucketFill { start_x, start_y, fill_color, tolerance } => {
+            ImageOperation::Ellipse { center_x, center_y, radius_x, radius_y, border_half_width, color, blend, anti_aliased } => {
+                let mut undo_image = SparseImage::new();
+
+                if anti_aliased.unwrap_or(true) {
+                    draw_ellipse_anti_aliased(
+                        update_op,
+                        *center_x,
+                        *center_y,
+                        *radius_x,
+                        *radius_y,
+                        *border_half_width,
+                        *color,
+                        *blend,
+                        undo,
+                        &mut undo_image
+                    );
+                } else {
+                    draw_ellipse(
+                        *center_x,
+                        *center_y,
+                        *radius_x,
+                        *radius_y,
+                        false,
+                        |center_x: i32, center_y: i32| {
+                            draw_block(update_op, center_x, center_y, *border_half_width, *color, *blend, undo, &mut undo_image);
+                        }
+                    );
+                }
+
+                if undo {
+                    Some(ImageOperation::SetSparseImage { image: undo_image })
+                } else {
+                    None
+                }
+            }
+            ImageOperation::FillEllipse { center_x, center_y, radius_x, radius_y, color, blend } => {
+                let mut undo_image = SparseImage::new();
+
+                draw_ellipse(
+                    *center_x,
+                    *center_y,
+                    *radius_x,
+                    *radius_y,
+                    true,
+                    |x: i32, y: i32| {
+                        draw_pixel(update_op, x, y, *color, *blend, undo, &mut undo_image);
+                    }
+                );
+
+                if undo {
+                    Some(ImageOperation::SetSparseImage { image: undo_image })
+                } else {
+                    None
+                }
+            }
+            ImageOperation::BucketFill { start_x, start_y, fill_color, tolerance, contiguous, anti_aliased } => {
                 let mut undo_image = OptionalImage::new(update_op.width(), update_op.height());
 
                 bucket_fill(
@@ -424,6 +598,8 @@ impl ImageOperation {
                     *start_y,
                     *fill_color,
                     *tolerance,
+                    *contiguous,
+                    *anti_aliased,
                     undo,
                     &mut undo_image
                 );
@@ -434,33 +610,107 @@ impl ImageOperation {
                     None
                 }
             }
-            ImageOperation::ColorGradient { start_x, start_y, end_x, end_y, first_color, second_color, gradient_type } => {
+            ImageOperation::ReplaceColor { start_x, start_y, end_x, end_y, side_half_width, from_color, to_color, tolerance } => {
+                let mut undo_image = SparseImage::new();
+
+                replace_color_line(
+                    update_op,
+                    *start_x,
+                    *start_y,
+                    *end_x,
+                    *end_y,
+                    *side_half_width,
+                    *from_color,
+                    *to_color,
+                    *tolerance,
+                    undo,
+                    &mut undo_image
+                );
+
+                if undo {
+                    Some(ImageOperation::SetSparseImage { image: undo_image })
+                } else {
+                    None
+                }
+            }
+            ImageOperation::Erase { alpha_scales, color_tolerance } => {
+                let mut undo_image = SparseImage::new();
+
+                erase_pixels(
+                    update_op,
+                    alpha_scales,
+                    *color_tolerance,
+                    undo,
+                    &mut undo_image
+                );
+
+                if undo {
+                    Some(ImageOperation::SetSparseImage { image: undo_image })
+                } else {
+                    None
+                }
+            }
+            ImageOperation::ColorGradient { start_x, start_y, end_x, end_y, first_color, second_color, gradient_type, dither } => {
+                apply_with_region_undo(update_op, undo, |update_op, bounds| {
+                    color_gradient(
+                        update_op,
+                        *start_x,
+                        *start_y,
+                        *end_x,
+                        *end_y,
+                        *first_color,
+                        *second_color,
+                        gradient_type.clone(),
+                        *dither,
+                        bounds
+                    )
+                })
+            }
+            ImageOperation::Curves { lut, channel } => {
+                apply_with_region_undo(update_op, undo, |update_op, _| apply_lut(update_op, lut, *channel))
+            }
+            ImageOperation::Flip { horizontal, start_x, start_y, end_x, end_y } => {
+                // A flip does not undo itself when only parts of the flipped area can be changed, such as with a mask
+                let min_x = (*start_x).clamp(0, update_op.width() as i32);
+                let min_y = (*start_y).clamp(0, update_op.height() as i32);
                 let undo_image = if undo {
                     Some(
                         sub_image(
                             update_op,
-                            0,
-                            0,
-                            update_op.width() as i32,
-                            update_op.height() as i32
+                            min_x,
+                            min_y,
+                            (*end_x).clamp(0, update_op.width() as i32),
+                            (*end_y).clamp(0, update_op.height() as i32)
                         )
                     )
                 } else {
                     None
                 };
 
-                color_gradient(
-                    update_op,
-                    *start_x,
-                    *start_y,
-                    *end_x,
-                    *end_y,
-                    *first_color,
-                    *second_color,
-                    gradient_type.clone()
-                );
+                flip(update_op, *horizontal, *start_x, *start_y, *end_x, *end_y);
 
-                undo_image.map(|image| ImageOperation::SetImage { start_x: 0, start_y: 0, image, blend: false })
+                undo_image.map(|image| ImageOperation::SetImage { start_x: min_x, start_y: min_y, image, blend: false })
+            }
+            ImageOperation::Grayscale { luminance_weights } => {
+                apply_with_region_undo(update_op, undo, |update_op, _| grayscale(update_op, luminance_weights.unwrap_or((0.2126, 0.7152, 0.0722))))
+            }
+            ImageOperation::BrightnessContrast { brightness, contrast } => {
+                apply_with_region_undo(update_op, undo, |update_op, _| brightness_contrast(update_op, *brightness, *contrast))
+            }
+            ImageOperation::GaussianBlur { radius } => {
+                apply_with_region_undo(update_op, undo, |update_op, _| gaussian_blur(update_op, *radius))
+            }
+            ImageOperation::HueSaturation { hue_shift, saturation_scale, value_scale } => {
+                apply_with_region_undo(update_op, undo, |update_op, _| hue_saturation(update_op, *hue_shift, *saturation_scale, *value_scale))
+            }
+            ImageOperation::Posterize { levels } => {
+                apply_with_region_undo(update_op, undo, |update_op, _| posterize(update_op, *levels))
+            }
+            ImageOperation::Threshold { level } => {
+                apply_with_region_undo(update_op, undo, |update_op, _| threshold(update_op, *level))
+            }
+            ImageOperation::Levels { in_black, in_white, out_black, out_white, gamma } => {
+                apply_with_region_undo(update_op, undo, |update_op, _| levels(update_op, *in_black, *in_white, *out_black, *out_white, *gamma))
             }
         }
     }
@@ -489,6 +739,69 @@ impl ImageOperation {
         }
     }
 
+    // The operation mirrored around the given axes, or None if it can not be mirrored
+    pub fn mirrored(&self, width: Option<i32>, height: Option<i32>) -> Option<ImageOperation> {
+        let mirror_x = |x: i32| width.map(|width| width - 1 - x).unwrap_or(x);
+        let mirror_y = |y: i32| height.map(|height| height - 1 - y).unwrap_or(y);
+
+        match self {
+            ImageOperation::Sequential(message, ops) => {
+                let ops = ops.iter().map(|op| op.mirrored(width, height)).collect::<Option<Vec<_>>>()?;
+                Some(ImageOperation::Sequential(message.clone(), ops))
+            }
+            ImageOperation::SetPixel { x, y, color } => {
+                Some(ImageOperation::SetPixel { x: mirror_x(*x), y: mirror_y(*y), color: *color })
+            }
+            ImageOperation::Block { x, y, color, blend, side_half_width } => {
+                Some(ImageOperation::Block { x: mirror_x(*x), y: mirror_y(*y), color: *color, blend: *blend, side_half_width: *side_half_width })
+            }
+            ImageOperation::Line { start_x, start_y, end_x, end_y, color, blend, anti_aliased, side_half_width, dash } => {
+                Some(ImageOperation::Line {
+                    start_x: mirror_x(*start_x),
+                    start_y: mirror_y(*start_y),
+                    end_x: mirror_x(*end_x),
+                    end_y: mirror_y(*end_y),
+                    color: *color,
+                    blend: *blend,
+                    anti_aliased: *anti_aliased,
+                    side_half_width: *side_half_width,
+                    dash: *dash
+                })
+            }
+            ImageOperation::PencilStroke { start_x, start_y, end_x, end_y, prev_start_x, prev_start_y, color, blend, anti_aliased, side_half_width } => {
+                Some(ImageOperation::PencilStroke {
+                    start_x: mirror_x(*start_x),
+                    start_y: mirror_y(*start_y),
+                    end_x: mirror_x(*end_x),
+                    end_y: mirror_y(*end_y),
+                    prev_start_x: prev_start_x.map(mirror_x),
+                    prev_start_y: prev_start_y.map(mirror_y),
+                    color: *color,
+                    blend: *blend,
+                    anti_aliased: *anti_aliased,
+                    side_half_width: *side_half_width
+                })
+            }
+            ImageOperation::Circle { center_x, center_y, radius, border_half_width, color, blend, anti_aliased } => {
+                Some(ImageOperation::Circle {
+                    center_x: mirror_x(*center_x),
+                    center_y: mirror_y(*center_y),
+                    radius: *radius,
+                    border_half_width: *border_half_width,
+                    color: *color,
+                    blend: *blend,
+                    anti_aliased: *anti_aliased
+                })
+            }
+            ImageOperation::FillCircle { center_x, center_y, radius, color, blend } => {
+                Some(ImageOperation::FillCircle { center_x: mirror_x(*center_x), center_y: mirror_y(*center_y), radius: *radius, color: *color, blend: *blend })
+            }
+            // Markers must only appear once
+            ImageOperation::Empty | ImageOperation::Marker(_, _) => Some(ImageOperation::Empty),
+            _ => None
+        }
+    }
+
     pub fn remove_markers(self) -> Self {
         match self {
             ImageOperation::Marker(_, _) => {
@@ -524,16 +837,94 @@ impl Display for ImageOperation {
             ImageOperation::PencilStroke { .. } => write!(f, "Pencil stroke"),
             ImageOperation::Rectangle { .. } => write!(f, "Rectangle"),
             ImageOperation::FillRectangle { .. } => write!(f, "Rectangle"),
+            ImageOperation::RoundedRectangle { .. } => write!(f, "Rounded rectangle"),
+            ImageOperation::FillRegion { .. } => write!(f, "Fill selection"),
             ImageOperation::Circle { .. } => write!(f, "Circle"),
             ImageOperation::FillCircle { .. } => write!(f, "Circle"),
+            ImageOperation::Ellipse { .. } => write!(f, "Ellipse"),
+            ImageOperation::FillEllipse { .. } => write!(f, "Ellipse"),
             ImageOperation::BucketFill { .. } => write!(f, "Bucket fill"),
+            ImageOperation::ReplaceColor { .. } => write!(f, "Replace color"),
+            ImageOperation::Erase { color_tolerance: Some(_), .. } => write!(f, "Erase by color"),
+            ImageOperation::Erase { color_tolerance: None, .. } => write!(f, "Erase"),
             ImageOperation::ColorGradient { .. } => write!(f, "Color gradient"),
+            ImageOperation::Curves { .. } => write!(f, "Curves"),
+            ImageOperation::Flip { horizontal: true, .. } => write!(f, "Flip horizontally"),
+            ImageOperation::Flip { horizontal: false, .. } => write!(f, "Flip vertically"),
+            ImageOperation::Grayscale { .. } => write!(f, "Grayscale"),
+            ImageOperation::BrightnessContrast { .. } => write!(f, "Brightness/contrast"),
+            ImageOperation::GaussianBlur { .. } => write!(f, "Gaussian blur"),
+            ImageOperation::HueSaturation { .. } => write!(f, "Hue/saturation"),
+            ImageOperation::Posterize { .. } => write!(f, "Posterize"),
+            ImageOperation::Threshold { .. } => write!(f, "Threshold"),
+            ImageOperation::Levels { .. } => write!(f, "Levels"),
         }
     }
 }
 
 pub type SparseImage = HashMap<(u32, u32), Color>;
 
+// Approximate memory used per pixel of a sparse image compared to the four bytes of a dense image
+const SPARSE_IMAGE_PIXEL_COST: usize = 16;
+
+fn compact_undo_image<T: ImageSource>(image: &T, undo_image: SparseImage) -> ImageOperation {
+    let min_x = undo_image.keys().map(|(x, _)| *x).min();
+    let min_y = undo_image.keys().map(|(_, y)| *y).min();
+    let max_x = undo_image.keys().map(|(x, _)| *x).max();
+    let max_y = undo_image.keys().map(|(_, y)| *y).max();
+
+    if let (Some(min_x), Some(min_y), Some(max_x), Some(max_y)) = (min_x, min_y, max_x, max_y) {
+        let area = (max_x - min_x + 1) as usize * (max_y - min_y + 1) as usize;
+
+        // When most of the changed area is modified, storing the area as an image is cheaper.
+        // The unchanged pixels within the area still have their original color.
+        if undo_image.len() * SPARSE_IMAGE_PIXEL_COST > area * 4 {
+            let mut dense_image = sub_image(image, min_x as i32, min_y as i32, max_x as i32 + 1, max_y as i32 + 1);
+            for ((x, y), color) in undo_image {
+                dense_image.put_pixel(x - min_x, y - min_y, color);
+            }
+
+            return ImageOperation::SetImage { start_x: min_x as i32, start_y: min_y as i32, image: dense_image, blend: false };
+        }
+    }
+
+    ImageOperation::SetSparseImage { image: undo_image }
+}
+
+// For operations that change (almost) every valid pixel, the undo image is the dense bounding box of them
+fn apply_with_region_undo<T: ImageOperationSource, F: FnOnce(&mut T, (i32, i32, i32, i32))>(update_op: &mut T,
+                                                                                              undo: bool,
+                                                                                              apply: F) -> Option<ImageOperation> {
+    let bounds = valid_bounding_box(update_op);
+    let (min_x, min_y, max_x, max_y) = bounds;
+
+    let undo_image = if undo {
+        Some(sub_image(update_op, min_x, min_y, max_x, max_y))
+    } else {
+        None
+    };
+
+    apply(update_op, bounds);
+
+    undo_image.map(|image| ImageOperation::SetImage { start_x: min_x, start_y: min_y, image, blend: false })
+}
+
+// The bounds (max exclusive) of the pixels that can be changed
+fn valid_bounding_box<T: ImageOperationSource>(image: &T) -> (i32, i32, i32, i32) {
+    let width = image.width() as i32;
+    let height = image.height() as i32;
+
+    match image.valid_region() {
+        Some(region) => (
+            region.left().max(0).min(width),
+            region.top().max(0).min(height),
+            region.right().max(0).min(width),
+            region.bottom().max(0).min(height)
+        ),
+        None => (0, 0, width, height)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct OptionalImage {
     width: u32,
@@ -594,4 +985,372 @@ pub fn select_latest<const N: usize>(ops: [Option<ImageOperation>; N]) -> Option
     }
 
     last
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::SelectionMask;
+    use crate::editor::test_helpers::TestImage;
+    use crate::editor::image_operation_helpers::select_by_color;
+
+    #[test]
+    fn test_flip_undo_with_mask() {
+        let original = image::RgbaImage::from_fn(4, 1, |x, _| image::Rgba([x as u8 * 50, 0, 0, 255]));
+        let mask = SelectionMask::new(4, 1, vec![true, false, false, false]);
+        let mut image = TestImage::new(original.clone()).with_region(Some(Region::new(0, 0, 4, 1).with_mask(Some(mask))));
+
+        let op = ImageOperation::Flip { horizontal: true, start_x: 0, start_y: 0, end_x: 4, end_y: 1 };
+        let undo_op = op.apply(&mut image, true).unwrap();
+        assert_eq!(&image::Rgba([150, 0, 0, 255]), image.image.get_pixel(0, 0));
+        assert_eq!(&image::Rgba([150, 0, 0, 255]), image.image.get_pixel(3, 0));
+
+        undo_op.apply(&mut image, false);
+        assert_eq!(original, image.image);
+    }
+
+    #[test]
+    fn test_color_gradient_undo_bounded_by_region() {
+        let original = image::RgbaImage::from_pixel(100, 100, image::Rgba([0, 0, 0, 255]));
+        let mut image = TestImage::new(original.clone()).with_region(Some(Region::new(10, 20, 5, 4)));
+
+        let op = ImageOperation::ColorGradient {
+            start_x: 0,
+            start_y: 0,
+            end_x: 100,
+            end_y: 0,
+            first_color: image::Rgba([255, 255, 255, 255]),
+            second_color: image::Rgba([255, 0, 0, 255]),
+            gradient_type: ColorGradientType::Linear,
+            dither: false
+        };
+
+        let undo_op = op.apply(&mut image, true).unwrap();
+        match &undo_op {
+            ImageOperation::SetImage { start_x, start_y, image, .. } => {
+                assert_eq!((10, 20), (*start_x, *start_y));
+                assert_eq!((5, 4), image.dimensions());
+            }
+            _ => panic!("Expected a set image operation.")
+        }
+
+        assert_ne!(original, image.image);
+        assert_eq!(&image::Rgba([0, 0, 0, 255]), image.image.get_pixel(9, 20));
+
+        undo_op.apply(&mut image, false);
+        assert_eq!(original, image.image);
+    }
+
+    #[test]
+    fn test_filter_undo_bounded_by_region() {
+        let original = image::RgbaImage::from_fn(100, 100, |x, y| image::Rgba([x as u8, y as u8, 200, 255]));
+        let mut image = TestImage::new(original.clone()).with_region(Some(Region::new(30, 40, 8, 6)));
+
+        let undo_op = ImageOperation::Grayscale { luminance_weights: None }.apply(&mut image, true).unwrap();
+        match &undo_op {
+            ImageOperation::SetImage { start_x, start_y, image, .. } => {
+                assert_eq!((30, 40), (*start_x, *start_y));
+                assert_eq!((8, 6), image.dimensions());
+            }
+            _ => panic!("Expected a set image operation.")
+        }
+
+        assert_ne!(original, image.image);
+        assert_eq!(original.get_pixel(29, 40), image.image.get_pixel(29, 40));
+
+        undo_op.apply(&mut image, false);
+        assert_eq!(original, image.image);
+    }
+
+    #[test]
+    fn test_color_gradient_unclipped_matches_clipped() {
+        let op = ImageOperation::ColorGradient {
+            start_x: 5,
+            start_y: 10,
+            end_x: 40,
+            end_y: 30,
+            first_color: image::Rgba([255, 255, 255, 255]),
+            second_color: image::Rgba([0, 0, 255, 128]),
+            gradient_type: ColorGradientType::Radial,
+            dither: true
+        };
+
+        // Without a region the rows are written directly to the buffer
+        let mut unclipped = TestImage::filled(50, 40, image::Rgba([255, 0, 0, 255]));
+        op.apply(&mut unclipped, false);
+
+        let mut clipped = TestImage::filled(50, 40, image::Rgba([255, 0, 0, 255])).with_region(Some(Region::new(0, 0, 50, 40)));
+        op.apply(&mut clipped, false);
+
+        assert_eq!(clipped.image, unclipped.image);
+    }
+
+    #[test]
+    fn test_bucket_fill_inside_selection() {
+        let original = image::RgbaImage::from_pixel(40, 30, image::Rgba([0, 0, 0, 255]));
+        let mut image = TestImage::new(original.clone()).with_region(Some(Region::new(10, 5, 15, 12)));
+
+        let op = ImageOperation::BucketFill {
+            start_x: 12,
+            start_y: 8,
+            fill_color: image::Rgba([0, 255, 0, 255]),
+            tolerance: 0.0,
+            contiguous: true,
+            anti_aliased: false
+        };
+
+        let undo_op = op.apply(&mut image, true).unwrap();
+        for (x, y, pixel) in image.image.enumerate_pixels() {
+            if x >= 10 && x < 25 && y >= 5 && y < 17 {
+                assert_eq!(&image::Rgba([0, 255, 0, 255]), pixel);
+            } else {
+                assert_eq!(&image::Rgba([0, 0, 0, 255]), pixel);
+            }
+        }
+
+        undo_op.apply(&mut image, false);
+        assert_eq!(original, image.image);
+    }
+
+    #[test]
+    fn test_select_by_color_not_connected_diagonally() {
+        let image = TestImage::new(image::RgbaImage::from_fn(4, 4, |x, y| {
+            if x == y {
+                image::Rgba([255, 0, 0, 255])
+            } else {
+                image::Rgba([255, 255, 255, 255])
+            }
+        }));
+
+        // Like bucket fill, only pixels sharing an edge are connected
+        let region = select_by_color(&image, 1, 1, 0.0, true).unwrap();
+        assert_eq!(cgmath::Point2::new(1, 1), region.position);
+        assert_eq!(cgmath::Point2::new(1, 1), region.size);
+
+        let region = select_by_color(&image, 1, 1, 0.0, false).unwrap();
+        assert_eq!(cgmath::Point2::new(0, 0), region.position);
+        assert_eq!(cgmath::Point2::new(4, 4), region.size);
+    }
+
+    #[test]
+    fn test_dotted_line_single_pixel_dots() {
+        let mut image = TestImage::filled(9, 1, image::Rgba([0, 0, 0, 0]));
+
+        let op = ImageOperation::Line {
+            start_x: 0,
+            start_y: 0,
+            end_x: 8,
+            end_y: 0,
+            color: image::Rgba([255, 0, 0, 255]),
+            blend: false,
+            anti_aliased: Some(false),
+            side_half_width: 0,
+            dash: Some((1, 1))
+        };
+
+        op.apply(&mut image, false);
+        for (x, _, pixel) in image.image.enumerate_pixels() {
+            if x % 2 == 0 {
+                assert_eq!(&image::Rgba([255, 0, 0, 255]), pixel, "{}", x);
+            } else {
+                assert_eq!(&image::Rgba([0, 0, 0, 0]), pixel, "{}", x);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bucket_fill_anti_aliased_diagonal() {
+        let black = image::Rgba([0, 0, 0, 255]);
+        let white = image::Rgba([255, 255, 255, 255]);
+        let original = image::RgbaImage::from_fn(8, 8, |x, y| if x <= y { black } else { white });
+        let mut image = TestImage::new(original.clone());
+
+        let op = ImageOperation::BucketFill {
+            start_x: 0,
+            start_y: 7,
+            fill_color: image::Rgba([255, 0, 0, 255]),
+            tolerance: 0.0,
+            contiguous: true,
+            anti_aliased: true
+        };
+
+        let undo_op = op.apply(&mut image, true).unwrap();
+
+        // The staircase is cut along the diagonal, so the pixels on both sides of it are partially covered
+        for i in 0..7 {
+            let outside = image.image.get_pixel(i + 1, i);
+            assert_eq!(255, outside[0]);
+            assert!(outside[1] > 0 && outside[1] < 255, "{:?}", outside);
+        }
+
+        for i in 1..7 {
+            let inside = image.image.get_pixel(i, i);
+            assert!(inside[0] > 0 && inside[0] < 255, "{:?}", inside);
+            assert_eq!(0, inside[1]);
+        }
+
+        assert_eq!(&image::Rgba([255, 0, 0, 255]), image.image.get_pixel(0, 7));
+        assert_eq!(&image::Rgba([255, 0, 0, 255]), image.image.get_pixel(0, 0));
+        assert_eq!(&white, image.image.get_pixel(7, 0));
+
+        undo_op.apply(&mut image, false);
+        assert_eq!(original, image.image);
+    }
+
+    #[test]
+    fn test_rounded_rectangle_anti_aliased_corners() {
+        let original = image::RgbaImage::new(40, 40);
+        let mut image = TestImage::new(original.clone());
+
+        let op = ImageOperation::RoundedRectangle {
+            start_x: 5,
+            start_y: 5,
+            end_x: 34,
+            end_y: 34,
+            corner_radius: 8,
+            border_half_width: 1,
+            color: image::Rgba([0, 0, 0, 255]),
+            blend: true
+        };
+
+        let undo_op = op.apply(&mut image, true).unwrap();
+
+        assert_eq!(0, image.image.get_pixel(5, 5)[3]);
+        assert_eq!(255, image.image.get_pixel(20, 5)[3]);
+        assert_eq!(255, image.image.get_pixel(5, 20)[3]);
+
+        let corner_alphas = (0..14)
+            .flat_map(|y| (0..14).map(move |x| (x, y)))
+            .map(|(x, y)| image.image.get_pixel(x, y)[3])
+            .collect::<Vec<_>>();
+        assert!(corner_alphas.iter().any(|&alpha| alpha > 0 && alpha < 255));
+        assert!(corner_alphas.iter().any(|&alpha| alpha == 255));
+
+        // All corners are drawn the same way
+        for (x, y, pixel) in image.image.enumerate_pixels() {
+            assert_eq!(pixel[3], image.image.get_pixel(39 - x, y)[3]);
+            assert_eq!(pixel[3], image.image.get_pixel(x, 39 - y)[3]);
+        }
+
+        undo_op.apply(&mut image, false);
+        assert_eq!(original, image.image);
+    }
+
+    #[test]
+    fn test_ellipse_anti_aliased_blend() {
+        let original = image::RgbaImage::from_pixel(40, 40, image::Rgba([255, 255, 255, 255]));
+
+        let op = |blend| ImageOperation::Ellipse {
+            center_x: 20,
+            center_y: 20,
+            radius_x: 12,
+            radius_y: 7,
+            border_half_width: 1,
+            color: image::Rgba([0, 0, 0, 255]),
+            blend,
+            anti_aliased: Some(true)
+        };
+
+        let mut blended = TestImage::new(original.clone());
+        op(true).apply(&mut blended, false);
+        assert!(blended.image.pixels().all(|pixel| pixel[3] == 255));
+        assert!(blended.image.pixels().any(|pixel| pixel[0] > 0 && pixel[0] < 255));
+
+        let mut replaced = TestImage::new(original.clone());
+        let undo_op = op(false).apply(&mut replaced, true).unwrap();
+        assert!(replaced.image.pixels().any(|pixel| pixel[3] > 0 && pixel[3] < 255));
+        assert_eq!(image::Rgba([255, 255, 255, 255]), *replaced.image.get_pixel(20, 20));
+
+        undo_op.apply(&mut replaced, false);
+        assert_eq!(original, replaced.image);
+    }
+
+    #[test]
+    fn test_symmetry_around_axis() {
+        let op = ImageOperation::SetPixel { x: 1, y: 2, color: image::Rgba([255, 0, 0, 255]) };
+        let axis = Position::new(5.0, 3.5);
+
+        let pixels = match Symmetry::Both.apply(op, axis) {
+            ImageOperation::Sequential(_, ops) => {
+                ops.iter()
+                    .map(|op| match op {
+                        ImageOperation::SetPixel { x, y, .. } => (*x, *y),
+                        _ => panic!("Expected a pixel.")
+                    })
+                    .collect::<Vec<_>>()
+            }
+            _ => panic!("Expected a sequential operation.")
+        };
+
+        assert_eq!(vec![(1, 2), (8, 2), (1, 4), (8, 4)], pixels);
+
+        // The axis lies halfway between the centers of the mirrored pixels
+        assert_eq!(axis.x, ((1.0 + 0.5) + (8.0 + 0.5)) * 0.5);
+        assert_eq!(axis.y, ((2.0 + 0.5) + (4.0 + 0.5)) * 0.5);
+    }
+
+    #[test]
+    fn test_symmetry_unsupported_applied_once() {
+        let op = ImageOperation::BucketFill {
+            start_x: 1,
+            start_y: 2,
+            fill_color: image::Rgba([255, 0, 0, 255]),
+            tolerance: 0.0,
+            contiguous: true,
+            anti_aliased: false
+        };
+
+        match Symmetry::Both.apply(op, Position::new(5.0, 3.5)) {
+            ImageOperation::BucketFill { start_x, start_y, .. } => assert_eq!((1, 2), (start_x, start_y)),
+            op => panic!("Expected the original operation, got: {:?}.", op)
+        }
+    }
+
+    #[test]
+    fn test_posterize_two_levels() {
+        let original = image::RgbaImage::from_fn(16, 16, |x, y| image::Rgba([(x * 16) as u8, (y * 16) as u8, (x * y) as u8, 77]));
+        let mut image = TestImage::new(original.clone());
+
+        let undo_op = ImageOperation::Posterize { levels: 2 }.apply(&mut image, true).unwrap();
+        for pixel in image.image.pixels() {
+            for channel in 0..3 {
+                assert!(pixel[channel] == 0 || pixel[channel] == 255, "{:?}", pixel);
+            }
+            assert_eq!(77, pixel[3]);
+        }
+
+        undo_op.apply(&mut image, false);
+        assert_eq!(original, image.image);
+    }
+
+    #[test]
+    fn test_bucket_fill_large_uniform_image() {
+        let mut image = TestImage::filled(2000, 2000, image::Rgba([255, 255, 255, 255]));
+
+        let op = ImageOperation::BucketFill {
+            start_x: 1000,
+            start_y: 1000,
+            fill_color: image::Rgba([0, 0, 255, 255]),
+            tolerance: 0.0,
+            contiguous: true,
+            anti_aliased: false
+        };
+
+        let undo_op = op.apply(&mut image, true).unwrap();
+        assert!(image.image.pixels().all(|pixel| pixel == &image::Rgba([0, 0, 255, 255])));
+
+        undo_op.apply(&mut image, false);
+        assert!(image.image.pixels().all(|pixel| pixel == &image::Rgba([255, 255, 255, 255])));
+    }
+
+    #[test]
+    fn test_compact_undo_image_large_area() {
+        // The area of the bounding box does not fit in an u32
+        let image = TestImage::filled(1, 1, image::Rgba([0, 0, 0, 255]));
+        let mut undo_image = SparseImage::new();
+        undo_image.insert((0, 0), image::Rgba([0, 0, 0, 255]));
+        undo_image.insert((100_000, 100_000), image::Rgba([0, 0, 0, 255]));
+
+        assert!(matches!(compact_undo_image(&image, undo_image), ImageOperation::SetSparseImage { .. }));
+    }
+}
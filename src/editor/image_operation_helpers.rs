@@ -1,11 +1,11 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, HashMap};
 
 use image::{Pixel, FilterType};
 
 use cgmath::{ElementWise, Vector4, Point2, Matrix3, Transform};
 
-use crate::editor::image_operation::{ImageSource, ImageOperationSource, SparseImage, OptionalImage, ColorGradientType};
-use crate::editor::Color;
+use crate::editor::image_operation::{ImageSource, ImageOperationSource, SparseImage, OptionalImage, ColorGradientType, ChannelSelector};
+use crate::editor::{Color, Region, SelectionMask};
 
 pub fn draw_pixel<T: ImageOperationSource>(update_op: &mut T,
                                            x: i32,
@@ -27,6 +27,34 @@ pub fn draw_pixel<T: ImageOperationSource>(update_op: &mut T,
     }
 }
 
+pub fn draw_changed_pixel<T: ImageOperationSource>(update_op: &mut T,
+                                                   x: i32,
+                                                   y: i32,
+                                                   color: Color,
+                                                   blend: bool,
+                                                   undo: bool,
+                                                   undo_image: &mut SparseImage) {
+    if x >= 0 && x < update_op.width() as i32 && y >= 0 && y < update_op.height() as i32 && update_op.is_valid_pixel(x, y) {
+        let current_color = update_op.get_pixel(x as u32, y as u32);
+        let mut new_color = color;
+        if blend {
+            new_color = current_color;
+            new_color.blend(&color);
+        }
+
+        // Unchanged pixels are not part of the undo image
+        if new_color == current_color {
+            return;
+        }
+
+        if undo && !undo_image.contains_key(&(x as u32, y as u32)) {
+            undo_image.insert((x as u32, y as u32), current_color);
+        }
+
+        update_op.put_pixel(x as u32, y as u32, new_color);
+    }
+}
+
 pub fn draw_block<T: ImageOperationSource>(update_op: &mut T,
                                            center_x: i32,
                                            center_y: i32,
@@ -131,6 +159,39 @@ pub fn draw_line<F: FnMut(i32, i32, LineSegmentPart)>(x1: i32, y1: i32, x2: i32,
     }
 }
 
+pub fn dash_segments(x1: i32, y1: i32, x2: i32, y2: i32, on_length: u32, off_length: u32) -> Vec<(i32, i32, i32, i32)> {
+    let dx = (x2 - x1) as f32;
+    let dy = (y2 - y1) as f32;
+    let length = (dx * dx + dy * dy).sqrt();
+    if length < 1E-6 {
+        return vec![(x1, y1, x2, y2)];
+    }
+
+    let point_at = |distance: f32| {
+        (
+            (x1 as f32 + dx * distance / length).round() as i32,
+            (y1 as f32 + dy * distance / length).round() as i32
+        )
+    };
+
+    // Each segment covers on_length pixels along the line, followed by a gap of off_length pixels.
+    // A segment of a single pixel starts and ends at the same point, which is drawn as a dot.
+    let on_length = on_length.max(1) as f32;
+    let period = on_length + off_length as f32;
+
+    let mut segments = Vec::new();
+    let mut distance = 0.0;
+    while distance <= length {
+        let start = point_at(distance);
+        let end = point_at((distance + on_length - 1.0).min(length));
+        segments.push((start.0, start.1, end.0, end.1));
+
+        distance += period;
+    }
+
+    segments
+}
+
 pub fn draw_line_thick<T: ImageOperationSource>(update_op: &mut T,
                                                 x1: i32, y1: i32, x2: i32, y2: i32,
                                                 side_half_width: i32,
@@ -565,14 +626,22 @@ pub fn draw_circle<F: FnMut(i32, i32)>(center_x: i32, center_y: i32, radius: i32
     }
 }
 
+// When a quadrant is given, only the pixels strictly inside it are drawn (given as the signs of the offsets from the center)
 pub fn draw_circle_anti_aliased<T: ImageOperationSource>(update_op: &mut T,
                                                          center_x: i32, center_y: i32,
                                                          radius: i32,
                                                          color: Color,
                                                          blend: bool,
+                                                         quadrant: Option<(i32, i32)>,
                                                          undo: bool,
                                                          undo_image: &mut SparseImage) {
     let mut set_pixel = |x: i32, y: i32, color: Color| {
+        if let Some((direction_x, direction_y)) = quadrant {
+            if (x - center_x) * direction_x <= 0 || (y - center_y) * direction_y <= 0 {
+                return;
+            }
+        }
+
         if !(x >= 0 && x < update_op.width() as i32 && y >= 0 && y < update_op.height() as i32) {
             return;
         }
@@ -643,6 +712,7 @@ pub fn draw_circle_anti_aliased_thick<T: ImageOperationSource>(update_op: &mut T
                 radius + (border_half_width - 1) + radius_offset,
                 color,
                 radius_offset.abs() == border_half_width,
+                None,
                 undo,
                 undo_image
             );
@@ -655,12 +725,192 @@ pub fn draw_circle_anti_aliased_thick<T: ImageOperationSource>(update_op: &mut T
             radius,
             color,
             true,
+            None,
             undo,
             undo_image
         );
     }
 }
 
+pub fn draw_rounded_rectangle<T: ImageOperationSource>(update_op: &mut T,
+                                                       start_x: i32, start_y: i32,
+                                                       end_x: i32, end_y: i32,
+                                                       corner_radius: i32,
+                                                       border_half_width: i32,
+                                                       color: Color,
+                                                       blend: bool,
+                                                       undo: bool,
+                                                       undo_image: &mut SparseImage) {
+    let corner_radius = corner_radius.min((end_x - start_x) / 2).min((end_y - start_y) / 2).max(0);
+
+    let edges = [
+        (start_x + corner_radius, start_y, end_x - corner_radius, start_y),
+        (start_x + corner_radius, end_y, end_x - corner_radius, end_y),
+        (start_x, start_y + corner_radius, start_x, end_y - corner_radius),
+        (end_x, start_y + corner_radius, end_x, end_y - corner_radius)
+    ];
+
+    for (x1, y1, x2, y2) in edges.iter() {
+        draw_line_thick(update_op, *x1, *y1, *x2, *y2, border_half_width, color, blend, undo, undo_image);
+    }
+
+    let corners = [
+        (start_x + corner_radius, start_y + corner_radius, -1, -1),
+        (end_x - corner_radius, start_y + corner_radius, 1, -1),
+        (start_x + corner_radius, end_y - corner_radius, -1, 1),
+        (end_x - corner_radius, end_y - corner_radius, 1, 1)
+    ];
+
+    // The corners are drawn as anti-aliased rings like circles, where only the outermost rings fade out.
+    // The rows and columns through the corner centers are covered by the edges.
+    for (center_x, center_y, direction_x, direction_y) in corners.iter() {
+        for radius_offset in -border_half_width..(border_half_width + 1) {
+            draw_circle_anti_aliased(
+                update_op,
+                *center_x,
+                *center_y,
+                corner_radius + radius_offset,
+                color,
+                radius_offset.abs() == border_half_width,
+                Some((*direction_x, *direction_y)),
+                undo,
+                undo_image
+            );
+        }
+    }
+}
+
+pub fn draw_ellipse<F: FnMut(i32, i32)>(center_x: i32, center_y: i32, radius_x: i32, radius_y: i32, filled: bool, mut set_pixel: F) {
+    let mut line_drawn = HashSet::new();
+    let mut draw = |x: i32, y: i32| {
+        if filled {
+            for row_y in [center_y - y, center_y + y].iter() {
+                if line_drawn.insert(*row_y) {
+                    for row_x in (center_x - x)..(center_x + x + 1) {
+                        set_pixel(row_x, *row_y);
+                    }
+                }
+            }
+        } else {
+            set_pixel(center_x + x, center_y + y);
+            set_pixel(center_x - x, center_y + y);
+            set_pixel(center_x + x, center_y - y);
+            set_pixel(center_x - x, center_y - y);
+        }
+    };
+
+    // Midpoint ellipse algorithm
+    let radius_x_squared = radius_x as i64 * radius_x as i64;
+    let radius_y_squared = radius_y as i64 * radius_y as i64;
+
+    let mut x = 0i64;
+    let mut y = radius_y as i64;
+    let mut dx = 0i64;
+    let mut dy = 2 * radius_x_squared * y;
+
+    // Region 1: the slope is less than one
+    let mut d1 = radius_y_squared as f64 - (radius_x_squared * radius_y as i64) as f64 + 0.25 * radius_x_squared as f64;
+    while dx < dy {
+        draw(x as i32, y as i32);
+
+        x += 1;
+        dx += 2 * radius_y_squared;
+        if d1 < 0.0 {
+            d1 += (dx + radius_y_squared) as f64;
+        } else {
+            y -= 1;
+            dy -= 2 * radius_x_squared;
+            d1 += (dx - dy + radius_y_squared) as f64;
+        }
+    }
+
+    // Region 2: the slope is greater than one
+    let mut d2 = radius_y_squared as f64 * (x as f64 + 0.5).powi(2)
+        + radius_x_squared as f64 * ((y - 1) as f64).powi(2)
+        - (radius_x_squared * radius_y_squared) as f64;
+    while y >= 0 {
+        draw(x as i32, y as i32);
+
+        y -= 1;
+        dy -= 2 * radius_x_squared;
+        if d2 > 0.0 {
+            d2 += (radius_x_squared - dy) as f64;
+        } else {
+            x += 1;
+            dx += 2 * radius_y_squared;
+            d2 += (dx - dy + radius_x_squared) as f64;
+        }
+    }
+}
+
+pub fn draw_ellipse_anti_aliased<T: ImageOperationSource>(update_op: &mut T,
+                                                          center_x: i32, center_y: i32,
+                                                          radius_x: i32, radius_y: i32,
+                                                          border_half_width: i32,
+                                                          color: Color,
+                                                          blend: bool,
+                                                          undo: bool,
+                                                          undo_image: &mut SparseImage) {
+    let radius_x = radius_x.max(1) as f32;
+    let radius_y = radius_y.max(1) as f32;
+    let half_width = border_half_width as f32 + 0.5;
+    let extent_x = (radius_x + half_width).ceil() as i32 + 1;
+    let extent_y = (radius_y + half_width).ceil() as i32 + 1;
+
+    for offset_y in -extent_y..(extent_y + 1) {
+        for offset_x in -extent_x..(extent_x + 1) {
+            let x = offset_x as f32;
+            let y = offset_y as f32;
+
+            // First order approximation of the distance to the ellipse
+            let value = (x * x) / (radius_x * radius_x) + (y * y) / (radius_y * radius_y) - 1.0;
+            let gradient_x = 2.0 * x / (radius_x * radius_x);
+            let gradient_y = 2.0 * y / (radius_y * radius_y);
+            let gradient_length = (gradient_x * gradient_x + gradient_y * gradient_y).sqrt();
+            let distance = if gradient_length > 0.0 { value.abs() / gradient_length } else { radius_x.min(radius_y) };
+
+            let coverage = (half_width - distance + 0.5).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                let mut pixel_color = color;
+                pixel_color[3] = (color[3] as f32 * coverage).round() as u8;
+                draw_pixel(update_op, center_x + offset_x, center_y + offset_y, pixel_color, blend, undo, undo_image);
+            }
+        }
+    }
+}
+
+pub fn flip<T: ImageOperationSource>(update_op: &mut T, horizontal: bool, start_x: i32, start_y: i32, end_x: i32, end_y: i32) {
+    let start_x = start_x.clamp(0, update_op.width() as i32) as u32;
+    let start_y = start_y.clamp(0, update_op.height() as i32) as u32;
+    let end_x = end_x.clamp(0, update_op.width() as i32) as u32;
+    let end_y = end_y.clamp(0, update_op.height() as i32) as u32;
+
+    if start_x >= end_x || start_y >= end_y {
+        return;
+    }
+
+    let mut swap = |update_op: &mut T, x1: u32, y1: u32, x2: u32, y2: u32| {
+        let pixel1 = update_op.get_pixel(x1, y1);
+        let pixel2 = update_op.get_pixel(x2, y2);
+        update_op.put_pixel(x1, y1, pixel2);
+        update_op.put_pixel(x2, y2, pixel1);
+    };
+
+    if horizontal {
+        for y in start_y..end_y {
+            for x in start_x..(start_x + (end_x - start_x) / 2) {
+                swap(update_op, x, y, start_x + end_x - 1 - x, y);
+            }
+        }
+    } else {
+        for y in start_y..(start_y + (end_y - start_y) / 2) {
+            for x in start_x..end_x {
+                swap(update_op, x, y, x, start_y + end_y - 1 - y);
+            }
+        }
+    }
+}
+
 pub fn fill_rectangle<F: FnMut(i32, i32)>(min_x: i32, min_y: i32, max_x: i32, max_y: i32, mut set_pixel: F) {
     for y in min_y..max_y + 1 {
         for x in min_x..max_x + 1 {
@@ -673,48 +923,309 @@ pub fn bucket_fill<T: ImageOperationSource>(update_op: &mut T,
                                             start_x: i32, start_y: i32,
                                             fill_color: Color,
                                             tolerance: f32,
+                                            contiguous: bool,
+                                            anti_aliased: bool,
                                             undo: bool,
                                             undo_image: &mut OptionalImage) {
     let width = update_op.width() as i32;
     let height = update_op.height() as i32;
 
-    if start_x >= 0 && start_x < width && start_y >= 0 && start_y < height {
-        let ref_color = update_op.get_pixel(start_x as u32, start_y as u32);
+    if start_x >= 0 && start_x < width && start_y >= 0 && start_y < height && update_op.is_valid_pixel(start_x, start_y) {
+        let filled = bucket_fill_mask(update_op, start_x, start_y, tolerance, contiguous);
+
+        for y in 0..height {
+            for x in 0..width {
+                let coverage = if anti_aliased {
+                    fill_coverage(&filled, width, height, x, y)
+                } else if filled[(y * width + x) as usize] {
+                    1.0
+                } else {
+                    0.0
+                };
+
+                if coverage <= 0.0 || !update_op.is_valid_pixel(x, y) {
+                    continue;
+                }
+
+                let mut color = fill_color;
+                color[3] = (color[3] as f32 * coverage).round() as u8;
+
+                if undo && !undo_image.contains_key(&(x as u32, y as u32)) {
+                    undo_image.insert((x as u32, y as u32), update_op.get_pixel(x as u32, y as u32));
+                }
+
+                update_op.put_pixel_with_blend(x as u32, y as u32, color);
+            }
+        }
+    }
+}
+
+fn bucket_fill_mask<T: ImageOperationSource>(update_op: &T,
+                                             start_x: i32, start_y: i32,
+                                             tolerance: f32,
+                                             contiguous: bool) -> Vec<bool> {
+    let ref_color = update_op.get_pixel(start_x as u32, start_y as u32);
+
+    // The flood stops at the selection boundary instead of only being clipped when written
+    fill_mask(
+        update_op.width() as i32, update_op.height() as i32,
+        start_x, start_y,
+        contiguous,
+        |x, y| {
+            update_op.is_valid_pixel(x, y)
+            && color_within_tolerance(&ref_color, tolerance, &update_op.get_pixel(x as u32, y as u32))
+        }
+    )
+}
+
+// The pixels matching the predicate, either in the whole image or only those 4-connected to the start pixel
+fn fill_mask<F: Fn(i32, i32) -> bool>(width: i32, height: i32,
+                                      start_x: i32, start_y: i32,
+                                      contiguous: bool,
+                                      matches: F) -> Vec<bool> {
+    let mut filled = vec![false; (width * height) as usize];
+    if !contiguous {
+        for y in 0..height {
+            for x in 0..width {
+                if matches(x, y) {
+                    filled[(y * width + x) as usize] = true;
+                }
+            }
+        }
+    } else {
+        let is_fillable = |filled: &[bool], x: i32, y: i32| {
+            !filled[(y * width + x) as usize] && matches(x, y)
+        };
 
+        // Scanline fill: fill the whole horizontal span of a seed, then seed each span above and below it
         let mut stack = Vec::new();
-        stack.push((start_x, start_y, ref_color));
+        stack.push((start_x, start_y));
 
-        let mut visited = vec![false; (update_op.width() * update_op.height()) as usize];
-        while let Some((x, y, color)) = stack.pop() {
-            if undo && !undo_image.contains_key(&(x as u32, y as u32)) {
-                undo_image.insert((x as u32, y as u32), color);
+        while let Some((x, y)) = stack.pop() {
+            if !is_fillable(&filled, x, y) {
+                continue;
             }
 
-            update_op.put_pixel_with_blend(x as u32, y as u32, fill_color);
-            visited[(y * width + x) as usize] = true;
-
-            for ny in (y - 1)..(y + 2) {
-                for nx in (x - 1)..(x + 2) {
-                    if nx >= 0 && nx < width && ny >= 0 && ny < height {
-                        if !visited[(ny * width + nx) as usize] {
-                            let color = update_op.get_pixel(nx as u32, ny as u32);
-                            if color_within_tolerance(&ref_color, tolerance, &color) {
-                                stack.push((nx, ny, color));
-                            }
+            let mut left = x;
+            while left > 0 && is_fillable(&filled, left - 1, y) {
+                left -= 1;
+            }
+
+            let mut right = x;
+            while right < width - 1 && is_fillable(&filled, right + 1, y) {
+                right += 1;
+            }
+
+            for fill_x in left..(right + 1) {
+                filled[(y * width + fill_x) as usize] = true;
+            }
+
+            for neighbor_y in [y - 1, y + 1].iter().cloned() {
+                if neighbor_y < 0 || neighbor_y >= height {
+                    continue;
+                }
+
+                let mut in_span = false;
+                for neighbor_x in left..(right + 1) {
+                    if is_fillable(&filled, neighbor_x, neighbor_y) {
+                        if !in_span {
+                            stack.push((neighbor_x, neighbor_y));
+                            in_span = true;
                         }
+                    } else {
+                        in_span = false;
+                    }
+                }
+            }
+        }
+    }
+
+    filled
+}
+
+// The fraction of the pixel covered by the filled area when its outline is traced through the midpoints
+// between filled and unfilled pixel centers (marching squares). Each quadrant of the pixel whose two
+// neighbors both differ from the pixel is cut in half by the outline. The image border is not an edge.
+fn fill_coverage(filled: &[bool], width: i32, height: i32, x: i32, y: i32) -> f32 {
+    let is_filled = |x: i32, y: i32| filled[(y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize];
+
+    let center = is_filled(x, y);
+    let mut coverage = if center { 1.0 } else { 0.0 };
+    for &(dx, dy) in &[(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+        if is_filled(x + dx, y) != center && is_filled(x, y + dy) != center {
+            coverage += if center { -0.125 } else { 0.125 };
+        }
+    }
+
+    coverage
+}
+
+pub fn select_by_color<T: ImageSource>(image: &T,
+                                       start_x: i32, start_y: i32,
+                                       tolerance: f32,
+                                       contiguous: bool) -> Option<Region> {
+    let width = image.width() as i32;
+    let height = image.height() as i32;
+
+    if !(start_x >= 0 && start_x < width && start_y >= 0 && start_y < height) {
+        return None;
+    }
+
+    let ref_color = image.get_pixel(start_x as u32, start_y as u32);
+    let is_similar = |color: &Color| {
+        // Unlike for filling, transparent pixels are only similar to other transparent pixels
+        if ref_color[3] == 0 || color[3] == 0 {
+            ref_color[3] == 0 && color[3] == 0
+        } else {
+            color_within_tolerance(&ref_color, tolerance, color)
+        }
+    };
+
+    let selected = fill_mask(
+        width, height,
+        start_x, start_y,
+        contiguous,
+        |x, y| is_similar(&image.get_pixel(x as u32, y as u32))
+    );
+
+    let mut min_x = width;
+    let mut min_y = height;
+    let mut max_x = -1;
+    let mut max_y = -1;
+    for y in 0..height {
+        for x in 0..width {
+            if selected[(y * width + x) as usize] {
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+            }
+        }
+    }
+
+    if max_x < min_x || max_y < min_y {
+        return None;
+    }
+
+    let mask_width = max_x - min_x + 1;
+    let mask_height = max_y - min_y + 1;
+    let mut mask = Vec::with_capacity((mask_width * mask_height) as usize);
+    for y in min_y..(max_y + 1) {
+        for x in min_x..(max_x + 1) {
+            mask.push(selected[(y * width + x) as usize]);
+        }
+    }
+
+    Some(
+        Region::new(min_x, min_y, mask_width, mask_height)
+            .with_mask(Some(SelectionMask::new(mask_width as u32, mask_height as u32, mask)))
+    )
+}
+
+pub fn select_polygon(points: &[(f32, f32)]) -> Option<Region> {
+    if points.len() < 3 {
+        return None;
+    }
+
+    let min_x = points.iter().map(|point| point.0).fold(f32::INFINITY, f32::min).floor() as i32;
+    let min_y = points.iter().map(|point| point.1).fold(f32::INFINITY, f32::min).floor() as i32;
+    let max_x = points.iter().map(|point| point.0).fold(f32::NEG_INFINITY, f32::max).ceil() as i32;
+    let max_y = points.iter().map(|point| point.1).fold(f32::NEG_INFINITY, f32::max).ceil() as i32;
+
+    let mask_width = max_x - min_x;
+    let mask_height = max_y - min_y;
+    if mask_width <= 0 || mask_height <= 0 {
+        return None;
+    }
+
+    let mut mask = Vec::with_capacity((mask_width * mask_height) as usize);
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            // Even-odd rule sampled at the pixel center
+            let center_x = x as f32 + 0.5;
+            let center_y = y as f32 + 0.5;
+
+            let mut inside = false;
+            let mut j = points.len() - 1;
+            for i in 0..points.len() {
+                let (x_i, y_i) = points[i];
+                let (x_j, y_j) = points[j];
+                if (y_i > center_y) != (y_j > center_y)
+                    && center_x < (x_j - x_i) * (center_y - y_i) / (y_j - y_i) + x_i {
+                    inside = !inside;
+                }
+
+                j = i;
+            }
+
+            mask.push(inside);
+        }
+    }
+
+    Some(
+        Region::new(min_x, min_y, mask_width, mask_height)
+            .with_mask(Some(SelectionMask::new(mask_width as u32, mask_height as u32, mask)))
+    )
+}
+
+pub fn feather_mask(image: &mut image::RgbaImage, region: &Region, radius: i32) {
+    if radius <= 0 {
+        return;
+    }
+
+    let width = image.width() as i32;
+    let height = image.height() as i32;
+
+    let mut inside = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            inside.push(region.contains(region.left() + x, region.top() + y));
+        }
+    }
+
+    let is_inside = |x: i32, y: i32| {
+        x >= 0 && x < width && y >= 0 && y < height && inside[(y * width + x) as usize]
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            if !is_inside(x, y) {
+                continue;
+            }
+
+            // Distance to the closest pixel outside of the selection, limited by the radius
+            let mut min_distance_squared = (radius * radius) as f32;
+            for offset_y in -radius..(radius + 1) {
+                for offset_x in -radius..(radius + 1) {
+                    if !is_inside(x + offset_x, y + offset_y) {
+                        let distance_squared = (offset_x * offset_x + offset_y * offset_y) as f32;
+                        min_distance_squared = min_distance_squared.min(distance_squared);
                     }
                 }
             }
+
+            let factor = (min_distance_squared.sqrt() / radius as f32).min(1.0);
+            let pixel = image.get_pixel_mut(x as u32, y as u32);
+            pixel[3] = (pixel[3] as f32 * factor).round() as u8;
         }
     }
 }
 
+const BAYER_MATRIX_4X4: [[u8; 4]; 4] = [
+    [0, 8, 2, 10],
+    [12, 4, 14, 6],
+    [3, 11, 1, 9],
+    [15, 7, 13, 5]
+];
+
 pub fn color_gradient<T: ImageOperationSource>(update_op: &mut T,
                                                start_x: i32, start_y: i32,
                                                end_x: i32, end_y: i32,
                                                first_color: Color,
                                                second_color: Color,
-                                               gradient_type: ColorGradientType) {
+                                               gradient_type: ColorGradientType,
+                                               dither: bool,
+                                               bounds: (i32, i32, i32, i32)) {
     let first_color = Vector4::new(first_color[0] as f32, first_color[1] as f32, first_color[2] as f32, first_color[3] as f32);
     let second_color = Vector4::new(second_color[0] as f32, second_color[1] as f32, second_color[2] as f32, second_color[3] as f32);
 
@@ -735,23 +1246,174 @@ pub fn color_gradient<T: ImageOperationSource>(update_op: &mut T,
         ColorGradientType::Radial => calc_distance(end_x, end_y)
     };
 
-    for y in 0..update_op.height() {
-        for x in 0..update_op.width() {
-            let distance = calc_distance(x as i32, y as i32);
+    let gradient_color = |x: u32, y: u32| {
+        let distance = calc_distance(x as i32, y as i32);
 
-            let factor = distance / max_distance;
-            let color = factor * first_color + (1.0 - factor) * second_color;
-            update_op.put_pixel_with_blend(
-                x,
-                y,
-                image::Rgba([
-                    color.x.clamp(0.0, 255.0) as u8,
-                    color.y.clamp(0.0, 255.0) as u8,
-                    color.z.clamp(0.0, 255.0) as u8,
-                    color.w.clamp(0.0, 255.0) as u8
-                ])
-            );
+        let factor = distance / max_distance;
+        let mut color = factor * first_color + (1.0 - factor) * second_color;
+        if dither {
+            // Ordered dithering: spread the quantization error using the Bayer threshold of the pixel
+            let threshold = (BAYER_MATRIX_4X4[(y % 4) as usize][(x % 4) as usize] as f32 + 0.5) / 16.0;
+            color = color.map(|channel| (channel + threshold).floor());
         }
+
+        image::Rgba([
+            color.x.clamp(0.0, 255.0) as u8,
+            color.y.clamp(0.0, 255.0) as u8,
+            color.z.clamp(0.0, 255.0) as u8,
+            color.w.clamp(0.0, 255.0) as u8
+        ])
+    };
+
+    let (min_x, min_y, max_x, max_y) = bounds;
+    if min_x >= max_x || min_y >= max_y {
+        return;
+    }
+
+    #[cfg(feature = "parallel")]
+    {
+        let row_length = update_op.width() as usize * 4;
+        if let Some(pixels) = update_op.unclipped_pixels_mut() {
+            use rayon::prelude::*;
+
+            // The rows are disjoint parts of the buffer, so each row can be written by its own thread
+            pixels
+                .par_chunks_mut(row_length)
+                .enumerate()
+                .filter(|(y, _)| *y as i32 >= min_y && (*y as i32) < max_y)
+                .for_each(|(y, row)| {
+                    for x in min_x..max_x {
+                        let offset = x as usize * 4;
+                        Color::from_slice_mut(&mut row[offset..offset + 4]).blend(&gradient_color(x as u32, y as u32));
+                    }
+                });
+
+            return;
+        }
+    }
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            if update_op.is_valid_pixel(x, y) {
+                update_op.put_pixel_with_blend(x as u32, y as u32, gradient_color(x as u32, y as u32));
+            }
+        }
+    }
+}
+
+pub fn replace_color_line<T: ImageOperationSource>(update_op: &mut T,
+                                                   x1: i32, y1: i32, x2: i32, y2: i32,
+                                                   side_half_width: i32,
+                                                   from_color: Color,
+                                                   to_color: Color,
+                                                   tolerance: f32,
+                                                   undo: bool,
+                                                   undo_image: &mut SparseImage) {
+    let width = update_op.width() as i32;
+    let height = update_op.height() as i32;
+
+    let mut pixels_visited = HashSet::new();
+    draw_line(
+        x1,
+        y1,
+        x2,
+        y2,
+        |center_x, center_y, _| {
+            for y in (center_y - side_half_width)..(center_y + side_half_width + 1) {
+                for x in (center_x - side_half_width)..(center_x + side_half_width + 1) {
+                    if x < 0 || x >= width || y < 0 || y >= height || !pixels_visited.insert((x, y)) {
+                        continue;
+                    }
+
+                    let color = update_op.get_pixel(x as u32, y as u32);
+
+                    // Transparent pixels always pass the tolerance check, but there is nothing to replace
+                    if color[3] == 0 || !color_within_tolerance(&from_color, tolerance, &color) {
+                        continue;
+                    }
+
+                    // Keep the alpha of the original pixel so that anti-aliased edges stay smooth
+                    let mut new_color = to_color;
+                    new_color[3] = ((to_color[3] as u32 * color[3] as u32) / 255) as u8;
+                    draw_pixel(update_op, x, y, new_color, false, undo, undo_image);
+                }
+            }
+        }
+    );
+}
+
+pub fn erase_strengths(x1: i32, y1: i32, x2: i32, y2: i32,
+                       side_half_width: i32,
+                       hardness: f32) -> HashMap<(i32, i32), f32> {
+    // The strength is full within the hard core of the round brush and then falls off linearly towards the edge
+    let radius = side_half_width as f32 + 0.5;
+    let soft_width = radius * (1.0 - hardness.clamp(0.0, 1.0));
+    let strength = |distance: f32| {
+        if soft_width <= 0.0 {
+            if distance <= radius { 1.0 } else { 0.0 }
+        } else {
+            ((radius - distance) / soft_width).clamp(0.0, 1.0)
+        }
+    };
+
+    // A pixel covered by several blocks of the line only gets the strongest strength
+    let mut pixel_strengths = HashMap::new();
+    draw_line(
+        x1,
+        y1,
+        x2,
+        y2,
+        |center_x, center_y, _| {
+            for y in (center_y - side_half_width)..(center_y + side_half_width + 1) {
+                for x in (center_x - side_half_width)..(center_x + side_half_width + 1) {
+                    let distance = (((x - center_x).pow(2) + (y - center_y).pow(2)) as f32).sqrt();
+                    let pixel_strength = strength(distance);
+                    if pixel_strength <= 0.0 {
+                        continue;
+                    }
+
+                    let current_strength = pixel_strengths.entry((x, y)).or_insert(0.0f32);
+                    *current_strength = current_strength.max(pixel_strength);
+                }
+            }
+        }
+    );
+
+    pixel_strengths
+}
+
+pub fn erase_pixels<T: ImageOperationSource>(update_op: &mut T,
+                                             alpha_scales: &[((i32, i32), f32)],
+                                             color_tolerance: Option<(Color, f32)>,
+                                             undo: bool,
+                                             undo_image: &mut SparseImage) {
+    let width = update_op.width() as i32;
+    let height = update_op.height() as i32;
+
+    for &((x, y), alpha_scale) in alpha_scales {
+        if x < 0 || x >= width || y < 0 || y >= height {
+            continue;
+        }
+
+        let mut color = update_op.get_pixel(x as u32, y as u32);
+        if color[3] == 0 {
+            continue;
+        }
+
+        // When erasing by color, only pixels similar to the reference color are erased
+        if let Some((reference_color, tolerance)) = color_tolerance.as_ref() {
+            if !color_within_tolerance(reference_color, *tolerance, &color) {
+                continue;
+            }
+        }
+
+        // Reduce the existing alpha such that partially transparent areas fade out instead of being cut out
+        color[3] = (color[3] as f32 * alpha_scale.clamp(0.0, 1.0)).round() as u8;
+        if color[3] == 0 {
+            color = image::Rgba([0, 0, 0, 0]);
+        }
+
+        draw_pixel(update_op, x, y, color, false, undo, undo_image);
     }
 }
 
@@ -796,6 +1458,411 @@ pub fn sub_image<T: ImageSource>(image: &T, min_x: i32, min_y: i32, max_x: i32,
     sub_image
 }
 
+pub fn average_color<T: ImageSource>(image: &T, center_x: i32, center_y: i32, half_size: i32) -> Color {
+    let min_x = (center_x - half_size).max(0);
+    let min_y = (center_y - half_size).max(0);
+    let max_x = (center_x + half_size).min(image.width() as i32 - 1);
+    let max_y = (center_y + half_size).min(image.height() as i32 - 1);
+
+    let mut sum = [0u32; 4];
+    let mut count = 0;
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let pixel = image.get_pixel(x as u32, y as u32);
+            for i in 0..4 {
+                sum[i] += pixel[i] as u32;
+            }
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        return image::Rgba([0, 0, 0, 0]);
+    }
+
+    image::Rgba([
+        ((sum[0] as f32 / count as f32).round()) as u8,
+        ((sum[1] as f32 / count as f32).round()) as u8,
+        ((sum[2] as f32 / count as f32).round()) as u8,
+        ((sum[3] as f32 / count as f32).round()) as u8
+    ])
+}
+
+pub fn sub_image_region<T: ImageSource>(image: &T, region: &Region) -> image::RgbaImage {
+    let mut sub_image = sub_image(image, region.left(), region.top(), region.right(), region.bottom());
+
+    if region.mask.is_some() {
+        for (x, y, pixel) in sub_image.enumerate_pixels_mut() {
+            if !region.contains(region.left() + x as i32, region.top() + y as i32) {
+                *pixel = image::Rgba([0, 0, 0, 0]);
+            }
+        }
+    }
+
+    sub_image
+}
+
+pub fn fill_region<T: ImageOperationSource>(update_op: &mut T, region: &Region, color: Color, blend: bool) {
+    let min_x = region.left().max(0);
+    let min_y = region.top().max(0);
+    let max_x = region.right().min(update_op.width() as i32);
+    let max_y = region.bottom().min(update_op.height() as i32);
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            if region.contains(x, y) {
+                if blend {
+                    update_op.put_pixel_with_blend(x as u32, y as u32, color);
+                } else {
+                    update_op.put_pixel(x as u32, y as u32, color);
+                }
+            }
+        }
+    }
+}
+
+pub struct Histogram {
+    pub luminance: [u32; 256],
+    pub red: [u32; 256],
+    pub green: [u32; 256],
+    pub blue: [u32; 256]
+}
+
+pub fn histogram<T: ImageSource>(image: &T, region: Option<&Region>) -> Histogram {
+    let mut histogram = Histogram {
+        luminance: [0; 256],
+        red: [0; 256],
+        green: [0; 256],
+        blue: [0; 256]
+    };
+
+    let (min_x, min_y, max_x, max_y) = match region {
+        Some(region) => (region.left(), region.top(), region.right(), region.bottom()),
+        None => (0, 0, image.width() as i32, image.height() as i32)
+    };
+
+    let min_x = min_x.max(0) as u32;
+    let min_y = min_y.max(0) as u32;
+    let max_x = (max_x.max(0) as u32).min(image.width());
+    let max_y = (max_y.max(0) as u32).min(image.height());
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let color = image.get_pixel(x, y);
+            if color[3] == 0 {
+                continue;
+            }
+
+            let luminance = 0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32;
+            histogram.luminance[(luminance.round() as usize).min(255)] += 1;
+            histogram.red[color[0] as usize] += 1;
+            histogram.green[color[1] as usize] += 1;
+            histogram.blue[color[2] as usize] += 1;
+        }
+    }
+
+    histogram
+}
+
+pub fn apply_lut<T: ImageOperationSource>(update_op: &mut T, lut: &[u8; 256], channel: ChannelSelector) {
+    for y in 0..update_op.height() {
+        for x in 0..update_op.width() {
+            let mut color = update_op.get_pixel(x, y);
+
+            match channel {
+                ChannelSelector::All => {
+                    color[0] = lut[color[0] as usize];
+                    color[1] = lut[color[1] as usize];
+                    color[2] = lut[color[2] as usize];
+                }
+                ChannelSelector::Red => {
+                    color[0] = lut[color[0] as usize];
+                }
+                ChannelSelector::Green => {
+                    color[1] = lut[color[1] as usize];
+                }
+                ChannelSelector::Blue => {
+                    color[2] = lut[color[2] as usize];
+                }
+            }
+
+            update_op.put_pixel(x, y, color);
+        }
+    }
+}
+
+pub fn grayscale<T: ImageOperationSource>(update_op: &mut T, luminance_weights: (f32, f32, f32)) {
+    let (weight_red, weight_green, weight_blue) = luminance_weights;
+
+    for y in 0..update_op.height() {
+        for x in 0..update_op.width() {
+            let mut color = update_op.get_pixel(x, y);
+            let luma = weight_red * color[0] as f32 + weight_green * color[1] as f32 + weight_blue * color[2] as f32;
+            let luma = luma.round().max(0.0).min(255.0) as u8;
+
+            color[0] = luma;
+            color[1] = luma;
+            color[2] = luma;
+            update_op.put_pixel(x, y, color);
+        }
+    }
+}
+
+pub fn brightness_contrast<T: ImageOperationSource>(update_op: &mut T, brightness: i32, contrast: f32) {
+    let contrast = (contrast * 2.55).max(-255.0).min(255.0);
+    let contrast_factor = (259.0 * (contrast + 255.0)) / (255.0 * (259.0 - contrast));
+
+    let mut lut = [0; 256];
+    for i in 0..256 {
+        let value = contrast_factor * (i as f32 - 128.0) + 128.0 + brightness as f32;
+        lut[i] = value.round().max(0.0).min(255.0) as u8;
+    }
+
+    apply_lut(update_op, &lut, ChannelSelector::All);
+}
+
+pub fn posterize<T: ImageOperationSource>(update_op: &mut T, levels: u8) {
+    let steps = (levels.max(2) - 1) as f32;
+
+    let mut lut = [0; 256];
+    for i in 0..256 {
+        let level = (i as f32 * steps / 255.0).round();
+        lut[i] = (level * 255.0 / steps).round() as u8;
+    }
+
+    apply_lut(update_op, &lut, ChannelSelector::All);
+}
+
+pub fn threshold<T: ImageOperationSource>(update_op: &mut T, level: u8) {
+    for y in 0..update_op.height() {
+        for x in 0..update_op.width() {
+            let mut color = update_op.get_pixel(x, y);
+            let luma = 0.2126 * color[0] as f32 + 0.7152 * color[1] as f32 + 0.0722 * color[2] as f32;
+            let value = if luma > level as f32 { 255 } else { 0 };
+
+            color[0] = value;
+            color[1] = value;
+            color[2] = value;
+            update_op.put_pixel(x, y, color);
+        }
+    }
+}
+
+pub fn levels<T: ImageOperationSource>(update_op: &mut T, in_black: u8, in_white: u8, out_black: u8, out_white: u8, gamma: f32) {
+    let in_black = in_black as f32;
+    let in_white = (in_white as f32).max(in_black + 1.0);
+    let out_black = out_black as f32;
+    let out_white = out_white as f32;
+    let inverse_gamma = 1.0 / gamma.max(0.01);
+
+    let mut lut = [0; 256];
+    for i in 0..256 {
+        let normalized = ((i as f32 - in_black) / (in_white - in_black)).max(0.0).min(1.0);
+        let value = out_black + (out_white - out_black) * normalized.powf(inverse_gamma);
+        lut[i] = value.round().max(0.0).min(255.0) as u8;
+    }
+
+    apply_lut(update_op, &lut, ChannelSelector::All);
+}
+
+pub fn gaussian_blur<T: ImageOperationSource>(update_op: &mut T, radius: f32) {
+    if radius <= 0.0 {
+        return;
+    }
+
+    let width = update_op.width() as i32;
+    let height = update_op.height() as i32;
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    let half_size = (radius * 3.0).ceil() as i32;
+    let sigma = radius;
+    let mut kernel = (-half_size..=half_size)
+        .map(|offset| (-((offset * offset) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let kernel_sum = kernel.iter().sum::<f32>();
+    for weight in &mut kernel {
+        *weight /= kernel_sum;
+    }
+
+    let mut source = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let color = update_op.get_pixel(x as u32, y as u32);
+            source.push([color[0] as f32, color[1] as f32, color[2] as f32, color[3] as f32]);
+        }
+    }
+
+    let blur_horizontal = |y: usize, row: &mut [[f32; 4]]| {
+        let y = y as i32;
+        for x in 0..width {
+            let mut sum = [0.0; 4];
+            for (index, weight) in kernel.iter().enumerate() {
+                let sample_x = (x + index as i32 - half_size).max(0).min(width - 1);
+                let sample = &source[(y * width + sample_x) as usize];
+                for channel in 0..4 {
+                    sum[channel] += weight * sample[channel];
+                }
+            }
+
+            row[x as usize] = sum;
+        }
+    };
+
+    let mut horizontal = vec![[0.0; 4]; (width * height) as usize];
+    for_each_row(&mut horizontal, width as usize, blur_horizontal);
+
+    let blur_vertical = |y: usize, row: &mut [[f32; 4]]| {
+        let y = y as i32;
+        for x in 0..width {
+            let mut sum = [0.0; 4];
+            for (index, weight) in kernel.iter().enumerate() {
+                let sample_y = (y + index as i32 - half_size).max(0).min(height - 1);
+                let sample = &horizontal[(sample_y * width + x) as usize];
+                for channel in 0..4 {
+                    sum[channel] += weight * sample[channel];
+                }
+            }
+
+            row[x as usize] = sum;
+        }
+    };
+
+    let mut blurred = vec![[0.0; 4]; (width * height) as usize];
+    for_each_row(&mut blurred, width as usize, blur_vertical);
+
+    for y in 0..height {
+        for x in 0..width {
+            let sum = &blurred[(y * width + x) as usize];
+            let mut color = update_op.get_pixel(x as u32, y as u32);
+            for channel in 0..4 {
+                color[channel] = sum[channel].round().max(0.0).min(255.0) as u8;
+            }
+
+            update_op.put_pixel(x as u32, y as u32, color);
+        }
+    }
+}
+
+// Rows only read from other buffers, so each row can be computed by its own thread
+fn for_each_row<F: Fn(usize, &mut [[f32; 4]]) + Sync + Send>(buffer: &mut [[f32; 4]], row_length: usize, compute_row: F) {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        buffer.par_chunks_mut(row_length).enumerate().for_each(|(y, row)| compute_row(y, row));
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        buffer.chunks_mut(row_length).enumerate().for_each(|(y, row)| compute_row(y, row));
+    }
+}
+
+pub fn hue_saturation<T: ImageOperationSource>(update_op: &mut T, hue_shift: f64, saturation_scale: f64, value_scale: f64) {
+    for y in 0..update_op.height() {
+        for x in 0..update_op.width() {
+            let color = update_op.get_pixel(x, y);
+            let (hue, saturation, value) = rgb_to_hsv(color);
+
+            let hue = (hue + hue_shift).rem_euclid(360.0);
+            let saturation = (saturation * saturation_scale).max(0.0).min(100.0);
+            let value = (value * value_scale).max(0.0).min(100.0);
+
+            if let Some(mut new_color) = hsv_to_rgb(hue, saturation, value) {
+                new_color[3] = color[3];
+                update_op.put_pixel(x, y, new_color);
+            }
+        }
+    }
+}
+
+pub fn curve_lut(points: &[(u8, u8)]) -> [u8; 256] {
+    let mut lut = [0; 256];
+    for i in 0..256 {
+        lut[i] = i as u8;
+    }
+
+    let mut points = points.to_vec();
+    points.sort_by_key(|point| point.0);
+    points.dedup_by_key(|point| point.0);
+
+    if points.is_empty() {
+        return lut;
+    }
+
+    let points = points
+        .iter()
+        .map(|&(x, y)| (x as f32, y as f32))
+        .collect::<Vec<_>>();
+
+    // Monotone cubic interpolation (Fritsch-Carlson), so the curve never overshoots between control points
+    let secants = points
+        .windows(2)
+        .map(|segment| (segment[1].1 - segment[0].1) / (segment[1].0 - segment[0].0))
+        .collect::<Vec<_>>();
+
+    let mut tangents = vec![0.0; points.len()];
+    if !secants.is_empty() {
+        tangents[0] = secants[0];
+        tangents[points.len() - 1] = secants[secants.len() - 1];
+    }
+
+    for index in 1..secants.len() {
+        let (before, after) = (secants[index - 1], secants[index]);
+        tangents[index] = if before * after <= 0.0 { 0.0 } else { (before + after) * 0.5 };
+    }
+
+    for (index, &secant) in secants.iter().enumerate() {
+        if secant == 0.0 {
+            tangents[index] = 0.0;
+            tangents[index + 1] = 0.0;
+            continue;
+        }
+
+        let alpha = tangents[index] / secant;
+        let beta = tangents[index + 1] / secant;
+        let magnitude = alpha * alpha + beta * beta;
+        if magnitude > 9.0 {
+            let scale = 3.0 / magnitude.sqrt();
+            tangents[index] = scale * alpha * secant;
+            tangents[index + 1] = scale * beta * secant;
+        }
+    }
+
+    for i in 0..256 {
+        let input = i as f32;
+
+        let (first_x, first_y) = points[0];
+        let (last_x, last_y) = points[points.len() - 1];
+        let output = if input <= first_x {
+            first_y
+        } else if input >= last_x {
+            last_y
+        } else {
+            let segment = points.iter().rposition(|point| point.0 <= input).unwrap_or(0).min(points.len() - 2);
+            let (x1, y1) = points[segment];
+            let (x2, y2) = points[segment + 1];
+
+            let width = x2 - x1;
+            let t = (input - x1) / width;
+            let tangent1 = tangents[segment] * width;
+            let tangent2 = tangents[segment + 1] * width;
+
+            let t2 = t * t;
+            let t3 = t2 * t;
+            (2.0 * t3 - 3.0 * t2 + 1.0) * y1
+                + (t3 - 2.0 * t2 + t) * tangent1
+                + (-2.0 * t3 + 3.0 * t2) * y2
+                + (t3 - t2) * tangent2
+        };
+
+        lut[i] = output.round().clamp(0.0, 255.0) as u8;
+    }
+
+    lut
+}
+
 pub fn rotate_image(image: &image::RgbaImage, rotation: f32, filter_type: FilterType) -> image::RgbaImage {
     let rotated_image_size = image.width() + image.height();
     let mut rotated_image: image::RgbaImage = image::RgbaImage::new(
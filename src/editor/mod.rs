@@ -3,16 +3,55 @@ pub mod editor;
 pub mod image_operation_helpers;
 pub mod image_operation;
 pub mod tools;
+pub mod guides;
+#[cfg(test)]
+pub mod test_helpers;
 
 pub use crate::editor::image::Image;
 pub use crate::editor::image::Color;
 pub use crate::editor::editor::Editor;
 pub use crate::editor::editor::EditorImage;
 
+#[derive(Clone, Debug)]
+pub struct SelectionMask {
+    width: u32,
+    height: u32,
+    data: std::rc::Rc<Vec<bool>>
+}
+
+impl SelectionMask {
+    pub fn new(width: u32, height: u32, data: Vec<bool>) -> SelectionMask {
+        assert_eq!(data.len(), (width * height) as usize);
+
+        SelectionMask {
+            width,
+            height,
+            data: std::rc::Rc::new(data)
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        if x >= 0 && x < self.width as i32 && y >= 0 && y < self.height as i32 {
+            self.data[(y * self.width as i32 + x) as usize]
+        } else {
+            false
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Region {
     pub position: cgmath::Point2<i32>,
-    pub size: cgmath::Point2<i32>
+    pub size: cgmath::Point2<i32>,
+    pub mask: Option<SelectionMask>
 }
 
 impl Region {
@@ -20,16 +59,23 @@ impl Region {
         Region {
             position: cgmath::Point2::new(position_x, position_y),
             size: cgmath::Point2::new(width, height),
+            mask: None
         }
     }
 
     pub fn from_position_and_size(position: cgmath::Point2<i32>, size: cgmath::Point2<i32>) -> Region {
         Region {
             position,
-            size
+            size,
+            mask: None
         }
     }
 
+    pub fn with_mask(mut self, mask: Option<SelectionMask>) -> Region {
+        self.mask = mask;
+        self
+    }
+
     pub fn top(&self) -> i32 {
         return self.position.y;
     }
@@ -47,6 +93,17 @@ impl Region {
     }
 
     pub fn contains(&self, x: i32, y: i32) -> bool {
-        return x >= self.left() && x < self.right() && y >= self.top() && y < self.bottom();
+        if !(x >= self.left() && x < self.right() && y >= self.top() && y < self.bottom()) {
+            return false;
+        }
+
+        if let Some(mask) = self.mask.as_ref() {
+            // The mask follows the region when it has been moved or scaled
+            let mask_x = ((x - self.left()) as i64 * mask.width() as i64 / self.size.x as i64) as i32;
+            let mask_y = ((y - self.top()) as i64 * mask.height() as i64 / self.size.y as i64) as i32;
+            return mask.contains(mask_x, mask_y);
+        }
+
+        return true;
     }
 }
\ No newline at end of file
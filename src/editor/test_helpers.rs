@@ -0,0 +1,71 @@
+use crate::editor::{Color, Region};
+use crate::editor::image_operation::{ImageSource, ImageOperationSource};
+
+// Image operations without the OpenGL texture of an editor image, with the same region clipping
+pub struct TestImage {
+    pub image: image::RgbaImage,
+    pub valid_region: Option<Region>
+}
+
+impl TestImage {
+    pub fn new(image: image::RgbaImage) -> TestImage {
+        TestImage {
+            image,
+            valid_region: None
+        }
+    }
+
+    pub fn filled(width: u32, height: u32, color: Color) -> TestImage {
+        TestImage::new(image::RgbaImage::from_pixel(width, height, color))
+    }
+
+    pub fn with_region(mut self, valid_region: Option<Region>) -> TestImage {
+        self.valid_region = valid_region;
+        self
+    }
+}
+
+impl ImageSource for TestImage {
+    fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> Color {
+        *self.image.get_pixel(x, y)
+    }
+}
+
+impl ImageOperationSource for TestImage {
+    fn put_pixel(&mut self, x: u32, y: u32, pixel: Color) {
+        if let Some(valid_region) = self.valid_region.as_ref() {
+            if !valid_region.contains(x as i32, y as i32) {
+                return;
+            }
+        }
+
+        self.image.put_pixel(x, y, pixel);
+    }
+
+    fn is_valid_pixel(&self, x: i32, y: i32) -> bool {
+        match self.valid_region.as_ref() {
+            Some(valid_region) => valid_region.contains(x, y),
+            None => true
+        }
+    }
+
+    fn valid_region(&self) -> Option<&Region> {
+        self.valid_region.as_ref()
+    }
+
+    fn unclipped_pixels_mut(&mut self) -> Option<&mut [u8]> {
+        if self.valid_region.is_none() {
+            Some(&mut *self.image)
+        } else {
+            None
+        }
+    }
+}
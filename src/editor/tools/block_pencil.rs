@@ -6,7 +6,7 @@ use crate::{editor};
 use crate::command_buffer::{Command, CommandBuffer};
 use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
 use crate::editor::image_operation::{ImageOperation, ImageOperationMarker};
-use crate::program::Renders;
+use crate::program::{Renders, MAX_BRUSH_SIZE};
 use crate::ui::button::{TextButton, GenericButton};
 
 pub struct BlockPencilDrawTool {
@@ -33,7 +33,7 @@ impl BlockPencilDrawTool {
                 "".to_owned(),
                 Position::new(70.0, 10.0),
                 Some(Box::new(|side_half_width| {
-                    *side_half_width = (*side_half_width + 1).min(100);
+                    *side_half_width = (*side_half_width + 1).min(MAX_BRUSH_SIZE);
                 })),
                 Some(Box::new(|side_half_width| {
                     *side_half_width = (*side_half_width - 1).max(0);
@@ -53,6 +53,9 @@ impl Tool for BlockPencilDrawTool {
             Command::SetSecondaryColor(color) => {
                 self.secondary_color = *color;
             }
+            Command::SetBrushSize(size) => {
+                self.side_half_width = (*size).clamp(0, MAX_BRUSH_SIZE);
+            }
             _ => {}
         }
     }
@@ -62,7 +65,7 @@ impl Tool for BlockPencilDrawTool {
                          event: &WindowEvent,
                          image_area_transform: &Matrix3<f32>,
                          image_area_rectangle: &Rectangle,
-                         _command_buffer: &mut CommandBuffer,
+                         command_buffer: &mut CommandBuffer,
                          _image: &editor::Image) -> Option<ImageOperation> {
         let create_begin_draw = |this: &Self, mouse_position: Position, color: editor::Color| {
             Some(
@@ -130,7 +133,8 @@ impl Tool for BlockPencilDrawTool {
                                 color,
                                 blend: false,
                                 anti_aliased: Some(false),
-                                side_half_width: self.side_half_width
+                                side_half_width: self.side_half_width,
+                                dash: None
                             }
                         );
                     }
@@ -142,7 +146,11 @@ impl Tool for BlockPencilDrawTool {
             _ => {}
         }
 
+        let prev_side_half_width = self.side_half_width;
         self.change_size_button.process_gui_event(window, event, &mut self.side_half_width);
+        if self.side_half_width != prev_side_half_width {
+            command_buffer.push(Command::SetBrushSize(self.side_half_width));
+        }
 
         return op;
     }
@@ -2,18 +2,22 @@ use glfw::{WindowEvent, Action};
 use cgmath::{Matrix3, Matrix4};
 
 use crate::rendering::prelude::{Position, Rectangle};
-use crate::editor;
+use crate::{editor, content};
 use crate::command_buffer::{Command, CommandBuffer};
 use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
 use crate::editor::image_operation::{ImageOperation};
-use crate::ui::button::{TextButton, GenericButton};
-use crate::program::Renders;
+use crate::ui::button::{TextButton, GenericButton, Checkbox};
+use crate::program::{Renders, ProgramAction, ProgramActionData};
+
+pub const DEFAULT_FILL_TOLERANCE_PERCENT: i32 = 10;
 
 pub struct BucketFillDrawTool {
     color: editor::Color,
     alternative_color: editor::Color,
-    tolerance: f32,
-    change_tolerance_button: TextButton<f32>
+    tolerance_percent: i32,
+    change_tolerance_button: TextButton<CommandBuffer>,
+    contiguous_checkbox: Checkbox<()>,
+    anti_aliasing_checkbox: Checkbox<()>
 }
 
 impl BucketFillDrawTool {
@@ -21,21 +25,53 @@ impl BucketFillDrawTool {
         BucketFillDrawTool {
             color: image::Rgba([0, 0, 0, 255]),
             alternative_color: image::Rgba([0, 0, 0, 255]),
-            tolerance: 0.1,
+            tolerance_percent: DEFAULT_FILL_TOLERANCE_PERCENT,
             change_tolerance_button: TextButton::new(
                 renders.ui_font.clone(),
                 "".to_owned(),
                 Position::new(70.0, 10.0),
-                Some(Box::new(|tolerance| {
-                    *tolerance = (*tolerance + 0.05).min(1.0);
-                })),
-                Some(Box::new(|tolerance| {
-                    *tolerance = (*tolerance - 0.05).max(0.0);
+                Some(Box::new(|command_buffer| {
+                    command_buffer.push(Command::TriggerProgramAction(ProgramAction::OpenFillToleranceDialog, ProgramActionData::Triggered));
                 })),
                 None,
+                None,
+            ),
+            contiguous_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Contiguous".to_owned(),
+                true,
+                Position::new(235.0, 16.0),
+                None
+            ),
+            anti_aliasing_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Anti-aliasing".to_owned(),
+                false,
+                Position::new(400.0, 16.0),
+                None
             )
         }
     }
+
+    fn create_op(&self, position: Position, fill_color: editor::Color) -> ImageOperation {
+        ImageOperation::BucketFill {
+            start_x: position.x as i32,
+            start_y: position.y as i32,
+            fill_color,
+            tolerance: self.tolerance(),
+            contiguous: self.contiguous_checkbox.checked,
+            anti_aliased: self.anti_aliasing_checkbox.checked
+        }
+    }
+
+    fn tolerance(&self) -> f32 {
+        // Kept as an integer percentage so that zero tolerance is exact and requires identical colors
+        self.tolerance_percent as f32 / 100.0
+    }
 }
 
 impl Tool for BucketFillDrawTool {
@@ -47,6 +83,9 @@ impl Tool for BucketFillDrawTool {
             Command::SetSecondaryColor(color) => {
                 self.alternative_color = *color;
             }
+            Command::SetFillTolerance(tolerance) => {
+                self.tolerance_percent = (tolerance * 100.0).round().max(0.0).min(100.0) as i32;
+            }
             _ => {}
         }
     }
@@ -56,37 +95,26 @@ impl Tool for BucketFillDrawTool {
                          event: &WindowEvent,
                          image_area_transform: &Matrix3<f32>,
                          _image_area_rectangle: &Rectangle,
-                         _command_buffer: &mut CommandBuffer,
+                         command_buffer: &mut CommandBuffer,
                          _image: &editor::Image) -> Option<ImageOperation> {
         let mut op = None;
 
         match event {
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
                 let mouse_position = get_transformed_mouse_position(window, image_area_transform);
-                op = Some(
-                    ImageOperation::BucketFill {
-                        start_x: mouse_position.x as i32,
-                        start_y: mouse_position.y as i32,
-                        fill_color: self.color,
-                        tolerance: self.tolerance
-                    }
-                );
+                op = Some(self.create_op(mouse_position, self.color));
             }
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button2, Action::Press, _) => {
                 let mouse_position = get_transformed_mouse_position(window, image_area_transform);
-                op = Some(
-                    ImageOperation::BucketFill {
-                        start_x: mouse_position.x as i32,
-                        start_y: mouse_position.y as i32,
-                        fill_color: self.alternative_color,
-                        tolerance: self.tolerance
-                    }
-                );
+                op = Some(self.create_op(mouse_position, self.alternative_color));
             }
             _ => {}
         }
 
-        self.change_tolerance_button.process_gui_event(window, event, &mut self.tolerance);
+        self.change_tolerance_button.process_gui_event(window, event, command_buffer);
+
+        self.contiguous_checkbox.process_gui_event(window, event, &mut ());
+        self.anti_aliasing_checkbox.process_gui_event(window, event, &mut ());
 
         return op;
     }
@@ -99,7 +127,10 @@ impl Tool for BucketFillDrawTool {
     }
 
     fn render_ui(&mut self, renders: &Renders, transform: &Matrix4<f32>, _image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
-        self.change_tolerance_button.change_text(format!("Tolerance: {:.0} %", self.tolerance * 100.0));
+        self.change_tolerance_button.change_text(format!("Tolerance: {} %", self.tolerance_percent));
         self.change_tolerance_button.render(renders, transform);
+
+        self.contiguous_checkbox.render(renders, transform);
+        self.anti_aliasing_checkbox.render(renders, transform);
     }
 }
@@ -1,24 +1,31 @@
+use std::ops::DerefMut;
+
 use glfw::{WindowEvent, Action};
 use cgmath::{Matrix3, Transform, Matrix4};
 
-use crate::rendering::prelude::{Position, Rectangle};
+use crate::rendering::prelude::{Position, Rectangle, Color};
+use crate::rendering::text_render::TextAlignment;
 use crate::{editor, content};
 use crate::command_buffer::{Command, CommandBuffer};
 use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
 use crate::editor::image_operation::{ImageOperation};
 use crate::ui::button::{TextButton, GenericButton, Checkbox};
-use crate::program::Renders;
+use crate::program::{Renders, MAX_STROKE_WIDTH};
+
+const RADIUS_SNAP_STEP: i32 = 5;
 
 pub struct CircleDrawTool {
     start_position: Option<Position>,
     end_position: Option<Position>,
     is_alternative_mode: bool,
-    border_color: editor::Color,
-    fill_color: editor::Color,
+    snap_radius: bool,
+    primary_color: editor::Color,
+    secondary_color: editor::Color,
     border_half_width: i32,
     change_border_size_button: TextButton<i32>,
     anti_aliasing_checkbox: Checkbox<()>,
-    border_checkbox: Checkbox<()>
+    border_checkbox: Checkbox<()>,
+    fill_checkbox: Checkbox<()>
 }
 
 impl CircleDrawTool {
@@ -27,15 +34,16 @@ impl CircleDrawTool {
             start_position: None,
             end_position: None,
             is_alternative_mode: false,
-            border_color: image::Rgba([0, 0, 0, 255]),
-            fill_color: image::Rgba([255, 0, 0, 255]),
+            snap_radius: false,
+            primary_color: image::Rgba([255, 0, 0, 255]),
+            secondary_color: image::Rgba([0, 0, 0, 255]),
             border_half_width: 1,
             change_border_size_button: TextButton::new(
                 renders.ui_font.clone(),
                 "".to_owned(),
                 Position::new(70.0, 10.0),
                 Some(Box::new(|border_half_width| {
-                    *border_half_width += 1;
+                    *border_half_width = (*border_half_width + 1).min(MAX_STROKE_WIDTH);
                 })),
                 Some(Box::new(|border_half_width| {
                     *border_half_width = (*border_half_width - 1).max(0);
@@ -59,65 +67,112 @@ impl CircleDrawTool {
                 true,
                 Position::new(400.0, 16.0),
                 None
+            ),
+            fill_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Fill".to_owned(),
+                true,
+                Position::new(500.0, 16.0),
+                None
             )
         }
     }
 
-    fn create_op(&self,
-                 start_position: &Position,
-                 end_position: &Position,
-                 fill_color: editor::Color,
-                 border_color: editor::Color) -> ImageOperation {
+    fn radius(&self, start_position: &Position, end_position: &Position) -> i32 {
         let start_x = start_position.x as i32;
         let start_y = start_position.y as i32;
         let end_x = end_position.x as i32;
         let end_y = end_position.y as i32;
         let radius = (((end_x - start_x).pow(2) + (end_y - start_y).pow(2)) as f64).sqrt() as i32;
 
-        if self.border_checkbox.checked {
-            ImageOperation::Sequential(
-                Some("Circle".to_owned()),
-                vec![
-                    ImageOperation::FillCircle {
-                        center_x: start_x,
-                        center_y: start_y,
-                        radius,
-                        color: fill_color,
-                        blend: true
-                    },
-                    ImageOperation::Circle {
-                        center_x: start_x,
-                        center_y: start_y,
-                        radius,
-                        border_half_width: self.border_half_width,
-                        color: border_color,
-                        blend: false,
-                        anti_aliased: Some(self.anti_aliasing_checkbox.checked)
-                    }
-                ]
-            )
+        if self.snap_radius {
+            ((radius as f64 / RADIUS_SNAP_STEP as f64).round() as i32) * RADIUS_SNAP_STEP
         } else {
-            ImageOperation::Sequential(
-                Some("Circle".to_owned()),
-                vec![
-                    ImageOperation::FillCircle {
-                        center_x: start_x,
-                        center_y: start_y,
-                        radius: radius - 4,
-                        color: fill_color,
-                        blend: true,
-                    },
-                    ImageOperation::Circle {
-                        center_x: start_x,
-                        center_y: start_y,
-                        radius: radius - 4,
-                        border_half_width: 2,
-                        color: fill_color,
-                        blend: false,
-                        anti_aliased: Some(self.anti_aliasing_checkbox.checked)
-                    }
-                ]
-            )
+            radius
+        }
+    }
+
+    fn create_op(&self,
+                 start_position: &Position,
+                 end_position: &Position,
+                 primary_color: editor::Color,
+                 secondary_color: editor::Color) -> Option<ImageOperation> {
+        let start_x = start_position.x as i32;
+        let start_y = start_position.y as i32;
+        let radius = self.radius(start_position, end_position);
+
+        match (self.fill_checkbox.checked, self.border_checkbox.checked) {
+            (true, true) => {
+                Some(
+                    ImageOperation::Sequential(
+                        Some("Circle".to_owned()),
+                        vec![
+                            ImageOperation::FillCircle {
+                                center_x: start_x,
+                                center_y: start_y,
+                                radius,
+                                color: secondary_color,
+                                blend: true
+                            },
+                            ImageOperation::Circle {
+                                center_x: start_x,
+                                center_y: start_y,
+                                radius,
+                                border_half_width: self.border_half_width,
+                                color: primary_color,
+                                blend: false,
+                                anti_aliased: Some(self.anti_aliasing_checkbox.checked)
+                            }
+                        ]
+                    )
+                )
+            }
+            (false, true) => {
+                Some(
+                    ImageOperation::Sequential(
+                        Some("Circle".to_owned()),
+                        vec![
+                            ImageOperation::Circle {
+                                center_x: start_x,
+                                center_y: start_y,
+                                radius,
+                                border_half_width: self.border_half_width,
+                                color: primary_color,
+                                blend: true,
+                                anti_aliased: Some(self.anti_aliasing_checkbox.checked)
+                            }
+                        ]
+                    )
+                )
+            }
+            (true, false) => {
+                Some(
+                    ImageOperation::Sequential(
+                        Some("Circle".to_owned()),
+                        vec![
+                            ImageOperation::FillCircle {
+                                center_x: start_x,
+                                center_y: start_y,
+                                radius: radius - 4,
+                                color: primary_color,
+                                blend: true,
+                            },
+                            ImageOperation::Circle {
+                                center_x: start_x,
+                                center_y: start_y,
+                                radius: radius - 4,
+                                border_half_width: 2,
+                                color: primary_color,
+                                blend: false,
+                                anti_aliased: Some(self.anti_aliasing_checkbox.checked)
+                            }
+                        ]
+                    )
+                )
+            }
+            (false, false) => None
         }
     }
 }
@@ -125,11 +180,14 @@ impl CircleDrawTool {
 impl Tool for CircleDrawTool {
     fn handle_command(&mut self, _command_buffer: &mut CommandBuffer, _image: &editor::Image, command: &Command) {
         match command {
+            Command::SetStrokeWidth(width) => {
+                self.border_half_width = (*width).clamp(0, MAX_STROKE_WIDTH);
+            }
             Command::SetPrimaryColor(color) => {
-                self.fill_color = *color;
+                self.primary_color = *color;
             }
             Command::SetSecondaryColor(color) => {
-                self.border_color = *color;
+                self.secondary_color = *color;
             }
             _ => {}
         }
@@ -140,8 +198,10 @@ impl Tool for CircleDrawTool {
                          event: &WindowEvent,
                          image_area_transform: &Matrix3<f32>,
                          _image_area_rectangle: &Rectangle,
-                         _command_buffer: &mut CommandBuffer,
+                         command_buffer: &mut CommandBuffer,
                          _image: &editor::Image) -> Option<ImageOperation> {
+        self.snap_radius = window.is_shift_down();
+
         let mut op = None;
         match event {
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
@@ -151,7 +211,7 @@ impl Tool for CircleDrawTool {
             }
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Release, _) => {
                 if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
-                    op = Some(self.create_op(start_position, end_position, self.fill_color, self.border_color));
+                    op = self.create_op(start_position, end_position, self.primary_color, self.secondary_color);
                 }
 
                 self.start_position = None;
@@ -164,7 +224,7 @@ impl Tool for CircleDrawTool {
             }
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button2, Action::Release, _) => {
                 if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
-                    op = Some(self.create_op(start_position, end_position, self.border_color, self.fill_color));
+                    op = self.create_op(start_position, end_position, self.secondary_color, self.primary_color);
                 }
 
                 self.start_position = None;
@@ -177,9 +237,14 @@ impl Tool for CircleDrawTool {
             _ => {}
         }
 
+        let prev_border_half_width = self.border_half_width;
         self.change_border_size_button.process_gui_event(window, event, &mut self.border_half_width);
+        if self.border_half_width != prev_border_half_width {
+            command_buffer.push(Command::SetStrokeWidth(self.border_half_width));
+        }
         self.anti_aliasing_checkbox.process_gui_event(window, event, &mut ());
         self.border_checkbox.process_gui_event(window, event, &mut ());
+        self.fill_checkbox.process_gui_event(window, event, &mut ());
 
         return op;
     }
@@ -190,13 +255,15 @@ impl Tool for CircleDrawTool {
                _transparent_area: &mut Option<Rectangle>) -> bool {
         let mut update_op = preview_image.update_operation();
         if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
-            let (fill_color, border_color) = if !self.is_alternative_mode {
-                (self.fill_color, self.border_color)
+            let (primary_color, secondary_color) = if !self.is_alternative_mode {
+                (self.primary_color, self.secondary_color)
             } else {
-                (self.border_color, self.fill_color)
+                (self.secondary_color, self.primary_color)
             };
 
-            self.create_op(start_position, end_position, fill_color, border_color).apply(&mut update_op, false);
+            if let Some(op) = self.create_op(start_position, end_position, primary_color, secondary_color) {
+                op.apply(&mut update_op, false);
+            }
         }
 
         return true;
@@ -208,5 +275,17 @@ impl Tool for CircleDrawTool {
 
         self.anti_aliasing_checkbox.render(renders, transform);
         self.border_checkbox.render(renders, transform);
+        self.fill_checkbox.render(renders, transform);
+
+        if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
+            renders.text_render.render_line(
+                renders.text_render.shader(),
+                transform,
+                renders.ui_font.borrow_mut().deref_mut(),
+                format!("Radius: {} px", self.radius(start_position, end_position)).chars().map(|c| (c, Color::new(0, 0, 0))),
+                Position::new(600.0, 10.0),
+                TextAlignment::Top
+            );
+        }
     }
 }
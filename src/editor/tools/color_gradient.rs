@@ -2,11 +2,11 @@ use glfw::{WindowEvent, Action};
 use cgmath::{Matrix3, Transform, Matrix4};
 
 use crate::rendering::prelude::{Position, Rectangle};
-use crate::editor;
+use crate::{editor, content};
 use crate::command_buffer::{Command, CommandBuffer};
 use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
 use crate::editor::image_operation::{ImageOperation, ColorGradientType};
-use crate::ui::button::{TextButton, GenericButton};
+use crate::ui::button::{TextButton, GenericButton, Checkbox};
 use crate::program::Renders;
 use crate::editor::tools::selection::Selection;
 
@@ -19,7 +19,8 @@ pub struct ColorGradientDrawTool {
     second_color: editor::Color,
     gradient_type: ColorGradientType,
     set_linear_button: TextButton<ColorGradientType>,
-    set_radial_button: TextButton<ColorGradientType>
+    set_radial_button: TextButton<ColorGradientType>,
+    dither_checkbox: Checkbox<()>
 }
 
 impl ColorGradientDrawTool {
@@ -51,6 +52,15 @@ impl ColorGradientDrawTool {
                 })),
                 None,
                 None,
+            ),
+            dither_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Dither".to_owned(),
+                false,
+                Position::new(300.0, 16.0),
+                None
             )
         }
     }
@@ -67,7 +77,8 @@ impl ColorGradientDrawTool {
             end_y: end_position.y as i32,
             first_color,
             second_color,
-            gradient_type: self.gradient_type.clone()
+            gradient_type: self.gradient_type.clone(),
+            dither: self.dither_checkbox.checked
         }
     }
 }
@@ -133,6 +144,7 @@ impl Tool for ColorGradientDrawTool {
 
         self.set_linear_button.process_gui_event(window, event, &mut self.gradient_type);
         self.set_radial_button.process_gui_event(window, event, &mut self.gradient_type);
+        self.dither_checkbox.process_gui_event(window, event, &mut ());
 
         return op;
     }
@@ -159,5 +171,6 @@ impl Tool for ColorGradientDrawTool {
     fn render_ui(&mut self, renders: &Renders, transform: &Matrix4<f32>, _image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
         self.set_linear_button.render(renders, transform);
         self.set_radial_button.render(renders, transform);
+        self.dither_checkbox.render(renders, transform);
     }
 }
@@ -1,12 +1,16 @@
 use glfw::{WindowEvent, Action};
-use cgmath::{Matrix3};
+use cgmath::{Matrix3, Matrix4};
 
-use crate::rendering::prelude::{Rectangle};
-use crate::editor;
+use crate::rendering::prelude::{Position, Rectangle};
+use crate::{editor, content};
 use crate::command_buffer::{Command, CommandBuffer};
 use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
 use crate::editor::image_operation::{ImageOperation, ImageSource};
+use crate::editor::image_operation_helpers::average_color;
+use crate::program::Renders;
+use crate::ui::button::{TextButton, GenericButton, Checkbox};
 
+#[derive(PartialEq)]
 enum ColorPickerMode {
     None,
     Color,
@@ -14,13 +18,38 @@ enum ColorPickerMode {
 }
 
 pub struct ColorPickerTool {
-    mode: ColorPickerMode
+    mode: ColorPickerMode,
+    sample_half_size: i32,
+    change_sample_size_button: TextButton<i32>,
+    sample_merged_checkbox: Checkbox<()>
 }
 
 impl ColorPickerTool {
-    pub fn new() -> ColorPickerTool {
+    pub fn new(renders: &Renders) -> ColorPickerTool {
         ColorPickerTool {
-            mode: ColorPickerMode::None
+            mode: ColorPickerMode::None,
+            sample_half_size: 0,
+            change_sample_size_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(70.0, 10.0),
+                Some(Box::new(|sample_half_size| {
+                    *sample_half_size = (*sample_half_size + 1).min(2);
+                })),
+                Some(Box::new(|sample_half_size| {
+                    *sample_half_size = (*sample_half_size - 1).max(0);
+                })),
+                None,
+            ),
+            sample_merged_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Sample merged".to_owned(),
+                false,
+                Position::new(300.0, 16.0),
+                None
+            )
         }
     }
 
@@ -29,33 +58,38 @@ impl ColorPickerTool {
                        image_area_transform: &Matrix3<f32>,
                        image: &editor::Image,
                        command_buffer: &mut CommandBuffer) {
-        match self.mode {
+        let secondary = match self.mode {
             ColorPickerMode::None => {
-
-            }
-            ColorPickerMode::Color => {
-                if let Some(color) = self.select_color(window, image_area_transform, image) {
-                    command_buffer.push(Command::SetPrimaryColor(color))
-                }
+                return;
             }
-            ColorPickerMode::AlternativeColor => {
-                if let Some(color) = self.select_color(window, image_area_transform, image) {
-                    command_buffer.push(Command::SetSecondaryColor(color))
+            ColorPickerMode::Color => false,
+            ColorPickerMode::AlternativeColor => true
+        };
+
+        if let Some((x, y)) = self.select_position(window, image_area_transform, image) {
+            if self.sample_merged_checkbox.checked {
+                command_buffer.push(Command::PickMergedColor { x, y, half_size: self.sample_half_size, secondary });
+            } else {
+                let color = average_color(image, x as i32, y as i32, self.sample_half_size);
+                if secondary {
+                    command_buffer.push(Command::SetSecondaryColor(color));
+                } else {
+                    command_buffer.push(Command::SetPrimaryColor(color));
                 }
             }
         }
     }
 
-    fn select_color(&self,
-                    window: &mut dyn EditorWindow,
-                    transform: &Matrix3<f32>,
-                    image: &editor::Image) -> Option<editor::Color> {
+    fn select_position(&self,
+                       window: &mut dyn EditorWindow,
+                       transform: &Matrix3<f32>,
+                       image: &editor::Image) -> Option<(u32, u32)> {
         let position = get_transformed_mouse_position(window, transform);
         let position_x = position.x.round() as i32;
         let position_y = position.y.round() as i32;
 
         if position_x >= 0 && position_x < image.width() as i32 && position_y >= 0 && position_y < image.height() as i32 {
-            Some(image.get_pixel(position_x as u32, position_y as u32))
+            Some((position_x as u32, position_y as u32))
         } else {
             None
         }
@@ -76,6 +110,10 @@ impl Tool for ColorPickerTool {
                 self.do_color_select(window, image_area_transform, image, command_buffer);
             }
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Release, _) => {
+                if self.mode == ColorPickerMode::Color {
+                    command_buffer.push(Command::CommitPrimaryColor);
+                }
+
                 self.mode = ColorPickerMode::None;
             }
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button2, Action::Press, _) => {
@@ -91,6 +129,9 @@ impl Tool for ColorPickerTool {
             _ => {}
         }
 
+        self.change_sample_size_button.process_gui_event(window, event, &mut self.sample_half_size);
+        self.sample_merged_checkbox.process_gui_event(window, event, &mut ());
+
         None
     }
 
@@ -100,4 +141,12 @@ impl Tool for ColorPickerTool {
                _transparent_area: &mut Option<Rectangle>) -> bool {
         false
     }
+
+    fn render_ui(&mut self, renders: &Renders, transform: &Matrix4<f32>, _image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
+        let sample_size = self.sample_half_size * 2 + 1;
+        self.change_sample_size_button.change_text(format!("Sample size: {}x{}", sample_size, sample_size));
+        self.change_sample_size_button.render(renders, transform);
+
+        self.sample_merged_checkbox.render(renders, transform);
+    }
 }
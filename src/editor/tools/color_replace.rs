@@ -0,0 +1,169 @@
+use glfw::{WindowEvent, Action};
+use cgmath::{Matrix3, Transform, Matrix4};
+
+use crate::rendering::prelude::{Position, Rectangle};
+use crate::editor;
+use crate::command_buffer::{Command, CommandBuffer};
+use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
+use crate::editor::image_operation::{ImageOperation, ImageOperationMarker};
+use crate::program::{Renders, MAX_BRUSH_SIZE};
+use crate::ui::button::{TextButton, GenericButton};
+
+pub struct ColorReplaceTool {
+    is_drawing: Option<(editor::Color, editor::Color)>,
+    prev_mouse_position: Option<Position>,
+    primary_color: editor::Color,
+    secondary_color: editor::Color,
+    side_half_width: i32,
+    tolerance_percent: i32,
+    change_size_button: TextButton<i32>,
+    change_tolerance_button: TextButton<i32>
+}
+
+impl ColorReplaceTool {
+    pub fn new(renders: &Renders) -> ColorReplaceTool {
+        ColorReplaceTool {
+            is_drawing: None,
+            prev_mouse_position: None,
+            primary_color: image::Rgba([0, 0, 0, 255]),
+            secondary_color: image::Rgba([0, 0, 0, 255]),
+            side_half_width: 3,
+            tolerance_percent: 10,
+            change_size_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(70.0, 10.0),
+                Some(Box::new(|side_half_width| {
+                    *side_half_width = (*side_half_width + 1).min(MAX_BRUSH_SIZE);
+                })),
+                Some(Box::new(|side_half_width| {
+                    *side_half_width = (*side_half_width - 1).max(0);
+                })),
+                None,
+            ),
+            change_tolerance_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(235.0, 10.0),
+                Some(Box::new(|tolerance_percent| {
+                    *tolerance_percent = (*tolerance_percent + 5).min(100);
+                })),
+                Some(Box::new(|tolerance_percent| {
+                    *tolerance_percent = (*tolerance_percent - 5).max(0);
+                })),
+                None,
+            )
+        }
+    }
+
+    fn create_op(&self, start_position: Position, end_position: Position, from_color: editor::Color, to_color: editor::Color) -> ImageOperation {
+        ImageOperation::ReplaceColor {
+            start_x: start_position.x as i32,
+            start_y: start_position.y as i32,
+            end_x: end_position.x as i32,
+            end_y: end_position.y as i32,
+            side_half_width: self.side_half_width,
+            from_color,
+            to_color,
+            tolerance: self.tolerance_percent as f32 / 100.0
+        }
+    }
+}
+
+impl Tool for ColorReplaceTool {
+    fn handle_command(&mut self, _command_buffer: &mut CommandBuffer, _image: &editor::Image, command: &Command) {
+        match command {
+            Command::SetPrimaryColor(color) => {
+                self.primary_color = *color;
+            }
+            Command::SetSecondaryColor(color) => {
+                self.secondary_color = *color;
+            }
+            Command::SetBrushSize(size) => {
+                self.side_half_width = (*size).clamp(0, MAX_BRUSH_SIZE);
+            }
+            _ => {}
+        }
+    }
+
+    fn process_gui_event(&mut self,
+                         window: &mut dyn EditorWindow,
+                         event: &WindowEvent,
+                         image_area_transform: &Matrix3<f32>,
+                         image_area_rectangle: &Rectangle,
+                         command_buffer: &mut CommandBuffer,
+                         _image: &editor::Image) -> Option<ImageOperation> {
+        let mut op = None;
+        match event {
+            glfw::WindowEvent::MouseButton(button @ (glfw::MouseButton::Button1 | glfw::MouseButton::Button2), Action::Press, _) => {
+                let (mouse_x, mouse_y) = window.get_cursor_pos();
+                if self.is_drawing.is_none() && image_area_rectangle.contains(&Position::new(mouse_x as f32, mouse_y as f32)) {
+                    // The primary button replaces the secondary color with the primary, the other button does the reverse
+                    let colors = if *button == glfw::MouseButton::Button1 {
+                        (self.secondary_color, self.primary_color)
+                    } else {
+                        (self.primary_color, self.secondary_color)
+                    };
+
+                    let mouse_position = get_transformed_mouse_position(window, image_area_transform);
+                    self.is_drawing = Some(colors);
+                    self.prev_mouse_position = Some(mouse_position);
+
+                    op = Some(
+                        ImageOperation::Sequential(
+                            Some("Replace color".to_owned()),
+                            vec![
+                                ImageOperation::Marker(ImageOperationMarker::BeginDraw, Some("Replace color".to_owned())),
+                                self.create_op(mouse_position, mouse_position, colors.0, colors.1)
+                            ]
+                        )
+                    );
+                }
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1 | glfw::MouseButton::Button2, Action::Release, _) => {
+                if self.is_drawing.is_some() {
+                    self.is_drawing = None;
+                    self.prev_mouse_position = None;
+                    op = Some(ImageOperation::Marker(ImageOperationMarker::EndDraw, None));
+                }
+            }
+            glfw::WindowEvent::CursorPos(raw_mouse_x, raw_mouse_y) => {
+                if let Some((from_color, to_color)) = self.is_drawing {
+                    let mouse_position = image_area_transform.transform_point(cgmath::Point2::new(*raw_mouse_x as f32, *raw_mouse_y as f32));
+
+                    if let Some(prev_mouse_position) = self.prev_mouse_position {
+                        op = Some(self.create_op(prev_mouse_position, mouse_position, from_color, to_color));
+                    }
+
+                    self.prev_mouse_position = Some(mouse_position);
+                }
+            }
+            _ => {}
+        }
+
+        let prev_side_half_width = self.side_half_width;
+        self.change_size_button.process_gui_event(window, event, &mut self.side_half_width);
+        if self.side_half_width != prev_side_half_width {
+            command_buffer.push(Command::SetBrushSize(self.side_half_width));
+        }
+
+        self.change_tolerance_button.process_gui_event(window, event, &mut self.tolerance_percent);
+
+        return op;
+    }
+
+    fn preview(&mut self,
+               _image: &editor::Image,
+               _preview_image: &mut editor::Image,
+               _transparent_area: &mut Option<Rectangle>) -> bool {
+        false
+    }
+
+    fn render_ui(&mut self, renders: &Renders, transform: &Matrix4<f32>, _image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
+        self.change_size_button.change_text(format!("Brush size: {}", self.side_half_width * 2 + 1));
+        self.change_size_button.render(renders, transform);
+
+        self.change_tolerance_button.change_text(format!("Tolerance: {} %", self.tolerance_percent));
+        self.change_tolerance_button.render(renders, transform);
+    }
+}
@@ -0,0 +1,251 @@
+use std::ops::DerefMut;
+
+use glfw::{WindowEvent, Action};
+use cgmath::{Matrix3, Transform, Matrix4};
+
+use crate::rendering::prelude::{Position, Rectangle, Color};
+use crate::rendering::text_render::TextAlignment;
+use crate::{editor, content};
+use crate::command_buffer::{Command, CommandBuffer};
+use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
+use crate::editor::image_operation::{ImageOperation};
+use crate::ui::button::{TextButton, GenericButton, Checkbox};
+use crate::program::{Renders, MAX_STROKE_WIDTH};
+
+pub struct EllipseDrawTool {
+    start_position: Option<Position>,
+    end_position: Option<Position>,
+    is_alternative_mode: bool,
+    is_circle: bool,
+    primary_color: editor::Color,
+    secondary_color: editor::Color,
+    border_half_width: i32,
+    change_border_size_button: TextButton<i32>,
+    anti_aliasing_checkbox: Checkbox<()>,
+    border_checkbox: Checkbox<()>,
+    fill_checkbox: Checkbox<()>
+}
+
+impl EllipseDrawTool {
+    pub fn new(renders: &Renders) -> EllipseDrawTool {
+        EllipseDrawTool {
+            start_position: None,
+            end_position: None,
+            is_alternative_mode: false,
+            is_circle: false,
+            primary_color: image::Rgba([255, 0, 0, 255]),
+            secondary_color: image::Rgba([0, 0, 0, 255]),
+            border_half_width: 1,
+            change_border_size_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(70.0, 10.0),
+                Some(Box::new(|border_half_width| {
+                    *border_half_width = (*border_half_width + 1).min(MAX_STROKE_WIDTH);
+                })),
+                Some(Box::new(|border_half_width| {
+                    *border_half_width = (*border_half_width - 1).max(0);
+                })),
+                None,
+            ),
+            anti_aliasing_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Anti-aliasing".to_owned(),
+                true,
+                Position::new(235.0, 16.0),
+                None
+            ),
+            border_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Border".to_owned(),
+                true,
+                Position::new(400.0, 16.0),
+                None
+            ),
+            fill_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Fill".to_owned(),
+                true,
+                Position::new(500.0, 16.0),
+                None
+            )
+        }
+    }
+
+    fn radii(&self, start_position: &Position, end_position: &Position) -> (i32, i32) {
+        let radius_x = (end_position.x as i32 - start_position.x as i32).abs();
+        let radius_y = (end_position.y as i32 - start_position.y as i32).abs();
+
+        if self.is_circle {
+            let radius = radius_x.max(radius_y);
+            (radius, radius)
+        } else {
+            (radius_x, radius_y)
+        }
+    }
+
+    fn create_op(&self,
+                 start_position: &Position,
+                 end_position: &Position,
+                 primary_color: editor::Color,
+                 secondary_color: editor::Color) -> Option<ImageOperation> {
+        let center_x = start_position.x as i32;
+        let center_y = start_position.y as i32;
+        let (radius_x, radius_y) = self.radii(start_position, end_position);
+
+        let mut ops = Vec::new();
+        if self.fill_checkbox.checked {
+            ops.push(
+                ImageOperation::FillEllipse {
+                    center_x,
+                    center_y,
+                    radius_x,
+                    radius_y,
+                    color: if self.border_checkbox.checked { secondary_color } else { primary_color },
+                    blend: true
+                }
+            );
+        }
+
+        if self.border_checkbox.checked {
+            ops.push(
+                ImageOperation::Ellipse {
+                    center_x,
+                    center_y,
+                    radius_x,
+                    radius_y,
+                    border_half_width: self.border_half_width,
+                    color: primary_color,
+                    blend: true,
+                    anti_aliased: Some(self.anti_aliasing_checkbox.checked)
+                }
+            );
+        }
+
+        if ops.is_empty() {
+            return None;
+        }
+
+        Some(ImageOperation::Sequential(Some("Ellipse".to_owned()), ops))
+    }
+}
+
+impl Tool for EllipseDrawTool {
+    fn handle_command(&mut self, _command_buffer: &mut CommandBuffer, _image: &editor::Image, command: &Command) {
+        match command {
+            Command::SetStrokeWidth(width) => {
+                self.border_half_width = (*width).clamp(0, MAX_STROKE_WIDTH);
+            }
+            Command::SetPrimaryColor(color) => {
+                self.primary_color = *color;
+            }
+            Command::SetSecondaryColor(color) => {
+                self.secondary_color = *color;
+            }
+            _ => {}
+        }
+    }
+
+    fn process_gui_event(&mut self,
+                         window: &mut dyn EditorWindow,
+                         event: &WindowEvent,
+                         image_area_transform: &Matrix3<f32>,
+                         _image_area_rectangle: &Rectangle,
+                         command_buffer: &mut CommandBuffer,
+                         _image: &editor::Image) -> Option<ImageOperation> {
+        self.is_circle = window.is_shift_down();
+
+        let mut op = None;
+        match event {
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
+                self.start_position = Some(get_transformed_mouse_position(window, image_area_transform));
+                self.end_position = None;
+                self.is_alternative_mode = false;
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Release, _) => {
+                if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
+                    op = self.create_op(start_position, end_position, self.primary_color, self.secondary_color);
+                }
+
+                self.start_position = None;
+                self.end_position = None;
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button2, Action::Press, _) => {
+                self.start_position = Some(get_transformed_mouse_position(window, image_area_transform));
+                self.end_position = None;
+                self.is_alternative_mode = true;
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button2, Action::Release, _) => {
+                if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
+                    op = self.create_op(start_position, end_position, self.secondary_color, self.primary_color);
+                }
+
+                self.start_position = None;
+                self.end_position = None;
+            }
+            glfw::WindowEvent::CursorPos(raw_mouse_x, raw_mouse_y) => {
+                let mouse_position = image_area_transform.transform_point(cgmath::Point2::new(*raw_mouse_x as f32, *raw_mouse_y as f32));
+                self.end_position = Some(mouse_position);
+            }
+            _ => {}
+        }
+
+        let prev_border_half_width = self.border_half_width;
+        self.change_border_size_button.process_gui_event(window, event, &mut self.border_half_width);
+        if self.border_half_width != prev_border_half_width {
+            command_buffer.push(Command::SetStrokeWidth(self.border_half_width));
+        }
+        self.anti_aliasing_checkbox.process_gui_event(window, event, &mut ());
+        self.border_checkbox.process_gui_event(window, event, &mut ());
+        self.fill_checkbox.process_gui_event(window, event, &mut ());
+
+        return op;
+    }
+
+    fn preview(&mut self,
+               _image: &editor::Image,
+               preview_image: &mut editor::Image,
+               _transparent_area: &mut Option<Rectangle>) -> bool {
+        let mut update_op = preview_image.update_operation();
+        if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
+            let (primary_color, secondary_color) = if !self.is_alternative_mode {
+                (self.primary_color, self.secondary_color)
+            } else {
+                (self.secondary_color, self.primary_color)
+            };
+
+            if let Some(op) = self.create_op(start_position, end_position, primary_color, secondary_color) {
+                op.apply(&mut update_op, false);
+            }
+        }
+
+        return true;
+    }
+
+    fn render_ui(&mut self, renders: &Renders, transform: &Matrix4<f32>, _image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
+        self.change_border_size_button.change_text(format!("Border size: {}", self.border_half_width * 2 + 1));
+        self.change_border_size_button.render(renders, transform);
+
+        self.anti_aliasing_checkbox.render(renders, transform);
+        self.border_checkbox.render(renders, transform);
+        self.fill_checkbox.render(renders, transform);
+
+        if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
+            let (radius_x, radius_y) = self.radii(start_position, end_position);
+            renders.text_render.render_line(
+                renders.text_render.shader(),
+                transform,
+                renders.ui_font.borrow_mut().deref_mut(),
+                format!("Radius: {} x {} px", radius_x, radius_y).chars().map(|c| (c, Color::new(0, 0, 0))),
+                Position::new(600.0, 10.0),
+                TextAlignment::Top
+            );
+        }
+    }
+}
@@ -1,19 +1,29 @@
+use std::collections::HashMap;
+
 use glfw::{WindowEvent, Action};
 use cgmath::{Matrix3, Transform, Matrix4};
 
 use crate::rendering::prelude::{Position, Rectangle};
-use crate::editor;
-use crate::command_buffer::{CommandBuffer};
+use crate::{editor, content};
+use crate::command_buffer::{Command, CommandBuffer};
 use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
-use crate::editor::image_operation::{ImageOperation, ImageOperationMarker};
-use crate::ui::button::{TextButton, GenericButton};
-use crate::program::Renders;
+use crate::editor::image_operation::{ImageOperation, ImageOperationMarker, ImageSource};
+use crate::editor::image_operation_helpers::erase_strengths;
+use crate::ui::button::{TextButton, GenericButton, Checkbox};
+use crate::program::{Renders, MAX_BRUSH_SIZE};
 
 pub struct EraserDrawTool {
     is_drawing: bool,
     prev_mouse_position: Option<Position>,
     side_half_width: i32,
-    change_size_button: TextButton<i32>
+    hardness_percent: i32,
+    tolerance_percent: i32,
+    reference_color: Option<editor::Color>,
+    stroke_strengths: HashMap<(i32, i32), f32>,
+    change_size_button: TextButton<i32>,
+    change_hardness_button: TextButton<i32>,
+    erase_by_color_checkbox: Checkbox<()>,
+    change_tolerance_button: TextButton<i32>
 }
 
 impl EraserDrawTool {
@@ -22,43 +32,112 @@ impl EraserDrawTool {
             is_drawing: false,
             prev_mouse_position: None,
             side_half_width: 3,
+            hardness_percent: 100,
+            tolerance_percent: 10,
+            reference_color: None,
+            stroke_strengths: HashMap::new(),
             change_size_button: TextButton::new(
                 renders.ui_font.clone(),
                 "".to_owned(),
                 Position::new(70.0, 10.0),
                 Some(Box::new(|side_half_width| {
-                    *side_half_width += 1;
+                    *side_half_width = (*side_half_width + 1).min(MAX_BRUSH_SIZE);
                 })),
                 Some(Box::new(|side_half_width| {
                     *side_half_width = (*side_half_width - 1).max(0);
                 })),
                 None,
+            ),
+            change_hardness_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(235.0, 10.0),
+                Some(Box::new(|hardness_percent| {
+                    *hardness_percent = (*hardness_percent + 10).min(100);
+                })),
+                Some(Box::new(|hardness_percent| {
+                    *hardness_percent = (*hardness_percent - 10).max(0);
+                })),
+                None,
+            ),
+            erase_by_color_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Erase by color".to_owned(),
+                false,
+                Position::new(400.0, 16.0),
+                None
+            ),
+            change_tolerance_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(565.0, 10.0),
+                Some(Box::new(|tolerance_percent| {
+                    *tolerance_percent = (*tolerance_percent + 5).min(100);
+                })),
+                Some(Box::new(|tolerance_percent| {
+                    *tolerance_percent = (*tolerance_percent - 5).max(0);
+                })),
+                None,
             )
         }
     }
+
+    fn create_op(&mut self, start_position: Position, end_position: Position) -> ImageOperation {
+        let segment_strengths = erase_strengths(
+            start_position.x as i32,
+            start_position.y as i32,
+            end_position.x as i32,
+            end_position.y as i32,
+            self.side_half_width,
+            self.hardness_percent as f32 / 100.0
+        );
+
+        // The segments of a stroke overlap, so each pixel is only erased up to the strongest strength of the stroke.
+        // The alpha left by the previous segments is scaled such that the original alpha is scaled by (1 - strength).
+        let mut alpha_scales = Vec::new();
+        for (position, strength) in segment_strengths {
+            let previous_strength = self.stroke_strengths.get(&position).cloned().unwrap_or(0.0);
+            if strength <= previous_strength || previous_strength >= 1.0 {
+                continue;
+            }
+
+            alpha_scales.push((position, (1.0 - strength) / (1.0 - previous_strength)));
+            self.stroke_strengths.insert(position, strength);
+        }
+
+        ImageOperation::Erase {
+            alpha_scales,
+            color_tolerance: self.reference_color.map(|color| (color, self.tolerance_percent as f32 / 100.0))
+        }
+    }
 }
 
 impl Tool for EraserDrawTool {
+    fn handle_command(&mut self, _command_buffer: &mut CommandBuffer, _image: &editor::Image, command: &Command) {
+        match command {
+            Command::SetBrushSize(size) => {
+                self.side_half_width = (*size).clamp(0, MAX_BRUSH_SIZE);
+            }
+            _ => {}
+        }
+    }
+
     fn process_gui_event(&mut self,
                          window: &mut dyn EditorWindow,
                          event: &WindowEvent,
                          image_area_transform: &Matrix3<f32>,
                          image_area_rectangle: &Rectangle,
-                         _command_buffer: &mut CommandBuffer,
-                         _image: &editor::Image) -> Option<ImageOperation> {
-        let create_begin_draw = |this: &Self, mouse_position: Position| {
+                         command_buffer: &mut CommandBuffer,
+                         image: &editor::Image) -> Option<ImageOperation> {
+        let create_begin_draw = |this: &mut Self, mouse_position: Position| {
             Some(
                 ImageOperation::Sequential(
                     None,
                     vec![
                         ImageOperation::Marker(ImageOperationMarker::BeginDraw, Some("Eraser".to_owned())),
-                        ImageOperation::Block {
-                            x: mouse_position.x as i32,
-                            y: mouse_position.y as i32,
-                            color: image::Rgba([0, 0, 0, 0]),
-                            blend: false,
-                            side_half_width: this.side_half_width
-                        }
+                        this.create_op(mouse_position, mouse_position)
                     ]
                 )
             )
@@ -67,16 +146,28 @@ impl Tool for EraserDrawTool {
         let mut op = None;
         match event {
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
-                self.is_drawing = true;
-
                 let mouse_position = get_transformed_mouse_position(window, image_area_transform);
-                if image_area_rectangle.contains(&mouse_position) {
+
+                // The reference color is sampled once, as the pixel under the brush is erased by the first segment
+                self.reference_color = None;
+                self.stroke_strengths.clear();
+                if self.erase_by_color_checkbox.checked {
+                    let (x, y) = (mouse_position.x as i32, mouse_position.y as i32);
+                    if x >= 0 && x < image.width() as i32 && y >= 0 && y < image.height() as i32 {
+                        self.reference_color = Some(image.get_pixel(x as u32, y as u32));
+                    }
+                }
+
+                self.is_drawing = !self.erase_by_color_checkbox.checked || self.reference_color.is_some();
+                if self.is_drawing && image_area_rectangle.contains(&mouse_position) {
                     op = create_begin_draw(self, mouse_position);
                 }
             }
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1 | glfw::MouseButton::Button2, Action::Release, _) => {
                 self.is_drawing = false;
                 self.prev_mouse_position = None;
+                self.reference_color = None;
+                self.stroke_strengths.clear();
                 op = Some(ImageOperation::Marker(ImageOperationMarker::EndDraw, None));
             }
             glfw::WindowEvent::CursorPos(raw_mouse_x, raw_mouse_y) => {
@@ -84,18 +175,7 @@ impl Tool for EraserDrawTool {
                     let mouse_position = image_area_transform.transform_point(cgmath::Point2::new(*raw_mouse_x as f32, *raw_mouse_y as f32));
 
                     if let Some(prev_mouse_position) = self.prev_mouse_position {
-                        op = Some(
-                            ImageOperation::Line {
-                                start_x: prev_mouse_position.x as i32,
-                                start_y: prev_mouse_position.y as i32,
-                                end_x: mouse_position.x as i32,
-                                end_y: mouse_position.y as i32,
-                                color: image::Rgba([0, 0, 0, 0]),
-                                blend: false,
-                                anti_aliased: Some(false),
-                                side_half_width: self.side_half_width
-                            }
-                        );
+                        op = Some(self.create_op(prev_mouse_position, mouse_position));
                     }
 
                     self.prev_mouse_position = Some(mouse_position);
@@ -104,7 +184,17 @@ impl Tool for EraserDrawTool {
             _ => {}
         }
 
+        let prev_side_half_width = self.side_half_width;
         self.change_size_button.process_gui_event(window, event, &mut self.side_half_width);
+        if self.side_half_width != prev_side_half_width {
+            command_buffer.push(Command::SetBrushSize(self.side_half_width));
+        }
+
+        self.change_hardness_button.process_gui_event(window, event, &mut self.hardness_percent);
+        self.erase_by_color_checkbox.process_gui_event(window, event, &mut ());
+        if self.erase_by_color_checkbox.checked {
+            self.change_tolerance_button.process_gui_event(window, event, &mut self.tolerance_percent);
+        }
 
         return op;
     }
@@ -119,5 +209,14 @@ impl Tool for EraserDrawTool {
     fn render_ui(&mut self, renders: &Renders, transform: &Matrix4<f32>, _image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
         self.change_size_button.change_text(format!("Eraser size: {}", self.side_half_width * 2 + 1));
         self.change_size_button.render(renders, transform);
+
+        self.change_hardness_button.change_text(format!("Hardness: {} %", self.hardness_percent));
+        self.change_hardness_button.render(renders, transform);
+
+        self.erase_by_color_checkbox.render(renders, transform);
+        if self.erase_by_color_checkbox.checked {
+            self.change_tolerance_button.change_text(format!("Tolerance: {} %", self.tolerance_percent));
+            self.change_tolerance_button.render(renders, transform);
+        }
     }
 }
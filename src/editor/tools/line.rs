@@ -4,17 +4,29 @@ use cgmath::{Matrix3, Transform, Matrix4};
 use crate::rendering::prelude::{Position, Rectangle};
 use crate::{editor, content};
 use crate::command_buffer::{Command, CommandBuffer};
-use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
-use crate::editor::image_operation::{ImageOperation};
+use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow, constrain_angle};
+use crate::editor::image_operation::{ImageOperation, Symmetry};
 use crate::ui::button::{TextButton, GenericButton, Checkbox};
-use crate::program::Renders;
+use crate::program::{Renders, MAX_STROKE_WIDTH};
+
+// The dash lengths are relative to the line width
+const DASH_STYLES: [(&str, Option<(u32, u32)>); 4] = [
+    ("Solid", None),
+    ("Dashed", Some((4, 2))),
+    ("Dotted", Some((1, 1))),
+    ("Long dash", Some((8, 3)))
+];
 
 pub struct LineDrawTool {
     start_position: Option<Position>,
     end_position: Option<Position>,
     color: editor::Color,
     side_half_width: i32,
+    symmetry: Symmetry,
+    symmetry_axis: Position,
+    dash_style: usize,
     change_size_button: TextButton<i32>,
+    change_dash_style_button: TextButton<usize>,
     anti_aliasing_checkbox: Checkbox<()>
 }
 
@@ -25,18 +37,33 @@ impl LineDrawTool {
             end_position: None,
             color: image::Rgba([0, 0, 0, 255]),
             side_half_width: 1,
+            symmetry: Symmetry::None,
+            symmetry_axis: Position::new(0.0, 0.0),
+            dash_style: 0,
             change_size_button: TextButton::new(
                 renders.ui_font.clone(),
                 "".to_owned(),
                 Position::new(70.0, 10.0),
                 Some(Box::new(|side_half_width| {
-                    *side_half_width += 1;
+                    *side_half_width = (*side_half_width + 1).min(MAX_STROKE_WIDTH);
                 })),
                 Some(Box::new(|side_half_width| {
                     *side_half_width = (*side_half_width - 1).max(0);
                 })),
                 None,
             ),
+            change_dash_style_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(400.0, 10.0),
+                Some(Box::new(|dash_style| {
+                    *dash_style = (*dash_style + 1) % DASH_STYLES.len();
+                })),
+                Some(Box::new(|dash_style| {
+                    *dash_style = (*dash_style + DASH_STYLES.len() - 1) % DASH_STYLES.len();
+                })),
+                None,
+            ),
             anti_aliasing_checkbox: Checkbox::new(
                 &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
                 &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
@@ -50,7 +77,10 @@ impl LineDrawTool {
     }
 
     fn create_op(&self, start_position: &Position, end_position: &Position) -> ImageOperation {
-        ImageOperation::Line {
+        let line_width = (self.side_half_width * 2 + 1) as u32;
+        let dash = DASH_STYLES[self.dash_style].1.map(|(on_length, off_length)| (on_length * line_width, off_length * line_width));
+
+        let op = ImageOperation::Line {
             start_x: start_position.x as i32,
             start_y: start_position.y as i32,
             end_x: end_position.x as i32,
@@ -58,17 +88,29 @@ impl LineDrawTool {
             color: self.color,
             blend: true,
             anti_aliased: Some(self.anti_aliasing_checkbox.checked),
-            side_half_width: self.side_half_width
-        }
+            side_half_width: self.side_half_width,
+            dash
+        };
+
+        self.symmetry.apply(op, self.symmetry_axis)
     }
 }
 
 impl Tool for LineDrawTool {
     fn handle_command(&mut self, _command_buffer: &mut CommandBuffer, _image: &editor::Image, command: &Command) {
         match command {
+            Command::SetStrokeWidth(width) => {
+                self.side_half_width = (*width).clamp(0, MAX_STROKE_WIDTH);
+            }
             Command::SetPrimaryColor(color) => {
                 self.color = *color;
             }
+            Command::SetSymmetry(symmetry) => {
+                self.symmetry = *symmetry;
+            }
+            Command::SetSymmetryAxis(axis) => {
+                self.symmetry_axis = *axis;
+            }
             _ => {}
         }
     }
@@ -78,7 +120,7 @@ impl Tool for LineDrawTool {
                          event: &WindowEvent,
                          image_area_transform: &Matrix3<f32>,
                          _image_area_rectangle: &Rectangle,
-                         _command_buffer: &mut CommandBuffer,
+                         command_buffer: &mut CommandBuffer,
                          _image: &editor::Image) -> Option<ImageOperation> {
         let mut op = None;
 
@@ -97,12 +139,24 @@ impl Tool for LineDrawTool {
             }
             glfw::WindowEvent::CursorPos(raw_mouse_x, raw_mouse_y) => {
                 let mouse_position = image_area_transform.transform_point(cgmath::Point2::new(*raw_mouse_x as f32, *raw_mouse_y as f32));
-                self.end_position = Some(mouse_position);
+                match self.start_position.as_ref() {
+                    Some(start_position) if window.is_shift_down() => {
+                        self.end_position = Some(constrain_angle(start_position, &mouse_position));
+                    }
+                    _ => {
+                        self.end_position = Some(mouse_position);
+                    }
+                }
             }
             _ => {}
         }
 
+        let prev_side_half_width = self.side_half_width;
         self.change_size_button.process_gui_event(window, event, &mut self.side_half_width);
+        if self.side_half_width != prev_side_half_width {
+            command_buffer.push(Command::SetStrokeWidth(self.side_half_width));
+        }
+        self.change_dash_style_button.process_gui_event(window, event, &mut self.dash_style);
         self.anti_aliasing_checkbox.process_gui_event(window, event, &mut ());
 
         return op;
@@ -124,6 +178,9 @@ impl Tool for LineDrawTool {
         self.change_size_button.change_text(format!("Line width: {}", self.side_half_width * 2 + 1));
         self.change_size_button.render(renders, transform);
 
+        self.change_dash_style_button.change_text(format!("Style: {}", DASH_STYLES[self.dash_style].0));
+        self.change_dash_style_button.render(renders, transform);
+
         self.anti_aliasing_checkbox.render(renders, transform);
     }
 }
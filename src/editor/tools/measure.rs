@@ -0,0 +1,118 @@
+use glfw::{WindowEvent, Action};
+use cgmath::{Matrix3, Matrix4, Transform};
+
+use crate::rendering::prelude::{Position, Rectangle, Color4};
+use crate::editor;
+use crate::command_buffer::CommandBuffer;
+use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow, constrain_angle};
+use crate::editor::image_operation::ImageOperation;
+use crate::program::Renders;
+
+pub struct MeasureTool {
+    start_position: Option<Position>,
+    end_position: Option<Position>,
+    is_measuring: bool
+}
+
+impl MeasureTool {
+    pub fn new() -> MeasureTool {
+        MeasureTool {
+            start_position: None,
+            end_position: None,
+            is_measuring: false
+        }
+    }
+
+    fn measurement(&self) -> Option<(f32, f32)> {
+        if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
+            let delta_x = end_position.x - start_position.x;
+            let delta_y = end_position.y - start_position.y;
+            let length = (delta_x * delta_x + delta_y * delta_y).sqrt();
+
+            let mut angle = (-delta_y).atan2(delta_x).to_degrees();
+            if angle < 0.0 {
+                angle += 360.0;
+            }
+
+            Some((length, angle))
+        } else {
+            None
+        }
+    }
+}
+
+impl Tool for MeasureTool {
+    fn process_gui_event(&mut self,
+                         window: &mut dyn EditorWindow,
+                         event: &WindowEvent,
+                         image_area_transform: &Matrix3<f32>,
+                         _image_area_rectangle: &Rectangle,
+                         _command_buffer: &mut CommandBuffer,
+                         _image: &editor::Image) -> Option<ImageOperation> {
+        match event {
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
+                let position = get_transformed_mouse_position(window, image_area_transform);
+                self.start_position = Some(position);
+                self.end_position = Some(position);
+                self.is_measuring = true;
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Release, _) => {
+                self.is_measuring = false;
+            }
+            glfw::WindowEvent::CursorPos(raw_mouse_x, raw_mouse_y) => {
+                if self.is_measuring {
+                    let mouse_position = image_area_transform.transform_point(cgmath::Point2::new(*raw_mouse_x as f32, *raw_mouse_y as f32));
+
+                    if let Some(start_position) = self.start_position.as_ref() {
+                        if window.is_shift_down() {
+                            self.end_position = Some(constrain_angle(start_position, &mouse_position));
+                        } else {
+                            self.end_position = Some(mouse_position);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn preview(&mut self,
+               _image: &editor::Image,
+               _preview_image: &mut editor::Image,
+               _transparent_area: &mut Option<Rectangle>) -> bool {
+        false
+    }
+
+    fn status_text(&self) -> Option<String> {
+        self.measurement().map(|(length, angle)| format!("length: {:.1} px, angle: {:.1}°", length, angle))
+    }
+
+    fn render_image_area(&mut self, renders: &Renders, transform: &Matrix4<f32>, image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
+        if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
+            renders.rectangle_render.render_line(
+                renders.rectangle_render.shader(),
+                &(transform * image_area_transform),
+                *start_position,
+                *end_position,
+                Color4::new(0, 148, 255, 255)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_text() {
+        let mut tool = MeasureTool::new();
+        assert_eq!(None, tool.status_text());
+
+        tool.start_position = Some(Position::new(10.0, 10.0));
+        tool.end_position = Some(Position::new(13.0, 6.0));
+        assert_eq!(Some("length: 5.0 px, angle: 53.1°".to_owned()), tool.status_text());
+    }
+}
@@ -1,6 +1,6 @@
 use glfw::WindowEvent;
 
-use cgmath::{Matrix3, Transform, Matrix4};
+use cgmath::{Matrix3, Transform, Matrix4, SquareMatrix};
 
 use crate::command_buffer::{Command, CommandBuffer};
 use crate::editor;
@@ -18,6 +18,14 @@ use crate::program::Renders;
 use crate::editor::tools::color_gradient::ColorGradientDrawTool;
 use crate::editor::tools::color_wheel::ColorWheelTool;
 use crate::editor::tools::block_pencil::BlockPencilDrawTool;
+use crate::editor::tools::measure::MeasureTool;
+use crate::editor::tools::stamp::StampBrushTool;
+use crate::editor::tools::ellipse::EllipseDrawTool;
+use crate::editor::tools::spray::SprayDrawTool;
+use crate::editor::tools::rounded_rectangle::RoundedRectangleDrawTool;
+use crate::editor::tools::text::TextDrawTool;
+use crate::editor::tools::color_replace::ColorReplaceTool;
+use crate::editor::guides::{Guide, snap_position, snap_to_grid};
 
 pub mod pencil;
 pub mod block_pencil;
@@ -25,18 +33,31 @@ pub mod eraser;
 pub mod line;
 pub mod rectangle;
 pub mod circle;
+pub mod ellipse;
 pub mod bucket_fill;
 pub mod color_picker;
 pub mod color_gradient;
 pub mod color_wheel;
 pub mod selection;
+pub mod measure;
+pub mod stamp;
+pub mod spray;
+pub mod rounded_rectangle;
+pub mod text;
+pub mod color_replace;
 pub mod effect;
 
 pub trait EditorWindow {
     fn get_cursor_pos(&self) -> (f64, f64);
     fn is_shift_down(&self) -> bool;
+    fn is_alt_down(&self) -> bool;
     fn width(&self) -> u32;
     fn height(&self) -> u32;
+
+    // The pen pressure in [0, 1], if the input device reports one
+    fn pressure(&self) -> Option<f32> {
+        None
+    }
 }
 
 pub trait Tool {
@@ -71,6 +92,11 @@ pub trait Tool {
                preview_image: &mut editor::Image,
                transparent_area: &mut Option<Rectangle>) -> bool;
 
+    // Shown after the cursor position in the status bar
+    fn status_text(&self) -> Option<String> {
+        None
+    }
+
     fn render_ui(&mut self,
                  _renders: &Renders,
                  _transform: &Matrix4<f32>,
@@ -108,7 +134,14 @@ pub enum Tools {
     ColorPicker,
     ColorGradient,
     ColorWheel(SelectColorMode),
-    BlockPencil
+    BlockPencil,
+    Measure,
+    StampBrush,
+    Ellipse,
+    Spray,
+    RoundedRectangle,
+    Text,
+    ColorReplace
 }
 
 impl Tools {
@@ -125,16 +158,56 @@ impl Tools {
             Tools::ColorGradient => 8,
             Tools::ColorWheel(_) => 9,
             Tools::BlockPencil => 10,
+            Tools::Measure => 11,
+            Tools::StampBrush => 12,
+            Tools::Ellipse => 13,
+            Tools::Spray => 14,
+            Tools::RoundedRectangle => 15,
+            Tools::Text => 16,
+            Tools::ColorReplace => 17,
+        }
+    }
+
+    pub fn snaps_to_guides(&self) -> bool {
+        match self {
+            Tools::Line | Tools::Rectangle | Tools::RoundedRectangle | Tools::Circle | Tools::Ellipse | Tools::Selection(_) | Tools::Measure => true,
+            _ => false
         }
     }
 }
 
+// The tools selected by the number keys, in the order 1-9 and then 0
+pub const NUMBER_KEY_TOOLS: [Tools; 10] = [
+    Tools::Pencil,
+    Tools::Eraser,
+    Tools::Line,
+    Tools::Rectangle,
+    Tools::Circle,
+    Tools::Selection(SelectionSubTool::Select),
+    Tools::BucketFill,
+    Tools::ColorPicker,
+    Tools::ColorGradient,
+    Tools::Text
+];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SelectionSubTool {
     Select,
+    SelectByColor,
     MovePixels,
     ResizePixels,
-    RotatePixels
+    RotatePixels,
+    Polygon
+}
+
+impl SelectionSubTool {
+    // The feather radius applies when copying from the rectangle selection and when lifting the selected pixels
+    pub fn uses_feather(&self) -> bool {
+        match self {
+            SelectionSubTool::Select | SelectionSubTool::MovePixels | SelectionSubTool::ResizePixels | SelectionSubTool::RotatePixels => true,
+            SelectionSubTool::SelectByColor | SelectionSubTool::Polygon => false
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -150,12 +223,19 @@ pub fn create_tools(renders: &Renders) -> Vec<Box<dyn Tool>> {
         Box::new(LineDrawTool::new(renders)),
         Box::new(RectangleDrawTool::new(renders)),
         Box::new(CircleDrawTool::new(renders)),
-        Box::new(SelectionTool::new()),
+        Box::new(SelectionTool::new(renders)),
         Box::new(BucketFillDrawTool::new(renders)),
-        Box::new(ColorPickerTool::new()),
+        Box::new(ColorPickerTool::new(renders)),
         Box::new(ColorGradientDrawTool::new(renders)),
         Box::new(ColorWheelTool::new()),
         Box::new(BlockPencilDrawTool::new(renders)),
+        Box::new(MeasureTool::new()),
+        Box::new(StampBrushTool::new(renders)),
+        Box::new(EllipseDrawTool::new(renders)),
+        Box::new(SprayDrawTool::new(renders)),
+        Box::new(RoundedRectangleDrawTool::new(renders)),
+        Box::new(TextDrawTool::new(renders)),
+        Box::new(ColorReplaceTool::new(renders)),
     ]
 }
 
@@ -181,7 +261,130 @@ pub fn get_valid_rectangle_as_int(start_position: &Position, end_position: &Posi
     (start_x as i32, start_y as i32, end_x as i32, end_y as i32)
 }
 
+// Snaps the end position to the nearest multiple of 45 degrees around the start position
+pub fn constrain_angle(start_position: &Position, end_position: &Position) -> Position {
+    let delta_x = end_position.x - start_position.x;
+    let delta_y = end_position.y - start_position.y;
+    let length = (delta_x * delta_x + delta_y * delta_y).sqrt();
+
+    let step = std::f32::consts::PI / 4.0;
+    let angle = (delta_y.atan2(delta_x) / step).round() * step;
+
+    Position::new(
+        start_position.x + length * angle.cos(),
+        start_position.y + length * angle.sin()
+    )
+}
+
 pub fn get_transformed_mouse_position(window: &mut dyn EditorWindow, transform: &Matrix3<f32>) -> Position {
     let (mouse_x, mouse_y) = window.get_cursor_pos();
     transform.transform_point(cgmath::Point2::new(mouse_x as f32, mouse_y as f32))
+}
+
+// A window event together with the pen pressure of the input device, if it has one
+pub type InputEvent = (WindowEvent, Option<f32>);
+
+pub struct SnappingEditorWindow<'a> {
+    window: &'a mut dyn EditorWindow,
+    image_area_transform: &'a Matrix3<f32>,
+    canvas_rectangle: Rectangle,
+    guides: &'a [Guide],
+    grid_size: Option<u32>,
+    max_distance: f32,
+    pressure: Option<f32>
+}
+
+impl<'a> SnappingEditorWindow<'a> {
+    pub fn new(window: &'a mut dyn EditorWindow,
+               image_area_transform: &'a Matrix3<f32>,
+               canvas_rectangle: Rectangle,
+               guides: &'a [Guide],
+               grid_size: Option<u32>,
+               max_distance: f32,
+               pressure: Option<f32>) -> SnappingEditorWindow<'a> {
+        SnappingEditorWindow {
+            window,
+            image_area_transform,
+            canvas_rectangle,
+            guides,
+            grid_size,
+            max_distance,
+            pressure
+        }
+    }
+
+    pub fn snap_event(&self, event: WindowEvent) -> WindowEvent {
+        match event {
+            WindowEvent::CursorPos(mouse_x, mouse_y) => {
+                let (mouse_x, mouse_y) = self.snap(mouse_x, mouse_y);
+                WindowEvent::CursorPos(mouse_x, mouse_y)
+            }
+            event => event
+        }
+    }
+
+    fn snap(&self, mouse_x: f64, mouse_y: f64) -> (f64, f64) {
+        if self.guides.is_empty() && self.grid_size.is_none() {
+            return (mouse_x, mouse_y);
+        }
+
+        // Positions over the panels are not snapped, as the buttons of the tools are hit-tested with them
+        if !self.canvas_rectangle.contains(&Position::new(mouse_x as f32, mouse_y as f32)) {
+            return (mouse_x, mouse_y);
+        }
+
+        let position = self.image_area_transform.transform_point(cgmath::Point2::new(mouse_x as f32, mouse_y as f32));
+
+        // Guides take precedence over the grid when close enough
+        let mut snapped_position = snap_position(self.guides, position, self.max_distance);
+        if let Some(grid_size) = self.grid_size {
+            let grid_position = snap_to_grid(position, grid_size);
+            if snapped_position.x == position.x {
+                snapped_position.x = grid_position.x;
+            }
+
+            if snapped_position.y == position.y {
+                snapped_position.y = grid_position.y;
+            }
+        }
+
+        if snapped_position == position {
+            return (mouse_x, mouse_y);
+        }
+
+        match self.image_area_transform.invert() {
+            Some(inverse_transform) => {
+                let snapped_mouse_position = inverse_transform.transform_point(snapped_position);
+                (snapped_mouse_position.x as f64, snapped_mouse_position.y as f64)
+            }
+            None => (mouse_x, mouse_y)
+        }
+    }
+}
+
+impl<'a> EditorWindow for SnappingEditorWindow<'a> {
+    fn get_cursor_pos(&self) -> (f64, f64) {
+        let (mouse_x, mouse_y) = self.window.get_cursor_pos();
+        self.snap(mouse_x, mouse_y)
+    }
+
+    fn is_shift_down(&self) -> bool {
+        self.window.is_shift_down()
+    }
+
+    fn is_alt_down(&self) -> bool {
+        self.window.is_alt_down()
+    }
+
+    fn width(&self) -> u32 {
+        self.window.width()
+    }
+
+    fn height(&self) -> u32 {
+        self.window.height()
+    }
+
+    fn pressure(&self) -> Option<f32> {
+        self.pressure
+    }
 }
\ No newline at end of file
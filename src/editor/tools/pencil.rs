@@ -1,14 +1,17 @@
 use glfw::{WindowEvent, Action};
-use cgmath::{Matrix3, Transform, Matrix4};
+use cgmath::{Matrix3, Transform, Matrix4, InnerSpace};
 
 use crate::rendering::prelude::{Position, Rectangle};
 use crate::{editor, content};
 use crate::command_buffer::{Command, CommandBuffer};
 use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
-use crate::editor::image_operation::{ImageOperation, ImageOperationMarker};
-use crate::program::Renders;
+use crate::editor::image_operation::{ImageOperation, ImageOperationMarker, Symmetry};
+use crate::program::{Renders, MAX_BRUSH_SIZE};
 use crate::ui::button::{TextButton, GenericButton, Checkbox};
 
+// The stroke goes back and forth between the two colors over this distance
+const GRADIENT_STROKE_LENGTH: f32 = 200.0;
+
 pub struct PencilDrawTool {
     is_drawing: Option<editor::Color>,
     prev_mouse_position: Option<Position>,
@@ -16,8 +19,12 @@ pub struct PencilDrawTool {
     color: editor::Color,
     alternative_color: editor::Color,
     side_half_width: i32,
+    symmetry: Symmetry,
+    symmetry_axis: Position,
+    stroke_length: f32,
     change_size_button: TextButton<i32>,
-    anti_aliasing_checkbox: Checkbox<()>
+    anti_aliasing_checkbox: Checkbox<()>,
+    gradient_checkbox: Checkbox<()>
 }
 
 impl PencilDrawTool {
@@ -29,12 +36,15 @@ impl PencilDrawTool {
             color: image::Rgba([0, 0, 0, 255]),
             alternative_color: image::Rgba([0, 0, 0, 255]),
             side_half_width: 1,
+            symmetry: Symmetry::None,
+            symmetry_axis: Position::new(0.0, 0.0),
+            stroke_length: 0.0,
             change_size_button: TextButton::new(
                 renders.ui_font.clone(),
                 "".to_owned(),
                 Position::new(70.0, 10.0),
                 Some(Box::new(|side_half_width| {
-                    *side_half_width = (*side_half_width + 1).min(100);
+                    *side_half_width = (*side_half_width + 1).min(MAX_BRUSH_SIZE);
                 })),
                 Some(Box::new(|side_half_width| {
                     *side_half_width = (*side_half_width - 1).max(0);
@@ -49,9 +59,40 @@ impl PencilDrawTool {
                 true,
                 Position::new(235.0, 16.0),
                 None
+            ),
+            gradient_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Color gradient".to_owned(),
+                false,
+                Position::new(400.0, 16.0),
+                None
             )
         }
     }
+
+    fn gradient_color(&self, start_color: editor::Color) -> editor::Color {
+        let end_color = if start_color == self.color { self.alternative_color } else { self.color };
+
+        let phase = (self.stroke_length / GRADIENT_STROKE_LENGTH) % 2.0;
+        let factor = if phase > 1.0 { 2.0 - phase } else { phase };
+
+        let mut color = start_color;
+        for channel in 0..4 {
+            color[channel] = (start_color[channel] as f32 * (1.0 - factor) + end_color[channel] as f32 * factor).round() as u8;
+        }
+
+        color
+    }
+
+    fn pressure_side_half_width(&self, window: &dyn EditorWindow) -> i32 {
+        // Without a pressure axis (e.g. a mouse), the width stays constant
+        match window.pressure() {
+            Some(pressure) => (self.side_half_width as f32 * pressure.clamp(0.0, 1.0)).round() as i32,
+            None => self.side_half_width
+        }
+    }
 }
 
 impl Tool for PencilDrawTool {
@@ -63,6 +104,15 @@ impl Tool for PencilDrawTool {
             Command::SetSecondaryColor(color) => {
                 self.alternative_color = *color;
             }
+            Command::SetBrushSize(size) => {
+                self.side_half_width = (*size).clamp(0, MAX_BRUSH_SIZE);
+            }
+            Command::SetSymmetry(symmetry) => {
+                self.symmetry = *symmetry;
+            }
+            Command::SetSymmetryAxis(axis) => {
+                self.symmetry_axis = *axis;
+            }
             _ => {}
         }
     }
@@ -72,51 +122,49 @@ impl Tool for PencilDrawTool {
                          event: &WindowEvent,
                          image_area_transform: &Matrix3<f32>,
                          image_area_rectangle: &Rectangle,
-                         _command_buffer: &mut CommandBuffer,
+                         command_buffer: &mut CommandBuffer,
                          _image: &editor::Image) -> Option<ImageOperation> {
-        let create_begin_draw = |this: &Self, mouse_position: Position, color: editor::Color| {
-            if this.anti_aliasing_checkbox.checked {
-                Some(
-                    ImageOperation::Sequential(
-                        Some("Pencil stroke".to_owned()),
-                        vec![
-                            ImageOperation::Marker(ImageOperationMarker::BeginDraw, Some("Pencil stroke".to_owned())),
-                            ImageOperation::FillCircle {
-                                center_x: mouse_position.x as i32,
-                                center_y: mouse_position.y as i32,
-                                radius: this.side_half_width,
-                                color,
-                                blend: false
-                            },
-                            ImageOperation::Circle {
-                                center_x: mouse_position.x as i32,
-                                center_y: mouse_position.y as i32,
-                                radius: this.side_half_width - 4,
-                                border_half_width: 2,
-                                color,
-                                blend: false,
-                                anti_aliased: Some(this.anti_aliasing_checkbox.checked)
-                            }
-                        ]
-                    )
+        let create_begin_draw = |this: &Self, mouse_position: Position, color: editor::Color, side_half_width: i32| {
+            let op = if this.anti_aliasing_checkbox.checked {
+                ImageOperation::Sequential(
+                    Some("Pencil stroke".to_owned()),
+                    vec![
+                        ImageOperation::Marker(ImageOperationMarker::BeginDraw, Some("Pencil stroke".to_owned())),
+                        ImageOperation::FillCircle {
+                            center_x: mouse_position.x as i32,
+                            center_y: mouse_position.y as i32,
+                            radius: side_half_width,
+                            color,
+                            blend: false
+                        },
+                        ImageOperation::Circle {
+                            center_x: mouse_position.x as i32,
+                            center_y: mouse_position.y as i32,
+                            radius: side_half_width - 4,
+                            border_half_width: 2,
+                            color,
+                            blend: false,
+                            anti_aliased: Some(this.anti_aliasing_checkbox.checked)
+                        }
+                    ]
                 )
             } else {
-                Some(
-                    ImageOperation::Sequential(
-                        Some("Pencil stroke".to_owned()),
-                        vec![
-                            ImageOperation::Marker(ImageOperationMarker::BeginDraw, Some("Pencil stroke".to_owned())),
-                            ImageOperation::FillCircle {
-                                center_x: mouse_position.x as i32,
-                                center_y: mouse_position.y as i32,
-                                radius: this.side_half_width,
-                                color,
-                                blend: false
-                            }
-                        ]
-                    )
+                ImageOperation::Sequential(
+                    Some("Pencil stroke".to_owned()),
+                    vec![
+                        ImageOperation::Marker(ImageOperationMarker::BeginDraw, Some("Pencil stroke".to_owned())),
+                        ImageOperation::FillCircle {
+                            center_x: mouse_position.x as i32,
+                            center_y: mouse_position.y as i32,
+                            radius: side_half_width,
+                            color,
+                            blend: false
+                        }
+                    ]
                 )
-            }
+            };
+
+            Some(this.symmetry.apply(op, this.symmetry_axis))
         };
 
         let mut op = None;
@@ -128,8 +176,9 @@ impl Tool for PencilDrawTool {
                     self.is_drawing = Some(self.color);
 
                     if !already_drawing {
+                        self.stroke_length = 0.0;
                         let mouse_position = get_transformed_mouse_position(window, image_area_transform);
-                        op = create_begin_draw(self, mouse_position, self.color);
+                        op = create_begin_draw(self, mouse_position, self.color, self.pressure_side_half_width(window));
                         self.prev_mouse_position = Some(mouse_position);
                     }
                 }
@@ -142,7 +191,13 @@ impl Tool for PencilDrawTool {
                     self.is_drawing = Some(self.alternative_color);
 
                     if !already_drawing {
-                        op = create_begin_draw(self, get_transformed_mouse_position(window, image_area_transform), self.alternative_color);
+                        self.stroke_length = 0.0;
+                        op = create_begin_draw(
+                            self,
+                            get_transformed_mouse_position(window, image_area_transform),
+                            self.alternative_color,
+                            self.pressure_side_half_width(window)
+                        );
                     }
                 }
             }
@@ -159,6 +214,15 @@ impl Tool for PencilDrawTool {
                     let mouse_position = image_area_transform.transform_point(cgmath::Point2::new(*raw_mouse_x as f32, *raw_mouse_y as f32));
 
                     if let Some(prev_mouse_position) = self.prev_mouse_position {
+                        let color = if self.gradient_checkbox.checked {
+                            self.stroke_length += (mouse_position - prev_mouse_position).magnitude();
+                            self.gradient_color(color)
+                        } else {
+                            color
+                        };
+
+                        let side_half_width = self.pressure_side_half_width(window);
+
                         let mut ops = Vec::new();
 
                         if self.anti_aliasing_checkbox.checked {
@@ -166,7 +230,7 @@ impl Tool for PencilDrawTool {
                                 ImageOperation::FillCircle {
                                     center_x: mouse_position.x as i32,
                                     center_y: mouse_position.y as i32,
-                                    radius: self.side_half_width,
+                                    radius: side_half_width,
                                     color,
                                     blend: false
                                 }
@@ -176,7 +240,7 @@ impl Tool for PencilDrawTool {
                                 ImageOperation::Circle {
                                     center_x: mouse_position.x as i32,
                                     center_y: mouse_position.y as i32,
-                                    radius: self.side_half_width - 4,
+                                    radius: side_half_width - 4,
                                     border_half_width: 2,
                                     color,
                                     blend: false,
@@ -196,11 +260,14 @@ impl Tool for PencilDrawTool {
                                 color,
                                 blend: false,
                                 anti_aliased: Some(self.anti_aliasing_checkbox.checked),
-                                side_half_width: self.side_half_width
+                                side_half_width
                             }
                         );
 
-                        op = Some(ImageOperation::Sequential(Some("Pencil stroke".to_owned()), ops));
+                        op = Some(self.symmetry.apply(
+                            ImageOperation::Sequential(Some("Pencil stroke".to_owned()), ops),
+                            self.symmetry_axis
+                        ));
                     }
 
                     self.prev_prev_mouse_position = self.prev_mouse_position;
@@ -210,8 +277,13 @@ impl Tool for PencilDrawTool {
             _ => {}
         }
 
+        let prev_side_half_width = self.side_half_width;
         self.change_size_button.process_gui_event(window, event, &mut self.side_half_width);
+        if self.side_half_width != prev_side_half_width {
+            command_buffer.push(Command::SetBrushSize(self.side_half_width));
+        }
         self.anti_aliasing_checkbox.process_gui_event(window, event, &mut ());
+        self.gradient_checkbox.process_gui_event(window, event, &mut ());
 
         return op;
     }
@@ -228,5 +300,6 @@ impl Tool for PencilDrawTool {
         self.change_size_button.render(renders, transform);
 
         self.anti_aliasing_checkbox.render(renders, transform);
+        self.gradient_checkbox.render(renders, transform);
     }
 }
@@ -7,17 +7,20 @@ use crate::command_buffer::{Command, CommandBuffer};
 use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow, get_valid_rectangle_as_int};
 use crate::editor::image_operation::{ImageOperation};
 use crate::ui::button::{TextButton, GenericButton, Checkbox};
-use crate::program::Renders;
+use crate::program::{Renders, MAX_STROKE_WIDTH};
 
 pub struct RectangleDrawTool {
     start_position: Option<Position>,
     end_position: Option<Position>,
     is_alternative_mode: bool,
-    border_color: editor::Color,
-    fill_color: editor::Color,
+    draw_from_center: bool,
+    is_square: bool,
+    primary_color: editor::Color,
+    secondary_color: editor::Color,
     border_half_width: i32,
     change_border_size_button: TextButton<i32>,
-    border_checkbox: Checkbox<()>
+    border_checkbox: Checkbox<()>,
+    fill_checkbox: Checkbox<()>
 }
 
 impl RectangleDrawTool {
@@ -26,15 +29,17 @@ impl RectangleDrawTool {
             start_position: None,
             end_position: None,
             is_alternative_mode: false,
-            border_color: image::Rgba([0, 0, 0, 255]),
-            fill_color: image::Rgba([255, 0, 0, 255]),
+            draw_from_center: false,
+            is_square: false,
+            primary_color: image::Rgba([255, 0, 0, 255]),
+            secondary_color: image::Rgba([0, 0, 0, 255]),
             border_half_width: 0,
             change_border_size_button: TextButton::new(
                 renders.ui_font.clone(),
                 "".to_owned(),
                 Position::new(70.0, 10.0),
                 Some(Box::new(|border_half_width| {
-                    *border_half_width += 1;
+                    *border_half_width = (*border_half_width + 1).min(MAX_STROKE_WIDTH);
                 })),
                 Some(Box::new(|border_half_width| {
                     *border_half_width = (*border_half_width - 1).max(0);
@@ -49,6 +54,38 @@ impl RectangleDrawTool {
                 true,
                 Position::new(235.0, 16.0),
                 None
+            ),
+            fill_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Fill".to_owned(),
+                true,
+                Position::new(335.0, 16.0),
+                None
+            )
+        }
+    }
+
+    fn get_bounds(&self, start_position: &Position, end_position: &Position) -> (Position, Position) {
+        let mut delta_x = end_position.x - start_position.x;
+        let mut delta_y = end_position.y - start_position.y;
+
+        if self.is_square {
+            let size = delta_x.abs().max(delta_y.abs());
+            delta_x = size.copysign(delta_x);
+            delta_y = size.copysign(delta_y);
+        }
+
+        if self.draw_from_center {
+            (
+                Position::new(start_position.x - delta_x, start_position.y - delta_y),
+                Position::new(start_position.x + delta_x, start_position.y + delta_y)
+            )
+        } else {
+            (
+                *start_position,
+                Position::new(start_position.x + delta_x, start_position.y + delta_y)
             )
         }
     }
@@ -56,19 +93,27 @@ impl RectangleDrawTool {
     fn create_op(&self,
                  start_position: &Position,
                  end_position: &Position,
-                 fill_color: editor::Color,
-                 border_color: editor::Color) -> ImageOperation {
-        let (start_x, start_y, end_x, end_y) = get_valid_rectangle_as_int(start_position, end_position);
-        let mut ops = vec![
-            ImageOperation::FillRectangle {
-                start_x,
-                start_y,
-                end_x,
-                end_y,
-                color: fill_color,
-                blend: true
-            }
-        ];
+                 primary_color: editor::Color,
+                 secondary_color: editor::Color) -> Option<ImageOperation> {
+        let (start_position, end_position) = self.get_bounds(start_position, end_position);
+        let (start_x, start_y, end_x, end_y) = get_valid_rectangle_as_int(&start_position, &end_position);
+
+        // When both are drawn, the outline uses the primary color and the fill the secondary
+        let fill_color = if self.border_checkbox.checked { secondary_color } else { primary_color };
+
+        let mut ops = Vec::new();
+        if self.fill_checkbox.checked {
+            ops.push(
+                ImageOperation::FillRectangle {
+                    start_x,
+                    start_y,
+                    end_x,
+                    end_y,
+                    color: fill_color,
+                    blend: true
+                }
+            );
+        }
 
         if self.border_checkbox.checked {
             ops.push(
@@ -77,25 +122,32 @@ impl RectangleDrawTool {
                     start_y,
                     end_x,
                     end_y,
-                    color: border_color,
+                    color: primary_color,
                     blend: true,
                     border_half_width: self.border_half_width
                 }
             );
         }
 
-        ImageOperation::Sequential(Some("Rectangle".to_owned()), ops)
+        if ops.is_empty() {
+            return None;
+        }
+
+        Some(ImageOperation::Sequential(Some("Rectangle".to_owned()), ops))
     }
 }
 
 impl Tool for RectangleDrawTool {
     fn handle_command(&mut self, _command_buffer: &mut CommandBuffer, _image: &editor::Image, command: &Command) {
         match command {
+            Command::SetStrokeWidth(width) => {
+                self.border_half_width = (*width).clamp(0, MAX_STROKE_WIDTH);
+            }
             Command::SetPrimaryColor(color) => {
-                self.fill_color = *color;
+                self.primary_color = *color;
             }
             Command::SetSecondaryColor(color) => {
-                self.border_color = *color;
+                self.secondary_color = *color;
             }
             _ => {}
         }
@@ -106,8 +158,11 @@ impl Tool for RectangleDrawTool {
                          event: &WindowEvent,
                          image_area_transform: &Matrix3<f32>,
                          _image_area_rectangle: &Rectangle,
-                         _command_buffer: &mut CommandBuffer,
+                         command_buffer: &mut CommandBuffer,
                          _image: &editor::Image) -> Option<ImageOperation> {
+        self.draw_from_center = window.is_alt_down();
+        self.is_square = window.is_shift_down();
+
         let mut op = None;
         match event {
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
@@ -117,7 +172,7 @@ impl Tool for RectangleDrawTool {
             }
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Release, _) => {
                 if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
-                    op = Some(self.create_op(start_position, end_position, self.fill_color, self.border_color));
+                    op = self.create_op(start_position, end_position, self.primary_color, self.secondary_color);
                 }
 
                 self.start_position = None;
@@ -130,7 +185,7 @@ impl Tool for RectangleDrawTool {
             }
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button2, Action::Release, _) => {
                 if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
-                    op = Some(self.create_op(start_position, end_position, self.border_color, self.fill_color));
+                    op = self.create_op(start_position, end_position, self.secondary_color, self.primary_color);
                 }
 
                 self.start_position = None;
@@ -143,8 +198,13 @@ impl Tool for RectangleDrawTool {
             _ => {}
         }
 
+        let prev_border_half_width = self.border_half_width;
         self.change_border_size_button.process_gui_event(window, event, &mut self.border_half_width);
+        if self.border_half_width != prev_border_half_width {
+            command_buffer.push(Command::SetStrokeWidth(self.border_half_width));
+        }
         self.border_checkbox.process_gui_event(window, event, &mut ());
+        self.fill_checkbox.process_gui_event(window, event, &mut ());
 
         return op;
     }
@@ -155,13 +215,15 @@ impl Tool for RectangleDrawTool {
                _transparent_area: &mut Option<Rectangle>) -> bool {
         let mut update_op = preview_image.update_operation();
         if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
-            let (fill_color, border_color) = if !self.is_alternative_mode {
-                (self.fill_color, self.border_color)
+            let (primary_color, secondary_color) = if !self.is_alternative_mode {
+                (self.primary_color, self.secondary_color)
             } else {
-                (self.border_color, self.fill_color)
+                (self.secondary_color, self.primary_color)
             };
 
-            self.create_op(start_position, end_position, fill_color, border_color).apply(&mut update_op, false);
+            if let Some(op) = self.create_op(start_position, end_position, primary_color, secondary_color) {
+                op.apply(&mut update_op, false);
+            }
         }
 
         return true;
@@ -172,5 +234,6 @@ impl Tool for RectangleDrawTool {
         self.change_border_size_button.render(renders, transform);
 
         self.border_checkbox.render(renders, transform);
+        self.fill_checkbox.render(renders, transform);
     }
 }
@@ -0,0 +1,153 @@
+use glfw::{WindowEvent, Action};
+use cgmath::{Matrix3, Transform, Matrix4};
+
+use crate::rendering::prelude::{Position, Rectangle};
+use crate::editor;
+use crate::command_buffer::{Command, CommandBuffer};
+use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow, get_valid_rectangle_as_int};
+use crate::editor::image_operation::{ImageOperation};
+use crate::ui::button::{TextButton, GenericButton};
+use crate::program::{Renders, MAX_STROKE_WIDTH};
+
+pub struct RoundedRectangleDrawTool {
+    start_position: Option<Position>,
+    end_position: Option<Position>,
+    is_alternative_mode: bool,
+    primary_color: editor::Color,
+    secondary_color: editor::Color,
+    border_half_width: i32,
+    corner_radius: i32,
+    change_border_size_button: TextButton<i32>,
+    change_corner_radius_button: TextButton<i32>
+}
+
+impl RoundedRectangleDrawTool {
+    pub fn new(renders: &Renders) -> RoundedRectangleDrawTool {
+        RoundedRectangleDrawTool {
+            start_position: None,
+            end_position: None,
+            is_alternative_mode: false,
+            primary_color: image::Rgba([255, 0, 0, 255]),
+            secondary_color: image::Rgba([0, 0, 0, 255]),
+            border_half_width: 1,
+            corner_radius: 10,
+            change_border_size_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(70.0, 10.0),
+                Some(Box::new(|border_half_width| {
+                    *border_half_width = (*border_half_width + 1).min(MAX_STROKE_WIDTH);
+                })),
+                Some(Box::new(|border_half_width| {
+                    *border_half_width = (*border_half_width - 1).max(0);
+                })),
+                None,
+            ),
+            change_corner_radius_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(235.0, 10.0),
+                Some(Box::new(|corner_radius| {
+                    *corner_radius = (*corner_radius + 1).min(200);
+                })),
+                Some(Box::new(|corner_radius| {
+                    *corner_radius = (*corner_radius - 1).max(0);
+                })),
+                None,
+            )
+        }
+    }
+
+    fn create_op(&self, start_position: &Position, end_position: &Position, color: editor::Color) -> ImageOperation {
+        let (start_x, start_y, end_x, end_y) = get_valid_rectangle_as_int(start_position, end_position);
+
+        ImageOperation::RoundedRectangle {
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+            corner_radius: self.corner_radius,
+            border_half_width: self.border_half_width,
+            color,
+            blend: true
+        }
+    }
+}
+
+impl Tool for RoundedRectangleDrawTool {
+    fn handle_command(&mut self, _command_buffer: &mut CommandBuffer, _image: &editor::Image, command: &Command) {
+        match command {
+            Command::SetPrimaryColor(color) => {
+                self.primary_color = *color;
+            }
+            Command::SetSecondaryColor(color) => {
+                self.secondary_color = *color;
+            }
+            Command::SetStrokeWidth(width) => {
+                self.border_half_width = (*width).clamp(0, MAX_STROKE_WIDTH);
+            }
+            _ => {}
+        }
+    }
+
+    fn process_gui_event(&mut self,
+                         window: &mut dyn EditorWindow,
+                         event: &WindowEvent,
+                         image_area_transform: &Matrix3<f32>,
+                         _image_area_rectangle: &Rectangle,
+                         command_buffer: &mut CommandBuffer,
+                         _image: &editor::Image) -> Option<ImageOperation> {
+        let mut op = None;
+        match event {
+            glfw::WindowEvent::MouseButton(button @ (glfw::MouseButton::Button1 | glfw::MouseButton::Button2), Action::Press, _) => {
+                self.start_position = Some(get_transformed_mouse_position(window, image_area_transform));
+                self.end_position = None;
+                self.is_alternative_mode = *button == glfw::MouseButton::Button2;
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1 | glfw::MouseButton::Button2, Action::Release, _) => {
+                if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
+                    let color = if !self.is_alternative_mode { self.primary_color } else { self.secondary_color };
+                    op = Some(self.create_op(start_position, end_position, color));
+                }
+
+                self.start_position = None;
+                self.end_position = None;
+            }
+            glfw::WindowEvent::CursorPos(raw_mouse_x, raw_mouse_y) => {
+                let mouse_position = image_area_transform.transform_point(cgmath::Point2::new(*raw_mouse_x as f32, *raw_mouse_y as f32));
+                self.end_position = Some(mouse_position);
+            }
+            _ => {}
+        }
+
+        let prev_border_half_width = self.border_half_width;
+        self.change_border_size_button.process_gui_event(window, event, &mut self.border_half_width);
+        if self.border_half_width != prev_border_half_width {
+            command_buffer.push(Command::SetStrokeWidth(self.border_half_width));
+        }
+        self.change_corner_radius_button.process_gui_event(window, event, &mut self.corner_radius);
+
+        return op;
+    }
+
+    fn preview(&mut self,
+               _image: &editor::Image,
+               preview_image: &mut editor::Image,
+               _transparent_area: &mut Option<Rectangle>) -> bool {
+        let mut update_op = preview_image.update_operation();
+        if let (Some(start_position), Some(end_position)) = (self.start_position.as_ref(), self.end_position.as_ref()) {
+            let color = if !self.is_alternative_mode { self.primary_color } else { self.secondary_color };
+            self.create_op(start_position, end_position, color).apply(&mut update_op, false);
+        }
+
+        return true;
+    }
+
+    fn render_ui(&mut self, renders: &Renders, transform: &Matrix4<f32>, _image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
+        self.change_border_size_button.change_text(format!("Border size: {}", self.border_half_width * 2 + 1));
+        self.change_border_size_button.render(renders, transform);
+
+        self.change_corner_radius_button.change_text(format!("Corner radius: {}", self.corner_radius));
+        self.change_corner_radius_button.render(renders, transform);
+    }
+}
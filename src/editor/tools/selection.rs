@@ -1,21 +1,28 @@
+use std::time::Instant;
+use std::ops::DerefMut;
+
 use glfw::{WindowEvent, Action, Key, Modifiers};
 use cgmath::{Matrix3, Transform, Matrix4, EuclideanSpace};
 
-use crate::rendering::prelude::{Position, Rectangle, Size, Color4};
-use crate::editor;
+use crate::rendering::prelude::{Position, Rectangle, Size, Color, Color4};
+use crate::rendering::text_render::TextAlignment;
+use crate::rendering::texture::Texture;
+use crate::{editor, content};
 use crate::command_buffer::{Command, CommandBuffer};
 use crate::editor::tools::{Tool, get_valid_rectangle, SelectionSubTool, Tools, get_transformed_mouse_position, EditorWindow, get_valid_rectangle_as_int};
 use crate::editor::image_operation::{ImageOperation, ImageSource, add_op_sequential, select_latest};
-use crate::editor::image_operation_helpers::sub_image;
-use crate::program::Renders;
-use crate::editor::Region;
+use crate::editor::image_operation_helpers::{sub_image_region, select_by_color, select_polygon, feather_mask};
+use crate::ui::button::{TextButton, GenericButton, Checkbox};
+use crate::program::{Renders, ProgramAction, ProgramActionData};
+use crate::editor::{Region, SelectionMask};
 
 #[derive(Debug, Clone)]
 pub struct Selection {
     pub start_x: i32,
     pub start_y: i32,
     pub end_x: i32,
-    pub end_y: i32
+    pub end_y: i32,
+    pub mask: Option<SelectionMask>
 }
 
 impl Selection {
@@ -46,14 +53,38 @@ impl Selection {
             self.start_y as i32,
             (self.end_x - self.start_x) as i32,
             (self.end_y - self.start_y) as i32
-        )
+        ).with_mask(self.mask.clone())
+    }
+
+    pub fn image<T: ImageSource>(&self, image: &T) -> image::RgbaImage {
+        sub_image_region(image, &self.region())
+    }
+
+    pub fn feathered_image<T: ImageSource>(&self, image: &T, feather_radius: i32) -> image::RgbaImage {
+        let mut image = self.image(image);
+        feather_mask(&mut image, &self.region(), feather_radius);
+        image
     }
 }
 
 struct SelectState {
-    is_selecting: bool,
-    copied_image: Option<image::RgbaImage>,
-    triggered_resize: bool
+    is_selecting: bool
+}
+
+const POLYGON_DOUBLE_CLICK_TIME: f32 = 0.4;
+
+struct PolygonSelectState {
+    points: Vec<Position>,
+    mouse_position: Option<Position>,
+    last_click: Option<Instant>
+}
+
+impl PolygonSelectState {
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.mouse_position = None;
+        self.last_click = None;
+    }
 }
 
 struct MovePixelsState {
@@ -105,27 +136,77 @@ pub struct SelectionTool {
     changed_selection: bool,
     start_position: Option<Position>,
     end_position: Option<Position>,
+    mask: Option<SelectionMask>,
+    mask_texture: Option<Texture>,
+    select_by_color_tolerance: f32,
+    change_tolerance_button: TextButton<f32>,
+    feather_radius: i32,
+    change_feather_button: TextButton<i32>,
+    contiguous_checkbox: Checkbox<()>,
     skip_erase_original_selection: bool,
+    own_selection_clears: usize,
     select_state: SelectState,
+    polygon_select_state: PolygonSelectState,
     move_pixels_state: MovePixelsState,
     resize_pixels_state: ResizePixelsState,
     rotate_pixels_state: RotatePixelsState
 }
 
 impl SelectionTool {
-    pub fn new() -> SelectionTool {
+    pub fn new(renders: &Renders) -> SelectionTool {
         SelectionTool {
             tool: SelectionSubTool::Select,
             changed_selection: false,
             start_position: None,
             end_position: None,
+            mask: None,
+            mask_texture: None,
+            select_by_color_tolerance: 0.1,
+            change_tolerance_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(70.0, 10.0),
+                Some(Box::new(|tolerance| {
+                    *tolerance = (*tolerance + 0.05).min(1.0);
+                })),
+                Some(Box::new(|tolerance| {
+                    *tolerance = (*tolerance - 0.05).max(0.0);
+                })),
+                None,
+            ),
+            feather_radius: 0,
+            change_feather_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(400.0, 10.0),
+                Some(Box::new(|feather_radius| {
+                    *feather_radius = (*feather_radius + 1).min(100);
+                })),
+                Some(Box::new(|feather_radius| {
+                    *feather_radius = (*feather_radius - 1).max(0);
+                })),
+                None,
+            ),
+            contiguous_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Contiguous".to_owned(),
+                true,
+                Position::new(235.0, 16.0),
+                None
+            ),
             // start_position: Some(Position::new(243.0, 325.0)),
             // end_position: Some(Position::new(739.0, 545.0)),
             skip_erase_original_selection: false,
+            own_selection_clears: 0,
             select_state: SelectState {
-                is_selecting: false,
-                copied_image: None,
-                triggered_resize: false
+                is_selecting: false
+            },
+            polygon_select_state: PolygonSelectState {
+                points: Vec::new(),
+                mouse_position: None,
+                last_click: None
             },
             move_pixels_state: MovePixelsState {
                 original_selection: None,
@@ -147,6 +228,14 @@ impl SelectionTool {
         }
     }
 
+    fn active_feather_radius(&self) -> i32 {
+        if self.tool.uses_feather() {
+            self.feather_radius
+        } else {
+            0
+        }
+    }
+
     fn selection(&self) -> Option<Selection> {
         match (self.start_position, self.end_position) {
             (Some(start_position), Some(end_position)) => {
@@ -156,7 +245,8 @@ impl SelectionTool {
                         start_x,
                         start_y,
                         end_x,
-                        end_y
+                        end_y,
+                        mask: self.mask.clone()
                     }
                 )
             }
@@ -192,23 +282,11 @@ impl SelectionTool {
             glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
                 let (mouse_x, mouse_y) = window.get_cursor_pos();
                 if image_area_rectangle.contains(&Position::new(mouse_x as f32, mouse_y as f32)) {
-                    if self.move_pixels_state.moved_pixels_image.is_some() {
-                        add_op_sequential(&mut op, self.create_move(false));
-                        self.move_pixels_state.clear();
-                    }
-
-                    if self.resize_pixels_state.resize_pixels_image.is_some() {
-                        add_op_sequential(&mut op, self.create_resize(false));
-                        self.resize_pixels_state.clear();
-                    }
-
-                    if self.rotate_pixels_state.rotate_pixels_image.is_some() {
-                        add_op_sequential(&mut op, self.create_rotation(false));
-                        self.rotate_pixels_state.clear();
-                    }
+                    self.apply_transformed_pixels(&mut op);
 
                     self.set_start_position(Some(get_transformed_mouse_position(window, image_area_transform)));
                     self.set_end_position(None);
+                    self.set_mask(None);
                     self.select_state.is_selecting = true;
                 }
             }
@@ -232,16 +310,7 @@ impl SelectionTool {
             }
             glfw::WindowEvent::Key(Key::Delete, _, Action::Press, _) => {
                 if let Some(selection) = self.selection() {
-                    op = Some(
-                        ImageOperation::FillRectangle {
-                            start_x: selection.start_x,
-                            start_y: selection.start_y,
-                            end_x: selection.end_x,
-                            end_y: selection.end_y,
-                            color: image::Rgba([0, 0, 0, 0]),
-                            blend: false
-                        }
-                    );
+                    op = Some(self.create_erased_area(&selection, false));
 
                     self.set_start_position(None);
                     self.set_end_position(None);
@@ -249,41 +318,16 @@ impl SelectionTool {
             }
             glfw::WindowEvent::Key(Key::C, _, Action::Press, Modifiers::Control) => {
                 if let Some(selection) = self.selection() {
-                    let copied_image = sub_image(image, selection.start_x, selection.start_y, selection.end_x, selection.end_y);
-                    command_buffer.push(Command::SetCopiedImage(copied_image.clone()));
-                    self.select_state.copied_image = Some(copied_image);
+                    command_buffer.push(Command::SetCopiedImage(selection.feathered_image(image, self.active_feather_radius())));
 
                     self.set_start_position(None);
                     self.set_end_position(None);
                 }
             }
-            glfw::WindowEvent::Key(Key::V, _, Action::Press, Modifiers::Control) => {
-                if let Some(copied_image) = self.select_state.copied_image.as_ref() {
-                    if (copied_image.width() <= image.width() && copied_image.height() <= image.height()) || self.select_state.triggered_resize {
-                        let copied_image = copied_image.clone();
-                        self.handle_paste(copied_image);
-                    } else {
-                        self.select_state.triggered_resize = true;
-                        command_buffer.push(Command::RequestResizeCanvas(copied_image.width(), copied_image.height()));
-                    }
-                }
-            }
             glfw::WindowEvent::Key(Key::X, _, Action::Press, Modifiers::Control) => {
                 if let Some(selection) = self.selection() {
-                    op = Some(
-                        ImageOperation::FillRectangle {
-                            start_x: selection.start_x,
-                            start_y: selection.start_y,
-                            end_x: selection.end_x,
-                            end_y: selection.end_y,
-                            color: image::Rgba([0, 0, 0, 0]),
-                            blend: false
-                        }
-                    );
-
-                    self.select_state.copied_image = Some(
-                        sub_image(image, selection.start_x, selection.start_y, selection.end_x, selection.end_y)
-                    );
+                    op = Some(self.create_erased_area(&selection, false));
+                    command_buffer.push(Command::SetCopiedImage(selection.feathered_image(image, self.active_feather_radius())));
 
                     self.set_start_position(None);
                     self.set_end_position(None);
@@ -295,6 +339,120 @@ impl SelectionTool {
         return op;
     }
 
+    fn process_event_select_by_color(&mut self,
+                                     window: &mut dyn EditorWindow,
+                                     event: &glfw::WindowEvent,
+                                     image_area_transform: &Matrix3<f32>,
+                                     image_area_rectangle: &Rectangle,
+                                     command_buffer: &mut CommandBuffer,
+                                     image: &editor::Image) -> Option<ImageOperation> {
+        let mut op = None;
+        match event {
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
+                let (mouse_x, mouse_y) = window.get_cursor_pos();
+                let ui_position = Position::new(mouse_x as f32, mouse_y as f32);
+                if image_area_rectangle.contains(&ui_position) {
+                    self.apply_transformed_pixels(&mut op);
+
+                    let mouse_position = get_transformed_mouse_position(window, image_area_transform);
+                    let region = select_by_color(
+                        image,
+                        mouse_position.x as i32,
+                        mouse_position.y as i32,
+                        self.select_by_color_tolerance,
+                        self.contiguous_checkbox.checked
+                    );
+
+                    if let Some(region) = region {
+                        self.set_start_position(Some(Position::new(region.left() as f32, region.top() as f32)));
+                        self.set_end_position(Some(Position::new(region.right() as f32, region.bottom() as f32)));
+                        self.set_mask(region.mask);
+                    } else {
+                        self.set_start_position(None);
+                        self.set_end_position(None);
+                        self.set_mask(None);
+                    }
+                }
+            }
+            glfw::WindowEvent::MouseButton(_, _, _) | glfw::WindowEvent::CursorPos(_, _) => {}
+            _ => {
+                op = self.process_event_select(window, event, image_area_transform, image_area_rectangle, command_buffer, image);
+            }
+        }
+
+        self.change_tolerance_button.process_gui_event(window, event, &mut self.select_by_color_tolerance);
+        self.contiguous_checkbox.process_gui_event(window, event, &mut ());
+
+        op
+    }
+
+    fn process_event_polygon(&mut self,
+                             window: &mut dyn EditorWindow,
+                             event: &glfw::WindowEvent,
+                             image_area_transform: &Matrix3<f32>,
+                             image_area_rectangle: &Rectangle,
+                             command_buffer: &mut CommandBuffer,
+                             image: &editor::Image) -> Option<ImageOperation> {
+        let mut op = None;
+        match event {
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
+                let (mouse_x, mouse_y) = window.get_cursor_pos();
+                if image_area_rectangle.contains(&Position::new(mouse_x as f32, mouse_y as f32)) {
+                    let now = Instant::now();
+                    let is_double_click = self.polygon_select_state.last_click
+                        .map(|last_click| (now - last_click).as_secs_f32() <= POLYGON_DOUBLE_CLICK_TIME)
+                        .unwrap_or(false);
+                    self.polygon_select_state.last_click = Some(now);
+
+                    if is_double_click && !self.polygon_select_state.points.is_empty() {
+                        self.close_polygon();
+                    } else {
+                        if self.polygon_select_state.points.is_empty() {
+                            self.apply_transformed_pixels(&mut op);
+
+                            self.set_start_position(None);
+                            self.set_end_position(None);
+                            self.set_mask(None);
+                        }
+
+                        let mouse_position = get_transformed_mouse_position(window, image_area_transform);
+                        self.polygon_select_state.points.push(Position::new(mouse_position.x.round(), mouse_position.y.round()));
+                    }
+                }
+            }
+            glfw::WindowEvent::CursorPos(raw_mouse_x, raw_mouse_y) => {
+                let mouse_position = image_area_transform.transform_point(cgmath::Point2::new(*raw_mouse_x as f32, *raw_mouse_y as f32));
+                self.polygon_select_state.mouse_position = Some(mouse_position);
+            }
+            glfw::WindowEvent::Key(Key::Enter, _, Action::Press, _) => {
+                self.close_polygon();
+            }
+            glfw::WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
+                self.polygon_select_state.clear();
+            }
+            glfw::WindowEvent::MouseButton(_, _, _) | glfw::WindowEvent::Key(Key::Enter, _, _, _) => {}
+            _ => {
+                op = self.process_event_select(window, event, image_area_transform, image_area_rectangle, command_buffer, image);
+            }
+        }
+
+        op
+    }
+
+    fn close_polygon(&mut self) {
+        let points = self.polygon_select_state.points
+            .iter()
+            .map(|point| (point.x, point.y))
+            .collect::<Vec<_>>();
+        self.polygon_select_state.clear();
+
+        if let Some(region) = select_polygon(&points) {
+            self.set_start_position(Some(Position::new(region.left() as f32, region.top() as f32)));
+            self.set_end_position(Some(Position::new(region.right() as f32, region.bottom() as f32)));
+            self.set_mask(region.mask);
+        }
+    }
+
     fn process_event_move_pixels(&mut self,
                                  window: &mut dyn EditorWindow,
                                  event: &glfw::WindowEvent,
@@ -312,15 +470,7 @@ impl SelectionTool {
                     if selection_rectangle.contains(&current_mouse_position) {
                         if self.move_pixels_state.moved_pixels_image.is_none() {
                             self.move_pixels_state.original_selection = Some(selection.clone());
-                            self.move_pixels_state.moved_pixels_image = Some(
-                                sub_image(
-                                    image,
-                                    selection.start_x,
-                                    selection.start_y,
-                                    selection.end_x,
-                                    selection.end_y
-                                )
-                            );
+                            self.move_pixels_state.moved_pixels_image = Some(selection.feathered_image(image, self.active_feather_radius()));
                         }
 
                         self.move_pixels_state.is_moving = true;
@@ -378,15 +528,7 @@ impl SelectionTool {
 
                         if self.resize_pixels_state.resize_pixels_image.is_none() {
                             self.resize_pixels_state.original_selection = Some(selection.clone());
-                            self.resize_pixels_state.resize_pixels_image = Some(
-                                sub_image(
-                                    image,
-                                    selection.start_x,
-                                    selection.start_y,
-                                    selection.end_x,
-                                    selection.end_y
-                                )
-                            );
+                            self.resize_pixels_state.resize_pixels_image = Some(selection.feathered_image(image, self.active_feather_radius()));
                         }
 
                         self.resize_pixels_state.is_resizing = true;
@@ -444,15 +586,7 @@ impl SelectionTool {
 
                         if self.rotate_pixels_state.rotate_pixels_image.is_none() {
                             self.resize_pixels_state.original_selection = Some(selection.clone());
-                            self.rotate_pixels_state.rotate_pixels_image = Some(
-                                sub_image(
-                                    image,
-                                    selection.start_x,
-                                    selection.start_y,
-                                    selection.end_x,
-                                    selection.end_y
-                                )
-                            );
+                            self.rotate_pixels_state.rotate_pixels_image = Some(selection.feathered_image(image, self.active_feather_radius()));
                         }
 
                         self.rotate_pixels_state.is_rotating = true;
@@ -491,14 +625,72 @@ impl SelectionTool {
         None
     }
 
-    fn handle_paste(&mut self, copied_image: image::RgbaImage) {
-        self.set_start_position(Some(Position::new(0.0, 0.0)));
-        self.set_end_position(Some(Position::new(copied_image.width() as f32, copied_image.height() as f32)));
+    fn transform_selection(&mut self,
+                           command_buffer: &mut CommandBuffer,
+                           image: &editor::Image,
+                           offset_x: i32,
+                           offset_y: i32,
+                           scale: f32,
+                           rotation: f32) {
+        if self.has_floating_pixels() {
+            self.apply_floating_pixels(command_buffer);
+
+            // Transform once the floating pixels have been applied to the image
+            command_buffer.push(Command::TransformSelection { offset_x, offset_y, scale, rotation });
+            return;
+        }
+
+        if let Some(selection) = self.selection() {
+            let width = ((selection.end_x - selection.start_x) as f32 * scale).round().max(1.0);
+            let height = ((selection.end_y - selection.start_y) as f32 * scale).round().max(1.0);
+            let start_position = Position::new((selection.start_x + offset_x) as f32, (selection.start_y + offset_y) as f32);
+
+            self.set_start_position(Some(start_position));
+            self.set_end_position(Some(Position::new(start_position.x + width, start_position.y + height)));
+
+            let selected_image = selection.feathered_image(image, self.active_feather_radius());
+            let op = if rotation != 0.0 {
+                self.resize_pixels_state.original_selection = Some(selection.clone());
+                self.rotate_pixels_state.rotate_pixels_image = Some(
+                    image::imageops::resize(&selected_image, width as u32, height as u32, image::FilterType::Triangle)
+                );
+                self.rotate_pixels_state.rotation = rotation.to_radians();
+                self.create_rotation(false)
+            } else if scale != 1.0 {
+                self.resize_pixels_state.original_selection = Some(selection.clone());
+                self.resize_pixels_state.resize_pixels_image = Some(selected_image);
+                self.create_resize(false)
+            } else {
+                self.move_pixels_state.original_selection = Some(selection.clone());
+                self.move_pixels_state.moved_pixels_image = Some(selected_image);
+                self.create_move(false)
+            };
+
+            self.clear_states();
+
+            // The rotated pixels no longer match the selection
+            if rotation != 0.0 {
+                self.set_start_position(None);
+                self.set_end_position(None);
+                self.set_mask(None);
+            }
+
+            self.push_floating_pixels(command_buffer, op);
+        }
+    }
+
+    fn handle_paste(&mut self, command_buffer: &mut CommandBuffer, copied_image: image::RgbaImage, position_x: i32, position_y: i32) {
+        // Pixels still floating from an earlier paste or transform are applied first, as their state would otherwise
+        // leak into the paste and show it at the wrong size or rotation
+        self.apply_floating_pixels(command_buffer);
+
+        self.set_mask(None);
+        self.set_start_position(Some(Position::new(position_x as f32, position_y as f32)));
+        self.set_end_position(Some(Position::new((position_x + copied_image.width() as i32) as f32, (position_y + copied_image.height() as i32) as f32)));
 
         self.move_pixels_state.original_selection = self.selection();
         self.move_pixels_state.moved_pixels_image = Some(copied_image);
         self.skip_erase_original_selection = true;
-        self.select_state.triggered_resize = false;
     }
 
     fn create_move(&self, preview: bool) -> Option<ImageOperation> {
@@ -595,7 +787,15 @@ impl SelectionTool {
     }
 
     fn create_erased_area(&self, selection: &Selection, preview: bool) -> ImageOperation{
-        if !preview {
+        if preview {
+            ImageOperation::Empty
+        } else if selection.mask.is_some() {
+            ImageOperation::FillRegion {
+                region: selection.region(),
+                color: image::Rgba([0, 0, 0, 0]),
+                blend: false
+            }
+        } else {
             ImageOperation::FillRectangle {
                 start_x: selection.start_x,
                 start_y: selection.start_y,
@@ -604,14 +804,55 @@ impl SelectionTool {
                 color: image::Rgba([0, 0, 0, 0]),
                 blend: false
             }
-        } else {
-            ImageOperation::Empty
         }
     }
 
     fn select_all(&mut self, image: &editor::Image) {
         self.set_start_position(Some(Position::new(0.0, 0.0)));
         self.set_end_position(Some(Position::new(image.width() as f32, image.height() as f32)));
+        self.set_mask(None);
+    }
+
+    fn set_mask(&mut self, mask: Option<SelectionMask>) {
+        self.mask = mask;
+        self.mask_texture = None;
+        self.changed_selection = true;
+    }
+
+    fn apply_transformed_pixels(&mut self, op: &mut Option<ImageOperation>) {
+        if self.move_pixels_state.moved_pixels_image.is_some() {
+            add_op_sequential(op, self.create_move(false));
+            self.move_pixels_state.clear();
+        }
+
+        if self.resize_pixels_state.resize_pixels_image.is_some() {
+            add_op_sequential(op, self.create_resize(false));
+            self.resize_pixels_state.clear();
+        }
+
+        if self.rotate_pixels_state.rotate_pixels_image.is_some() {
+            add_op_sequential(op, self.create_rotation(false));
+            self.rotate_pixels_state.clear();
+        }
+    }
+
+    fn has_floating_pixels(&self) -> bool {
+        self.move_pixels_state.moved_pixels_image.is_some()
+            || self.resize_pixels_state.resize_pixels_image.is_some()
+            || self.rotate_pixels_state.rotate_pixels_image.is_some()
+    }
+
+    fn apply_floating_pixels(&mut self, command_buffer: &mut CommandBuffer) {
+        let mut op = None;
+        self.apply_transformed_pixels(&mut op);
+        self.clear_states();
+        self.push_floating_pixels(command_buffer, op);
+    }
+
+    fn push_floating_pixels(&mut self, command_buffer: &mut CommandBuffer, op: Option<ImageOperation>) {
+        if push_floating_pixels_op(command_buffer, op) {
+            self.own_selection_clears += 1;
+        }
     }
 
     fn set_start_position(&mut self, position: Option<Position>) {
@@ -643,12 +884,25 @@ impl SelectionTool {
     }
 }
 
+// The floating pixels are outside the current selection, which would otherwise clip them
+fn push_floating_pixels_op(command_buffer: &mut CommandBuffer, op: Option<ImageOperation>) -> bool {
+    if let Some(op) = op {
+        command_buffer.push(Command::SetSelection(None));
+        command_buffer.push(Command::ApplyImageOp(op));
+        true
+    } else {
+        false
+    }
+}
+
 impl Tool for SelectionTool {
     fn on_active(&mut self, _window: &mut dyn EditorWindow, tool: Tools) -> Option<ImageOperation> {
         if let Tools::Selection(sub_tool) = tool {
             self.tool = sub_tool;
         }
 
+        self.polygon_select_state.clear();
+
         None
     }
 
@@ -694,13 +948,16 @@ impl Tool for SelectionTool {
 
         let mut op = match self.tool {
             SelectionSubTool::Select => self.process_event_select(window, event, image_area_transform, image_area_rectangle, command_buffer, image),
+            SelectionSubTool::SelectByColor => self.process_event_select_by_color(window, event, image_area_transform, image_area_rectangle, command_buffer, image),
             SelectionSubTool::MovePixels => self.process_event_move_pixels(window, event, image_area_transform, image_area_rectangle, command_buffer, image),
             SelectionSubTool::ResizePixels => self.process_event_resize_pixels(window, event, image_area_transform, image_area_rectangle, command_buffer, image),
             SelectionSubTool::RotatePixels => self.process_event_rotate_pixels(window, event, image_area_transform, image_area_rectangle, command_buffer, image),
+            SelectionSubTool::Polygon => self.process_event_polygon(window, event, image_area_transform, image_area_rectangle, command_buffer, image),
         };
 
         match event {
-            glfw::WindowEvent::Key(Key::Enter, _, Action::Release, _) => {
+            // Enter closes the polygon for the polygon selection, so it must not also clear the new selection
+            glfw::WindowEvent::Key(Key::Enter, _, Action::Release, _) if self.tool != SelectionSubTool::Polygon => {
                 add_op_sequential(
                     &mut op,
                     select_latest([
@@ -716,9 +973,25 @@ impl Tool for SelectionTool {
                 self.clear_states();
                 command_buffer.push(Command::SetTool(Tools::Selection(SelectionSubTool::Select)));
             }
+            glfw::WindowEvent::Key(Key::T, _, Action::Press, Modifiers::Control) => {
+                if self.selection().is_some() {
+                    command_buffer.push(Command::TriggerProgramAction(
+                        ProgramAction::OpenTransformSelectionDialog,
+                        ProgramActionData::Triggered
+                    ));
+                }
+            }
             _ => {}
         }
 
+        if self.tool.uses_feather() {
+            let prev_feather_radius = self.feather_radius;
+            self.change_feather_button.process_gui_event(window, event, &mut self.feather_radius);
+            if self.feather_radius != prev_feather_radius {
+                command_buffer.push(Command::SetSelectionFeather(self.feather_radius));
+            }
+        }
+
         self.after_change_selection(command_buffer);
 
         op
@@ -731,24 +1004,38 @@ impl Tool for SelectionTool {
             Command::SelectAll => {
                 self.select_all(image);
             }
-            Command::ResizeCanvas(_, _) => {
-                if self.select_state.triggered_resize {
-                    if let Some(copied_image) = self.select_state.copied_image.as_ref() {
-                        let copied_image = copied_image.clone();
-                        self.handle_paste(copied_image);
-                    }
-                }
+            Command::PlacePaste(copied_image, position_x, position_y) => {
+                self.handle_paste(command_buffer, copied_image.clone(), *position_x, *position_y);
             }
-            Command::AbortedResizeCanvas => {
-                if self.select_state.triggered_resize {
-                    if let Some(copied_image) = self.select_state.copied_image.as_ref() {
-                        let copied_image = copied_image.clone();
-                        self.handle_paste(copied_image);
-                    }
+            Command::TransformSelection { offset_x, offset_y, scale, rotation } => {
+                self.transform_selection(command_buffer, image, *offset_x, *offset_y, *scale, *rotation);
+            }
+            Command::SetSelectionFeather(feather_radius) => {
+                self.feather_radius = (*feather_radius).max(0);
+            }
+            Command::SetSelection(Some(selection)) if !self.select_state.is_selecting => {
+                // This tool also emits its own selection, which should not be applied again
+                let bounds = |selection: &Selection| (selection.start_x, selection.start_y, selection.end_x, selection.end_y);
+                if self.selection().map(|current| bounds(&current)) != Some(bounds(selection)) {
+                    self.start_position = Some(selection.start_position());
+                    self.end_position = Some(selection.end_position());
+                    self.mask = selection.mask.clone();
+                    self.mask_texture = None;
                 }
             }
-            Command::SetClipboard(image) => {
-                self.select_state.copied_image = Some(image.clone());
+            Command::SetSelection(None) => {
+                // The selection is only cleared to apply the floating pixels, which keeps the selection of the tool
+                if self.own_selection_clears > 0 {
+                    self.own_selection_clears -= 1;
+                } else if !self.select_state.is_selecting {
+                    self.start_position = None;
+                    self.end_position = None;
+                    self.mask = None;
+                    self.mask_texture = None;
+
+                    // Floating pixels are positioned in the image as it was before, for example, a crop
+                    self.clear_states();
+                }
             }
             _ => {}
         }
@@ -779,7 +1066,75 @@ impl Tool for SelectionTool {
         return true;
     }
 
+    fn render_ui(&mut self, renders: &Renders, transform: &Matrix4<f32>, _image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
+        if self.tool == SelectionSubTool::SelectByColor {
+            self.change_tolerance_button.change_text(format!("Tolerance: {:.0} %", self.select_by_color_tolerance * 100.0));
+            self.change_tolerance_button.render(renders, transform);
+
+            self.contiguous_checkbox.render(renders, transform);
+        }
+
+        if self.tool.uses_feather() {
+            self.change_feather_button.change_text(format!("Feather: {} px", self.feather_radius));
+            self.change_feather_button.render(renders, transform);
+        }
+    }
+
     fn render_image_area(&mut self, renders: &Renders, transform: &Matrix4<f32>, image_area_transform: &Matrix4<f32>, image: &editor::Image) {
+        if !self.polygon_select_state.points.is_empty() {
+            let points = &self.polygon_select_state.points;
+            let lines = points.iter().zip(points.iter().skip(1))
+                .map(|(start, end)| (*start, *end))
+                .chain(self.polygon_select_state.mouse_position.map(|mouse_position| (points[points.len() - 1], mouse_position)));
+
+            for (start, end) in lines {
+                renders.rectangle_render.render_line(
+                    renders.rectangle_render.shader(),
+                    &(transform * image_area_transform),
+                    start,
+                    end,
+                    Color4::new(0, 0, 0, 255)
+                );
+            }
+
+            return;
+        }
+
+        if let (Some(selection), Some(mask)) = (self.selection(), self.mask.as_ref()) {
+            if self.mask_texture.is_none() {
+                let mask_image = image::RgbaImage::from_fn(mask.width(), mask.height(), |x, y| {
+                    if mask.contains(x as i32, y as i32) {
+                        image::Rgba([0, 148, 255, 64])
+                    } else {
+                        image::Rgba([0, 0, 0, 0])
+                    }
+                });
+
+                self.mask_texture = Some(Texture::from_image(&mask_image));
+            }
+
+            if let Some(mask_texture) = self.mask_texture.as_ref() {
+                renders.texture_render.render_sized(
+                    renders.texture_render.shader(),
+                    &(transform * image_area_transform),
+                    mask_texture,
+                    selection.start_position(),
+                    selection.size().x,
+                    selection.size().y,
+                    None
+                );
+            }
+
+            renders.rectangle_render.render(
+                renders.rectangle_render.shader(),
+                &(transform * image_area_transform),
+                &selection.rectangle(),
+                Color4::new(0, 0, 0, 255)
+            );
+
+            return;
+        }
+
         if let Some(mut selection) = self.selection() {
             let clamp_x = |x: i32| x.clamp(0, image.width() as i32 - 1);
             let clamp_y = |y: i32| y.clamp(0, image.height() as i32 - 1);
@@ -804,7 +1159,21 @@ impl Tool for SelectionTool {
                 &(transform * image_area_transform),
                 &selection.rectangle(),
                 Color4::new(0, 0, 0, 255)
-            )
+            );
+
+            if let (true, Some(end_position)) = (self.select_state.is_selecting, self.end_position) {
+                // Rendered in screen space next to the cursor such that the text does not scale with the zoom
+                let text_position = image_area_transform.transform_point(cgmath::Point3::new(end_position.x, end_position.y, 0.0));
+                let size = selection.size();
+                renders.text_render.render_line(
+                    renders.text_render.shader(),
+                    transform,
+                    renders.ui_font.borrow_mut().deref_mut(),
+                    format!("{} x {} px", size.x, size.y).chars().map(|c| (c, Color::new(0, 0, 0))),
+                    Position::new(text_position.x + 12.0, text_position.y + 12.0),
+                    TextAlignment::Top
+                );
+            }
         }
     }
 
@@ -816,4 +1185,22 @@ impl Tool for SelectionTool {
             image,
         );
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_floating_pixels_op() {
+        let mut command_buffer = CommandBuffer::new();
+        assert!(!push_floating_pixels_op(&mut command_buffer, None));
+        assert!(command_buffer.pop().is_none());
+
+        let op = ImageOperation::SetImage { start_x: 5, start_y: 5, image: image::RgbaImage::new(2, 2), blend: true };
+        assert!(push_floating_pixels_op(&mut command_buffer, Some(op)));
+        assert!(matches!(command_buffer.pop(), Some(Command::SetSelection(None))));
+        assert!(matches!(command_buffer.pop(), Some(Command::ApplyImageOp(ImageOperation::SetImage { start_x: 5, start_y: 5, .. }))));
+        assert!(command_buffer.pop().is_none());
+    }
+}
@@ -0,0 +1,214 @@
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use glfw::{WindowEvent, Action};
+use cgmath::{Matrix3, Transform, Matrix4};
+
+use crate::rendering::prelude::{Position, Rectangle};
+use crate::editor;
+use crate::command_buffer::{Command, CommandBuffer};
+use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
+use crate::editor::image_operation::{ImageOperation, ImageOperationMarker};
+use crate::program::Renders;
+use crate::ui::button::{TextButton, GenericButton};
+
+pub struct SprayDrawTool {
+    is_spraying: Option<editor::Color>,
+    mouse_position: Position,
+    last_update: Option<Instant>,
+    pending_dots: f32,
+    pending_ops: Vec<ImageOperation>,
+    random_state: u64,
+    color: editor::Color,
+    alternative_color: editor::Color,
+    radius: i32,
+    density: i32,
+    change_radius_button: TextButton<i32>,
+    change_density_button: TextButton<i32>
+}
+
+impl SprayDrawTool {
+    pub fn new(renders: &Renders) -> SprayDrawTool {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0);
+
+        SprayDrawTool {
+            is_spraying: None,
+            mouse_position: Position::new(0.0, 0.0),
+            last_update: None,
+            pending_dots: 0.0,
+            pending_ops: Vec::new(),
+            random_state: seed | 1,
+            color: image::Rgba([0, 0, 0, 255]),
+            alternative_color: image::Rgba([0, 0, 0, 255]),
+            radius: 10,
+            density: 400,
+            change_radius_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(70.0, 10.0),
+                Some(Box::new(|radius| {
+                    *radius = (*radius + 1).min(100);
+                })),
+                Some(Box::new(|radius| {
+                    *radius = (*radius - 1).max(1);
+                })),
+                None,
+            ),
+            change_density_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(235.0, 10.0),
+                Some(Box::new(|density| {
+                    *density = (*density + 50).min(5000);
+                })),
+                Some(Box::new(|density| {
+                    *density = (*density - 50).max(50);
+                })),
+                None,
+            )
+        }
+    }
+
+    fn next_random(&mut self) -> f32 {
+        // xorshift64
+        self.random_state ^= self.random_state << 13;
+        self.random_state ^= self.random_state >> 7;
+        self.random_state ^= self.random_state << 17;
+        (self.random_state >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn spray(&mut self, color: editor::Color, num_dots: usize) {
+        let mut dot_color = color;
+        dot_color[3] = color[3] / 2;
+
+        for _ in 0..num_dots {
+            let distance = self.radius as f32 * self.next_random().sqrt();
+            let angle = 2.0 * std::f32::consts::PI * self.next_random();
+
+            self.pending_ops.push(ImageOperation::Block {
+                x: (self.mouse_position.x + distance * angle.cos()).round() as i32,
+                y: (self.mouse_position.y + distance * angle.sin()).round() as i32,
+                color: dot_color,
+                blend: true,
+                side_half_width: 0
+            });
+        }
+    }
+
+    fn take_pending_op(&mut self) -> Option<ImageOperation> {
+        if self.pending_ops.is_empty() {
+            return None;
+        }
+
+        Some(ImageOperation::Sequential(Some("Spray".to_owned()), std::mem::take(&mut self.pending_ops)))
+    }
+}
+
+impl Tool for SprayDrawTool {
+    fn update(&mut self) {
+        if let Some(color) = self.is_spraying {
+            let now = Instant::now();
+            if let Some(last_update) = self.last_update {
+                self.pending_dots += (now - last_update).as_secs_f32() * self.density as f32;
+
+                let num_dots = self.pending_dots.floor();
+                self.pending_dots -= num_dots;
+                self.spray(color, num_dots as usize);
+            }
+
+            self.last_update = Some(now);
+        }
+    }
+
+    fn handle_command(&mut self, _command_buffer: &mut CommandBuffer, _image: &editor::Image, command: &Command) {
+        match command {
+            Command::SetPrimaryColor(color) => {
+                self.color = *color;
+            }
+            Command::SetSecondaryColor(color) => {
+                self.alternative_color = *color;
+            }
+            _ => {}
+        }
+    }
+
+    fn process_gui_event(&mut self,
+                         window: &mut dyn EditorWindow,
+                         event: &WindowEvent,
+                         image_area_transform: &Matrix3<f32>,
+                         image_area_rectangle: &Rectangle,
+                         command_buffer: &mut CommandBuffer,
+                         _image: &editor::Image) -> Option<ImageOperation> {
+        let mut op = None;
+        match event {
+            glfw::WindowEvent::MouseButton(button @ (glfw::MouseButton::Button1 | glfw::MouseButton::Button2), Action::Press, _) => {
+                let (mouse_x, mouse_y) = window.get_cursor_pos();
+                if self.is_spraying.is_none() && image_area_rectangle.contains(&Position::new(mouse_x as f32, mouse_y as f32)) {
+                    let color = if *button == glfw::MouseButton::Button1 { self.color } else { self.alternative_color };
+                    self.is_spraying = Some(color);
+                    self.mouse_position = get_transformed_mouse_position(window, image_area_transform);
+                    self.last_update = Some(Instant::now());
+                    self.pending_dots = 0.0;
+
+                    // Start with a few dots so that a single click leaves a mark
+                    self.spray(color, (self.density / 20).max(1) as usize);
+
+                    let mut ops = vec![ImageOperation::Marker(ImageOperationMarker::BeginDraw, Some("Spray".to_owned()))];
+                    ops.append(&mut self.pending_ops);
+                    op = Some(ImageOperation::Sequential(Some("Spray".to_owned()), ops));
+                }
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1 | glfw::MouseButton::Button2, Action::Release, _) => {
+                if self.is_spraying.is_some() {
+                    self.is_spraying = None;
+                    self.last_update = None;
+
+                    // An operation containing the end marker is not applied, so the remaining dots are pushed first
+                    if let Some(pending_op) = self.take_pending_op() {
+                        command_buffer.push(Command::ApplyImageOp(pending_op));
+                    }
+
+                    op = Some(ImageOperation::Marker(ImageOperationMarker::EndDraw, None));
+                }
+            }
+            glfw::WindowEvent::CursorPos(raw_mouse_x, raw_mouse_y) => {
+                if self.is_spraying.is_some() {
+                    self.mouse_position = image_area_transform.transform_point(cgmath::Point2::new(*raw_mouse_x as f32, *raw_mouse_y as f32));
+                    op = self.take_pending_op();
+                }
+            }
+            _ => {}
+        }
+
+        self.change_radius_button.process_gui_event(window, event, &mut self.radius);
+        self.change_density_button.process_gui_event(window, event, &mut self.density);
+
+        return op;
+    }
+
+    fn preview(&mut self,
+               _image: &editor::Image,
+               preview_image: &mut editor::Image,
+               _transparent_area: &mut Option<Rectangle>) -> bool {
+        if self.pending_ops.is_empty() {
+            return false;
+        }
+
+        let mut update_op = preview_image.update_operation();
+        for op in &self.pending_ops {
+            op.apply(&mut update_op, false);
+        }
+
+        return true;
+    }
+
+    fn render_ui(&mut self, renders: &Renders, transform: &Matrix4<f32>, _image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
+        self.change_radius_button.change_text(format!("Radius: {}", self.radius));
+        self.change_radius_button.render(renders, transform);
+
+        self.change_density_button.change_text(format!("Density: {}", self.density));
+        self.change_density_button.render(renders, transform);
+    }
+}
@@ -0,0 +1,208 @@
+use glfw::{WindowEvent, Action};
+use cgmath::{Matrix3, Transform, Matrix4};
+
+use crate::rendering::prelude::{Position, Rectangle};
+use crate::{editor, content};
+use crate::command_buffer::{Command, CommandBuffer};
+use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
+use crate::editor::image_operation::{ImageOperation, ImageOperationMarker};
+use crate::editor::image_operation_helpers::draw_line;
+use crate::program::{Renders, ProgramAction, ProgramActionData};
+use crate::ui::button::{TextButton, GenericButton, Checkbox};
+
+pub struct StampBrushTool {
+    stamp: Option<image::RgbaImage>,
+    stroke_stamp: Option<image::RgbaImage>,
+    prev_mouse_position: Option<Position>,
+    steps_since_stamp: i32,
+    color: editor::Color,
+    alternative_color: editor::Color,
+    spacing: i32,
+    change_spacing_button: TextButton<i32>,
+    load_stamp_button: TextButton<CommandBuffer>,
+    tint_checkbox: Checkbox<()>
+}
+
+impl StampBrushTool {
+    pub fn new(renders: &Renders) -> StampBrushTool {
+        StampBrushTool {
+            stamp: None,
+            stroke_stamp: None,
+            prev_mouse_position: None,
+            steps_since_stamp: 0,
+            color: image::Rgba([0, 0, 0, 255]),
+            alternative_color: image::Rgba([0, 0, 0, 255]),
+            spacing: 10,
+            change_spacing_button: TextButton::new(
+                renders.ui_font.clone(),
+                "".to_owned(),
+                Position::new(70.0, 10.0),
+                Some(Box::new(|spacing| {
+                    *spacing = (*spacing + 1).min(200);
+                })),
+                Some(Box::new(|spacing| {
+                    *spacing = (*spacing - 1).max(1);
+                })),
+                None,
+            ),
+            load_stamp_button: TextButton::new(
+                renders.ui_font.clone(),
+                "Load stamp".to_owned(),
+                Position::new(335.0, 10.0),
+                Some(Box::new(|command_buffer| {
+                    command_buffer.push(Command::TriggerProgramAction(ProgramAction::OpenStampImage, ProgramActionData::Triggered));
+                })),
+                None,
+                None,
+            ),
+            tint_checkbox: Checkbox::new(
+                &image::open(content::get_path("content/ui/checkbox_unchecked.png")).unwrap().into_rgba(),
+                &image::open(content::get_path("content/ui/checkbox_checked.png")).unwrap().into_rgba(),
+                renders.ui_font.clone(),
+                "Tint".to_owned(),
+                false,
+                Position::new(235.0, 16.0),
+                None
+            )
+        }
+    }
+
+    fn create_stroke_stamp(&self, color: editor::Color) -> Option<image::RgbaImage> {
+        let mut stamp = self.stamp.clone()?;
+
+        if self.tint_checkbox.checked {
+            for pixel in stamp.pixels_mut() {
+                for channel in 0..4 {
+                    pixel[channel] = ((pixel[channel] as u32 * color[channel] as u32) / 255) as u8;
+                }
+            }
+        }
+
+        Some(stamp)
+    }
+
+    fn create_stamp_op(stamp: &image::RgbaImage, position: (i32, i32)) -> ImageOperation {
+        ImageOperation::SetImage {
+            start_x: position.0 - stamp.width() as i32 / 2,
+            start_y: position.1 - stamp.height() as i32 / 2,
+            image: stamp.clone(),
+            blend: true
+        }
+    }
+}
+
+impl Tool for StampBrushTool {
+    fn handle_command(&mut self, _command_buffer: &mut CommandBuffer, _image: &editor::Image, command: &Command) {
+        match command {
+            Command::SetPrimaryColor(color) => {
+                self.color = *color;
+            }
+            Command::SetSecondaryColor(color) => {
+                self.alternative_color = *color;
+            }
+            Command::SetStampImage(image) => {
+                self.stamp = Some(image.clone());
+            }
+            _ => {}
+        }
+    }
+
+    fn process_gui_event(&mut self,
+                         window: &mut dyn EditorWindow,
+                         event: &WindowEvent,
+                         image_area_transform: &Matrix3<f32>,
+                         image_area_rectangle: &Rectangle,
+                         command_buffer: &mut CommandBuffer,
+                         _image: &editor::Image) -> Option<ImageOperation> {
+        let mut op = None;
+        match event {
+            glfw::WindowEvent::MouseButton(button @ (glfw::MouseButton::Button1 | glfw::MouseButton::Button2), Action::Press, _) => {
+                let (mouse_x, mouse_y) = window.get_cursor_pos();
+                if self.stroke_stamp.is_none() && image_area_rectangle.contains(&Position::new(mouse_x as f32, mouse_y as f32)) {
+                    let color = if *button == glfw::MouseButton::Button1 { self.color } else { self.alternative_color };
+                    self.stroke_stamp = self.create_stroke_stamp(color);
+
+                    if let Some(stamp) = self.stroke_stamp.as_ref() {
+                        let mouse_position = get_transformed_mouse_position(window, image_area_transform);
+                        op = Some(
+                            ImageOperation::Sequential(
+                                Some("Stamp stroke".to_owned()),
+                                vec![
+                                    ImageOperation::Marker(ImageOperationMarker::BeginDraw, Some("Stamp stroke".to_owned())),
+                                    StampBrushTool::create_stamp_op(stamp, (mouse_position.x as i32, mouse_position.y as i32))
+                                ]
+                            )
+                        );
+
+                        self.prev_mouse_position = Some(mouse_position);
+                        self.steps_since_stamp = 0;
+                    }
+                }
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1 | glfw::MouseButton::Button2, Action::Release, _) => {
+                if self.stroke_stamp.is_some() {
+                    self.stroke_stamp = None;
+                    self.prev_mouse_position = None;
+                    op = Some(ImageOperation::Marker(ImageOperationMarker::EndDraw, None));
+                }
+            }
+            glfw::WindowEvent::CursorPos(raw_mouse_x, raw_mouse_y) => {
+                if let (Some(stamp), Some(prev_mouse_position)) = (self.stroke_stamp.as_ref(), self.prev_mouse_position) {
+                    let mouse_position = image_area_transform.transform_point(cgmath::Point2::new(*raw_mouse_x as f32, *raw_mouse_y as f32));
+                    let start = (prev_mouse_position.x as i32, prev_mouse_position.y as i32);
+                    let end = (mouse_position.x as i32, mouse_position.y as i32);
+
+                    let mut points = Vec::new();
+                    draw_line(start.0, start.1, end.0, end.1, |x, y, _| points.push((x, y)));
+
+                    // The line can be generated in either direction, and the start point has already been handled
+                    if points.first() != Some(&start) {
+                        points.reverse();
+                    }
+
+                    let mut ops = Vec::new();
+                    for point in points.into_iter().skip(1) {
+                        self.steps_since_stamp += 1;
+                        if self.steps_since_stamp >= self.spacing {
+                            self.steps_since_stamp = 0;
+                            ops.push(StampBrushTool::create_stamp_op(stamp, point));
+                        }
+                    }
+
+                    if !ops.is_empty() {
+                        op = Some(ImageOperation::Sequential(Some("Stamp stroke".to_owned()), ops));
+                    }
+
+                    self.prev_mouse_position = Some(mouse_position);
+                }
+            }
+            _ => {}
+        }
+
+        self.change_spacing_button.process_gui_event(window, event, &mut self.spacing);
+        self.load_stamp_button.process_gui_event(window, event, command_buffer);
+        self.tint_checkbox.process_gui_event(window, event, &mut ());
+
+        return op;
+    }
+
+    fn preview(&mut self,
+               _image: &editor::Image,
+               _preview_image: &mut editor::Image,
+               _transparent_area: &mut Option<Rectangle>) -> bool {
+        false
+    }
+
+    fn render_ui(&mut self, renders: &Renders, transform: &Matrix4<f32>, _image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
+        self.change_spacing_button.change_text(format!("Spacing: {}", self.spacing));
+        self.change_spacing_button.render(renders, transform);
+
+        match self.stamp.as_ref() {
+            Some(stamp) => self.load_stamp_button.change_text(format!("Load stamp ({}x{})", stamp.width(), stamp.height())),
+            None => self.load_stamp_button.change_text("Load stamp".to_owned())
+        }
+        self.load_stamp_button.render(renders, transform);
+
+        self.tint_checkbox.render(renders, transform);
+    }
+}
@@ -0,0 +1,208 @@
+use glfw::{WindowEvent, Action, Key};
+use cgmath::{Matrix3, Transform, Matrix4};
+
+use crate::rendering::prelude::{Position, Rectangle, Color4};
+use crate::rendering::font::Font;
+use crate::{editor, content};
+use crate::command_buffer::{Command, CommandBuffer};
+use crate::editor::tools::{Tool, get_transformed_mouse_position, EditorWindow};
+use crate::editor::image_operation::ImageOperation;
+use crate::program::{Renders, ProgramAction, ProgramActionData};
+use crate::ui::button::{TextButton, GenericButton};
+
+const FONT_PATH: &str = "content/fonts/NotoMono-Regular.ttf";
+
+pub struct TextDrawTool {
+    text: String,
+    font_size: u32,
+    color: editor::Color,
+    font: Option<Font>,
+    text_image: Option<image::RgbaImage>,
+    position: Option<Position>,
+    drag_offset: Option<Position>,
+    awaiting_text: bool,
+    edit_text_button: TextButton<CommandBuffer>
+}
+
+impl TextDrawTool {
+    pub fn new(renders: &Renders) -> TextDrawTool {
+        TextDrawTool {
+            text: String::new(),
+            font_size: 24,
+            color: image::Rgba([0, 0, 0, 255]),
+            font: None,
+            text_image: None,
+            position: None,
+            drag_offset: None,
+            awaiting_text: false,
+            edit_text_button: TextButton::new(
+                renders.ui_font.clone(),
+                "Edit text".to_owned(),
+                Position::new(70.0, 10.0),
+                Some(Box::new(|command_buffer| {
+                    command_buffer.push(Command::TriggerProgramAction(ProgramAction::OpenTextDialog, ProgramActionData::Triggered));
+                })),
+                None,
+                None,
+            )
+        }
+    }
+
+    fn rasterize(&mut self) {
+        if self.text.is_empty() {
+            self.text_image = None;
+            return;
+        }
+
+        // The font is only re-created when the size changes, as this rasterizes all glyphs
+        if self.font.is_none() {
+            self.font = Font::new(&content::get_path(FONT_PATH), self.font_size);
+            if self.font.is_none() {
+                println!("Failed to load font: {}.", FONT_PATH);
+            }
+        }
+
+        self.text_image = self.font.as_mut().map(|font| font.rasterize(&self.text, self.color));
+    }
+
+    fn text_rectangle(&self) -> Option<Rectangle> {
+        match (self.position, self.text_image.as_ref()) {
+            (Some(position), Some(text_image)) => {
+                Some(Rectangle::new(position.x, position.y, text_image.width() as f32, text_image.height() as f32))
+            }
+            _ => None
+        }
+    }
+
+    fn create_op(&self) -> Option<ImageOperation> {
+        match (self.position, self.text_image.as_ref()) {
+            (Some(position), Some(text_image)) => {
+                Some(
+                    ImageOperation::SetImage {
+                        start_x: position.x.round() as i32,
+                        start_y: position.y.round() as i32,
+                        image: text_image.clone(),
+                        blend: true
+                    }
+                )
+            }
+            _ => None
+        }
+    }
+
+    fn clear(&mut self) {
+        self.position = None;
+        self.drag_offset = None;
+        self.awaiting_text = false;
+    }
+}
+
+impl Tool for TextDrawTool {
+    fn on_deactivate(&mut self, _command_buffer: &mut CommandBuffer) -> Option<ImageOperation> {
+        let op = self.create_op();
+        self.clear();
+        op
+    }
+
+    fn handle_command(&mut self, _command_buffer: &mut CommandBuffer, _image: &editor::Image, command: &Command) {
+        match command {
+            Command::SetText { text, size, color } => {
+                if *size != self.font_size {
+                    self.font_size = *size;
+                    self.font = None;
+                }
+
+                self.text = text.clone();
+                self.color = *color;
+                self.awaiting_text = false;
+                self.rasterize();
+            }
+            Command::AbortedText => {
+                // A position placed only to open the dialog is dropped, while already placed text is kept
+                if self.awaiting_text {
+                    self.clear();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn process_gui_event(&mut self,
+                         window: &mut dyn EditorWindow,
+                         event: &WindowEvent,
+                         image_area_transform: &Matrix3<f32>,
+                         image_area_rectangle: &Rectangle,
+                         command_buffer: &mut CommandBuffer,
+                         _image: &editor::Image) -> Option<ImageOperation> {
+        let mut op = None;
+        match event {
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
+                let (mouse_x, mouse_y) = window.get_cursor_pos();
+                if image_area_rectangle.contains(&Position::new(mouse_x as f32, mouse_y as f32)) {
+                    let mouse_position = get_transformed_mouse_position(window, image_area_transform);
+                    match (self.position, self.text_rectangle()) {
+                        (Some(position), Some(text_rectangle)) if text_rectangle.contains(&mouse_position) => {
+                            self.drag_offset = Some(Position::new(mouse_position.x - position.x, mouse_position.y - position.y));
+                        }
+                        (Some(_), _) => {
+                            self.position = Some(mouse_position);
+                        }
+                        (None, _) => {
+                            self.position = Some(mouse_position);
+                            self.awaiting_text = true;
+                            command_buffer.push(Command::TriggerProgramAction(ProgramAction::OpenTextDialog, ProgramActionData::Triggered));
+                        }
+                    }
+                }
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Release, _) => {
+                self.drag_offset = None;
+            }
+            glfw::WindowEvent::CursorPos(raw_mouse_x, raw_mouse_y) => {
+                if let Some(drag_offset) = self.drag_offset {
+                    let mouse_position = image_area_transform.transform_point(cgmath::Point2::new(*raw_mouse_x as f32, *raw_mouse_y as f32));
+                    self.position = Some(Position::new(mouse_position.x - drag_offset.x, mouse_position.y - drag_offset.y));
+                }
+            }
+            glfw::WindowEvent::Key(Key::Enter, _, Action::Press, _) => {
+                op = self.create_op();
+                self.clear();
+            }
+            glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
+                self.clear();
+            }
+            _ => {}
+        }
+
+        self.edit_text_button.process_gui_event(window, event, command_buffer);
+
+        return op;
+    }
+
+    fn preview(&mut self,
+               _image: &editor::Image,
+               preview_image: &mut editor::Image,
+               _transparent_area: &mut Option<Rectangle>) -> bool {
+        let mut update_op = preview_image.update_operation();
+        if let Some(op) = self.create_op() {
+            op.apply(&mut update_op, false);
+        }
+
+        return true;
+    }
+
+    fn render_ui(&mut self, renders: &Renders, transform: &Matrix4<f32>, _image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
+        self.edit_text_button.render(renders, transform);
+    }
+
+    fn render_image_area(&mut self, renders: &Renders, transform: &Matrix4<f32>, image_area_transform: &Matrix4<f32>, _image: &editor::Image) {
+        if let Some(text_rectangle) = self.text_rectangle() {
+            renders.rectangle_render.render(
+                renders.rectangle_render.shader(),
+                &(transform * image_area_transform),
+                &text_rectangle,
+                Color4::new(0, 148, 255, 255)
+            );
+        }
+    }
+}
@@ -2,8 +2,9 @@ use std::sync::mpsc::Receiver;
 
 use glfw::{Context, Key, Action, Glfw, Window, WindowEvent};
 
-use crate::program::{Program, TOP_PANEL_HEIGHT, SIDE_PANELS_WIDTH};
-use crate::{editor, ui};
+use crate::program::Program;
+use crate::editor;
+use crate::ui::UiTheme;
 use crate::editor::tools::EditorWindow;
 use std::path::Path;
 
@@ -19,16 +20,17 @@ pub fn main() {
     let width = image_to_edit.width();
     let height = image_to_edit.height();
 
+    let theme = UiTheme::light();
 
-    let width = width + SIDE_PANELS_WIDTH;
-    let height = height + TOP_PANEL_HEIGHT;
+    let width = width + theme.side_panels_width();
+    let height = height + theme.top_panel_height;
     let (mut glfw, mut window, mut events) = setup_window(width, height);
 
     let mut program = Program::new(
         width,
         height,
         editor::Editor::new(editor::EditorImage::from_rgba(Some(path.to_path_buf()), image_to_edit)),
-        ui::create(),
+        theme,
     );
 
     let target_fps = 60.0;
@@ -37,11 +39,12 @@ pub fn main() {
         let frame_start_draw = std::time::Instant::now();
 
         glfw.poll_events();
-        let mut events = glfw::flush_messages(&mut events).map(|(_, event)| event);
+        let mut events = glfw::flush_messages(&mut events).map(|(_, event)| (event, None));
         program.update(&mut window, &mut events);
 
         unsafe {
-            gl::ClearColor(214.0 / 255.0, 214.0 / 255.0, 214.0 / 255.0, 1.0);
+            let (red, green, blue, alpha) = program.theme().clear_color();
+            gl::ClearColor(red, green, blue, alpha);
             gl::Clear(gl::COLOR_BUFFER_BIT);
         }
 
@@ -76,6 +79,10 @@ impl EditorWindow for glfw::Window {
         return self.get_key(Key::LeftShift) == Action::Press || self.get_key(Key::RightShift) == Action::Press;
     }
 
+    fn is_alt_down(&self) -> bool {
+        return self.get_key(Key::LeftAlt) == Action::Press || self.get_key(Key::RightAlt) == Action::Press;
+    }
+
     fn width(&self) -> u32 {
         self.get_size().0 as u32
     }
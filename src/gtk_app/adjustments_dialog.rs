@@ -0,0 +1,306 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use gtk::{Application, ApplicationWindow, GLArea, Orientation, ResponseType};
+
+use gtk::prelude::*;
+
+use crate::gtk_app::GTKProgramRef;
+use crate::gtk_app::helpers::{create_dialog, get_action_area};
+use crate::program::ProgramAction;
+use crate::command_buffer::Command;
+use crate::editor::image_operation::ImageOperation;
+use crate::editor::tools::bucket_fill::DEFAULT_FILL_TOLERANCE_PERCENT;
+
+struct Slider {
+    label: &'static str,
+    min: f64,
+    max: f64,
+    step: f64,
+    default: f64
+}
+
+pub fn add(_app: &Application,
+           window: &ApplicationWindow,
+           gtk_program: GTKProgramRef,
+           program_gl_area: Rc<GLArea>) {
+    add_slider_dialog(
+        window,
+        gtk_program.clone(),
+        program_gl_area.clone(),
+        "Brightness/contrast",
+        ProgramAction::OpenBrightnessContrastDialog,
+        vec![
+            Slider { label: "Brightness:", min: -255.0, max: 255.0, step: 1.0, default: 0.0 },
+            Slider { label: "Contrast:", min: -100.0, max: 100.0, step: 1.0, default: 0.0 }
+        ],
+        |values| {
+            ImageOperation::BrightnessContrast {
+                brightness: values[0].round() as i32,
+                contrast: values[1] as f32
+            }
+        }
+    );
+
+    add_slider_dialog(
+        window,
+        gtk_program.clone(),
+        program_gl_area.clone(),
+        "Gaussian blur",
+        ProgramAction::OpenGaussianBlurDialog,
+        vec![
+            Slider { label: "Radius:", min: 0.0, max: 50.0, step: 0.5, default: 0.0 }
+        ],
+        |values| {
+            ImageOperation::GaussianBlur {
+                radius: values[0] as f32
+            }
+        }
+    );
+
+    add_slider_dialog(
+        window,
+        gtk_program.clone(),
+        program_gl_area.clone(),
+        "Hue/saturation",
+        ProgramAction::OpenHueSaturationDialog,
+        vec![
+            Slider { label: "Hue (°):", min: -180.0, max: 180.0, step: 1.0, default: 0.0 },
+            Slider { label: "Saturation (%):", min: 0.0, max: 200.0, step: 1.0, default: 100.0 },
+            Slider { label: "Lightness (%):", min: 0.0, max: 200.0, step: 1.0, default: 100.0 }
+        ],
+        |values| {
+            ImageOperation::HueSaturation {
+                hue_shift: values[0],
+                saturation_scale: values[1] / 100.0,
+                value_scale: values[2] / 100.0
+            }
+        }
+    );
+
+    add_slider_dialog(
+        window,
+        gtk_program.clone(),
+        program_gl_area.clone(),
+        "Posterize",
+        ProgramAction::OpenPosterizeDialog,
+        vec![
+            Slider { label: "Levels:", min: 2.0, max: 255.0, step: 1.0, default: 4.0 }
+        ],
+        |values| {
+            ImageOperation::Posterize {
+                levels: values[0].round().max(2.0).min(255.0) as u8
+            }
+        }
+    );
+
+    add_slider_dialog(
+        window,
+        gtk_program.clone(),
+        program_gl_area.clone(),
+        "Threshold",
+        ProgramAction::OpenThresholdDialog,
+        vec![
+            Slider { label: "Level:", min: 0.0, max: 255.0, step: 1.0, default: 128.0 }
+        ],
+        |values| {
+            ImageOperation::Threshold {
+                level: values[0].round().max(0.0).min(255.0) as u8
+            }
+        }
+    );
+
+    add_slider_dialog(
+        window,
+        gtk_program.clone(),
+        program_gl_area.clone(),
+        "Levels",
+        ProgramAction::OpenLevelsDialog,
+        vec![
+            Slider { label: "Input black point:", min: 0.0, max: 254.0, step: 1.0, default: 0.0 },
+            Slider { label: "Input white point:", min: 1.0, max: 255.0, step: 1.0, default: 255.0 },
+            Slider { label: "Output black point:", min: 0.0, max: 255.0, step: 1.0, default: 0.0 },
+            Slider { label: "Output white point:", min: 0.0, max: 255.0, step: 1.0, default: 255.0 },
+            Slider { label: "Gamma:", min: 0.1, max: 10.0, step: 0.05, default: 1.0 }
+        ],
+        |values| {
+            ImageOperation::Levels {
+                in_black: values[0].round() as u8,
+                in_white: values[1].round() as u8,
+                out_black: values[2].round() as u8,
+                out_white: values[3].round() as u8,
+                gamma: values[4] as f32
+            }
+        }
+    );
+
+    add_setting_slider_dialog(
+        window,
+        gtk_program.clone(),
+        program_gl_area.clone(),
+        "Fill tolerance",
+        ProgramAction::OpenFillToleranceDialog,
+        vec![
+            Slider { label: "Tolerance (%):", min: 0.0, max: 100.0, step: 1.0, default: DEFAULT_FILL_TOLERANCE_PERCENT as f64 }
+        ],
+        |values| {
+            Command::SetFillTolerance(values[0].round() as f32 / 100.0)
+        }
+    );
+}
+
+fn add_scales(dialog: &gtk::Dialog, sliders: &[Slider]) -> Rc<Vec<gtk::Scale>> {
+    let mut scales = Vec::new();
+    for slider in sliders {
+        let label = gtk::Label::new(Some(slider.label));
+        label.set_xalign(0.0);
+        dialog.content_area().add(&label);
+
+        let scale = gtk::Scale::with_range(
+            Orientation::Horizontal,
+            slider.min,
+            slider.max,
+            slider.step
+        );
+        scale.set_width_request(300);
+        scale.set_value(slider.default);
+        dialog.content_area().add(&scale);
+        scales.push(scale);
+    }
+
+    Rc::new(scales)
+}
+
+fn add_slider_dialog<F: Fn(&[f64]) -> ImageOperation + 'static>(window: &ApplicationWindow,
+                                                                 gtk_program: GTKProgramRef,
+                                                                 program_gl_area: Rc<GLArea>,
+                                                                 title: &str,
+                                                                 action: ProgramAction,
+                                                                 sliders: Vec<Slider>,
+                                                                 create_op: F) {
+    let create_op = Rc::new(create_op);
+
+    let dialog = Rc::new(create_dialog(window, title));
+    dialog.add_buttons(&[
+        ("Ok", gtk::ResponseType::Ok),
+        ("Cancel", gtk::ResponseType::Cancel)
+    ]);
+    get_action_area(&dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    let scales = add_scales(&dialog, &sliders);
+
+    let current_values = {
+        let scales = scales.clone();
+        move || scales.iter().map(|scale| scale.value()).collect::<Vec<_>>()
+    };
+    let current_values = Rc::new(current_values);
+
+    for scale in scales.iter() {
+        let gtk_program_clone = gtk_program.clone();
+        let program_gl_area_clone = program_gl_area.clone();
+        let current_values_clone = current_values.clone();
+        let create_op_clone = create_op.clone();
+        scale.connect_value_changed(move |_| {
+            if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                program.command_buffer.push(Command::PreviewImageOp(create_op_clone(&current_values_clone())));
+            }
+
+            program_gl_area_clone.queue_render();
+        });
+    }
+
+    let dialog_clone = dialog.clone();
+    let scales_clone = scales.clone();
+    gtk_program.actions.borrow_mut().insert(
+        action,
+        Box::new(move |_| {
+            for (scale, slider) in scales_clone.iter().zip(sliders.iter()) {
+                scale.set_value(slider.default);
+            }
+
+            dialog_clone.show_all();
+        })
+    );
+
+    let gtk_program_clone = gtk_program.clone();
+    dialog.connect_response(move |dialog, response| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            match response {
+                ResponseType::Ok => {
+                    program.command_buffer.push(Command::ApplyImageOp(create_op(&current_values())));
+                }
+                _ => {
+                    program.command_buffer.push(Command::ClearPreviewImageOp);
+                }
+            }
+        }
+
+        program_gl_area.queue_render();
+        dialog.hide();
+    });
+}
+
+// Unlike the adjustments, a setting keeps its value between openings and is changed while the sliders move
+fn add_setting_slider_dialog<F: Fn(&[f64]) -> Command + 'static>(window: &ApplicationWindow,
+                                                                 gtk_program: GTKProgramRef,
+                                                                 program_gl_area: Rc<GLArea>,
+                                                                 title: &str,
+                                                                 action: ProgramAction,
+                                                                 sliders: Vec<Slider>,
+                                                                 create_command: F) {
+    let create_command = Rc::new(create_command);
+
+    let dialog = Rc::new(create_dialog(window, title));
+    dialog.add_buttons(&[
+        ("Ok", gtk::ResponseType::Ok),
+        ("Cancel", gtk::ResponseType::Cancel)
+    ]);
+    get_action_area(&dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    let scales = add_scales(&dialog, &sliders);
+    let opened_values = Rc::new(RefCell::new(scales.iter().map(|scale| scale.value()).collect::<Vec<_>>()));
+
+    for scale in scales.iter() {
+        let gtk_program_clone = gtk_program.clone();
+        let program_gl_area_clone = program_gl_area.clone();
+        let scales_clone = scales.clone();
+        let create_command_clone = create_command.clone();
+        scale.connect_value_changed(move |_| {
+            let values = scales_clone.iter().map(|scale| scale.value()).collect::<Vec<_>>();
+            if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                program.command_buffer.push(create_command_clone(&values));
+            }
+
+            program_gl_area_clone.queue_render();
+        });
+    }
+
+    let dialog_clone = dialog.clone();
+    let scales_clone = scales.clone();
+    let opened_values_clone = opened_values.clone();
+    gtk_program.actions.borrow_mut().insert(
+        action,
+        Box::new(move |_| {
+            *opened_values_clone.borrow_mut() = scales_clone.iter().map(|scale| scale.value()).collect();
+            dialog_clone.show_all();
+        })
+    );
+
+    let gtk_program_clone = gtk_program.clone();
+    dialog.connect_response(move |dialog, response| {
+        // Cancelling restores the values from when the dialog was opened
+        if response != ResponseType::Ok {
+            for (scale, value) in scales.iter().zip(opened_values.borrow().iter()) {
+                scale.set_value(*value);
+            }
+        }
+
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            let values = scales.iter().map(|scale| scale.value()).collect::<Vec<_>>();
+            program.command_buffer.push(create_command(&values));
+        }
+
+        program_gl_area.queue_render();
+        dialog.hide();
+    });
+}
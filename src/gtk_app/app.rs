@@ -1,17 +1,18 @@
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{RefCell, Cell};
 use std::ops::{Deref, DerefMut};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use gtk::prelude::*;
-use gtk::{Application, ApplicationWindow, GLArea, Orientation, EventBox, gdk, gdk_pixbuf};
+use gtk::{Application, ApplicationWindow, GLArea, Orientation, EventBox, gdk};
 use gtk::gio::ApplicationFlags;
-use gtk::gdk_pixbuf::Colorspace;
 
-use crate::gtk_app::{GTKProgram, menu, input_support, GTKProgramRef, color_select_dialog};
-use crate::program::{SIDE_PANELS_WIDTH, TOP_PANEL_HEIGHT, ProgramActionData, ProgramAction};
+use crate::gtk_app::{GTKProgram, menu, input_support, GTKProgramRef, color_select_dialog, histogram_dialog, curves_dialog, adjustments_dialog, autosave};
+use crate::gtk_app::helpers::{rgba_image_to_pixbuf, pixbuf_to_rgba_image, show_error_dialog, show_warning_dialog};
+use crate::program::{ProgramActionData, ProgramAction};
+use crate::ui::UiTheme;
 use crate::editor::EditorImage;
 use crate::command_buffer::Command;
 
@@ -26,18 +27,28 @@ pub fn main() {
     });
 
     application.connect_activate(|app| {
-        let program_args = std::env::args().collect::<Vec<_>>();
-
-        let (image_to_edit_path, image_to_edit) = if program_args.len() >= 2 {
-            let image_to_edit_path = Path::new(&program_args[1]).to_path_buf();
-            let image_to_edit = image::open(&image_to_edit_path).unwrap().into_rgba();
-            (Some(image_to_edit_path), image_to_edit)
-        } else {
-            (None, image::RgbaImage::new(1280, 800))
+        let program_arguments = parse_arguments(std::env::args().skip(1));
+
+        // A file that cannot be opened falls back to a blank image, the error is shown once the window exists
+        let mut open_error = None;
+        let opened_image = program_arguments.path.as_ref().and_then(|path| {
+            match image::open(path) {
+                Ok(image) => Some((path.clone(), image.into_rgba())),
+                Err(err) => {
+                    open_error = Some(format!("Failed to open {}: {}.", path.display(), err));
+                    None
+                }
+            }
+        });
+
+        let (image_to_edit_path, image_to_edit) = match opened_image {
+            Some((path, image)) => (Some(path), image),
+            None => (None, image::RgbaImage::new(1280, 800))
         };
 
-        let width = (image_to_edit.width() + SIDE_PANELS_WIDTH) as i32;
-        let height = (image_to_edit.height() + TOP_PANEL_HEIGHT + 27) as i32;
+        let theme = UiTheme::light();
+        let width = (image_to_edit.width() + theme.side_panels_width()) as i32;
+        let height = (image_to_edit.height() + theme.top_panel_height + 27) as i32;
 
         let window = ApplicationWindow::builder()
             .application(app)
@@ -68,10 +79,16 @@ pub fn main() {
         menu::add(app, &window, gtk_program.clone(), gl_area.clone());
         input_support::add(gtk_program.clone(), gl_area.clone(), event_box.clone());
         color_select_dialog::add(app, &window, gtk_program.clone());
+        histogram_dialog::add(app, &window, gtk_program.clone());
+        curves_dialog::add(app, &window, gtk_program.clone(), gl_area.clone());
+        adjustments_dialog::add(app, &window, gtk_program.clone(), gl_area.clone());
+        autosave::add(&window, gtk_program.clone());
 
         let gtk_program_clone = gtk_program.clone();
         let image_to_edit = Rc::new(RefCell::new(Some(image_to_edit)));
         let clipboard_clone = clipboard.clone();
+        let window_clone = window.clone();
+        let gl_area_clone = gl_area.clone();
         gl_area.connect_realize(move |area| {
             area.context().unwrap().make_current();
             gtk_program_clone.initialize(
@@ -84,6 +101,25 @@ pub fn main() {
             );
 
             get_clipboard_image(gtk_program_clone.clone(), clipboard_clone.as_ref());
+
+            if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                if let Some(max_history_length) = program_arguments.max_history_length {
+                    program.editor.set_max_history_length(max_history_length);
+                }
+
+                if program_arguments.fit {
+                    program.command_buffer.push(Command::ZoomToFit);
+                } else if let Some(zoom) = program_arguments.zoom {
+                    program.command_buffer.push(Command::SetZoom(zoom));
+                }
+            }
+
+            autosave::check_recovery(
+                &window_clone,
+                gtk_program_clone.clone(),
+                gl_area_clone.clone(),
+                image_to_edit_path.as_deref()
+            );
         });
 
         let clipboard_change = Arc::new(AtomicBool::new(false));
@@ -110,23 +146,30 @@ pub fn main() {
             ProgramAction::SetCopiedImage,
             Box::new(move |data| {
                 if let ProgramActionData::Image(image) = data {
-                    let gtk_image = gdk_pixbuf::Pixbuf::new(
-                        Colorspace::Rgb,
-                        true,
-                        8,
-                        image.width() as i32,
-                        image.height() as i32,
-                    ).unwrap();
-
-                    for y in 0..image.height() {
-                        for x in 0..image.width() {
-                            let pixel = image.get_pixel(x, y);
-                            gtk_image.put_pixel(x, y, pixel[0], pixel[1], pixel[2], pixel[3]);
-                        }
+                    if let Some(gtk_image) = rgba_image_to_pixbuf(&image) {
+                        self_change.store(true, Ordering::SeqCst);
+                        clipboard_clone.set_image(&gtk_image);
                     }
+                }
+            })
+        );
 
-                    self_change.store(true, Ordering::SeqCst);
-                    clipboard_clone.set_image(&gtk_image);
+        let window_clone = window.clone();
+        gtk_program.actions.borrow_mut().insert(
+            ProgramAction::ShowError,
+            Box::new(move |data| {
+                if let ProgramActionData::Message(message) = data {
+                    show_error_dialog(&window_clone, &message);
+                }
+            })
+        );
+
+        let window_clone = window.clone();
+        gtk_program.actions.borrow_mut().insert(
+            ProgramAction::ShowWarning,
+            Box::new(move |data| {
+                if let ProgramActionData::Message(message) = data {
+                    show_warning_dialog(&window_clone, &message);
                 }
             })
         );
@@ -134,6 +177,7 @@ pub fn main() {
         let window = Rc::new(window);
         let window_clone = window.clone();
 
+        let warned_no_window = Cell::new(false);
         gl_area.connect_render(move |area, context| {
             match clipboard_change.compare_exchange(true, false, Ordering::SeqCst, Ordering::SeqCst) {
                 Ok(true) => {
@@ -144,15 +188,34 @@ pub fn main() {
 
             context.make_current();
 
+            let (red, green, blue, alpha) = match gtk_program.program.borrow().as_ref() {
+                Some(program) => program.theme().clear_color(),
+                None => UiTheme::light().clear_color()
+            };
+
             unsafe {
-                gl::ClearColor(214.0 / 255.0, 214.0 / 255.0, 214.0 / 255.0, 1.0);
+                gl::ClearColor(red, green, blue, alpha);
                 gl::Clear(gl::COLOR_BUFFER_BIT);
             }
 
+            let area_window = match area.window() {
+                Some(area_window) => {
+                    warned_no_window.set(false);
+                    area_window
+                }
+                None => {
+                    if !warned_no_window.replace(true) {
+                        println!("Warning: no window to render to, skipping frames until there is.");
+                    }
+
+                    return Inhibit(true);
+                }
+            };
+
             let transform = cgmath::ortho(
                 0.0,
-                area.window().unwrap().width() as f32,
-                area.window().unwrap().height() as f32,
+                area_window.width() as f32,
+                area_window.height() as f32,
                 0.0,
                 0.0,
                 1.0
@@ -162,7 +225,7 @@ pub fn main() {
                                                            gtk_program.editor_window.borrow_mut().as_mut()) {
                 window_clone.set_title(&format!(
                     "ImageEditor - {}",
-                    program.editor.image().path().map(|path| path.file_name().unwrap().to_str().unwrap()).unwrap_or("Untitled")
+                    program.editor.image().path().and_then(|path| path.file_name()).and_then(|name| name.to_str()).unwrap_or("Untitled")
                 ));
 
                 program.update(
@@ -191,21 +254,71 @@ pub fn main() {
         });
 
         window.show_all();
+
+        if let Some(open_error) = open_error {
+            show_error_dialog(window.deref(), &open_error);
+        }
     });
 
     application.run();
 }
 
-fn get_clipboard_image(gtk_program: GTKProgramRef, clipboard: &gtk::Clipboard) {
-    clipboard.request_contents(
-        &gdk::Atom::intern("image/png"),
-        move |_, data| {
-            if data.data_type().name() == "image/png" {
-                let image = image::load_from_memory_with_format(data.data().as_slice(), image::ImageFormat::PNG).unwrap().into_rgba();
-                if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
-                    program.command_buffer.push(Command::SetClipboard(image));
+struct ProgramArguments {
+    path: Option<PathBuf>,
+    zoom: Option<f32>,
+    fit: bool,
+    max_history_length: Option<usize>
+}
+
+fn parse_arguments(mut arguments: impl Iterator<Item=String>) -> ProgramArguments {
+    let mut program_arguments = ProgramArguments {
+        path: None,
+        zoom: None,
+        fit: false,
+        max_history_length: None
+    };
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--fit" => {
+                program_arguments.fit = true;
+            }
+            "--zoom" => {
+                match arguments.next().map(|zoom| zoom.trim_end_matches('%').parse::<f32>()) {
+                    Some(Ok(zoom)) if zoom > 0.0 => {
+                        program_arguments.zoom = Some(zoom / 100.0);
+                    }
+                    _ => {
+                        println!("Expected a positive zoom percent after --zoom.");
+                    }
                 }
             }
+            "--history" => {
+                match arguments.next().map(|length| length.parse::<usize>()) {
+                    Some(Ok(length)) if length > 0 => {
+                        program_arguments.max_history_length = Some(length);
+                    }
+                    _ => {
+                        println!("Expected a positive number of entries after --history.");
+                    }
+                }
+            }
+            _ => {
+                program_arguments.path = Some(Path::new(&argument).to_path_buf());
+            }
         }
-    );
+    }
+
+    program_arguments
+}
+
+fn get_clipboard_image(gtk_program: GTKProgramRef, clipboard: &gtk::Clipboard) {
+    // Any image format GTK understands is accepted, e.g. screenshots offered by other applications
+    clipboard.request_image(move |_, pixbuf| {
+        if let Some(image) = pixbuf.and_then(pixbuf_to_rgba_image) {
+            if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
+                program.command_buffer.push(Command::SetClipboard(image));
+            }
+        }
+    });
 }
\ No newline at end of file
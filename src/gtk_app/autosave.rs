@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::cell::Cell;
+
+use gtk::prelude::*;
+use gtk::{ApplicationWindow, GLArea, glib};
+
+use crate::gtk_app::GTKProgramRef;
+use crate::command_buffer::Command;
+use crate::editor::editor::PROJECT_EXTENSION;
+
+pub const AUTOSAVE_INTERVAL_SECONDS: u32 = 60;
+
+fn recovery_directory() -> PathBuf {
+    glib::user_data_dir().join("imageeditor").join("recovery")
+}
+
+// Each running instance has its own recovery file, named by its process id
+fn recovery_path() -> PathBuf {
+    recovery_directory().join(format!("{}.{}", std::process::id(), PROJECT_EXTENSION))
+}
+
+pub fn add(window: &ApplicationWindow, gtk_program: GTKProgramRef) {
+    let gtk_program_clone = gtk_program.clone();
+    let autosaved_revision = Cell::new(None);
+    glib::timeout_add_seconds_local(AUTOSAVE_INTERVAL_SECONDS, move || {
+        if let Some(program) = gtk_program_clone.program.borrow().as_ref() {
+            // Nothing to recover before the first change, and the recovery file is up to date if nothing changed since.
+            // Without a process list, rewriting the file is also what tells other instances that this one is running.
+            let revision = program.editor.revision();
+            let changed = autosaved_revision.get() != Some(revision);
+            if program.editor.history().next().is_some() && (changed || cfg!(not(target_os = "linux"))) {
+                let result = std::fs::create_dir_all(recovery_directory())
+                    .and_then(|_| program.editor.image().save_project(&recovery_path()));
+
+                match result {
+                    Ok(()) => autosaved_revision.set(Some(revision)),
+                    Err(err) => println!("Failed to write recovery file due to: {:?}.", err)
+                }
+            }
+        }
+
+        glib::Continue(true)
+    });
+
+    // The recovery file is only needed if the program does not exit normally
+    window.connect_destroy(|_| {
+        if recovery_path().exists() {
+            if let Err(err) = std::fs::remove_file(recovery_path()) {
+                println!("Failed to remove recovery file due to: {:?}.", err);
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn is_instance_running(process_id: u32, _recovery_path: &Path) -> bool {
+    Path::new("/proc").join(process_id.to_string()).exists()
+}
+
+// Without a process list, an instance is considered running while it keeps its recovery file up to date
+#[cfg(not(target_os = "linux"))]
+fn is_instance_running(_process_id: u32, recovery_path: &Path) -> bool {
+    std::fs::metadata(recovery_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified_time| modified_time.elapsed().ok())
+        .map(|elapsed| elapsed.as_secs() < 2 * AUTOSAVE_INTERVAL_SECONDS as u64)
+        .unwrap_or(false)
+}
+
+// The latest recovery file left by an instance that is no longer running
+fn find_recovery_file() -> Option<(PathBuf, std::time::SystemTime)> {
+    let entries = std::fs::read_dir(recovery_directory()).ok()?;
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|extension| extension == PROJECT_EXTENSION).unwrap_or(false))
+        .filter_map(|path| {
+            let process_id = path.file_stem()?.to_str()?.parse::<u32>().ok()?;
+            if process_id == std::process::id() || is_instance_running(process_id, &path) {
+                return None;
+            }
+
+            let modified_time = std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok()?;
+            Some((path, modified_time))
+        })
+        .max_by_key(|(_, modified_time)| *modified_time)
+}
+
+pub fn check_recovery(window: &ApplicationWindow,
+                      gtk_program: GTKProgramRef,
+                      gl_area: Rc<GLArea>,
+                      opened_path: Option<&Path>) {
+    let modified_time = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+
+    let (found_recovery_path, recovery_time) = match find_recovery_file() {
+        Some(recovery_file) => recovery_file,
+        None => return
+    };
+
+    if let Some(opened_time) = opened_path.and_then(modified_time) {
+        if opened_time >= recovery_time {
+            return;
+        }
+    }
+
+    let dialog = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::YesNo,
+        "A recovery file from a previous session was found. Do you want to restore it?"
+    );
+
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Yes {
+            // Taking over the file makes it ours, so another instance can not restore it as well
+            match std::fs::rename(&found_recovery_path, recovery_path()) {
+                Ok(()) => {
+                    if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
+                        program.command_buffer.push(Command::RecoverProject(recovery_path()));
+                    }
+
+                    gl_area.queue_render();
+                }
+                Err(err) => {
+                    println!("Failed to take over recovery file due to: {:?}.", err);
+                }
+            }
+        } else if response == gtk::ResponseType::No {
+            if let Err(err) = std::fs::remove_file(&found_recovery_path) {
+                println!("Failed to remove recovery file due to: {:?}.", err);
+            }
+        }
+
+        dialog.close();
+    });
+
+    dialog.show_all();
+}
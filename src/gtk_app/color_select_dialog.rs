@@ -171,6 +171,7 @@ pub fn add(_app: &Application,
                     match *color_select_mode.borrow_mut() {
                         SelectColorMode::PrimaryColor => {
                             program.command_buffer.push(Command::SetPrimaryColor(color));
+                            program.command_buffer.push(Command::CommitPrimaryColor);
                         }
                         SelectColorMode::SecondaryColor => {
                             program.command_buffer.push(Command::SetSecondaryColor(color));
@@ -426,6 +427,10 @@ impl EditorWindow for EditorWindowFixed {
         self.is_shift_down
     }
 
+    fn is_alt_down(&self) -> bool {
+        false
+    }
+
     fn width(&self) -> u32 {
         self.width
     }
@@ -0,0 +1,340 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::ops::Deref;
+
+use gtk::{Application, ApplicationWindow, GLArea, Orientation, EventBox, gdk, ResponseType};
+
+use gtk::prelude::*;
+
+use crate::gtk_app::GTKProgramRef;
+use crate::gtk_app::helpers::{create_dialog, get_action_area};
+use crate::program::{Renders, ProgramAction};
+use crate::command_buffer::Command;
+use crate::editor::image_operation::{ImageOperation, ChannelSelector};
+use crate::editor::image_operation_helpers::curve_lut;
+use crate::rendering::prelude::{Position, Rectangle, Color4};
+
+const CURVE_SIZE: i32 = 256;
+const POINT_SELECT_DISTANCE: f64 = 8.0;
+
+pub fn add(_app: &Application,
+           window: &ApplicationWindow,
+           gtk_program: GTKProgramRef,
+           program_gl_area: Rc<GLArea>) {
+    let curves_dialog = Rc::new(RefCell::new(Option::<CurvesDialog>::None));
+    let curve = Rc::new(RefCell::new(Curve::new()));
+
+    let dialog = Rc::new(create_dialog(window, "Curves"));
+    dialog.add_buttons(&[
+        ("Ok", gtk::ResponseType::Ok),
+        ("Cancel", gtk::ResponseType::Cancel)
+    ]);
+    get_action_area(&dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    let channel_box = gtk::Box::new(Orientation::Horizontal, 4);
+    channel_box.add(&gtk::Label::new(Some("Channel:")));
+
+    let channel_selector = gtk::ComboBoxText::new();
+    channel_selector.append_text("RGB");
+    channel_selector.append_text("Red");
+    channel_selector.append_text("Green");
+    channel_selector.append_text("Blue");
+    channel_selector.set_active(Some(0));
+    channel_box.add(&channel_selector);
+    dialog.content_area().add(&channel_box);
+
+    let gl_area = Rc::new(GLArea::new());
+    gl_area.set_width_request(CURVE_SIZE);
+    gl_area.set_height_request(CURVE_SIZE);
+
+    let event_box = Rc::new(EventBox::new());
+    event_box.add(gl_area.deref());
+    event_box.add_events(gdk::EventMask::POINTER_MOTION_MASK);
+    dialog.content_area().add(event_box.deref());
+
+    let update_preview = {
+        let gtk_program = gtk_program.clone();
+        let curve = curve.clone();
+        let gl_area = gl_area.clone();
+        let program_gl_area = program_gl_area.clone();
+
+        Rc::new(move || {
+            if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
+                program.command_buffer.push(Command::PreviewImageOp(curve.borrow().create_op()));
+            }
+
+            gl_area.queue_render();
+            program_gl_area.queue_render();
+        })
+    };
+
+    let curve_clone = curve.clone();
+    let update_preview_clone = update_preview.clone();
+    channel_selector.connect_changed(move |channel_selector| {
+        curve_clone.borrow_mut().channel = match channel_selector.active() {
+            Some(1) => ChannelSelector::Red,
+            Some(2) => ChannelSelector::Green,
+            Some(3) => ChannelSelector::Blue,
+            _ => ChannelSelector::All
+        };
+
+        update_preview_clone();
+    });
+
+    let curve_clone = curve.clone();
+    let update_preview_clone = update_preview.clone();
+    event_box.connect_button_press_event(move |_, event| {
+        let (mouse_x, mouse_y) = event.coords().unwrap();
+        let changed = curve_clone.borrow_mut().press(mouse_x, mouse_y, event.button() == 3);
+        if changed {
+            update_preview_clone();
+        }
+
+        Inhibit(true)
+    });
+
+    let curve_clone = curve.clone();
+    event_box.connect_button_release_event(move |_, _| {
+        curve_clone.borrow_mut().dragging = None;
+        Inhibit(true)
+    });
+
+    let curve_clone = curve.clone();
+    let update_preview_clone = update_preview.clone();
+    event_box.connect_motion_notify_event(move |_, event| {
+        let (mouse_x, mouse_y) = event.coords().unwrap();
+        let changed = curve_clone.borrow_mut().drag(mouse_x, mouse_y);
+        if changed {
+            update_preview_clone();
+        }
+
+        Inhibit(true)
+    });
+
+    let dialog_clone = dialog.clone();
+    let curve_clone = curve.clone();
+    let channel_selector_clone = channel_selector.clone();
+    let gl_area_clone = gl_area.clone();
+    gtk_program.actions.borrow_mut().insert(
+        ProgramAction::OpenCurvesDialog,
+        Box::new(move |_| {
+            *curve_clone.borrow_mut() = Curve::new();
+            channel_selector_clone.set_active(Some(0));
+
+            gl_area_clone.queue_render();
+            dialog_clone.show_all();
+        })
+    );
+
+    let gtk_program_clone = gtk_program.clone();
+    let curve_clone = curve.clone();
+    dialog.connect_response(move |dialog, response| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            match response {
+                ResponseType::Ok => {
+                    program.command_buffer.push(Command::ApplyImageOp(curve_clone.borrow().create_op()));
+                }
+                _ => {
+                    program.command_buffer.push(Command::ClearPreviewImageOp);
+                }
+            }
+        }
+
+        program_gl_area.queue_render();
+        dialog.hide();
+    });
+
+    let curves_dialog_clone = curves_dialog.clone();
+    gl_area.connect_realize(move |area| {
+        area.context().unwrap().make_current();
+        *curves_dialog_clone.borrow_mut() = Some(CurvesDialog::new());
+    });
+
+    gl_area.connect_render(move |area, context| {
+        context.make_current();
+
+        unsafe {
+            gl::ClearColor(1.0, 1.0, 1.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+
+        let transform = cgmath::ortho(
+            0.0,
+            area.width_request() as f32,
+            area.height_request() as f32,
+            0.0,
+            0.0,
+            1.0
+        );
+
+        if let Some(curves_dialog) = curves_dialog.borrow().as_ref() {
+            curves_dialog.render(&transform, &curve.borrow());
+        }
+
+        Inhibit(true)
+    });
+}
+
+struct Curve {
+    points: Vec<(u8, u8)>,
+    dragging: Option<usize>,
+    channel: ChannelSelector
+}
+
+impl Curve {
+    pub fn new() -> Curve {
+        Curve {
+            points: vec![(0, 0), (255, 255)],
+            dragging: None,
+            channel: ChannelSelector::All
+        }
+    }
+
+    pub fn create_op(&self) -> ImageOperation {
+        ImageOperation::Curves {
+            lut: curve_lut(&self.points),
+            channel: self.channel
+        }
+    }
+
+    pub fn press(&mut self, mouse_x: f64, mouse_y: f64, remove: bool) -> bool {
+        let (input, output) = Curve::to_curve_position(mouse_x, mouse_y);
+
+        if let Some(index) = self.find_point(mouse_x, mouse_y) {
+            if remove {
+                if index != 0 && index != self.points.len() - 1 {
+                    self.points.remove(index);
+                    return true;
+                }
+            } else {
+                self.dragging = Some(index);
+            }
+
+            return false;
+        }
+
+        if remove {
+            return false;
+        }
+
+        let index = self.points.iter().position(|point| point.0 > input).unwrap_or(self.points.len());
+        if index == 0 || self.points[index - 1].0 == input {
+            return false;
+        }
+
+        self.points.insert(index, (input, output));
+        self.dragging = Some(index);
+        true
+    }
+
+    pub fn drag(&mut self, mouse_x: f64, mouse_y: f64) -> bool {
+        if let Some(index) = self.dragging {
+            let (input, output) = Curve::to_curve_position(mouse_x, mouse_y);
+
+            // The end points are fixed to the edges while inner points are kept between their neighbours
+            let input = if index == 0 || index == self.points.len() - 1 {
+                self.points[index].0
+            } else {
+                input.clamp(self.points[index - 1].0 + 1, self.points[index + 1].0 - 1)
+            };
+
+            self.points[index] = (input, output);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn find_point(&self, mouse_x: f64, mouse_y: f64) -> Option<usize> {
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| {
+                let delta_x = point.0 as f64 - mouse_x;
+                let delta_y = (255 - point.1) as f64 - mouse_y;
+                (index, (delta_x * delta_x + delta_y * delta_y).sqrt())
+            })
+            .filter(|(_, distance)| *distance <= POINT_SELECT_DISTANCE)
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .map(|(index, _)| index)
+    }
+
+    fn to_curve_position(mouse_x: f64, mouse_y: f64) -> (u8, u8) {
+        (
+            mouse_x.round().clamp(0.0, 255.0) as u8,
+            (255.0 - mouse_y.round()).clamp(0.0, 255.0) as u8
+        )
+    }
+}
+
+struct CurvesDialog {
+    renders: Renders
+}
+
+impl CurvesDialog {
+    pub fn new() -> CurvesDialog {
+        CurvesDialog {
+            renders: Renders::new()
+        }
+    }
+
+    pub fn render(&self, transform: &cgmath::Matrix4<f32>, curve: &Curve) {
+        let size = CURVE_SIZE as f32;
+        let grid_color = Color4::new(214, 214, 214, 255);
+
+        for i in 1..4 {
+            let position = size * i as f32 / 4.0;
+
+            self.renders.rectangle_render.render_line(
+                self.renders.rectangle_render.shader(),
+                transform,
+                Position::new(position, 0.0),
+                Position::new(position, size),
+                grid_color
+            );
+
+            self.renders.rectangle_render.render_line(
+                self.renders.rectangle_render.shader(),
+                transform,
+                Position::new(0.0, position),
+                Position::new(size, position),
+                grid_color
+            );
+        }
+
+        self.renders.rectangle_render.render_line(
+            self.renders.rectangle_render.shader(),
+            transform,
+            Position::new(0.0, size),
+            Position::new(size, 0.0),
+            grid_color
+        );
+
+        let curve_color = match curve.channel {
+            ChannelSelector::All => Color4::new(0, 0, 0, 255),
+            ChannelSelector::Red => Color4::new(255, 0, 0, 255),
+            ChannelSelector::Green => Color4::new(0, 160, 0, 255),
+            ChannelSelector::Blue => Color4::new(0, 0, 255, 255)
+        };
+
+        let lut = curve_lut(&curve.points);
+        for input in 1..256 {
+            self.renders.rectangle_render.render_line(
+                self.renders.rectangle_render.shader(),
+                transform,
+                Position::new((input - 1) as f32, 255.0 - lut[input - 1] as f32),
+                Position::new(input as f32, 255.0 - lut[input] as f32),
+                curve_color
+            );
+        }
+
+        for (input, output) in &curve.points {
+            self.renders.solid_rectangle_render.render(
+                self.renders.solid_rectangle_render.shader(),
+                transform,
+                &Rectangle::new(*input as f32 - 3.0, 255.0 - *output as f32 - 3.0, 6.0, 6.0),
+                curve_color
+            );
+        }
+    }
+}
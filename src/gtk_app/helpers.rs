@@ -3,24 +3,32 @@ use std::path::PathBuf;
 use std::ops::{Deref};
 
 use gtk::prelude::*;
-use gtk::{FileChooserAction, ApplicationWindow, Inhibit, Orientation, ResponseType, Align};
+use gtk::{FileChooserAction, ApplicationWindow, Inhibit, Orientation, ResponseType, Align, gdk_pixbuf};
+use gtk::gdk_pixbuf::Colorspace;
 use gtk::glib::translate::from_glib_none;
 
 use crate::gtk_app::{GTKProgram, GTKProgramRef};
 
+const IMAGE_FILE_PATTERNS: &[&str] = &["*.png", "*.jpg", "*.jpeg", "*.bmp", "*.tif", "*.tiff", "*.gif", "*.ico", "*.iep"];
+
 pub fn create_file_dialog<F: Fn(&GTKProgram, PathBuf) -> bool + 'static>(window: &ApplicationWindow,
                                                                          gtk_program: GTKProgramRef,
                                                                          title: &str,
                                                                          action: FileChooserAction,
                                                                          on_file: F) -> gtk::FileChooserDialog {
+    create_file_dialog_with_patterns(window, gtk_program, title, action, IMAGE_FILE_PATTERNS, on_file)
+}
+
+pub fn create_file_dialog_with_patterns<F: Fn(&GTKProgram, PathBuf) -> bool + 'static>(window: &ApplicationWindow,
+                                                                                       gtk_program: GTKProgramRef,
+                                                                                       title: &str,
+                                                                                       action: FileChooserAction,
+                                                                                       patterns: &[&str],
+                                                                                       on_file: F) -> gtk::FileChooserDialog {
     let file_filter = gtk::FileFilter::new();
-    file_filter.add_pattern("*.png");
-    file_filter.add_pattern("*.jpg");
-    file_filter.add_pattern("*.jpeg");
-    file_filter.add_pattern("*.bmp");
-    file_filter.add_pattern("*.tif");
-    file_filter.add_pattern("*.tiff");
-    file_filter.add_pattern("*.gif");
+    for pattern in patterns {
+        file_filter.add_pattern(pattern);
+    }
 
     let file_dialog = gtk::FileChooserDialogBuilder::new()
         .transient_for(window)
@@ -71,6 +79,43 @@ pub fn create_file_dialog<F: Fn(&GTKProgram, PathBuf) -> bool + 'static>(window:
     file_dialog
 }
 
+pub fn rgba_image_to_pixbuf(image: &image::RgbaImage) -> Option<gdk_pixbuf::Pixbuf> {
+    let pixbuf = gdk_pixbuf::Pixbuf::new(
+        Colorspace::Rgb,
+        true,
+        8,
+        image.width() as i32,
+        image.height() as i32,
+    )?;
+
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            let pixel = image.get_pixel(x, y);
+            pixbuf.put_pixel(x, y, pixel[0], pixel[1], pixel[2], pixel[3]);
+        }
+    }
+
+    Some(pixbuf)
+}
+
+pub fn pixbuf_to_rgba_image(pixbuf: &gdk_pixbuf::Pixbuf) -> Option<image::RgbaImage> {
+    let buffer = match pixbuf.save_to_bufferv("png", &[]) {
+        Ok(buffer) => buffer,
+        Err(err) => {
+            println!("Failed to encode clipboard image due to: {:?}.", err);
+            return None;
+        }
+    };
+
+    match image::load_from_memory_with_format(&buffer, image::ImageFormat::PNG) {
+        Ok(image) => Some(image.into_rgba()),
+        Err(err) => {
+            println!("Failed to decode clipboard image due to: {:?}.", err);
+            None
+        }
+    }
+}
+
 pub fn create_entry(container: &gtk::Box, label: &str, default_value: &str) -> gtk::Entry {
     let box_widget = gtk::Box::new(Orientation::Horizontal, 5);
 
@@ -124,6 +169,30 @@ pub fn create_dialog(window: &ApplicationWindow, title: &str) -> gtk::Dialog {
     dialog
 }
 
+pub fn show_error_dialog<W: IsA<gtk::Window>>(window: &W, message: &str) {
+    show_message_dialog(window, gtk::MessageType::Error, message);
+}
+
+pub fn show_warning_dialog<W: IsA<gtk::Window>>(window: &W, message: &str) {
+    show_message_dialog(window, gtk::MessageType::Warning, message);
+}
+
+fn show_message_dialog<W: IsA<gtk::Window>>(window: &W, message_type: gtk::MessageType, message: &str) {
+    let dialog = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        message_type,
+        gtk::ButtonsType::Ok,
+        message
+    );
+
+    dialog.connect_response(|dialog, _| {
+        dialog.close();
+    });
+
+    dialog.show_all();
+}
+
 pub fn get_action_area(dialog: &gtk::Dialog) -> gtk::Box {
     unsafe {
         from_glib_none(gtk::ffi::gtk_dialog_get_action_area(dialog.as_ptr()))
@@ -0,0 +1,177 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::ops::Deref;
+
+use gtk::{Application, ApplicationWindow, GLArea, Orientation};
+
+use gtk::prelude::*;
+
+use crate::gtk_app::GTKProgramRef;
+use crate::gtk_app::helpers::{create_dialog, get_action_area};
+use crate::program::{Renders, ProgramAction};
+use crate::editor::image_operation_helpers::{Histogram, histogram};
+use crate::rendering::prelude::{Rectangle, Color4};
+
+const HISTOGRAM_WIDTH: i32 = 256;
+const HISTOGRAM_HEIGHT: i32 = 150;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HistogramChannel {
+    Luminance,
+    Red,
+    Green,
+    Blue
+}
+
+pub fn add(_app: &Application,
+           window: &ApplicationWindow,
+           gtk_program: GTKProgramRef) {
+    let histogram_dialog = Rc::new(RefCell::new(Option::<HistogramDialog>::None));
+    let current_histogram = Rc::new(RefCell::new(Option::<Histogram>::None));
+    let channel = Rc::new(RefCell::new(HistogramChannel::Luminance));
+
+    let dialog = Rc::new(create_dialog(window, "Histogram"));
+    dialog.add_buttons(&[
+        ("Close", gtk::ResponseType::Close)
+    ]);
+    get_action_area(&dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    let gl_area = Rc::new(GLArea::new());
+    gl_area.set_width_request(HISTOGRAM_WIDTH);
+    gl_area.set_height_request(HISTOGRAM_HEIGHT);
+    dialog.content_area().add(gl_area.deref());
+
+    let channel_group = gtk::Box::new(Orientation::Horizontal, 4);
+
+    let channel_luminance = gtk::RadioButtonBuilder::new()
+        .label("Luminance")
+        .build();
+    channel_group.add(&channel_luminance);
+
+    let add_channel_button = |label: &str, button_channel: HistogramChannel| {
+        let button = gtk::RadioButtonBuilder::new()
+            .label(label)
+            .build();
+        button.join_group(Some(&channel_luminance));
+        channel_group.add(&button);
+
+        let channel_clone = channel.clone();
+        let gl_area_clone = gl_area.clone();
+        button.connect_toggled(move |button| {
+            if button.is_active() {
+                *channel_clone.borrow_mut() = button_channel;
+                gl_area_clone.queue_render();
+            }
+        });
+    };
+
+    add_channel_button("Red", HistogramChannel::Red);
+    add_channel_button("Green", HistogramChannel::Green);
+    add_channel_button("Blue", HistogramChannel::Blue);
+
+    let channel_clone = channel.clone();
+    let gl_area_clone = gl_area.clone();
+    channel_luminance.connect_toggled(move |button| {
+        if button.is_active() {
+            *channel_clone.borrow_mut() = HistogramChannel::Luminance;
+            gl_area_clone.queue_render();
+        }
+    });
+
+    dialog.content_area().add(&channel_group);
+
+    let gtk_program_clone = gtk_program.clone();
+    let dialog_clone = dialog.clone();
+    let current_histogram_clone = current_histogram.clone();
+    let gl_area_clone = gl_area.clone();
+    gtk_program.actions.borrow_mut().insert(
+        ProgramAction::OpenHistogramDialog,
+        Box::new(move |_| {
+            if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                *current_histogram_clone.borrow_mut() = Some(histogram(program.editor.active_layer(), program.editor.valid_region()));
+            }
+
+            gl_area_clone.queue_render();
+            dialog_clone.show_all();
+        })
+    );
+
+    dialog.connect_response(move |dialog, _| {
+        dialog.hide();
+    });
+
+    let histogram_dialog_clone = histogram_dialog.clone();
+    gl_area.connect_realize(move |area| {
+        area.context().unwrap().make_current();
+        *histogram_dialog_clone.borrow_mut() = Some(HistogramDialog::new());
+    });
+
+    gl_area.connect_render(move |area, context| {
+        context.make_current();
+
+        unsafe {
+            gl::ClearColor(1.0, 1.0, 1.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+
+        let transform = cgmath::ortho(
+            0.0,
+            area.width_request() as f32,
+            area.height_request() as f32,
+            0.0,
+            0.0,
+            1.0
+        );
+
+        if let (Some(histogram_dialog), Some(histogram)) = (histogram_dialog.borrow().as_ref(), current_histogram.borrow().as_ref()) {
+            histogram_dialog.render(&transform, histogram, *channel.borrow());
+        }
+
+        Inhibit(true)
+    });
+}
+
+struct HistogramDialog {
+    renders: Renders
+}
+
+impl HistogramDialog {
+    pub fn new() -> HistogramDialog {
+        HistogramDialog {
+            renders: Renders::new()
+        }
+    }
+
+    pub fn render(&self, transform: &cgmath::Matrix4<f32>, histogram: &Histogram, channel: HistogramChannel) {
+        let (counts, color) = match channel {
+            HistogramChannel::Luminance => (&histogram.luminance, Color4::new(0, 0, 0, 255)),
+            HistogramChannel::Red => (&histogram.red, Color4::new(255, 0, 0, 255)),
+            HistogramChannel::Green => (&histogram.green, Color4::new(0, 160, 0, 255)),
+            HistogramChannel::Blue => (&histogram.blue, Color4::new(0, 0, 255, 255))
+        };
+
+        let max_count = counts.iter().cloned().max().unwrap_or(0);
+        if max_count == 0 {
+            return;
+        }
+
+        for (value, count) in counts.iter().enumerate() {
+            let bar_height = (*count as f32 / max_count as f32) * HISTOGRAM_HEIGHT as f32;
+            if bar_height <= 0.0 {
+                continue;
+            }
+
+            self.renders.solid_rectangle_render.render(
+                self.renders.solid_rectangle_render.shader(),
+                transform,
+                &Rectangle::new(
+                    value as f32,
+                    HISTOGRAM_HEIGHT as f32 - bar_height,
+                    1.0,
+                    bar_height
+                ),
+                color
+            );
+        }
+    }
+}
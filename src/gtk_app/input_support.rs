@@ -2,10 +2,13 @@ use std::collections::HashMap;
 use std::iter::FromIterator;
 use std::rc::Rc;
 
-use gtk::{gdk, EventBox, GLArea};
+use gtk::{gdk, glib, EventBox, GLArea};
 use gtk::prelude::*;
 
 use crate::gtk_app::{GTKProgramRef};
+use crate::gtk_app::helpers::show_error_dialog;
+use crate::command_buffer::Command;
+use crate::editor::editor::PROJECT_EXTENSION;
 
 pub fn add(gtk_program: GTKProgramRef,
            gl_area: Rc<GLArea>,
@@ -30,10 +33,13 @@ pub fn add(gtk_program: GTKProgramRef,
             editor_window.mouse_position = event.coords().unwrap();
         }
 
-        gtk_program_clone.event_queue.borrow_mut().push_back(glfw::WindowEvent::MouseButton(
-            get_glfw_mouse_button(event.button()),
-            glfw::Action::Press,
-            glfw::Modifiers::empty()
+        gtk_program_clone.event_queue.borrow_mut().push_back((
+            glfw::WindowEvent::MouseButton(
+                get_glfw_mouse_button(event.button()),
+                glfw::Action::Press,
+                glfw::Modifiers::empty()
+            ),
+            event.axis(gdk::AxisUse::Pressure).map(|pressure| pressure as f32)
         ));
 
         gl_area_clone.queue_render();
@@ -47,10 +53,13 @@ pub fn add(gtk_program: GTKProgramRef,
             editor_window.mouse_position = event.coords().unwrap();
         }
 
-        gtk_program_clone.event_queue.borrow_mut().push_back(glfw::WindowEvent::MouseButton(
-            get_glfw_mouse_button(event.button()),
-            glfw::Action::Release,
-            glfw::Modifiers::empty()
+        gtk_program_clone.event_queue.borrow_mut().push_back((
+            glfw::WindowEvent::MouseButton(
+                get_glfw_mouse_button(event.button()),
+                glfw::Action::Release,
+                glfw::Modifiers::empty()
+            ),
+            None
         ));
 
         gl_area_clone.queue_render();
@@ -65,9 +74,9 @@ pub fn add(gtk_program: GTKProgramRef,
            editor_window.mouse_position = mouse_position;
         }
 
-        gtk_program_clone.event_queue.borrow_mut().push_back(glfw::WindowEvent::CursorPos(
-            mouse_position.0,
-            mouse_position.1
+        gtk_program_clone.event_queue.borrow_mut().push_back((
+            glfw::WindowEvent::CursorPos(mouse_position.0, mouse_position.1),
+            event.axis(gdk::AxisUse::Pressure).map(|pressure| pressure as f32)
         ));
 
         gl_area_clone.queue_render();
@@ -79,16 +88,16 @@ pub fn add(gtk_program: GTKProgramRef,
     event_box.connect_scroll_event(move |_, event| {
         match event.scroll_direction() {
             Some(gdk::ScrollDirection::Down) => {
-                gtk_program_clone.event_queue.borrow_mut().push_back(glfw::WindowEvent::Scroll(0.0, -1.0));
+                gtk_program_clone.event_queue.borrow_mut().push_back((glfw::WindowEvent::Scroll(0.0, -1.0), None));
             }
             Some(gdk::ScrollDirection::Up) => {
-                gtk_program_clone.event_queue.borrow_mut().push_back(glfw::WindowEvent::Scroll(0.0, 1.0));
+                gtk_program_clone.event_queue.borrow_mut().push_back((glfw::WindowEvent::Scroll(0.0, 1.0), None));
             }
             Some(gdk::ScrollDirection::Right) => {
-                gtk_program_clone.event_queue.borrow_mut().push_back(glfw::WindowEvent::Scroll(1.0, 0.0));
+                gtk_program_clone.event_queue.borrow_mut().push_back((glfw::WindowEvent::Scroll(1.0, 0.0), None));
             }
             Some(gdk::ScrollDirection::Left) => {
-                gtk_program_clone.event_queue.borrow_mut().push_back(glfw::WindowEvent::Scroll(-1.0, 0.0));
+                gtk_program_clone.event_queue.borrow_mut().push_back((glfw::WindowEvent::Scroll(-1.0, 0.0), None));
             }
             _ => {}
         }
@@ -101,11 +110,9 @@ pub fn add(gtk_program: GTKProgramRef,
     let gtk_program_clone = gtk_program.clone();
     event_box.connect_key_press_event(move |_, event| {
         if let Some((key, modifier)) = get_glfw_key(event.keyval(), event.state()) {
-            gtk_program_clone.event_queue.borrow_mut().push_back(glfw::WindowEvent::Key(
-                key,
-                0,
-                glfw::Action::Press,
-                modifier
+            gtk_program_clone.event_queue.borrow_mut().push_back((
+                glfw::WindowEvent::Key(key, 0, glfw::Action::Press, modifier),
+                None
             ));
         }
 
@@ -116,6 +123,11 @@ pub fn add(gtk_program: GTKProgramRef,
                         editor_window.shift_down = true;
                     }
                 }
+                glfw::Key::LeftAlt | glfw::Key::RightAlt => {
+                    if let Some(editor_window) = gtk_program_clone.editor_window.borrow_mut().as_mut() {
+                        editor_window.alt_down = true;
+                    }
+                }
                 _ => {}
             }
         }
@@ -128,11 +140,9 @@ pub fn add(gtk_program: GTKProgramRef,
     let gtk_program_clone = gtk_program.clone();
     event_box.connect_key_release_event(move |_, event| {
         if let Some((key, modifier)) = get_glfw_key(event.keyval(), event.state()) {
-            gtk_program_clone.event_queue.borrow_mut().push_back(glfw::WindowEvent::Key(
-                key,
-                0,
-                glfw::Action::Release,
-                modifier
+            gtk_program_clone.event_queue.borrow_mut().push_back((
+                glfw::WindowEvent::Key(key, 0, glfw::Action::Release, modifier),
+                None
             ));
         }
 
@@ -143,6 +153,11 @@ pub fn add(gtk_program: GTKProgramRef,
                         editor_window.shift_down = false;
                     }
                 }
+                glfw::Key::LeftAlt | glfw::Key::RightAlt => {
+                    if let Some(editor_window) = gtk_program_clone.editor_window.borrow_mut().as_mut() {
+                        editor_window.alt_down = false;
+                    }
+                }
                 _ => {}
             }
         }
@@ -150,6 +165,64 @@ pub fn add(gtk_program: GTKProgramRef,
         gl_area_clone.queue_render();
         Inhibit(true)
     });
+
+    add_drop_support(gtk_program.clone(), gl_area.clone(), event_box.clone());
+}
+
+fn add_drop_support(gtk_program: GTKProgramRef,
+                    gl_area: Rc<GLArea>,
+                    event_box: Rc<EventBox>) {
+    event_box.drag_dest_set(
+        gtk::DestDefaults::ALL,
+        &[gtk::TargetEntry::new("text/uri-list", gtk::TargetFlags::OTHER_APP, 0)],
+        gdk::DragAction::COPY
+    );
+
+    event_box.connect_drag_data_received(move |event_box, _, _, _, data, _, _| {
+        let path = data.uris()
+            .iter()
+            .filter_map(|uri| glib::filename_from_uri(uri).ok())
+            .map(|(path, _)| path)
+            .next();
+
+        let path = match path {
+            Some(path) => path,
+            None => {
+                println!("Failed to open dropped data: not a file.");
+                return;
+            }
+        };
+
+        // Dropping while holding shift adds the image as a layer instead of replacing the image
+        let as_layer = gtk_program.editor_window.borrow().as_ref().map(|editor_window| editor_window.shift_down).unwrap_or(false);
+
+        if !as_layer && path.extension().map(|extension| extension == PROJECT_EXTENSION).unwrap_or(false) {
+            if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
+                program.command_buffer.push(Command::OpenProject(path));
+            }
+        } else {
+            match image::open(&path) {
+                Ok(image) => {
+                    if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
+                        if as_layer {
+                            program.command_buffer.push(Command::ImportAsLayer(path, image.into_rgba()));
+                        } else {
+                            program.command_buffer.push(Command::SwitchImage(path, image.into_rgba()));
+                        }
+                    }
+                }
+                Err(err) => {
+                    let message = format!("Failed to open {}: {}.", path.display(), err);
+                    match event_box.toplevel().and_then(|window| window.downcast::<gtk::Window>().ok()) {
+                        Some(window) => show_error_dialog(&window, &message),
+                        None => println!("{}", message)
+                    }
+                }
+            }
+        }
+
+        gl_area.queue_render();
+    });
 }
 
 pub fn get_glfw_key(key: gdk::keys::Key, state: gdk::ModifierType) -> Option<(glfw::Key, glfw::Modifiers)> {
@@ -163,6 +236,10 @@ pub fn get_glfw_key(key: gdk::keys::Key, state: gdk::ModifierType) -> Option<(gl
             modifiers |= glfw::Modifiers::Control;
         }
 
+        if (state & gdk::ModifierType::MOD1_MASK) == gdk::ModifierType::MOD1_MASK {
+            modifiers |= glfw::Modifiers::Alt;
+        }
+
         Some((key, modifiers))
     } else {
         None
@@ -244,6 +321,8 @@ lazy_static::lazy_static! {
             (gdk::keys::constants::Page_Down, glfw::Key::PageDown),
             (gdk::keys::constants::Shift_L, glfw::Key::LeftShift),
             (gdk::keys::constants::Shift_R, glfw::Key::RightShift),
+            (gdk::keys::constants::Alt_L, glfw::Key::LeftAlt),
+            (gdk::keys::constants::Alt_R, glfw::Key::RightAlt),
         ].into_iter()
     );
 }
\ No newline at end of file
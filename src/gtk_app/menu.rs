@@ -2,17 +2,21 @@ use std::rc::Rc;
 use std::str::FromStr;
 use std::iter::FromIterator;
 use std::ops::{ Deref};
-use std::cell::RefCell;
-use std::path::PathBuf;
+use std::cell::{RefCell, Cell};
+use std::path::{Path, PathBuf};
 
 use gtk::prelude::*;
-use gtk::{GLArea, gio, Application, ApplicationWindow, glib, FileChooserAction, ResponseType, Orientation};
+use gtk::{GLArea, gio, gdk, Application, ApplicationWindow, glib, FileChooserAction, ResponseType, Orientation};
 
 use crate::gtk_app::{GTKProgram, GTKProgramRef};
-use crate::gtk_app::helpers::{create_entry, create_file_dialog, create_dialog, get_action_area, create_spin_button};
-use crate::command_buffer::{Command, BackgroundType};
+use crate::gtk_app::helpers::{create_entry, create_file_dialog, create_file_dialog_with_patterns, create_dialog, get_action_area, create_spin_button, show_error_dialog, show_warning_dialog};
+use crate::command_buffer::{Command, BackgroundType, FilterKind};
 use crate::program::{ProgramAction, ProgramActionData};
-use crate::editor::editor::ImageFormat;
+use crate::editor::editor::{ImageFormat, SaveWarning, PROJECT_EXTENSION};
+use crate::editor::guides::DEFAULT_GRID_SIZE;
+use crate::ui::UiTheme;
+use crate::editor::image_operation::Symmetry;
+use crate::editor::tools::selection::Selection;
 
 
 pub fn add(app: &Application,
@@ -29,6 +33,8 @@ pub fn add(app: &Application,
     add_edit_menu(app, window, gtk_program.clone(), gl_area.clone(), &menu_bar);
     add_image_menu(app, window, gtk_program.clone(), gl_area.clone(), &menu_bar);
     add_layers_menu(app, window, gtk_program.clone(), gl_area.clone(), &menu_bar);
+    add_view_menu(app, window, gtk_program.clone(), gl_area.clone(), &menu_bar);
+    add_text_dialog(window, gtk_program.clone(), gl_area.clone());
 }
 
 fn add_program_menu(app: &Application,
@@ -44,12 +50,23 @@ fn add_program_menu(app: &Application,
     let open_file = gio::SimpleAction::new("open_file", None);
 
     let gl_area_clone = gl_area.clone();
+    let window_clone = window.clone();
     let open_file_dialog = create_file_dialog(
         window,
         gtk_program.clone(),
         "Open image",
         FileChooserAction::Open,
         move |gtk_program, path| {
+            // Projects contain layers and are loaded by the editor, which has the required OpenGL context
+            if path.extension().map(|extension| extension == PROJECT_EXTENSION).unwrap_or(false) {
+                if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
+                    program.command_buffer.push(Command::OpenProject(path));
+                    gl_area_clone.queue_render();
+                }
+
+                return true;
+            }
+
             match image::open(&path) {
                 Ok(image) => {
                     if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
@@ -58,7 +75,7 @@ fn add_program_menu(app: &Application,
                     }
                 }
                 Err(err) => {
-                    println!("Failed to open file due to: {:?}.", err);
+                    show_error_dialog(&window_clone, &format!("Failed to open {}: {}.", path.display(), err));
                 }
             }
 
@@ -80,6 +97,71 @@ fn add_program_menu(app: &Application,
     }));
     app.add_action(&open_file);
 
+    // Import as layer
+    menu.append(Some("Import as layer"), Some("app.import_as_layer"));
+    let import_as_layer = gio::SimpleAction::new("import_as_layer", None);
+
+    let gl_area_clone = gl_area.clone();
+    let window_clone = window.clone();
+    let import_as_layer_dialog = create_file_dialog(
+        window,
+        gtk_program.clone(),
+        "Import as layer",
+        FileChooserAction::Open,
+        move |gtk_program, path| {
+            match image::open(&path) {
+                Ok(image) => {
+                    if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
+                        program.command_buffer.push(Command::ImportAsLayer(path, image.into_rgba()));
+                        gl_area_clone.queue_render();
+                    }
+                }
+                Err(err) => {
+                    show_error_dialog(&window_clone, &format!("Failed to import {}: {}.", path.display(), err));
+                }
+            }
+
+            true
+        }
+    );
+
+    import_as_layer.connect_activate(glib::clone!(@weak window => move |_, _| {
+        import_as_layer_dialog.show();
+    }));
+    app.add_action(&import_as_layer);
+
+    // Stamp image
+    let gl_area_clone = gl_area.clone();
+    let window_clone = window.clone();
+    let open_stamp_dialog = create_file_dialog(
+        window,
+        gtk_program.clone(),
+        "Open stamp image",
+        FileChooserAction::Open,
+        move |gtk_program, path| {
+            match image::open(&path) {
+                Ok(image) => {
+                    if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
+                        program.command_buffer.push(Command::SetStampImage(image.into_rgba()));
+                        gl_area_clone.queue_render();
+                    }
+                }
+                Err(err) => {
+                    show_error_dialog(&window_clone, &format!("Failed to open stamp image {}: {}.", path.display(), err));
+                }
+            }
+
+            true
+        }
+    );
+
+    gtk_program.actions.borrow_mut().insert(
+        ProgramAction::OpenStampImage,
+        Box::new(move |_| {
+            open_stamp_dialog.show();
+        })
+    );
+
     // Save
     menu.append(Some("Save"), Some("app.save_file"));
     let save_file = gio::SimpleAction::new("save_file", None);
@@ -87,9 +169,7 @@ fn add_program_menu(app: &Application,
     save_file.connect_activate(glib::clone!(@weak window => move |_, _| {
         if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
             if let (Some(path), Some(image_format)) = (program.editor.image().path(), program.editor.image().image_format()) {
-               if let Err(err) = program.editor.image().save(path, &image_format) {
-                    println!("Failed to save file due to: {:?}.", err);
-               }
+                show_save_result(&window, path, program.editor.image().save(path, &image_format));
             }
         }
     }));
@@ -98,6 +178,91 @@ fn add_program_menu(app: &Application,
     // Save as
     add_save_as_dialog(app, window, gtk_program.clone(), menu);
 
+    // Export (GPU)
+    menu.append(Some("Export as rendered"), Some("app.export_gpu"));
+    let export_gpu = gio::SimpleAction::new("export_gpu", None);
+
+    let gl_area_clone = gl_area.clone();
+    let export_gpu_dialog = create_file_dialog(
+        window,
+        gtk_program.clone(),
+        "Export as rendered",
+        FileChooserAction::Save,
+        move |gtk_program, path| {
+            let image_format = path
+                .extension()
+                .map(|ext| ext.to_str()).flatten()
+                .map(|extension| ImageFormat::from_extension(extension)).flatten();
+
+            if let Some(image_format) = image_format {
+                if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
+                    program.command_buffer.push(Command::ExportImageGpu(path, image_format));
+                    gl_area_clone.queue_render();
+                }
+
+                true
+            } else {
+                false
+            }
+        }
+    );
+
+    export_gpu.connect_activate(glib::clone!(@weak window => move |_, _| {
+        export_gpu_dialog.show();
+    }));
+    app.add_action(&export_gpu);
+
+    // Load palette
+    menu.append(Some("Load palette"), Some("app.load_palette"));
+    let load_palette = gio::SimpleAction::new("load_palette", None);
+
+    let gl_area_clone = gl_area.clone();
+    let load_palette_dialog = create_file_dialog_with_patterns(
+        window,
+        gtk_program.clone(),
+        "Load palette",
+        FileChooserAction::Open,
+        &["*.gpl"],
+        move |gtk_program, path| {
+            if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
+                program.command_buffer.push(Command::LoadPalette(path));
+                gl_area_clone.queue_render();
+            }
+
+            true
+        }
+    );
+
+    load_palette.connect_activate(glib::clone!(@weak window => move |_, _| {
+        load_palette_dialog.show();
+    }));
+    app.add_action(&load_palette);
+
+    // Save palette
+    menu.append(Some("Save palette"), Some("app.save_palette"));
+    let save_palette = gio::SimpleAction::new("save_palette", None);
+
+    let save_palette_dialog = create_file_dialog_with_patterns(
+        window,
+        gtk_program.clone(),
+        "Save palette",
+        FileChooserAction::Save,
+        &["*.gpl"],
+        move |gtk_program, path| {
+            let path = if path.extension().is_none() { path.with_extension("gpl") } else { path };
+            if let Some(program) = gtk_program.program.borrow_mut().as_mut() {
+                program.command_buffer.push(Command::SavePalette(path));
+            }
+
+            true
+        }
+    );
+
+    save_palette.connect_activate(glib::clone!(@weak window => move |_, _| {
+        save_palette_dialog.show();
+    }));
+    app.add_action(&save_palette);
+
     // Quit
     menu.append(Some("Quit"), Some("app.quit"));
     let quit = gio::SimpleAction::new("quit", None);
@@ -189,6 +354,14 @@ fn add_new_image_dialog(app: &Application,
     app.add_action(&new_image);
 }
 
+fn show_save_result(window: &ApplicationWindow, path: &Path, result: std::io::Result<Option<SaveWarning>>) {
+    match result {
+        Ok(Some(warning)) => show_warning_dialog(window, &warning.to_string()),
+        Ok(None) => {}
+        Err(err) => show_error_dialog(window, &format!("Failed to save {}: {}.", path.display(), err))
+    }
+}
+
 fn add_save_as_dialog(app: &Application,
                       window: &ApplicationWindow,
                       gtk_program: GTKProgramRef,
@@ -196,18 +369,18 @@ fn add_save_as_dialog(app: &Application,
     menu.append(Some("Save as"), Some("app.save_file_as"));
     let save_file_as = gio::SimpleAction::new("save_file_as", None);
 
-    // JPEG quality dialog
-    let jpeg_quality_dialog = create_dialog(window, "JPEG Quality");
-    jpeg_quality_dialog.content_area().set_spacing(4);
-    jpeg_quality_dialog.set_width_request(200);
+    // Export options dialog for formats without alpha
+    let export_options_dialog = create_dialog(window, "Export options");
+    export_options_dialog.content_area().set_spacing(4);
+    export_options_dialog.set_width_request(200);
 
-    jpeg_quality_dialog.add_buttons(&[
+    export_options_dialog.add_buttons(&[
         ("Ok", gtk::ResponseType::Ok)
     ]);
 
     let jpeg_quality_label = gtk::Label::new(Some("Quality:"));
     jpeg_quality_label.set_xalign(0.0);
-    jpeg_quality_dialog.content_area().add(&jpeg_quality_label);
+    export_options_dialog.content_area().add(&jpeg_quality_label);
 
     let jpeg_quality_scale = gtk::Scale::with_range(
         Orientation::Horizontal,
@@ -215,23 +388,47 @@ fn add_save_as_dialog(app: &Application,
         100.0,
         1.0
     );
-    jpeg_quality_dialog.content_area().add(&jpeg_quality_scale);
+    export_options_dialog.content_area().add(&jpeg_quality_scale);
 
-    get_action_area(&jpeg_quality_dialog).set_property("halign", gtk::Align::Center).unwrap();
+    let matte_color_label = gtk::Label::new(Some("Background for transparent areas:"));
+    matte_color_label.set_xalign(0.0);
+    export_options_dialog.content_area().add(&matte_color_label);
+
+    let matte_color_button = gtk::ColorButton::new();
+    export_options_dialog.content_area().add(&matte_color_button);
+
+    get_action_area(&export_options_dialog).set_property("halign", gtk::Align::Center).unwrap();
 
     let current_save_path = Rc::new(RefCell::new(Option::<PathBuf>::None));
+    let current_save_format = Rc::new(RefCell::new(Option::<ImageFormat>::None));
 
     let gtk_program_clone = gtk_program.clone();
     let current_save_path_clone = current_save_path.clone();
+    let current_save_format_clone = current_save_format.clone();
     let jpeg_quality_scale_clone = jpeg_quality_scale.clone();
-    jpeg_quality_dialog.connect_response(move |dialog, response| {
+    let matte_color_button_clone = matte_color_button.clone();
+    let window_clone = window.clone();
+    export_options_dialog.connect_response(move |dialog, response| {
         match response {
             ResponseType::Ok => {
-                if let Some(path) = current_save_path_clone.borrow_mut().clone() {
+                let path = current_save_path_clone.borrow().clone();
+                let image_format = current_save_format_clone.borrow().clone();
+                if let (Some(path), Some(image_format)) = (path, image_format) {
                     if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
-                        if let Err(err) = program.editor.image_mut().save_as(&path, &ImageFormat::Jpeg(jpeg_quality_scale_clone.value() as u8)) {
-                            println!("Failed to save file due to: {:?}.", err);
-                        }
+                        let image_format = match image_format {
+                            ImageFormat::Jpeg(_) => ImageFormat::Jpeg(jpeg_quality_scale_clone.value() as u8),
+                            image_format => image_format
+                        };
+
+                        let matte_color = matte_color_button_clone.rgba();
+                        program.editor.image_mut().set_matte_color(image::Rgba([
+                            (matte_color.red * 255.0).round() as u8,
+                            (matte_color.green * 255.0).round() as u8,
+                            (matte_color.blue * 255.0).round() as u8,
+                            255
+                        ]));
+
+                        show_save_result(&window_clone, &path, program.editor.image_mut().save_as(&path, &image_format));
                     }
                 }
 
@@ -245,6 +442,7 @@ fn add_save_as_dialog(app: &Application,
 
     // Main save as dialog
     let current_save_path_clone = current_save_path.clone();
+    let window_clone = window.clone();
     let save_file_as_dialog = create_file_dialog(
         window,
         gtk_program.clone(),
@@ -261,14 +459,30 @@ fn add_save_as_dialog(app: &Application,
 
                 if let Some(image_format) = image_format {
                     match image_format {
-                        ImageFormat::Jpeg(quality) => {
-                            jpeg_quality_scale.set_value(quality as f64);
-                            jpeg_quality_dialog.show_all();
+                        image_format if !image_format.has_alpha() => {
+                            let matte_color = program.editor.image().matte_color();
+                            matte_color_button.set_rgba(&gdk::RGBA {
+                                red: matte_color[0] as f64 / 255.0,
+                                green: matte_color[1] as f64 / 255.0,
+                                blue: matte_color[2] as f64 / 255.0,
+                                alpha: 1.0
+                            });
+
+                            export_options_dialog.show_all();
+
+                            let is_jpeg = if let ImageFormat::Jpeg(quality) = image_format {
+                                jpeg_quality_scale.set_value(quality as f64);
+                                true
+                            } else {
+                                false
+                            };
+                            jpeg_quality_label.set_visible(is_jpeg);
+                            jpeg_quality_scale.set_visible(is_jpeg);
+
+                            *current_save_format.borrow_mut() = Some(image_format);
                         }
                         image_format => {
-                            if let Err(err) = program.editor.image_mut().save_as(&path, &image_format) {
-                                println!("Failed to save file due to: {:?}.", err);
-                            }
+                            show_save_result(&window_clone, &path, program.editor.image_mut().save_as(&path, &image_format));
                         }
                     }
                 } else {
@@ -325,6 +539,9 @@ fn add_edit_menu(app: &Application,
     }));
     app.add_action(&redo);
 
+    // Snapshots
+    add_snapshot_dialogs(app, window, gtk_program.clone(), gl_area.clone(), &edit_menu);
+
     edit_menu.append(Some("Select all"), Some("app.select_all"));
     let select_all = gio::SimpleAction::new("select_all", None);
     let gl_area_clone = gl_area.clone();
@@ -334,12 +551,290 @@ fn add_edit_menu(app: &Application,
         gl_area_clone.queue_render();
     }));
     app.add_action(&select_all);
+
+    // Selection bounds
+    add_selection_bounds_dialog(app, window, gtk_program.clone(), gl_area.clone(), &edit_menu);
+
+    // Transform selection
+    edit_menu.append(Some("Transform selection"), Some("app.transform_selection"));
+    let transform_selection = gio::SimpleAction::new("transform_selection", None);
+
+    let transform_selection_dialog = create_dialog(window, "Transform selection");
+    transform_selection_dialog.set_width_request(220);
+    get_action_area(&transform_selection_dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    transform_selection_dialog.add_buttons(&[
+        ("Apply", gtk::ResponseType::Ok),
+        ("Cancel", gtk::ResponseType::Cancel)
+    ]);
+
+    let entry_offset_x = create_spin_button(&transform_selection_dialog.content_area(), "Offset x: ", 0.0, -100000.0, 100000.0, 1.0);
+    let entry_offset_y = create_spin_button(&transform_selection_dialog.content_area(), "Offset y: ", 0.0, -100000.0, 100000.0, 1.0);
+    let entry_scale = create_spin_button(&transform_selection_dialog.content_area(), "Scale (%): ", 100.0, 1.0, 10000.0, 1.0);
+    let entry_rotation = create_spin_button(&transform_selection_dialog.content_area(), "Rotation (°): ", 0.0, -360.0, 360.0, 1.0);
+
+    let transform_selection_dialog_clone = transform_selection_dialog.clone();
+    let entry_offset_x_clone = entry_offset_x.clone();
+    let entry_offset_y_clone = entry_offset_y.clone();
+    let entry_scale_clone = entry_scale.clone();
+    let entry_rotation_clone = entry_rotation.clone();
+    gtk_program.actions.borrow_mut().insert(
+        ProgramAction::OpenTransformSelectionDialog,
+        Box::new(move |_| {
+            entry_offset_x_clone.set_value(0.0);
+            entry_offset_y_clone.set_value(0.0);
+            entry_scale_clone.set_value(100.0);
+            entry_rotation_clone.set_value(0.0);
+            transform_selection_dialog_clone.show_all();
+        })
+    );
+
+    let gtk_program_clone = gtk_program.clone();
+    let gl_area_clone = gl_area.clone();
+    transform_selection.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            if program.editor.valid_region().is_some() {
+                program.actions.trigger(ProgramAction::OpenTransformSelectionDialog);
+                gl_area_clone.queue_render();
+            }
+        }
+    }));
+
+    let gtk_program_clone = gtk_program.clone();
+    let gl_area_clone = gl_area.clone();
+    transform_selection_dialog.connect_response(move |dialog, response| {
+        match response {
+            ResponseType::Ok => {
+                if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                    program.command_buffer.push(Command::TransformSelection {
+                        offset_x: entry_offset_x.value() as i32,
+                        offset_y: entry_offset_y.value() as i32,
+                        scale: entry_scale.value() as f32 / 100.0,
+                        rotation: entry_rotation.value() as f32
+                    });
+                    gl_area_clone.queue_render();
+                }
+
+                dialog.hide();
+            }
+            _ => {
+                dialog.hide();
+            }
+        }
+    });
+    app.add_action(&transform_selection);
+}
+
+fn add_selection_bounds_dialog(app: &Application,
+                               window: &ApplicationWindow,
+                               gtk_program: GTKProgramRef,
+                               gl_area: Rc<GLArea>,
+                               edit_menu: &gio::Menu) {
+    edit_menu.append(Some("Selection bounds"), Some("app.selection_bounds"));
+    let selection_bounds = gio::SimpleAction::new("selection_bounds", None);
+
+    let selection_bounds_dialog = create_dialog(window, "Selection bounds");
+    selection_bounds_dialog.set_width_request(220);
+    get_action_area(&selection_bounds_dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    selection_bounds_dialog.add_buttons(&[
+        ("Select", gtk::ResponseType::Ok),
+        ("Cancel", gtk::ResponseType::Cancel)
+    ]);
+
+    let entry_x = create_spin_button(&selection_bounds_dialog.content_area(), "X: ", 0.0, -100000.0, 100000.0, 1.0);
+    let entry_y = create_spin_button(&selection_bounds_dialog.content_area(), "Y: ", 0.0, -100000.0, 100000.0, 1.0);
+    let entry_width = create_spin_button(&selection_bounds_dialog.content_area(), "Width: ", 1.0, 1.0, 100000.0, 1.0);
+    let entry_height = create_spin_button(&selection_bounds_dialog.content_area(), "Height: ", 1.0, 1.0, 100000.0, 1.0);
+
+    let selection_bounds_dialog_clone = selection_bounds_dialog.clone();
+    gtk_program.actions.borrow_mut().insert(
+        ProgramAction::OpenSelectionBoundsDialog,
+        Box::new(move |_| {
+            selection_bounds_dialog_clone.show_all();
+        })
+    );
+
+    let gtk_program_clone = gtk_program.clone();
+    let gl_area_clone = gl_area.clone();
+    let entry_x_clone = entry_x.clone();
+    let entry_y_clone = entry_y.clone();
+    let entry_width_clone = entry_width.clone();
+    let entry_height_clone = entry_height.clone();
+    selection_bounds.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            // Start from the current selection, or the whole image if nothing is selected
+            let (x, y, width, height) = match program.editor.valid_region() {
+                Some(region) => (region.position.x, region.position.y, region.size.x, region.size.y),
+                None => (0, 0, program.editor.image().width() as i32, program.editor.image().height() as i32)
+            };
+
+            entry_x_clone.set_value(x as f64);
+            entry_y_clone.set_value(y as f64);
+            entry_width_clone.set_value(width as f64);
+            entry_height_clone.set_value(height as f64);
+
+            program.actions.trigger(ProgramAction::OpenSelectionBoundsDialog);
+            gl_area_clone.queue_render();
+        }
+    }));
+
+    let gtk_program_clone = gtk_program.clone();
+    let gl_area_clone = gl_area.clone();
+    selection_bounds_dialog.connect_response(move |dialog, response| {
+        match response {
+            ResponseType::Ok => {
+                if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                    let start_x = entry_x.value() as i32;
+                    let start_y = entry_y.value() as i32;
+                    program.command_buffer.push(Command::SetSelection(Some(Selection {
+                        start_x,
+                        start_y,
+                        end_x: start_x + entry_width.value() as i32,
+                        end_y: start_y + entry_height.value() as i32,
+                        mask: None
+                    })));
+                    gl_area_clone.queue_render();
+                }
+
+                dialog.hide();
+            }
+            _ => {
+                dialog.hide();
+            }
+        }
+    });
+    app.add_action(&selection_bounds);
+}
+
+fn add_snapshot_dialogs(app: &Application,
+                        window: &ApplicationWindow,
+                        gtk_program: GTKProgramRef,
+                        gl_area: Rc<GLArea>,
+                        edit_menu: &gio::Menu) {
+    // Save snapshot
+    edit_menu.append(Some("Save snapshot"), Some("app.save_snapshot"));
+    let save_snapshot = gio::SimpleAction::new("save_snapshot", None);
+    let gtk_program_clone = gtk_program.clone();
+    save_snapshot.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::TriggerProgramAction(ProgramAction::OpenSaveSnapshotDialog, ProgramActionData::Triggered));
+        }
+    }));
+    app.add_action(&save_snapshot);
+
+    let save_snapshot_dialog = create_dialog(window, "Save snapshot");
+    save_snapshot_dialog.set_width_request(220);
+    get_action_area(&save_snapshot_dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    save_snapshot_dialog.add_buttons(&[
+        ("Save", gtk::ResponseType::Ok),
+        ("Cancel", gtk::ResponseType::Cancel)
+    ]);
+
+    let entry_name = create_entry(&save_snapshot_dialog.content_area(), "Name: ", "");
+
+    let save_snapshot_dialog_clone = save_snapshot_dialog.clone();
+    let entry_name_clone = entry_name.clone();
+    let gtk_program_clone = gtk_program.clone();
+    gtk_program.actions.borrow_mut().insert(
+        ProgramAction::OpenSaveSnapshotDialog,
+        Box::new(move |_| {
+            if let Some(program) = gtk_program_clone.program.borrow().as_ref() {
+                entry_name_clone.set_text(&format!("Snapshot {}", program.editor.snapshot_names().len() + 1));
+            }
+
+            save_snapshot_dialog_clone.show_all();
+        })
+    );
+
+    let gtk_program_clone = gtk_program.clone();
+    save_snapshot_dialog.connect_response(move |dialog, response| {
+        match response {
+            ResponseType::Ok => {
+                let name = entry_name.text().to_string();
+                if !name.is_empty() {
+                    if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                        program.command_buffer.push(Command::SaveSnapshot(name));
+                    }
+                }
+
+                dialog.hide();
+            }
+            _ => {
+                dialog.hide();
+            }
+        }
+    });
+
+    // Restore snapshot
+    edit_menu.append(Some("Restore snapshot"), Some("app.restore_snapshot"));
+    let restore_snapshot = gio::SimpleAction::new("restore_snapshot", None);
+    let gtk_program_clone = gtk_program.clone();
+    restore_snapshot.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::TriggerProgramAction(ProgramAction::OpenRestoreSnapshotDialog, ProgramActionData::Triggered));
+        }
+    }));
+    app.add_action(&restore_snapshot);
+
+    let restore_snapshot_dialog = create_dialog(window, "Restore snapshot");
+    restore_snapshot_dialog.set_width_request(220);
+    get_action_area(&restore_snapshot_dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    restore_snapshot_dialog.add_buttons(&[
+        ("Restore", gtk::ResponseType::Ok),
+        ("Cancel", gtk::ResponseType::Cancel)
+    ]);
+
+    let snapshot_box = gtk::Box::new(Orientation::Horizontal, 4);
+    snapshot_box.add(&gtk::Label::new(Some("Snapshot:")));
+    let snapshot_selector = gtk::ComboBoxText::new();
+    snapshot_box.add(&snapshot_selector);
+    restore_snapshot_dialog.content_area().add(&snapshot_box);
+
+    let restore_snapshot_dialog_clone = restore_snapshot_dialog.clone();
+    let snapshot_selector_clone = snapshot_selector.clone();
+    let gtk_program_clone = gtk_program.clone();
+    gtk_program.actions.borrow_mut().insert(
+        ProgramAction::OpenRestoreSnapshotDialog,
+        Box::new(move |_| {
+            snapshot_selector_clone.remove_all();
+            if let Some(program) = gtk_program_clone.program.borrow().as_ref() {
+                for name in program.editor.snapshot_names() {
+                    snapshot_selector_clone.append_text(&name);
+                }
+            }
+            snapshot_selector_clone.set_active(Some(0));
+
+            restore_snapshot_dialog_clone.show_all();
+        })
+    );
+
+    let gtk_program_clone = gtk_program.clone();
+    restore_snapshot_dialog.connect_response(move |dialog, response| {
+        match response {
+            ResponseType::Ok => {
+                if let Some(name) = snapshot_selector.active_text() {
+                    if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                        program.command_buffer.push(Command::RestoreSnapshot(name.to_string()));
+                        gl_area.queue_render();
+                    }
+                }
+
+                dialog.hide();
+            }
+            _ => {
+                dialog.hide();
+            }
+        }
+    });
 }
 
 fn add_image_menu(app: &Application,
                   window: &ApplicationWindow,
                   gtk_program: GTKProgramRef,
-                  _gl_area: Rc<GLArea>,
+                  gl_area: Rc<GLArea>,
                   menu_bar: &gio::Menu) {
     let layer_menu = gio::Menu::new();
     menu_bar.append_submenu(Some("_Image"), &layer_menu);
@@ -491,6 +986,141 @@ fn add_image_menu(app: &Application,
         }
     });
     app.add_action(&resize_canvas);
+
+    // Histogram
+    layer_menu.append(Some("Histogram"), Some("app.histogram"));
+    let show_histogram = gio::SimpleAction::new("histogram", None);
+    let gtk_program_clone = gtk_program.clone();
+    let gl_area_clone = gl_area.clone();
+    show_histogram.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.actions.trigger(ProgramAction::OpenHistogramDialog);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&show_histogram);
+
+    // Curves
+    layer_menu.append(Some("Curves"), Some("app.curves"));
+    let show_curves = gio::SimpleAction::new("curves", None);
+    let gtk_program_clone = gtk_program.clone();
+    let gl_area_clone = gl_area.clone();
+    show_curves.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.actions.trigger(ProgramAction::OpenCurvesDialog);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&show_curves);
+
+    // Flip
+    let add_flip_item = |label: &str, action_name: &str, horizontal: bool| {
+        layer_menu.append(Some(label), Some(&format!("app.{}", action_name)));
+        let flip = gio::SimpleAction::new(action_name, None);
+        let gtk_program_clone = gtk_program.clone();
+        let gl_area_clone = gl_area.clone();
+        flip.connect_activate(move |_, _| {
+            if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                program.command_buffer.push(if horizontal { Command::FlipHorizontal } else { Command::FlipVertical });
+            }
+
+            gl_area_clone.queue_render();
+        });
+        app.add_action(&flip);
+    };
+
+    add_flip_item("Flip horizontally", "flip_horizontal", true);
+    add_flip_item("Flip vertically", "flip_vertical", false);
+
+    // Rotate canvas
+    let add_rotate_item = |label: &str, action_name: &str, quarter_turns: u8| {
+        layer_menu.append(Some(label), Some(&format!("app.{}", action_name)));
+        let rotate = gio::SimpleAction::new(action_name, None);
+        let gtk_program_clone = gtk_program.clone();
+        let gl_area_clone = gl_area.clone();
+        rotate.connect_activate(move |_, _| {
+            if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                program.command_buffer.push(Command::RotateCanvas(quarter_turns));
+            }
+
+            gl_area_clone.queue_render();
+        });
+        app.add_action(&rotate);
+    };
+
+    add_rotate_item("Rotate 90° clockwise", "rotate_canvas_90", 1);
+    add_rotate_item("Rotate 180°", "rotate_canvas_180", 2);
+    add_rotate_item("Rotate 90° counter-clockwise", "rotate_canvas_270", 3);
+
+    // Crop to selection
+    layer_menu.append(Some("Crop to selection"), Some("app.crop_to_selection"));
+    let crop_to_selection = gio::SimpleAction::new("crop_to_selection", None);
+    let gtk_program_clone = gtk_program.clone();
+    let gl_area_clone = gl_area.clone();
+    crop_to_selection.connect_activate(move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            if program.editor.valid_region().is_some() {
+                program.command_buffer.push(Command::CropToSelection);
+                gl_area_clone.queue_render();
+            }
+        }
+    });
+    app.add_action(&crop_to_selection);
+
+    // Flatten
+    layer_menu.append(Some("Flatten"), Some("app.flatten_image"));
+    let flatten_image = gio::SimpleAction::new("flatten_image", None);
+    let gtk_program_clone = gtk_program.clone();
+    let gl_area_clone = gl_area.clone();
+    flatten_image.connect_activate(move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::FlattenImage);
+            gl_area_clone.queue_render();
+        }
+    });
+    app.add_action(&flatten_image);
+
+    // Adjustments
+    let adjustments_menu = gio::Menu::new();
+    layer_menu.append_submenu(Some("Adjustments"), &adjustments_menu);
+
+    let add_filter_item = |label: &str, action_name: &str, filter: FilterKind| {
+        adjustments_menu.append(Some(label), Some(&format!("app.{}", action_name)));
+        let apply_filter = gio::SimpleAction::new(action_name, None);
+        let gtk_program_clone = gtk_program.clone();
+        let gl_area_clone = gl_area.clone();
+        apply_filter.connect_activate(move |_, _| {
+            if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                program.command_buffer.push(Command::ApplyFilter(filter));
+            }
+
+            gl_area_clone.queue_render();
+        });
+        app.add_action(&apply_filter);
+    };
+
+    add_filter_item("Grayscale", "filter_grayscale", FilterKind::Grayscale);
+
+    let add_dialog_item = |label: &str, action_name: &str, action: ProgramAction| {
+        adjustments_menu.append(Some(label), Some(&format!("app.{}", action_name)));
+        let open_dialog = gio::SimpleAction::new(action_name, None);
+        let gtk_program_clone = gtk_program.clone();
+        let gl_area_clone = gl_area.clone();
+        open_dialog.connect_activate(move |_, _| {
+            if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                program.actions.trigger(action);
+                gl_area_clone.queue_render();
+            }
+        });
+        app.add_action(&open_dialog);
+    };
+
+    add_dialog_item("Brightness/contrast", "brightness_contrast", ProgramAction::OpenBrightnessContrastDialog);
+    add_dialog_item("Gaussian blur", "gaussian_blur", ProgramAction::OpenGaussianBlurDialog);
+    add_dialog_item("Hue/saturation", "hue_saturation", ProgramAction::OpenHueSaturationDialog);
+    add_dialog_item("Posterize", "posterize", ProgramAction::OpenPosterizeDialog);
+    add_dialog_item("Threshold", "threshold", ProgramAction::OpenThresholdDialog);
+    add_dialog_item("Levels", "levels", ProgramAction::OpenLevelsDialog);
 }
 
 fn create_resize_dialog(window: &ApplicationWindow,
@@ -574,6 +1204,437 @@ fn add_layers_menu(app: &Application,
         }
     }));
     app.add_action(&delete_layer);
+
+    // Move layer up
+    layer_menu.append(Some("Move layer up"), Some("app.move_layer_up"));
+    let move_layer_up = gio::SimpleAction::new("move_layer_up", None);
+    let gl_area_clone = gl_area.clone();
+    let gtk_program_clone = gtk_program.clone();
+    move_layer_up.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::MoveLayerUp);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&move_layer_up);
+
+    // Move layer down
+    layer_menu.append(Some("Move layer down"), Some("app.move_layer_down"));
+    let move_layer_down = gio::SimpleAction::new("move_layer_down", None);
+    let gl_area_clone = gl_area.clone();
+    let gtk_program_clone = gtk_program.clone();
+    move_layer_down.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::MoveLayerDown);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&move_layer_down);
+
+    // Merge down
+    layer_menu.append(Some("Merge down"), Some("app.merge_down"));
+    let merge_down = gio::SimpleAction::new("merge_down", None);
+    let gl_area_clone = gl_area.clone();
+    let gtk_program_clone = gtk_program.clone();
+    merge_down.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::MergeDown);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&merge_down);
+
+    // Rename layer
+    layer_menu.append(Some("Rename layer"), Some("app.rename_layer"));
+    let rename_layer = gio::SimpleAction::new("rename_layer", None);
+    let gtk_program_clone = gtk_program.clone();
+    rename_layer.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::TriggerProgramAction(ProgramAction::OpenRenameLayerDialog, ProgramActionData::Triggered));
+        }
+    }));
+    app.add_action(&rename_layer);
+
+    let rename_layer_dialog = create_dialog(window, "Rename layer");
+    rename_layer_dialog.set_width_request(220);
+    get_action_area(&rename_layer_dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    rename_layer_dialog.add_buttons(&[
+        ("Rename", gtk::ResponseType::Ok),
+        ("Cancel", gtk::ResponseType::Cancel)
+    ]);
+
+    let entry_name = create_entry(&rename_layer_dialog.content_area(), "Name: ", "");
+    let rename_layer_index = Rc::new(Cell::new(0));
+
+    let rename_layer_dialog_clone = rename_layer_dialog.clone();
+    let entry_name_clone = entry_name.clone();
+    let rename_layer_index_clone = rename_layer_index.clone();
+    let gtk_program_clone = gtk_program.clone();
+    gtk_program.actions.borrow_mut().insert(
+        ProgramAction::OpenRenameLayerDialog,
+        Box::new(move |_| {
+            if let Some(program) = gtk_program_clone.program.borrow().as_ref() {
+                let layer_index = program.editor.active_layer_index();
+                rename_layer_index_clone.set(layer_index);
+                entry_name_clone.set_text(&program.editor.image().layers()[layer_index].name);
+            }
+
+            rename_layer_dialog_clone.show_all();
+        })
+    );
+
+    let gtk_program_clone = gtk_program.clone();
+    let gl_area_clone = gl_area.clone();
+    rename_layer_dialog.connect_response(move |dialog, response| {
+        match response {
+            ResponseType::Ok => {
+                let name = entry_name.text().to_string();
+
+                if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                    program.command_buffer.push(Command::SetLayerName(rename_layer_index.get(), name));
+                    gl_area_clone.queue_render();
+                }
+
+                dialog.hide();
+            }
+            _ => {
+                dialog.hide();
+            }
+        }
+    });
+}
+
+fn add_view_menu(app: &Application,
+                 window: &ApplicationWindow,
+                 gtk_program: GTKProgramRef,
+                 gl_area: Rc<GLArea>,
+                 menu_bar: &gio::Menu) {
+    let view_menu = gio::Menu::new();
+    menu_bar.append_submenu(Some("_View"), &view_menu);
+
+    // Add guide
+    view_menu.append(Some("Add guide"), Some("app.add_guide"));
+    let add_guide = gio::SimpleAction::new("add_guide", None);
+
+    let add_guide_dialog = create_dialog(window, "Add guide");
+    add_guide_dialog.set_width_request(220);
+    get_action_area(&add_guide_dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    add_guide_dialog.add_buttons(&[
+        ("Add", gtk::ResponseType::Ok),
+        ("Cancel", gtk::ResponseType::Cancel)
+    ]);
+
+    let orientation_group = gtk::Box::new(gtk::Orientation::Vertical, 2);
+
+    let orientation_horizontal = gtk::RadioButtonBuilder::new()
+        .label("Horizontal")
+        .build();
+    orientation_group.add(&orientation_horizontal);
+
+    let orientation_vertical = gtk::RadioButtonBuilder::new()
+        .label("Vertical")
+        .build();
+    orientation_group.add(&orientation_vertical);
+    orientation_vertical.join_group(Some(&orientation_horizontal));
+    add_guide_dialog.content_area().add(&orientation_group);
+
+    let entry_position = create_spin_button(&add_guide_dialog.content_area(), "Position: ", 0.0, 0.0, 100000.0, 1.0);
+
+    let add_guide_dialog_clone = add_guide_dialog.clone();
+    add_guide.connect_activate(glib::clone!(@weak window => move |_, _| {
+        add_guide_dialog_clone.show_all();
+    }));
+
+    let gtk_program_clone = gtk_program.clone();
+    let gl_area_clone = gl_area.clone();
+    add_guide_dialog.connect_response(move |dialog, response| {
+        match response {
+            ResponseType::Ok => {
+                let vertical = orientation_vertical.is_active();
+                let position = entry_position.value() as i32;
+
+                if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                    program.command_buffer.push(Command::AddGuide { vertical, position });
+                    gl_area_clone.queue_render();
+                }
+
+                dialog.hide();
+            }
+            _ => {
+                dialog.hide();
+            }
+        }
+    });
+    app.add_action(&add_guide);
+
+    // Clear guides
+    view_menu.append(Some("Clear guides"), Some("app.clear_guides"));
+    let clear_guides = gio::SimpleAction::new("clear_guides", None);
+    let gl_area_clone = gl_area.clone();
+    let gtk_program_clone = gtk_program.clone();
+    clear_guides.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::ClearGuides);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&clear_guides);
+
+    // Rulers
+    view_menu.append(Some("Show rulers"), Some("app.toggle_rulers"));
+    let toggle_rulers = gio::SimpleAction::new("toggle_rulers", None);
+    let gl_area_clone = gl_area.clone();
+    let gtk_program_clone = gtk_program.clone();
+    toggle_rulers.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::ToggleRulers);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&toggle_rulers);
+
+    // Snap to grid
+    view_menu.append(Some("Snap to grid"), Some("app.toggle_snap_to_grid"));
+    let toggle_snap_to_grid = gio::SimpleAction::new("toggle_snap_to_grid", None);
+    let gl_area_clone = gl_area.clone();
+    let gtk_program_clone = gtk_program.clone();
+    toggle_snap_to_grid.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::ToggleSnapToGrid);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&toggle_snap_to_grid);
+
+    // Grid size
+    view_menu.append(Some("Grid size"), Some("app.grid_size"));
+    let grid_size = gio::SimpleAction::new("grid_size", None);
+
+    let grid_size_dialog = create_dialog(window, "Grid size");
+    grid_size_dialog.set_width_request(220);
+    get_action_area(&grid_size_dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    grid_size_dialog.add_buttons(&[
+        ("Apply", gtk::ResponseType::Ok),
+        ("Cancel", gtk::ResponseType::Cancel)
+    ]);
+
+    let entry_grid_size = create_spin_button(&grid_size_dialog.content_area(), "Size: ", DEFAULT_GRID_SIZE as f64, 1.0, 1024.0, 1.0);
+
+    let grid_size_dialog_clone = grid_size_dialog.clone();
+    grid_size.connect_activate(glib::clone!(@weak window => move |_, _| {
+        grid_size_dialog_clone.show_all();
+    }));
+
+    let gtk_program_clone = gtk_program.clone();
+    let gl_area_clone = gl_area.clone();
+    grid_size_dialog.connect_response(move |dialog, response| {
+        match response {
+            ResponseType::Ok => {
+                if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                    program.command_buffer.push(Command::SetGridSize(entry_grid_size.value() as u32));
+                    gl_area_clone.queue_render();
+                }
+
+                dialog.hide();
+            }
+            _ => {
+                dialog.hide();
+            }
+        }
+    });
+    app.add_action(&grid_size);
+
+    // Onion skin
+    view_menu.append(Some("Toggle onion skin"), Some("app.toggle_onion_skin"));
+    let toggle_onion_skin = gio::SimpleAction::new("toggle_onion_skin", None);
+    let gl_area_clone = gl_area.clone();
+    let gtk_program_clone = gtk_program.clone();
+    toggle_onion_skin.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::ToggleOnionSkin);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&toggle_onion_skin);
+
+    // Composition overlay
+    view_menu.append(Some("Toggle composition overlay"), Some("app.toggle_composition_overlay"));
+    let toggle_composition_overlay = gio::SimpleAction::new("toggle_composition_overlay", None);
+    let gl_area_clone = gl_area.clone();
+    let gtk_program_clone = gtk_program.clone();
+    toggle_composition_overlay.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::ToggleCompositionOverlay);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&toggle_composition_overlay);
+
+    // Tiled preview
+    view_menu.append(Some("Toggle tiled preview"), Some("app.toggle_tiled_preview"));
+    let toggle_tiled_preview = gio::SimpleAction::new("toggle_tiled_preview", None);
+    let gl_area_clone = gl_area.clone();
+    let gtk_program_clone = gtk_program.clone();
+    toggle_tiled_preview.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::ToggleTiledPreview);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&toggle_tiled_preview);
+
+    // Symmetry
+    let symmetry_menu = gio::Menu::new();
+    view_menu.append_submenu(Some("Symmetry"), &symmetry_menu);
+    let add_symmetry_item = |label: &str, action_name: &str, symmetry: Symmetry| {
+        symmetry_menu.append(Some(label), Some(&format!("app.{}", action_name)));
+        let set_symmetry = gio::SimpleAction::new(action_name, None);
+        let gtk_program_clone = gtk_program.clone();
+        let gl_area_clone = gl_area.clone();
+        set_symmetry.connect_activate(move |_, _| {
+            if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                program.command_buffer.push(Command::SetSymmetry(symmetry));
+            }
+
+            gl_area_clone.queue_render();
+        });
+        app.add_action(&set_symmetry);
+    };
+
+    add_symmetry_item("None", "set_no_symmetry", Symmetry::None);
+    add_symmetry_item("Vertical axis", "set_vertical_symmetry", Symmetry::Vertical);
+    add_symmetry_item("Horizontal axis", "set_horizontal_symmetry", Symmetry::Horizontal);
+    add_symmetry_item("Both axes", "set_both_symmetry", Symmetry::Both);
+
+    // Fit image
+    view_menu.append(Some("Fit image"), Some("app.zoom_to_fit"));
+    let zoom_to_fit = gio::SimpleAction::new("zoom_to_fit", None);
+    let gl_area_clone = gl_area.clone();
+    let gtk_program_clone = gtk_program.clone();
+    zoom_to_fit.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::ZoomToFit);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&zoom_to_fit);
+
+    // Actual size
+    view_menu.append(Some("Actual size"), Some("app.zoom_to_actual_size"));
+    let zoom_to_actual_size = gio::SimpleAction::new("zoom_to_actual_size", None);
+    let gl_area_clone = gl_area.clone();
+    let gtk_program_clone = gtk_program.clone();
+    zoom_to_actual_size.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::ZoomToActualSize);
+            gl_area_clone.queue_render();
+        }
+    }));
+    app.add_action(&zoom_to_actual_size);
+
+    // Fit to window on open
+    view_menu.append(Some("Toggle fit large images on open"), Some("app.toggle_fit_to_window_on_open"));
+    let toggle_fit_to_window_on_open = gio::SimpleAction::new("toggle_fit_to_window_on_open", None);
+    let gtk_program_clone = gtk_program.clone();
+    toggle_fit_to_window_on_open.connect_activate(glib::clone!(@weak window => move |_, _| {
+        if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+            program.command_buffer.push(Command::ToggleFitToWindowOnOpen);
+        }
+    }));
+    app.add_action(&toggle_fit_to_window_on_open);
+
+    // Theme
+    let add_theme_item = |label: &str, action_name: &str, theme: UiTheme| {
+        view_menu.append(Some(label), Some(&format!("app.{}", action_name)));
+        let set_theme = gio::SimpleAction::new(action_name, None);
+        let gtk_program_clone = gtk_program.clone();
+        let gl_area_clone = gl_area.clone();
+        set_theme.connect_activate(move |_, _| {
+            if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                program.command_buffer.push(Command::SetTheme(theme));
+            }
+
+            gl_area_clone.queue_render();
+        });
+        app.add_action(&set_theme);
+    };
+
+    add_theme_item("Light theme", "set_light_theme", UiTheme::light());
+    add_theme_item("Dark theme", "set_dark_theme", UiTheme::dark());
+}
+
+fn add_text_dialog(window: &ApplicationWindow,
+                   gtk_program: GTKProgramRef,
+                   gl_area: Rc<GLArea>) {
+    let text_dialog = create_dialog(window, "Text");
+    text_dialog.set_width_request(260);
+    get_action_area(&text_dialog).set_property("halign", gtk::Align::Center).unwrap();
+
+    text_dialog.add_buttons(&[
+        ("Ok", gtk::ResponseType::Ok),
+        ("Cancel", gtk::ResponseType::Cancel)
+    ]);
+
+    let entry_text = create_entry(&text_dialog.content_area(), "Text: ", "");
+    let font_size_button = create_spin_button(&text_dialog.content_area(), "Size: ", 24.0, 6.0, 200.0, 1.0);
+    let color_button = gtk::ColorButton::new();
+    color_button.set_use_alpha(true);
+    text_dialog.content_area().add(&color_button);
+
+    let text_dialog_clone = text_dialog.clone();
+    let color_button_clone = color_button.clone();
+    let gtk_program_clone = gtk_program.clone();
+    gtk_program.actions.borrow_mut().insert(
+        ProgramAction::OpenTextDialog,
+        Box::new(move |_| {
+            if let Some(program) = gtk_program_clone.program.borrow().as_ref() {
+                let color = program.primary_color();
+                color_button_clone.set_rgba(&gdk::RGBA {
+                    red: color[0] as f64 / 255.0,
+                    green: color[1] as f64 / 255.0,
+                    blue: color[2] as f64 / 255.0,
+                    alpha: color[3] as f64 / 255.0
+                });
+            }
+
+            text_dialog_clone.show_all();
+        })
+    );
+
+    let gtk_program_clone = gtk_program.clone();
+    text_dialog.connect_response(move |dialog, response| {
+        match response {
+            ResponseType::Ok => {
+                let color = color_button.rgba();
+                if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                    program.command_buffer.push(Command::SetText {
+                        text: entry_text.text().to_string(),
+                        size: font_size_button.value() as u32,
+                        color: image::Rgba([
+                            (color.red * 255.0).round() as u8,
+                            (color.green * 255.0).round() as u8,
+                            (color.blue * 255.0).round() as u8,
+                            (color.alpha * 255.0).round() as u8
+                        ])
+                    });
+                    gl_area.queue_render();
+                }
+
+                dialog.hide();
+            }
+            _ => {
+                if let Some(program) = gtk_program_clone.program.borrow_mut().as_mut() {
+                    program.command_buffer.push(Command::AbortedText);
+                    gl_area.queue_render();
+                }
+
+                dialog.hide();
+            }
+        }
+    });
 }
 
 fn parse_new_size(gtk_program: &GTKProgram, entry_width: &gtk::Entry, entry_height: &gtk::Entry) -> Option<(u32, u32)> {
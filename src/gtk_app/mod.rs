@@ -2,9 +2,10 @@ use std::collections::{VecDeque, HashMap};
 use std::rc::Rc;
 use std::cell::{RefCell};
 
-use crate::{editor, ui};
+use crate::editor;
+use crate::ui::UiTheme;
 use crate::program::{Program, ProgramAction, ProgramActionData};
-use crate::editor::tools::EditorWindow;
+use crate::editor::tools::{EditorWindow, InputEvent};
 use crate::editor::EditorImage;
 
 pub mod app;
@@ -12,13 +13,17 @@ pub mod helpers;
 pub mod input_support;
 pub mod menu;
 pub mod color_select_dialog;
+pub mod histogram_dialog;
+pub mod curves_dialog;
+pub mod adjustments_dialog;
+pub mod autosave;
 
 pub type GTKProgramRef = Rc<GTKProgram>;
 
 pub struct GTKProgram {
     pub program: RefCell<Option<Program>>,
     pub editor_window: RefCell<Option<GTKEditorWindow>>,
-    pub event_queue: RefCell<VecDeque<glfw::WindowEvent>>,
+    pub event_queue: RefCell<VecDeque<InputEvent>>,
     pub actions: RefCell<HashMap<ProgramAction, Box<dyn Fn(ProgramActionData)>>>
 }
 
@@ -38,7 +43,7 @@ impl GTKProgram {
                 view_width,
                 view_height,
                 editor::Editor::new(image),
-                ui::create(),
+                UiTheme::light(),
             )
         );
 
@@ -46,6 +51,7 @@ impl GTKProgram {
             GTKEditorWindow {
                 mouse_position: (0.0, 0.0),
                 shift_down: false,
+                alt_down: false,
                 width: view_width,
                 height: view_height
             }
@@ -54,7 +60,7 @@ impl GTKProgram {
 
     pub fn change_size(&self, width: u32, height: u32) {
         if let Some(editor_window) = self.editor_window.borrow_mut().as_mut() {
-            self.event_queue.borrow_mut().push_back(glfw::WindowEvent::FramebufferSize(width as i32, height as i32));
+            self.event_queue.borrow_mut().push_back((glfw::WindowEvent::FramebufferSize(width as i32, height as i32), None));
             editor_window.width = width;
             editor_window.height = height;
         }
@@ -64,6 +70,7 @@ impl GTKProgram {
 pub struct GTKEditorWindow {
     pub mouse_position: (f64, f64),
     pub shift_down: bool,
+    pub alt_down: bool,
     pub width: u32,
     pub height: u32
 }
@@ -77,6 +84,10 @@ impl EditorWindow for GTKEditorWindow {
         self.shift_down
     }
 
+    fn is_alt_down(&self) -> bool {
+        self.alt_down
+    }
+
     fn width(&self) -> u32 {
         self.width
     }
@@ -1,18 +1,18 @@
-use std::cell::RefCell;
+use std::cell::{RefCell, Cell};
 use std::rc::Rc;
 use std::ops::DerefMut;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
-use cgmath::{Matrix3, Matrix4, Matrix, SquareMatrix};
+use cgmath::{Matrix3, Matrix4, Matrix, SquareMatrix, Transform};
 
 use glfw::{Key, Action, Modifiers};
 
-use crate::command_buffer::{CommandBuffer, Command, BackgroundType};
+use crate::command_buffer::{CommandBuffer, Command, BackgroundType, FilterKind};
 use crate::{editor, ui, content};
 use crate::rendering::shader::Shader;
 use crate::rendering::prelude::{Position, Rectangle, Color, Color4, Size};
 use crate::rendering::texture_render::TextureRender;
-use crate::editor::tools::{Tool, create_tools, Tools, EditorWindow, get_transformed_mouse_position, SelectionSubTool};
+use crate::editor::tools::{Tool, create_tools, Tools, EditorWindow, get_transformed_mouse_position, SelectionSubTool, SnappingEditorWindow, InputEvent, NUMBER_KEY_TOOLS};
 use crate::rendering::text_render::{TextRender, TextAlignment};
 use crate::rendering::solid_rectangle_render::SolidRectangleRender;
 use crate::rendering::ShaderAndRender;
@@ -20,17 +20,36 @@ use crate::rendering::texture::Texture;
 use crate::rendering::font::Font;
 use crate::rendering::rectangle_render::RectangleRender;
 use crate::editor::editor::{LayerState, EditorOperation};
+use crate::editor::image_operation::{ImageOperation, Symmetry};
+use crate::editor::image_operation_helpers::average_color;
 use crate::ui::layers::LayersManager;
+use crate::ui::UiTheme;
+use crate::ui::palette;
 use crate::editor::EditorImage;
-
-pub const LEFT_SIDE_PANEL_WIDTH: u32 = 70;
-pub const RIGHT_SIDE_PANEL_WIDTH: u32 = 150;
-pub const SIDE_PANELS_WIDTH: u32 = LEFT_SIDE_PANEL_WIDTH + RIGHT_SIDE_PANEL_WIDTH;
-pub const TOP_PANEL_HEIGHT: u32 = 40;
+use crate::editor::guides::{Guide, SNAP_DISTANCE, DEFAULT_GRID_SIZE};
 
 pub const LAYER_BUFFER: f32 = 5.0;
 pub const LAYER_SPACING: f32 = 10.0;
 
+pub const ONION_SKIN_OPACITY: f32 = 0.35;
+pub const ONION_SKIN_TINT: Color4 = Color4 { x: 255, y: 0, z: 0, w: 96 };
+
+pub const MIN_ZOOM: f32 = 0.3;
+pub const MAX_ZOOM: f32 = 32.0;
+pub const ZOOM_STEP: f32 = 1.1;
+pub const SCROLL_PAN_STEP: f32 = 40.0;
+
+pub const DEFAULT_BRUSH_SIZE: i32 = 1;
+pub const MAX_BRUSH_SIZE: i32 = 64;
+pub const DEFAULT_STROKE_WIDTH: i32 = 1;
+pub const MAX_STROKE_WIDTH: i32 = 32;
+
+pub const NUM_RECENT_COLORS: usize = 6;
+
+pub const RULER_SIZE: f32 = 24.0;
+pub const RULER_MIN_TICK_SPACING: f32 = 50.0;
+pub const RULER_STEPS: [i32; 12] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000];
+
 pub struct Program {
     renders: Renders,
     pub command_buffer: CommandBuffer,
@@ -49,8 +68,26 @@ pub struct Program {
     view_height: u32,
     view_x: f32,
     view_y: f32,
+    warned_singular_transform: Cell<bool>,
+    pan_anchor: Option<(Position, f32, f32)>,
     primary_color: editor::Color,
     secondary_color: editor::Color,
+    recent_colors: VecDeque<editor::Color>,
+    brush_size: i32,
+    guides: Vec<Guide>,
+    dragged_guide: Option<Guide>,
+    snap_to_grid: bool,
+    grid_size: u32,
+    show_rulers: bool,
+    onion_skin: bool,
+    composition_overlay: bool,
+    tiled_preview: bool,
+    symmetry: Symmetry,
+    symmetry_axis: Position,
+    pub fit_to_window_on_open: bool,
+    clipboard: Option<image::RgbaImage>,
+    pending_paste: Option<image::RgbaImage>,
+    theme: UiTheme,
     pub actions: ProgramActionsManager
 }
 
@@ -58,7 +95,7 @@ impl Program {
     pub fn new(view_width: u32,
                view_height: u32,
                editor: editor::Editor,
-               ui_manager: ui::Manager) -> Program {
+               theme: UiTheme) -> Program {
         let preview_image = editor.new_image_same();
         let width = editor.image().width();
         let height = editor.image().height();
@@ -74,6 +111,8 @@ impl Program {
         let renders = Renders::new();
         let tools = create_tools(&renders);
 
+        let ui_manager = ui::create(&theme, &renders);
+
         let mut program = Program {
             renders,
             command_buffer: CommandBuffer::new(),
@@ -88,23 +127,48 @@ impl Program {
             zoom: 1.0,
             window_width: view_width,
             window_height: view_height,
-            view_width: view_width - SIDE_PANELS_WIDTH,
-            view_height: view_height - TOP_PANEL_HEIGHT,
+            view_width: view_width.saturating_sub(theme.side_panels_width()),
+            view_height: view_height.saturating_sub(theme.top_panel_height),
             view_x: 0.0,
             view_y: 0.0,
+            warned_singular_transform: Cell::new(false),
+            pan_anchor: None,
             primary_color: image::Rgba([0, 0, 0, 0]),
             secondary_color: image::Rgba([0, 0, 0, 0]),
+            recent_colors: VecDeque::new(),
+            brush_size: DEFAULT_BRUSH_SIZE,
+            guides: Vec::new(),
+            dragged_guide: None,
+            snap_to_grid: false,
+            grid_size: DEFAULT_GRID_SIZE,
+            show_rulers: false,
+            onion_skin: false,
+            composition_overlay: false,
+            tiled_preview: false,
+            symmetry: Symmetry::None,
+            symmetry_axis: Position::new(0.0, 0.0),
+            fit_to_window_on_open: true,
+            clipboard: None,
+            pending_paste: None,
+            theme,
             actions: ProgramActionsManager::new()
         };
 
         program.command_buffer.push(Command::SetImageSize(width, height));
         program.command_buffer.push(Command::SetPrimaryColor(image::Rgba([255, 0, 0, 255])));
         program.command_buffer.push(Command::SetSecondaryColor(image::Rgba([0, 0, 0, 255])));
+        program.command_buffer.push(Command::SetBrushSize(DEFAULT_BRUSH_SIZE));
+        program.command_buffer.push(Command::SetStrokeWidth(DEFAULT_STROKE_WIDTH));
         program.command_buffer.push(Command::SwitchedTool(program.active_tool));
+        program.image_size_changed();
 
         program
     }
 
+    pub fn theme(&self) -> &UiTheme {
+        &self.theme
+    }
+
     pub fn primary_color(&self) -> editor::Color {
         self.primary_color
     }
@@ -115,10 +179,10 @@ impl Program {
 
     pub fn update(&mut self,
                   window: &mut dyn EditorWindow,
-                  events: impl Iterator<Item=glfw::WindowEvent>) {
+                  events: impl Iterator<Item=InputEvent>) {
         self.tools[self.active_tool.index()].update();
 
-        for event in events {
+        for (event, pressure) in events {
             match event {
                 glfw::WindowEvent::FramebufferSize(width, height) => {
                     unsafe {
@@ -139,15 +203,40 @@ impl Program {
 
                     self.layers_manager.process_gui_event(
                         window,
-                        self.window_width - SIDE_PANELS_WIDTH,
+                        &self.theme,
+                        self.window_width - self.theme.side_panels_width(),
                         &event,
+                        &mut self.command_buffer,
                         &mut self.editor
                     );
 
-                    let image_area_transform = self.image_area_transform(false).invert().unwrap();
+                    // Guides dragged out of the rulers should not be drawn on by the tools
+                    if self.dragged_guide.is_some() {
+                        continue;
+                    }
+
+                    let image_area_transform = match self.inverse_image_area_transform() {
+                        Some(image_area_transform) => image_area_transform,
+                        None => continue
+                    };
                     let image_area_rectangle = self.image_area_rectangle();
-                    let op = self.tools[self.active_tool.index()].process_gui_event(
+
+                    let guides: &[Guide] = if self.active_tool.snaps_to_guides() { &self.guides } else { &[] };
+                    let grid_size = if self.snap_to_grid && self.active_tool.snaps_to_guides() { Some(self.grid_size) } else { None };
+                    let canvas_rectangle = self.canvas_rectangle();
+                    let mut snapping_window = SnappingEditorWindow::new(
                         window,
+                        &image_area_transform,
+                        canvas_rectangle,
+                        guides,
+                        grid_size,
+                        SNAP_DISTANCE / self.zoom,
+                        pressure
+                    );
+                    let event = snapping_window.snap_event(event);
+
+                    let op = self.tools[self.active_tool.index()].process_gui_event(
+                        &mut snapping_window,
                         &event,
                         &image_area_transform,
                         &image_area_rectangle,
@@ -188,6 +277,36 @@ impl Program {
                     self.editor.apply_editor_op(EditorOperation::SetImage(EditorImage::from_rgba(Some(path), image)));
                     self.image_size_changed();
                 }
+                Command::OpenProject(path) => {
+                    match EditorImage::load_project(&path) {
+                        Ok(image) => {
+                            self.editor.apply_editor_op(EditorOperation::SetImage(image));
+                            self.image_size_changed();
+                        }
+                        Err(err) => {
+                            self.actions.trigger_with_data(
+                                ProgramAction::ShowError,
+                                ProgramActionData::Message(format!("Failed to open project {}: {}.", path.display(), err))
+                            );
+                        }
+                    }
+                }
+                Command::RecoverProject(recovery_path) => {
+                    match EditorImage::load_project(&recovery_path) {
+                        Ok(mut image) => {
+                            // Keep the path of the opened image so that saving replaces it rather than the recovery file
+                            image.set_path(self.editor.image().path().map(|path| path.to_path_buf()));
+                            self.editor.apply_editor_op(EditorOperation::SetImage(image));
+                            self.image_size_changed();
+                        }
+                        Err(err) => {
+                            self.actions.trigger_with_data(
+                                ProgramAction::ShowError,
+                                ProgramActionData::Message(format!("Failed to open recovery file {}: {}.", recovery_path.display(), err))
+                            );
+                        }
+                    }
+                }
                 Command::SetTool(tool) => {
                     self.switch_tool(window, tool);
                     self.command_buffer.push(Command::SwitchedTool(self.active_tool));
@@ -201,6 +320,12 @@ impl Program {
                 Command::ApplyImageOp(op) => {
                     self.editor.apply_image_op(op);
                 }
+                Command::PreviewImageOp(op) => {
+                    self.editor.preview_image_op(op);
+                }
+                Command::ClearPreviewImageOp => {
+                    self.editor.clear_preview_image_op();
+                }
                 Command::UndoImageOp => {
                     self.editor.undo_op();
                     self.update_view_size();
@@ -209,24 +334,189 @@ impl Program {
                     self.editor.redo_op();
                     self.update_view_size();
                 }
+                Command::JumpToHistory(index) => {
+                    self.editor.goto_history(index);
+                    self.update_view_size();
+                }
+                Command::SaveSnapshot(name) => {
+                    self.editor.save_snapshot(name);
+                }
+                Command::RestoreSnapshot(name) => {
+                    if self.editor.restore_snapshot(&name) {
+                        self.image_size_changed();
+                    }
+                }
                 Command::NewLayer => {
                     self.editor.add_layer();
                 }
                 Command::DuplicateLayer => {
                     self.editor.duplicate_active_layer();
                 }
+                Command::ImportAsLayer(path, image) => {
+                    let name = path.file_stem().map(|name| name.to_string_lossy().into_owned());
+                    self.editor.import_layer(name, &image);
+                }
                 Command::DeleteLayer => {
                     self.editor.delete_active_layer();
                 }
-                Command::RequestResizeCanvas(new_width, new_height) => {
-                    self.actions.trigger_with_data(
-                        ProgramAction::ResizeCanvas,
-                        ProgramActionData::Size(new_width, new_height, Some("Canvas too small - resize needed".to_owned()))
-                    );
+                Command::MoveLayerUp => {
+                    self.editor.move_layer_up(self.editor.active_layer_index());
+                }
+                Command::MoveLayerDown => {
+                    self.editor.move_layer_down(self.editor.active_layer_index());
+                }
+                Command::MergeDown => {
+                    self.editor.merge_down_active_layer();
+                }
+                Command::SetLayerName(layer_index, name) => {
+                    self.editor.apply_editor_op(EditorOperation::SetLayerName(layer_index, name));
+                }
+                Command::AddGuide { vertical, position } => {
+                    let guide = Guide::new(vertical, position);
+                    if !self.guides.contains(&guide) {
+                        self.guides.push(guide);
+                    }
+                }
+                Command::ClearGuides => {
+                    self.guides.clear();
+                }
+                Command::ToggleSnapToGrid => {
+                    self.snap_to_grid = !self.snap_to_grid;
+                }
+                Command::SetGridSize(grid_size) => {
+                    self.grid_size = grid_size.max(1);
+                }
+                Command::ToggleRulers => {
+                    self.show_rulers = !self.show_rulers;
+                }
+                Command::ToggleOnionSkin => {
+                    self.onion_skin = !self.onion_skin;
+                }
+                Command::ApplyFilter(filter) => {
+                    let op = match filter {
+                        FilterKind::Grayscale => ImageOperation::Grayscale { luminance_weights: None }
+                    };
+
+                    self.command_buffer.push(Command::ApplyImageOp(op));
+                }
+                Command::FlipHorizontal | Command::FlipVertical => {
+                    let (start_x, start_y, end_x, end_y) = match self.editor.valid_region() {
+                        Some(region) => (
+                            region.position.x,
+                            region.position.y,
+                            region.position.x + region.size.x,
+                            region.position.y + region.size.y
+                        ),
+                        None => (0, 0, self.editor.image().width() as i32, self.editor.image().height() as i32)
+                    };
+
+                    self.command_buffer.push(Command::ApplyImageOp(ImageOperation::Flip {
+                        horizontal: matches!(command, Command::FlipHorizontal),
+                        start_x,
+                        start_y,
+                        end_x,
+                        end_y
+                    }));
+                }
+                Command::LoadPalette(path) => {
+                    match palette::load_palette(&path) {
+                        Ok(colors) => {
+                            self.ui_manager.set_palette(colors);
+                        }
+                        Err(err) => {
+                            self.actions.trigger_with_data(
+                                ProgramAction::ShowError,
+                                ProgramActionData::Message(format!("Failed to load palette {}: {}.", path.display(), err))
+                            );
+                        }
+                    }
+                }
+                Command::SavePalette(path) => {
+                    if let Err(err) = palette::save_palette(&path, self.ui_manager.palette_colors()) {
+                        self.actions.trigger_with_data(
+                            ProgramAction::ShowError,
+                            ProgramActionData::Message(format!("Failed to save palette {}: {}.", path.display(), err))
+                        );
+                    }
+                }
+                Command::ToggleCompositionOverlay => {
+                    self.composition_overlay = !self.composition_overlay;
+                }
+                Command::ToggleTiledPreview => {
+                    self.tiled_preview = !self.tiled_preview;
+                }
+                Command::ZoomToFit => {
+                    self.zoom_to_fit();
+                }
+                Command::SetZoom(zoom) => {
+                    self.view_x = 0.0;
+                    self.view_y = 0.0;
+                    self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+                    self.update_view_size();
+                }
+                Command::ZoomToActualSize => {
+                    self.zoom_to_actual_size();
+                }
+                Command::ToggleFitToWindowOnOpen => {
+                    self.fit_to_window_on_open = !self.fit_to_window_on_open;
+                }
+                Command::SetTheme(theme) => {
+                    self.theme = theme;
+                    let palette_colors = self.ui_manager.palette_colors().clone();
+                    self.ui_manager = ui::create(&self.theme, &self.renders);
+                    self.ui_manager.set_palette(palette_colors);
+                    self.command_buffer.push(Command::SetPrimaryColor(self.primary_color));
+                    self.command_buffer.push(Command::SetSecondaryColor(self.secondary_color));
+                    self.command_buffer.push(Command::SetBrushSize(self.brush_size));
+                    self.command_buffer.push(Command::SetRecentColors(self.recent_colors.iter().cloned().collect()));
+                    self.command_buffer.push(Command::SwitchedTool(self.active_tool));
+                    self.update_view_size();
                 }
                 Command::SetCopiedImage(image) => {
+                    self.clipboard = Some(image.clone());
                     self.actions.trigger_with_data(ProgramAction::SetCopiedImage, ProgramActionData::Image(image));
                 }
+                Command::ExportImageGpu(path, image_format) => {
+                    match self.editor.export_gpu(&self.renders.texture_render, &path, &image_format) {
+                        Ok(Some(warning)) => {
+                            self.actions.trigger_with_data(ProgramAction::ShowWarning, ProgramActionData::Message(warning.to_string()));
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            self.actions.trigger_with_data(
+                                ProgramAction::ShowError,
+                                ProgramActionData::Message(format!("Failed to export {}: {}.", path.display(), err))
+                            );
+                        }
+                    }
+                }
+                Command::SetClipboard(image) => {
+                    self.clipboard = Some(image);
+                }
+                Command::Paste(image) => {
+                    let width = self.editor.image().width();
+                    let height = self.editor.image().height();
+
+                    if image.width() <= width && image.height() <= height {
+                        self.place_paste(window, image);
+                    } else {
+                        self.actions.trigger_with_data(
+                            ProgramAction::ResizeCanvas,
+                            ProgramActionData::Size(
+                                image.width().max(width),
+                                image.height().max(height),
+                                Some("Canvas too small - resize or cancel to clip".to_owned())
+                            )
+                        );
+
+                        self.pending_paste = Some(image);
+                    }
+                }
+                Command::AbortedResizeCanvas => {
+                    if let Some(image) = self.pending_paste.take() {
+                        self.place_paste(window, image);
+                    }
+                }
                 Command::TriggerProgramAction(action, data) => {
                     match action {
                         ProgramAction::OpenSelectPrimaryColorDialog | ProgramAction::OpenSelectSecondaryColorDialog => {
@@ -256,16 +546,70 @@ impl Program {
 
                             self.editor.apply_editor_op(EditorOperation::SetImage(image));
                             self.image_size_changed();
+
+                            if let Some(image) = self.pending_paste.take() {
+                                self.place_paste(window, image);
+                            }
+                        }
+                        Command::RotateCanvas(quarter_turns) => {
+                            let mut image = self.editor.image().clone();
+                            image.rotate_canvas(quarter_turns);
+
+                            self.command_buffer.push(Command::SetSelection(None));
+                            self.editor.apply_editor_op(EditorOperation::SetImage(image));
+                            self.image_size_changed();
+                        }
+                        Command::CropToSelection => {
+                            if let Some(region) = self.editor.valid_region().cloned() {
+                                let mut image = self.editor.image().clone();
+                                image.crop(&region);
+
+                                self.command_buffer.push(Command::SetSelection(None));
+                                self.editor.apply_editor_op(EditorOperation::SetImage(image));
+                                self.image_size_changed();
+                            }
+                        }
+                        Command::FlattenImage => {
+                            let mut image = self.editor.image().clone();
+                            image.flatten();
+                            self.editor.apply_editor_op(EditorOperation::SetImage(image));
                         }
                         Command::SetSelection(ref selection) => {
                             self.editor.set_valid_region(selection.as_ref().map(|selection| selection.region()));
                         }
+                        Command::PickMergedColor { x, y, half_size, secondary } => {
+                            let color = average_color(self.editor.image(), x as i32, y as i32, half_size);
+                            if secondary {
+                                self.command_buffer.push(Command::SetSecondaryColor(color));
+                            } else {
+                                // Set right away, as the color can be committed before the pushed command is handled
+                                self.primary_color = color;
+                                self.command_buffer.push(Command::SetPrimaryColor(color));
+                            }
+                        }
                         Command::SetPrimaryColor(color) => {
                             self.primary_color = color;
                         }
+                        Command::CommitPrimaryColor => {
+                            if add_recent_color(&mut self.recent_colors, self.primary_color) {
+                                self.command_buffer.push(Command::SetRecentColors(self.recent_colors.iter().cloned().collect()));
+                            }
+                        }
                         Command::SetSecondaryColor(color) => {
                             self.secondary_color = color;
                         }
+                        Command::SetBrushSize(size) => {
+                            self.brush_size = size.clamp(0, MAX_BRUSH_SIZE);
+                        }
+                        Command::ChangeBrushSize(change) => {
+                            self.command_buffer.push(Command::SetBrushSize((self.brush_size + change).clamp(0, MAX_BRUSH_SIZE)));
+                        }
+                        Command::SetSymmetry(symmetry) => {
+                            self.symmetry = symmetry;
+                        }
+                        Command::SetSymmetryAxis(axis) => {
+                            self.symmetry_axis = axis;
+                        }
                         _ => {}
                     }
 
@@ -283,6 +627,26 @@ impl Program {
         }
     }
 
+    fn place_paste(&mut self, window: &mut dyn EditorWindow, image: image::RgbaImage) {
+        let (image, position_x, position_y) = paste_placement(
+            image,
+            self.editor.image().width(),
+            self.editor.image().height(),
+            self.view_x,
+            self.view_y
+        );
+
+        match self.active_tool {
+            Tools::Selection(_) => {}
+            _ => {
+                self.switch_tool(window, Tools::Selection(SelectionSubTool::Select));
+                self.command_buffer.push(Command::SwitchedTool(self.active_tool));
+            }
+        }
+
+        self.command_buffer.push(Command::PlacePaste(image, position_x, position_y));
+    }
+
     fn switch_tool(&mut self,
                    window: &mut dyn EditorWindow,
                    tool: Tools) {
@@ -303,7 +667,7 @@ impl Program {
         self.preview_image.update_operation();
     }
 
-    fn process_internal_events(&mut self, _window: &mut dyn EditorWindow, event: &glfw::WindowEvent) {
+    fn process_internal_events(&mut self, window: &mut dyn EditorWindow, event: &glfw::WindowEvent) {
         match event {
             glfw::WindowEvent::Key(Key::Z, _, Action::Press, Modifiers::Control) => {
                 self.command_buffer.push(Command::UndoImageOp);
@@ -313,11 +677,25 @@ impl Program {
             }
             glfw::WindowEvent::Key(Key::S, _, Action::Press, Modifiers::Control) => {
                 if let (Some(path), Some(image_format)) = (self.editor.image().path(), self.editor.image().image_format()) {
-                    if let Err(err) = self.editor.image().save(path, &image_format) {
-                        println!("Failed to save file due to: {:?}.", err);
+                    match self.editor.image().save(path, &image_format) {
+                        Ok(Some(warning)) => {
+                            self.actions.trigger_with_data(ProgramAction::ShowWarning, ProgramActionData::Message(warning.to_string()));
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            self.actions.trigger_with_data(
+                                ProgramAction::ShowError,
+                                ProgramActionData::Message(format!("Failed to save {}: {}.", path.display(), err))
+                            );
+                        }
                     }
                 }
             }
+            glfw::WindowEvent::Key(Key::V, _, Action::Press, Modifiers::Control) => {
+                if let Some(image) = self.clipboard.as_ref() {
+                    self.command_buffer.push(Command::Paste(image.clone()));
+                }
+            }
             glfw::WindowEvent::Key(Key::N, _, Action::Press, Modifiers::Control) => {
                 self.actions.trigger(ProgramAction::NewImage);
             }
@@ -340,44 +718,149 @@ impl Program {
             glfw::WindowEvent::Key(Key::Left, _, Action::Press | Action::Repeat, _) => {
                 if self.sees_not_whole() {
                     self.view_x -= 10.0;
+                    self.clamp_view();
                 }
             }
             glfw::WindowEvent::Key(Key::Right, _, Action::Press | Action::Repeat, _) => {
                 if self.sees_not_whole() {
                     self.view_x += 10.0;
+                    self.clamp_view();
                 }
             }
             glfw::WindowEvent::Key(Key::Up, _, Action::Press | Action::Repeat, _) => {
                 if self.sees_not_whole() {
                     self.view_y -= 10.0;
+                    self.clamp_view();
                 }
             }
             glfw::WindowEvent::Key(Key::Down, _, Action::Press | Action::Repeat, _) => {
                 if self.sees_not_whole() {
                     self.view_y += 10.0;
+                    self.clamp_view();
                 }
             }
-            glfw::WindowEvent::Scroll(_, y) => {
-                let prev_zoom = self.zoom;
-                self.zoom = (self.zoom + *y as f32 * 0.1).max(0.3);
+            glfw::WindowEvent::Scroll(x, y) => {
+                // Shift turns vertical scrolling into horizontal panning
+                let (pan_x, zoom_y) = if window.is_shift_down() {
+                    (*x - *y, 0.0)
+                } else {
+                    (*x, *y)
+                };
+
+                if pan_x != 0.0 && self.sees_not_whole() {
+                    self.view_x += pan_x as f32 * SCROLL_PAN_STEP / self.zoom;
+                    self.clamp_view();
+                }
+
+                if zoom_y != 0.0 {
+                    let (mouse_x, mouse_y) = window.get_cursor_pos();
+                    self.zoom_at(scroll_zoom(self.zoom, zoom_y as f32), Position::new(mouse_x as f32, mouse_y as f32));
+                }
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
+                let (mouse_x, mouse_y) = window.get_cursor_pos();
+                let mouse_position = Position::new(mouse_x as f32, mouse_y as f32);
+                for (index, rectangle) in self.history_entry_rectangles().into_iter().enumerate() {
+                    if rectangle.contains(&mouse_position) {
+                        self.command_buffer.push(Command::JumpToHistory(index));
+                        break;
+                    }
+                }
 
-                if self.zoom < 1.0 || prev_zoom < 1.0 {
-                    self.view_x = self.editor.image().width() as f32 * 0.5 - (self.view_width as f32 / self.zoom) * 0.5;
-                    self.view_y = self.editor.image().height() as f32 * 0.5 - (self.view_height as f32 / self.zoom) * 0.5;
+                self.begin_drag_guide(&mouse_position);
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Release, _) => {
+                if let Some(guide) = self.dragged_guide.take() {
+                    let (mouse_x, mouse_y) = window.get_cursor_pos();
+                    let mouse_position = Position::new(mouse_x as f32, mouse_y as f32);
+
+                    // Dropping a guide back onto the rulers removes it
+                    let (top_ruler, left_ruler) = self.ruler_rectangles();
+                    if self.canvas_rectangle().contains(&mouse_position) && !top_ruler.contains(&mouse_position) && !left_ruler.contains(&mouse_position) {
+                        self.command_buffer.push(Command::AddGuide { vertical: guide.vertical, position: guide.position });
+                    }
+                }
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button3, Action::Press, _) => {
+                let (mouse_x, mouse_y) = window.get_cursor_pos();
+                let mouse_position = Position::new(mouse_x as f32, mouse_y as f32);
+                if self.image_area_rectangle().contains(&mouse_position) {
+                    self.pan_anchor = Some((mouse_position, self.view_x, self.view_y));
+                }
+            }
+            glfw::WindowEvent::MouseButton(glfw::MouseButton::Button3, Action::Release, _) => {
+                self.pan_anchor = None;
+            }
+            glfw::WindowEvent::CursorPos(mouse_x, mouse_y) => {
+                if let Some((anchor_position, anchor_view_x, anchor_view_y)) = self.pan_anchor {
+                    self.view_x = anchor_view_x - (*mouse_x as f32 - anchor_position.x) / self.zoom;
+                    self.view_y = anchor_view_y - (*mouse_y as f32 - anchor_position.y) / self.zoom;
+                    self.clamp_view();
                 }
 
-                self.update_view_size();
+                if let (Some(guide), Some(inverse_image_area_transform)) = (self.dragged_guide.as_mut(), self.image_area_transform(false).invert()) {
+                    let image_position = inverse_image_area_transform.transform_point(cgmath::Point2::new(*mouse_x as f32, *mouse_y as f32));
+                    guide.position = if guide.vertical { image_position.x.floor() as i32 } else { image_position.y.floor() as i32 };
+                }
             }
             glfw::WindowEvent::Key(Key::Num0, _, Action::Press, Modifiers::Control) => {
-                self.view_x = 0.0;
-                self.view_y = 0.0;
-                self.zoom = 1.0;
-                self.update_view_size();
+                self.zoom_to_actual_size();
+            }
+            glfw::WindowEvent::Key(Key::Num9, _, Action::Press, Modifiers::Control) => {
+                self.zoom_to_fit();
+            }
+            glfw::WindowEvent::Key(Key::X, _, Action::Press, modifiers) if modifiers.is_empty() => {
+                self.command_buffer.push(Command::SetPrimaryColor(self.secondary_color));
+                self.command_buffer.push(Command::SetSecondaryColor(self.primary_color));
+            }
+            glfw::WindowEvent::Key(Key::D, _, Action::Press, modifiers) if modifiers.is_empty() => {
+                self.command_buffer.push(Command::SetPrimaryColor(image::Rgba([0, 0, 0, 255])));
+                self.command_buffer.push(Command::SetSecondaryColor(image::Rgba([255, 255, 255, 255])));
+            }
+            // Key events only reach the editor when it has focus, so these do not fire while typing in a modal dialog
+            glfw::WindowEvent::Key(key @ (Key::Num0 | Key::Num1 | Key::Num2 | Key::Num3 | Key::Num4 | Key::Num5 | Key::Num6 | Key::Num7 | Key::Num8 | Key::Num9), _, Action::Press, modifiers) if modifiers.is_empty() => {
+                let digit = *key as i32 - Key::Num0 as i32;
+                let tool = NUMBER_KEY_TOOLS[((digit + 9) % 10) as usize];
+                self.command_buffer.push(Command::SetTool(tool));
             }
             _ => {}
         }
     }
 
+    fn begin_drag_guide(&mut self, mouse_position: &Position) {
+        if !self.show_rulers {
+            return;
+        }
+
+        let inverse_image_area_transform = match self.inverse_image_area_transform() {
+            Some(inverse_image_area_transform) => inverse_image_area_transform,
+            None => return
+        };
+
+        let image_position = inverse_image_area_transform.transform_point(cgmath::Point2::new(mouse_position.x, mouse_position.y));
+        let max_distance = SNAP_DISTANCE / self.zoom;
+
+        // A guide crossing the ruler is picked up, otherwise a new guide is dragged out perpendicular to the ruler
+        let (top_ruler, left_ruler) = self.ruler_rectangles();
+        let (vertical, position) = if top_ruler.contains(mouse_position) {
+            (true, image_position.x)
+        } else if left_ruler.contains(mouse_position) {
+            (false, image_position.y)
+        } else {
+            return;
+        };
+
+        let existing_guide = self.guides.iter().position(|guide| {
+            guide.vertical == vertical && (guide.image_position() - position).abs() <= max_distance
+        });
+
+        self.dragged_guide = match existing_guide {
+            Some(index) => Some(self.guides.remove(index)),
+            None if vertical => Some(Guide::new(false, image_position.y.floor() as i32)),
+            None => Some(Guide::new(true, image_position.x.floor() as i32))
+        };
+    }
+
     fn sees_not_whole(&self) -> bool {
         let ratio_x = (self.editor.image().width() as f32 * self.zoom) / self.view_width as f32;
         let ratio_y = (self.editor.image().height() as f32 * self.zoom) / self.view_height as f32;
@@ -385,6 +868,10 @@ impl Program {
     }
 
     pub fn render(&mut self, window: &mut dyn EditorWindow, transform: &Matrix4<f32>) {
+        if self.inverse_image_area_transform().is_none() {
+            return;
+        }
+
         let image_area_transform = self.image_area_transform_matrix4(true);
         let image_area_transform_full = self.image_area_transform_matrix4(false);
 
@@ -427,6 +914,10 @@ impl Program {
             );
         }
 
+        if self.tiled_preview {
+            self.render_tiles(transform, image_area_transform_full);
+        }
+
         let image_crop_rectangle = Rectangle::new(
             self.view_x,
             self.view_y,
@@ -445,15 +936,45 @@ impl Program {
             self.preview_image.clear_cpu();
         }
 
-        for (index, (state, image)) in self.editor.image().layers().iter().enumerate() {
-            if state == &LayerState::Visible {
-                self.renders.texture_render.render_sub(
+        // The closest layer below the active one is shown even when hidden, as animators usually hide the other frames
+        let onion_skin_layer_index = if self.onion_skin {
+            let active_layer_index = self.editor.active_layer_index();
+            self.editor.image().layers()
+                .iter()
+                .enumerate()
+                .take(active_layer_index)
+                .rev()
+                .find(|(_, layer)| layer.state != LayerState::Deleted)
+                .map(|(index, _)| index)
+        } else {
+            None
+        };
+
+        for (index, layer) in self.editor.image().layers().iter().enumerate() {
+            if index == self.editor.active_layer_index() {
+                if let Some(onion_skin_layer) = onion_skin_layer_index.and_then(|index| self.editor.image().layers().get(index)) {
+                    self.renders.texture_render.render_sub_tinted(
+                        self.renders.texture_render.shader(),
+                        &(transform * image_area_transform),
+                        onion_skin_layer.image.get_texture(),
+                        Position::new(0.0, 0.0),
+                        self.zoom,
+                        Some(image_crop_rectangle.clone()),
+                        ONION_SKIN_OPACITY,
+                        ONION_SKIN_TINT
+                    );
+                }
+            }
+
+            if layer.state == LayerState::Visible {
+                self.renders.texture_render.render_sub_with_opacity(
                     self.renders.texture_render.shader(),
                     &(transform * image_area_transform),
-                    image.get_texture(),
+                    layer.image.get_texture(),
                     Position::new(0.0, 0.0),
                     self.zoom,
-                    Some(image_crop_rectangle.clone())
+                    Some(image_crop_rectangle.clone()),
+                    layer.opacity
                 );
             }
 
@@ -477,13 +998,14 @@ impl Program {
                     );
                 }
 
-                self.renders.texture_render.render_sub(
+                self.renders.texture_render.render_sub_with_opacity(
                     self.renders.texture_render.shader(),
                     &(transform * image_area_transform),
                     self.preview_image.get_texture(),
                     Position::new(0.0, 0.0),
                     self.zoom,
-                    Some(image_crop_rectangle.clone())
+                    Some(image_crop_rectangle.clone()),
+                    layer.opacity
                 );
             }
         }
@@ -506,6 +1028,13 @@ impl Program {
             }
         }
 
+        if self.composition_overlay {
+            self.render_composition_overlay(transform, image_area_transform_full);
+        }
+
+        self.render_guides(transform, image_area_transform_full);
+        self.render_symmetry_axes(transform, image_area_transform_full);
+
         self.renders.rectangle_render.render(
             self.renders.rectangle_render.shader(),
             &(transform * image_area_transform_full),
@@ -519,17 +1048,125 @@ impl Program {
         )
     }
 
+    fn render_tiles(&self, transform: &Matrix4<f32>, image_area_transform_full: &Matrix4<f32>) {
+        let width = self.editor.image().width() as f32;
+        let height = self.editor.image().height() as f32;
+
+        // The tiles are drawn below the canvas, which covers the center tile
+        for tile_y in -1..=1 {
+            for tile_x in -1..=1 {
+                if tile_x == 0 && tile_y == 0 {
+                    continue;
+                }
+
+                for layer in self.editor.image().layers() {
+                    if layer.state == LayerState::Visible {
+                        self.renders.texture_render.render_sub_with_opacity(
+                            self.renders.texture_render.shader(),
+                            &(transform * image_area_transform_full),
+                            layer.image.get_texture(),
+                            Position::new(tile_x as f32 * width, tile_y as f32 * height),
+                            1.0,
+                            None,
+                            layer.opacity
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn render_guides(&self, transform: &Matrix4<f32>, image_area_transform_full: &Matrix4<f32>) {
+        let width = self.editor.image().width() as f32;
+        let height = self.editor.image().height() as f32;
+
+        for guide in self.guides.iter().chain(self.dragged_guide.iter()) {
+            let position = guide.image_position();
+            let (start, end) = if guide.vertical {
+                (Position::new(position, 0.0), Position::new(position, height))
+            } else {
+                (Position::new(0.0, position), Position::new(width, position))
+            };
+
+            self.renders.rectangle_render.render_line(
+                self.renders.rectangle_render.shader(),
+                &(transform * image_area_transform_full),
+                start,
+                end,
+                Color4::new(0, 200, 255, 255)
+            );
+        }
+    }
+
+    fn render_symmetry_axes(&self, transform: &Matrix4<f32>, image_area_transform_full: &Matrix4<f32>) {
+        let width = self.editor.image().width() as f32;
+        let height = self.editor.image().height() as f32;
+        let axis = self.symmetry_axis;
+        let color = Color4::new(255, 0, 255, 200);
+
+        if self.symmetry == Symmetry::Vertical || self.symmetry == Symmetry::Both {
+            self.renders.rectangle_render.render_line(
+                self.renders.rectangle_render.shader(),
+                &(transform * image_area_transform_full),
+                Position::new(axis.x, 0.0),
+                Position::new(axis.x, height),
+                color
+            );
+        }
+
+        if self.symmetry == Symmetry::Horizontal || self.symmetry == Symmetry::Both {
+            self.renders.rectangle_render.render_line(
+                self.renders.rectangle_render.shader(),
+                &(transform * image_area_transform_full),
+                Position::new(0.0, axis.y),
+                Position::new(width, axis.y),
+                color
+            );
+        }
+    }
+
+    fn render_composition_overlay(&self, transform: &Matrix4<f32>, image_area_transform_full: &Matrix4<f32>) {
+        let width = self.editor.image().width() as f32;
+        let height = self.editor.image().height() as f32;
+
+        let render_lines = |fraction: f32, color: Color4| {
+            self.renders.rectangle_render.render_line(
+                self.renders.rectangle_render.shader(),
+                &(transform * image_area_transform_full),
+                Position::new(width * fraction, 0.0),
+                Position::new(width * fraction, height),
+                color
+            );
+
+            self.renders.rectangle_render.render_line(
+                self.renders.rectangle_render.shader(),
+                &(transform * image_area_transform_full),
+                Position::new(0.0, height * fraction),
+                Position::new(width, height * fraction),
+                color
+            );
+        };
+
+        // Rule of thirds
+        let thirds_color = Color4::new(255, 255, 255, 160);
+        render_lines(1.0 / 3.0, thirds_color);
+        render_lines(2.0 / 3.0, thirds_color);
+
+        // Symmetry axes
+        render_lines(0.5, Color4::new(255, 106, 0, 160));
+    }
+
     fn render_ui(&mut self,
                  window: &mut dyn EditorWindow,
                  transform: &Matrix4<f32>,
                  image_area_transform_full: &Matrix4<f32>) {
-        let menu_color = Color4::new(255, 255, 255, 255);
+        let menu_color = self.theme.menu_color;
         self.renders.solid_rectangle_render.render(
             self.renders.solid_rectangle_render.shader(),
             transform,
             &Rectangle::from_position_and_size(
                 Position::new(0.0, 0.0),
-                Size::new(LEFT_SIDE_PANEL_WIDTH as f32, self.window_height as f32)
+                Size::new(self.theme.left_side_panel_width as f32, self.window_height as f32)
             ),
             menu_color
         );
@@ -539,7 +1176,7 @@ impl Program {
             transform,
             &Rectangle::from_position_and_size(
                 Position::new(0.0, 0.0),
-                Size::new(self.window_width as f32, TOP_PANEL_HEIGHT as f32)
+                Size::new(self.window_width as f32, self.theme.top_panel_height as f32)
             ),
             menu_color
         );
@@ -548,31 +1185,54 @@ impl Program {
             self.renders.solid_rectangle_render.shader(),
             transform,
             &Rectangle::from_position_and_size(
-                Position::new(self.window_width as f32 - RIGHT_SIDE_PANEL_WIDTH as f32, 0.0),
-                Size::new(RIGHT_SIDE_PANEL_WIDTH as f32, self.window_height as f32)
+                Position::new(self.window_width as f32 - self.theme.right_side_panel_width as f32, 0.0),
+                Size::new(self.theme.right_side_panel_width as f32, self.window_height as f32)
             ),
             menu_color
         );
 
-        let mouse_position = get_transformed_mouse_position(window, &self.image_area_transform(false).invert().unwrap());
-        self.renders.text_render.render_line(
-            self.renders.text_render.shader(),
-            transform,
-            self.renders.ui_font.borrow_mut().deref_mut(),
-            format!("{:.0} %, {:.0}, {:.0}", self.zoom * 100.0, mouse_position.x.round(), mouse_position.y.round()).chars().map(|c| (c, Color::new(0, 0, 0))),
-            Position::new(self.window_width as f32 - RIGHT_SIDE_PANEL_WIDTH as f32 - 160.0, 10.0),
-            TextAlignment::Top
-        );
+        if self.show_rulers {
+            self.render_rulers(transform);
+        }
+
+        if let Some(inverse_image_area_transform) = self.inverse_image_area_transform() {
+            let mouse_position = get_transformed_mouse_position(window, &inverse_image_area_transform);
+
+            let mut status_text = format!("{:.0} %, {:.0}, {:.0}", self.zoom * 100.0, mouse_position.x.round(), mouse_position.y.round());
+            if mouse_position.x >= 0.0 && mouse_position.y >= 0.0 {
+                if let Some(color) = self.editor.image().composited_pixel(mouse_position.x as u32, mouse_position.y as u32) {
+                    status_text += &format!(", #{:02x}{:02x}{:02x}{:02x}", color[0], color[1], color[2], color[3]);
+                }
+            }
+
+            if let Some(tool_status_text) = self.tools[self.active_tool.index()].status_text() {
+                status_text += &format!(", {}", tool_status_text);
+            }
+
+            // Longer status texts grow to the left instead of into the right side panel
+            let status_end_x = self.window_width as f32 - self.theme.right_side_panel_width as f32 - 10.0;
+            let status_width = self.renders.ui_font.borrow_mut().line_width(&status_text);
+
+            self.renders.text_render.render_line(
+                self.renders.text_render.shader(),
+                transform,
+                self.renders.ui_font.borrow_mut().deref_mut(),
+                status_text.chars().map(|c| (c, self.theme.text_color)),
+                Position::new((status_end_x - 250.0).min(status_end_x - status_width), 10.0),
+                TextAlignment::Top
+            );
+        }
 
-        let last_layer_position_y = self.layers_manager.render(
+        self.layers_manager.render(
             transform,
             &self.renders,
+            &self.theme,
             &self.editor,
-            self.window_width - SIDE_PANELS_WIDTH,
+            self.window_width - self.theme.side_panels_width(),
             &self.transparent_background_texture,
         );
 
-        self.render_history(transform, last_layer_position_y);
+        self.render_history(transform);
 
         self.ui_manager.render(&self.renders, &transform);
 
@@ -584,16 +1244,160 @@ impl Program {
         );
     }
 
-    fn render_history(&self, transform: &Matrix4<f32>, last_layer_position_y: f32) {
-        let history_position_x = (self.window_width - SIDE_PANELS_WIDTH) as f32 + LEFT_SIDE_PANEL_WIDTH as f32;
-        let mut history_position_y = last_layer_position_y;
+    fn render_rulers(&self, transform: &Matrix4<f32>) {
+        let image_area_transform = self.image_area_transform(false);
+        let inverse_image_area_transform = match image_area_transform.invert() {
+            Some(inverse_image_area_transform) => inverse_image_area_transform,
+            None => return
+        };
+
+        let canvas_rectangle = self.canvas_rectangle();
+        let start_x = canvas_rectangle.left();
+        let start_y = canvas_rectangle.top();
+        let end_x = canvas_rectangle.right();
+        let end_y = canvas_rectangle.bottom();
+
+        let background_color = Color4::new(230, 230, 230, 255);
+        let tick_color = Color4::new(0, 0, 0, 255);
+
+        let (top_ruler, left_ruler) = self.ruler_rectangles();
+        for ruler in &[top_ruler, left_ruler] {
+            self.renders.solid_rectangle_render.render(
+                self.renders.solid_rectangle_render.shader(),
+                transform,
+                ruler,
+                background_color
+            );
+        }
+
+        // Use the smallest step that keeps the labels apart at the current zoom
+        let step = RULER_STEPS.iter().cloned()
+            .find(|step| *step as f32 * self.zoom >= RULER_MIN_TICK_SPACING)
+            .unwrap_or(RULER_STEPS[RULER_STEPS.len() - 1]);
+
+        let image_start = inverse_image_area_transform.transform_point(cgmath::Point2::new(start_x + RULER_SIZE, start_y + RULER_SIZE));
+        let image_end = inverse_image_area_transform.transform_point(cgmath::Point2::new(end_x, end_y));
+
+        let mut font = self.renders.ui_font_small.borrow_mut();
+
+        let mut x = (image_start.x / step as f32).ceil() as i32 * step;
+        while x as f32 <= image_end.x {
+            let screen_x = image_area_transform.transform_point(cgmath::Point2::new(x as f32, 0.0)).x;
+
+            self.renders.rectangle_render.render_line(
+                self.renders.rectangle_render.shader(),
+                transform,
+                Position::new(screen_x, start_y + RULER_SIZE * 0.5),
+                Position::new(screen_x, start_y + RULER_SIZE),
+                tick_color
+            );
+
+            self.renders.text_render.render_line(
+                self.renders.text_render.shader(),
+                transform,
+                font.deref_mut(),
+                x.to_string().chars().map(|c| (c, Color::new(0, 0, 0))),
+                Position::new(screen_x + 2.0, start_y + 1.0),
+                TextAlignment::Top
+            );
+
+            x += step;
+        }
+
+        let mut y = (image_start.y / step as f32).ceil() as i32 * step;
+        while y as f32 <= image_end.y {
+            let screen_y = image_area_transform.transform_point(cgmath::Point2::new(0.0, y as f32)).y;
+
+            self.renders.rectangle_render.render_line(
+                self.renders.rectangle_render.shader(),
+                transform,
+                Position::new(start_x + RULER_SIZE * 0.5, screen_y),
+                Position::new(start_x + RULER_SIZE, screen_y),
+                tick_color
+            );
+
+            self.renders.text_render.render_line(
+                self.renders.text_render.shader(),
+                transform,
+                font.deref_mut(),
+                y.to_string().chars().map(|c| (c, Color::new(0, 0, 0))),
+                Position::new(start_x + 1.0, screen_y + 2.0),
+                TextAlignment::Top
+            );
+
+            y += step;
+        }
+
+        // Covers the labels that overlap in the corner
+        self.renders.solid_rectangle_render.render(
+            self.renders.solid_rectangle_render.shader(),
+            transform,
+            &Rectangle::new(start_x, start_y, RULER_SIZE, RULER_SIZE),
+            background_color
+        );
+    }
+
+    fn canvas_rectangle(&self) -> Rectangle {
+        Rectangle::new(
+            self.theme.left_side_panel_width as f32,
+            self.theme.top_panel_height as f32,
+            self.window_width.saturating_sub(self.theme.side_panels_width()) as f32,
+            self.window_height.saturating_sub(self.theme.top_panel_height) as f32
+        )
+    }
+
+    fn ruler_rectangles(&self) -> (Rectangle, Rectangle) {
+        let canvas_rectangle = self.canvas_rectangle();
+        (
+            Rectangle::new(canvas_rectangle.left(), canvas_rectangle.top(), canvas_rectangle.size.x, RULER_SIZE),
+            Rectangle::new(canvas_rectangle.left(), canvas_rectangle.top(), RULER_SIZE, canvas_rectangle.size.y)
+        )
+    }
+
+    fn history_position(&self) -> Position {
+        Position::new(
+            (self.window_width - self.theme.side_panels_width()) as f32 + self.theme.left_side_panel_width as f32,
+            LayersManager::last_layer_position_y(&self.theme, self.window_width - self.theme.side_panels_width(), &self.editor)
+        )
+    }
+
+    fn history_entry_rectangles(&self) -> Vec<Rectangle> {
+        let history_position = self.history_position();
+        let mut entry_position_y = history_position.y + 5.0 + self.renders.ui_font.borrow_mut().line_height();
+        let entry_height = self.renders.ui_font_small.borrow_mut().line_height();
+
+        let mut rectangles = Vec::new();
+        for action in self.editor.history() {
+            // Operations of an unfinished draw are not part of the history yet
+            if let EditorOperation::ImageOp(_, op) = action {
+                if op.is_any_marker() {
+                    break;
+                }
+            }
+
+            rectangles.push(Rectangle::new(history_position.x, entry_position_y, self.theme.right_side_panel_width as f32, entry_height));
+            entry_position_y += entry_height;
+        }
+
+        for _ in self.editor.redo_history() {
+            rectangles.push(Rectangle::new(history_position.x, entry_position_y, self.theme.right_side_panel_width as f32, entry_height));
+            entry_position_y += entry_height;
+        }
+
+        rectangles
+    }
+
+    fn render_history(&self, transform: &Matrix4<f32>) {
+        let history_position = self.history_position();
+        let history_position_x = history_position.x;
+        let mut history_position_y = history_position.y;
 
         self.renders.solid_rectangle_render.render(
             self.renders.solid_rectangle_render.shader(),
             transform,
             &Rectangle::from_position_and_size(
                 Position::new(history_position_x, history_position_y),
-                Size::new(RIGHT_SIDE_PANEL_WIDTH as f32, 1.0)
+                Size::new(self.theme.right_side_panel_width as f32, 1.0)
             ),
             Color4::new(0, 0, 0, 255)
         );
@@ -604,29 +1408,25 @@ impl Program {
             &self.renders.text_render.shader(),
             transform,
             self.renders.ui_font.borrow_mut().deref_mut(),
-            "History".chars().map(|c| (c, Color::new(0, 0, 0))),
+            "History".chars().map(|c| (c, self.theme.text_color)),
             Position::new(history_position_x + 5.0, history_position_y),
             TextAlignment::Top
         );
-        history_position_y += self.renders.ui_font.borrow_mut().line_height();
 
-        for action in self.editor.history() {
-            if let EditorOperation::ImageOp(_, op) = action {
-                if op.is_any_marker() {
-                    break;
-                }
-            }
+        // Operations that can be redone are shown dimmed after the current state
+        let actions = self.editor.history()
+            .map(|action| (action, self.theme.text_color))
+            .chain(self.editor.redo_history().map(|action| (action, Color::new(160, 160, 160))));
 
+        for ((action, color), rectangle) in actions.zip(self.history_entry_rectangles()) {
             self.renders.text_render.render_line(
                 &self.renders.text_render.shader(),
                 transform,
                 self.renders.ui_font_small.borrow_mut().deref_mut(),
-                format!("{}", action).chars().map(|c| (c, Color::new(0, 0, 0))),
-                Position::new(history_position_x + 5.0, history_position_y),
+                format!("{}", action).chars().map(|c| (c, color)),
+                Position::new(rectangle.left() + 5.0, rectangle.top()),
                 TextAlignment::Top
             );
-
-            history_position_y += self.renders.ui_font_small.borrow_mut().line_height();
         }
     }
 
@@ -660,25 +1460,93 @@ impl Program {
 
     fn image_size_changed(&mut self) {
         self.preview_image = self.editor.new_image_same();
-        self.zoom = 1.0;
+        self.command_buffer.push(Command::SetSymmetryAxis(Position::new(
+            self.editor.image().width() as f32 * 0.5,
+            self.editor.image().height() as f32 * 0.5
+        )));
+        self.zoom_to_actual_size();
+
+        if self.fit_to_window_on_open {
+            let (available_width, available_height) = self.available_view_size();
+            if self.editor.image().width() > available_width || self.editor.image().height() > available_height {
+                self.zoom_to_fit();
+            }
+        }
+    }
+
+    fn available_view_size(&self) -> (u32, u32) {
+        (
+            self.window_width.saturating_sub(self.theme.side_panels_width()),
+            self.window_height.saturating_sub(self.theme.top_panel_height)
+        )
+    }
+
+    fn zoom_to_actual_size(&mut self) {
         self.view_x = 0.0;
         self.view_y = 0.0;
+        self.zoom = 1.0;
+        self.update_view_size();
+    }
+
+    fn zoom_to_fit(&mut self) {
+        let (available_width, available_height) = self.available_view_size();
+        let zoom_x = available_width as f32 / self.editor.image().width() as f32;
+        let zoom_y = available_height as f32 / self.editor.image().height() as f32;
+
+        self.zoom = zoom_x.min(zoom_y).clamp(MIN_ZOOM, MAX_ZOOM);
         self.update_view_size();
     }
 
     fn update_view_size(&mut self) {
-        self.view_width = (self.window_width - SIDE_PANELS_WIDTH).min((self.editor.image().width() as f32 * self.zoom.max(1.0)) as u32);
-        self.view_height = (self.window_height - TOP_PANEL_HEIGHT).min((self.editor.image().height() as f32 * self.zoom.max(1.0)) as u32);
+        let (available_width, available_height) = self.available_view_size();
+        self.view_width = available_width.min((self.editor.image().width() as f32 * self.zoom.max(1.0)) as u32).max(1);
+        self.view_height = available_height.min((self.editor.image().height() as f32 * self.zoom.max(1.0)) as u32).max(1);
+        self.clamp_view();
+    }
+
+    fn zoom_at(&mut self, zoom: f32, screen_position: Position) {
+        let image_position_before = self.inverse_image_area_transform().map(|transform| transform.transform_point(screen_position));
+
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.update_view_size();
+
+        // Keep the image point under the cursor fixed
+        let image_position_after = self.inverse_image_area_transform().map(|transform| transform.transform_point(screen_position));
+        if let (Some(image_position_before), Some(image_position_after)) = (image_position_before, image_position_after) {
+            self.view_x += image_position_before.x - image_position_after.x;
+            self.view_y += image_position_before.y - image_position_after.y;
+            self.clamp_view();
+        }
+    }
+
+    fn clamp_view(&mut self) {
+        let image_width = self.editor.image().width() as f32;
+        let image_height = self.editor.image().height() as f32;
+        let visible_width = self.view_width as f32 / self.zoom;
+        let visible_height = self.view_height as f32 / self.zoom;
+
+        // Keep the view inside the image when only part of it is visible, otherwise center the image
+        if visible_width < image_width {
+            self.view_x = self.view_x.clamp(0.0, image_width - visible_width);
+        } else {
+            self.view_x = image_width * 0.5 - visible_width * 0.5;
+        }
+
+        if visible_height < image_height {
+            self.view_y = self.view_y.clamp(0.0, image_height - visible_height);
+        } else {
+            self.view_y = image_height * 0.5 - visible_height * 0.5;
+        }
     }
 
     fn image_area_transform(&self, only_origin: bool) -> Matrix3<f32> {
-        let mut origin_x = LEFT_SIDE_PANEL_WIDTH as f32;
-        let mut origin_y = TOP_PANEL_HEIGHT as f32;
+        let mut origin_x = self.theme.left_side_panel_width as f32;
+        let mut origin_y = self.theme.top_panel_height as f32;
 
         let center_origin_x = self.window_width as f32 / 2.0 - self.view_width as f32 / 2.0;
         let center_origin_y = self.window_height as f32 / 2.0 - self.view_height as f32 / 2.0;
 
-        if (origin_x + self.view_width as f32) < self.window_width as f32 - SIDE_PANELS_WIDTH as f32 {
+        if (origin_x + self.view_width as f32) < self.window_width as f32 - self.theme.side_panels_width() as f32 {
             origin_x = center_origin_x;
         }
 
@@ -686,31 +1554,17 @@ impl Program {
             origin_y = center_origin_y;
         }
 
-        let origin_transform = cgmath::Matrix3::from_cols(
-            cgmath::Vector3::new(1.0, 0.0, origin_x),
-            cgmath::Vector3::new(0.0, 1.0, origin_y),
-            cgmath::Vector3::new(0.0, 0.0, 1.0),
-        ).transpose();
-
         if only_origin {
-            origin_transform
+            view_transform(origin_x, origin_y, 1.0, 0.0, 0.0)
         } else {
-            origin_transform
-            *
-            cgmath::Matrix3::from_cols(
-                cgmath::Vector3::new(self.zoom, 0.0, 0.0),
-                cgmath::Vector3::new(0.0, self.zoom, 0.0),
-                cgmath::Vector3::new(0.0, 0.0, 1.0),
-            ).transpose()
-            *
-            cgmath::Matrix3::from_cols(
-                cgmath::Vector3::new(1.0, 0.0, -self.view_x),
-                cgmath::Vector3::new(0.0, 1.0, -self.view_y),
-                cgmath::Vector3::new(0.0, 0.0, 1.0),
-            ).transpose()
+            view_transform(origin_x, origin_y, self.zoom, self.view_x, self.view_y)
         }
     }
 
+    fn inverse_image_area_transform(&self) -> Option<Matrix3<f32>> {
+        invert_view_transform(self.image_area_transform(false), &self.warned_singular_transform)
+    }
+
     fn image_area_transform_matrix4(&self, only_origin: bool) -> Matrix4<f32> {
         let image_area_transform = self.image_area_transform(only_origin).transpose();
 
@@ -730,7 +1584,76 @@ impl Program {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+// Moves the color to the front of the recent colors, returning if they changed
+fn add_recent_color(recent_colors: &mut VecDeque<editor::Color>, color: editor::Color) -> bool {
+    if recent_colors.front() == Some(&color) {
+        return false;
+    }
+
+    recent_colors.retain(|recent_color| recent_color != &color);
+    recent_colors.push_front(color);
+    recent_colors.truncate(NUM_RECENT_COLORS);
+    true
+}
+
+// Each scroll notch changes the zoom by the same ratio
+fn scroll_zoom(zoom: f32, notches: f32) -> f32 {
+    (zoom * ZOOM_STEP.powf(notches)).clamp(MIN_ZOOM, MAX_ZOOM)
+}
+
+// Maps image coordinates to screen coordinates
+fn view_transform(origin_x: f32, origin_y: f32, zoom: f32, view_x: f32, view_y: f32) -> Matrix3<f32> {
+    cgmath::Matrix3::from_cols(
+        cgmath::Vector3::new(1.0, 0.0, origin_x),
+        cgmath::Vector3::new(0.0, 1.0, origin_y),
+        cgmath::Vector3::new(0.0, 0.0, 1.0),
+    ).transpose()
+    *
+    cgmath::Matrix3::from_cols(
+        cgmath::Vector3::new(zoom, 0.0, 0.0),
+        cgmath::Vector3::new(0.0, zoom, 0.0),
+        cgmath::Vector3::new(0.0, 0.0, 1.0),
+    ).transpose()
+    *
+    cgmath::Matrix3::from_cols(
+        cgmath::Vector3::new(1.0, 0.0, -view_x),
+        cgmath::Vector3::new(0.0, 1.0, -view_y),
+        cgmath::Vector3::new(0.0, 0.0, 1.0),
+    ).transpose()
+}
+
+// The transform is inverted every frame, so only warn once until it can be inverted again
+fn invert_view_transform(transform: Matrix3<f32>, warned: &Cell<bool>) -> Option<Matrix3<f32>> {
+    let inverse_transform = transform.invert();
+    if inverse_transform.is_none() {
+        if !warned.replace(true) {
+            println!("Warning: the image area transform is not invertible, skipping frames until it is.");
+        }
+    } else {
+        warned.set(false);
+    }
+
+    inverse_transform
+}
+
+// Clips the pasted image to the canvas and places it at the top left of the view, inside the canvas
+fn paste_placement(image: image::RgbaImage, width: u32, height: u32, view_x: f32, view_y: f32) -> (image::RgbaImage, i32, i32) {
+    let image = if image.width() > width || image.height() > height {
+        image::RgbaImage::from_fn(
+            image.width().min(width),
+            image.height().min(height),
+            |x, y| *image.get_pixel(x, y)
+        )
+    } else {
+        image
+    };
+
+    let position_x = (view_x.max(0.0) as i32).min(width as i32 - image.width() as i32).max(0);
+    let position_y = (view_y.max(0.0) as i32).min(height as i32 - image.height() as i32).max(0);
+    (image, position_x, position_y)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ProgramAction {
     NewImage,
     OpenImage,
@@ -739,7 +1662,25 @@ pub enum ProgramAction {
     ResizeCanvas,
     SetCopiedImage,
     OpenSelectPrimaryColorDialog,
-    OpenSelectSecondaryColorDialog
+    OpenSelectSecondaryColorDialog,
+    OpenHistogramDialog,
+    OpenCurvesDialog,
+    OpenBrightnessContrastDialog,
+    OpenGaussianBlurDialog,
+    OpenHueSaturationDialog,
+    OpenPosterizeDialog,
+    OpenThresholdDialog,
+    OpenLevelsDialog,
+    OpenFillToleranceDialog,
+    OpenTransformSelectionDialog,
+    OpenSelectionBoundsDialog,
+    OpenStampImage,
+    OpenRenameLayerDialog,
+    OpenTextDialog,
+    OpenSaveSnapshotDialog,
+    OpenRestoreSnapshotDialog,
+    ShowError,
+    ShowWarning
 }
 
 #[derive(Debug, Clone)]
@@ -747,7 +1688,8 @@ pub enum ProgramActionData {
     None,
     Triggered,
     Size(u32, u32, Option<String>),
-    Image(image::RgbaImage)
+    Image(image::RgbaImage),
+    Message(String)
 }
 
 impl ProgramActionData {
@@ -821,4 +1763,106 @@ impl Renders {
             ui_font_small: Rc::new(RefCell::new(Font::new(&content::get_path("content/fonts/NotoMono-Regular.ttf"), 14).unwrap()))
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(expected: Position, actual: Position) {
+        assert!((expected.x - actual.x).abs() < 1E-3, "{:?} != {:?}", expected, actual);
+        assert!((expected.y - actual.y).abs() < 1E-3, "{:?} != {:?}", expected, actual);
+    }
+
+    #[test]
+    fn test_add_recent_color() {
+        let red = image::Rgba([255, 0, 0, 255]);
+        let green = image::Rgba([0, 255, 0, 255]);
+
+        let mut recent_colors = VecDeque::new();
+        assert!(add_recent_color(&mut recent_colors, red));
+        assert!(add_recent_color(&mut recent_colors, green));
+        assert!(!add_recent_color(&mut recent_colors, green));
+        assert!(add_recent_color(&mut recent_colors, red));
+        assert_eq!(vec![red, green], recent_colors.iter().cloned().collect::<Vec<_>>());
+
+        for value in 0..(NUM_RECENT_COLORS * 2) {
+            add_recent_color(&mut recent_colors, image::Rgba([value as u8, 0, 0, 255]));
+        }
+        assert_eq!(NUM_RECENT_COLORS, recent_colors.len());
+    }
+
+    #[test]
+    fn test_scroll_zoom_notches_return() {
+        let mut zoom = 1.0;
+        for _ in 0..10 {
+            zoom = scroll_zoom(zoom, 1.0);
+        }
+        assert!(zoom > 2.0);
+
+        for _ in 0..10 {
+            zoom = scroll_zoom(zoom, -1.0);
+        }
+        assert!((zoom - 1.0).abs() < 1E-4, "{}", zoom);
+    }
+
+    #[test]
+    fn test_scroll_zoom_clamped() {
+        assert_eq!(MAX_ZOOM, scroll_zoom(MAX_ZOOM, 1.0));
+        assert_eq!(MIN_ZOOM, scroll_zoom(MIN_ZOOM, -1.0));
+    }
+
+    #[test]
+    fn test_view_transform_min_zoom() {
+        let transform = view_transform(70.0, 40.0, MIN_ZOOM, -100.0, -50.0);
+        assert_close(Position::new(70.0 + 100.0 * MIN_ZOOM, 40.0 + 50.0 * MIN_ZOOM), transform.transform_point(Position::new(0.0, 0.0)));
+        assert_close(Position::new(70.0 + 1100.0 * MIN_ZOOM, 40.0 + 850.0 * MIN_ZOOM), transform.transform_point(Position::new(1000.0, 800.0)));
+
+        let inverse_transform = transform.invert().unwrap();
+        assert_close(Position::new(1000.0, 800.0), inverse_transform.transform_point(transform.transform_point(Position::new(1000.0, 800.0))));
+    }
+
+    #[test]
+    fn test_view_transform_max_zoom() {
+        let transform = view_transform(70.0, 40.0, MAX_ZOOM, 990.0, 795.0);
+        assert_close(Position::new(70.0, 40.0), transform.transform_point(Position::new(990.0, 795.0)));
+        assert_close(Position::new(70.0 + 10.0 * MAX_ZOOM, 40.0 + 5.0 * MAX_ZOOM), transform.transform_point(Position::new(1000.0, 800.0)));
+
+        // A screen pixel maps to a fraction of an image pixel
+        let inverse_transform = transform.invert().unwrap();
+        assert_close(Position::new(990.0 + 1.0 / MAX_ZOOM, 795.0), inverse_transform.transform_point(Position::new(71.0, 40.0)));
+    }
+
+    #[test]
+    fn test_degenerate_view_transform() {
+        // A zero zoom can not be inverted, which makes the frame be skipped
+        let warned = Cell::new(false);
+        assert!(invert_view_transform(view_transform(70.0, 40.0, 0.0, 0.0, 0.0), &warned).is_none());
+        assert!(warned.get());
+        assert!(invert_view_transform(view_transform(70.0, 40.0, 0.0, 0.0, 0.0), &warned).is_none());
+
+        assert!(invert_view_transform(view_transform(70.0, 40.0, 1.0, 0.0, 0.0), &warned).is_some());
+        assert!(!warned.get());
+    }
+
+    #[test]
+    fn test_paste_larger_than_canvas() {
+        let image = image::RgbaImage::from_fn(30, 8, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let (clipped, position_x, position_y) = paste_placement(image.clone(), 20, 10, 15.0, 5.0);
+
+        assert_eq!((20, 8), clipped.dimensions());
+        assert_eq!((0, 2), (position_x, position_y));
+        for (x, y, pixel) in clipped.enumerate_pixels() {
+            assert_eq!(image.get_pixel(x, y), pixel);
+        }
+    }
+
+    #[test]
+    fn test_paste_smaller_than_canvas() {
+        let image = image::RgbaImage::new(5, 5);
+        let (pasted, position_x, position_y) = paste_placement(image, 20, 10, 3.0, -4.0);
+
+        assert_eq!((5, 5), pasted.dimensions());
+        assert_eq!((3, 0), (position_x, position_y));
+    }
+}
@@ -15,7 +15,9 @@ pub struct FontCharacter {
     pub texture_top: f32,
     pub texture_left: f32,
     pub texture_bottom: f32,
-    pub texture_right: f32
+    pub texture_right: f32,
+
+    pub bitmap_x: usize
 }
 
 pub struct Font {
@@ -57,13 +59,30 @@ impl Font {
     }
 
     pub fn get(&mut self, character: char) -> Option<&FontCharacter> {
-        if !self.font_map.characters.contains_key(&character) {
-            self.characters.push(character as u32);
-            self.font_map = FontMap::new(&self.filename, self.size, &self.characters).unwrap();
-            println!("Re-created font map.");
+        self.load_characters(std::iter::once(character));
+        self.get_only(character)
+    }
+
+    pub fn load_characters<T: Iterator<Item=char>>(&mut self, characters: T) {
+        let mut added = false;
+        for character in characters {
+            if !self.characters.contains(&(character as u32)) {
+                self.characters.push(character as u32);
+                added = true;
+            }
         }
 
-        self.get_only(character)
+        if added {
+            match FontMap::new(&self.filename, self.size, &self.characters) {
+                Some(font_map) => {
+                    self.font_map = font_map;
+                    println!("Re-created font map.");
+                }
+                None => {
+                    println!("Failed to re-create font map.");
+                }
+            }
+        }
     }
 
     pub fn line_height(&self) -> f32 {
@@ -72,13 +91,55 @@ impl Font {
 
     pub fn line_width(&mut self, text: &str) -> f32 {
         let mut line_width = 0.0;
+        self.load_characters(text.chars());
         for character in text.chars() {
-            let font_character = self.get(character).unwrap();
-            line_width += font_character.advance_x;
+            if let Some(font_character) = self.get_only(character) {
+                line_width += font_character.advance_x;
+            }
         }
 
         line_width
     }
+
+    pub fn rasterize(&mut self, text: &str, color: image::Rgba<u8>) -> image::RgbaImage {
+        self.load_characters(text.chars());
+
+        let characters = text.chars().filter_map(|character| self.get_only(character)).collect::<Vec<_>>();
+        let baseline = characters.iter().map(|character| character.bearing.y).max().unwrap_or(0);
+        let height = characters.iter().map(|character| baseline - character.bearing.y + character.size.y).max().unwrap_or(0);
+        let width = characters.iter().map(|character| character.advance_x).sum::<f32>().ceil() as i32;
+
+        let mut image = image::RgbaImage::new(width.max(1) as u32, height.max(1) as u32);
+
+        let mut draw_x = 0.0;
+        for character in characters {
+            let start_x = draw_x as i32 + character.bearing.x;
+            let start_y = baseline - character.bearing.y;
+
+            for y in 0..character.size.y {
+                for x in 0..character.size.x {
+                    let image_x = start_x + x;
+                    let image_y = start_y + y;
+                    if image_x < 0 || image_x >= image.width() as i32 || image_y < 0 || image_y >= image.height() as i32 {
+                        continue;
+                    }
+
+                    let coverage = self.font_map.bitmap[y as usize * self.font_map.texture_width as usize + character.bitmap_x + x as usize];
+                    let alpha = ((color[3] as u32 * coverage as u32) / 255) as u8;
+
+                    // Glyphs can overlap slightly, keep the most opaque coverage
+                    let pixel = image.get_pixel_mut(image_x as u32, image_y as u32);
+                    if alpha > pixel[3] {
+                        *pixel = image::Rgba([color[0], color[1], color[2], alpha]);
+                    }
+                }
+            }
+
+            draw_x += character.advance_x;
+        }
+
+        image
+    }
 }
 
 pub type FontRef = Rc<RefCell<Font>>;
@@ -87,11 +148,70 @@ struct FontMap {
     texture_id: u32,
     texture_width: u32,
     texture_height: u32,
-    characters: HashMap<char, FontCharacter>
+    characters: HashMap<char, FontCharacter>,
+    bitmap: Vec<u8>
 }
 
 impl FontMap {
     fn new(filename: &str, size: u32, characters: &Vec<u32>) -> Option<FontMap> {
+        let glyphs = GlyphBitmap::new(filename, size, characters)?;
+        let texture_width = glyphs.width;
+        let texture_height = glyphs.height;
+        let buffer = glyphs.bitmap;
+
+        let mut texture_id = 0;
+
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1); // Disable byte-alignment restriction
+
+            gl::GenTextures(1, &mut texture_id);
+            gl::BindTexture(gl::TEXTURE_2D, texture_id);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RED as i32,
+                texture_width as i32,
+                texture_height as i32,
+                0,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                &buffer[0] as *const u8 as *const c_void);
+        }
+
+        Some(
+            FontMap {
+                texture_id,
+                texture_width,
+                texture_height,
+                characters: glyphs.characters,
+                bitmap: buffer
+            }
+        )
+    }
+}
+
+impl Drop for FontMap {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture_id);
+        }
+    }
+}
+
+// The rasterized glyphs placed next to each other, before being uploaded as a texture
+struct GlyphBitmap {
+    width: u32,
+    height: u32,
+    characters: HashMap<char, FontCharacter>,
+    bitmap: Vec<u8>
+}
+
+impl GlyphBitmap {
+    fn new(filename: &str, size: u32, characters: &Vec<u32>) -> Option<GlyphBitmap> {
         let library = freetype::Library::init().unwrap();
         let face = library.new_face(filename, 0).ok()?;
         face.set_pixel_sizes(0, size).unwrap();
@@ -100,7 +220,10 @@ impl FontMap {
 
         let mut max_character_size = size;
         for character in characters {
-            face.load_char(*character as usize, freetype::face::LoadFlag::RENDER).unwrap();
+            if face.load_char(*character as usize, freetype::face::LoadFlag::RENDER).is_err() {
+                continue;
+            }
+
             let glyph = face.glyph();
             let bitmap = glyph.bitmap();
             max_character_size = std::cmp::max(max_character_size, bitmap.width() as u32);
@@ -114,7 +237,11 @@ impl FontMap {
         let characters_per_row = characters.len();
 
         for (character_index, character) in characters.iter().enumerate() {
-            face.load_char(*character as usize, freetype::face::LoadFlag::RENDER).unwrap();
+            if face.load_char(*character as usize, freetype::face::LoadFlag::RENDER).is_err() {
+                println!("Failed to load glyph for character: {:?}.", std::char::from_u32(*character));
+                continue;
+            }
+
             let glyph = face.glyph();
             let bitmap = glyph.bitmap();
 
@@ -141,49 +268,42 @@ impl FontMap {
                     texture_top: 0.0,
                     texture_left: character_offset as f32 / texture_width as f32,
                     texture_bottom: character_size.y as f32 / max_character_size as f32,
-                    texture_right: (character_offset as f32 + character_size.x as f32) / texture_width as f32
+                    texture_right: (character_offset as f32 + character_size.x as f32) / texture_width as f32,
+
+                    bitmap_x: character_offset
                 }
             );
         }
 
-        let mut texture_id = 0;
-
-        unsafe {
-            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1); // Disable byte-alignment restriction
-
-            gl::GenTextures(1, &mut texture_id);
-            gl::BindTexture(gl::TEXTURE_2D, texture_id);
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RED as i32,
-                texture_width as i32,
-                texture_height as i32,
-                0,
-                gl::RED,
-                gl::UNSIGNED_BYTE,
-                &buffer[0] as *const u8 as *const c_void);
-        }
-
         Some(
-            FontMap {
-                texture_id,
-                texture_width,
-                texture_height,
-                characters: font_characters
+            GlyphBitmap {
+                width: texture_width,
+                height: texture_height,
+                characters: font_characters,
+                bitmap: buffer
             }
         )
     }
 }
 
-impl Drop for FontMap {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DeleteTextures(1, &self.texture_id);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_ascii_glyph() {
+        let filename = concat!(env!("CARGO_MANIFEST_DIR"), "/content/fonts/NotoMono-Regular.ttf");
+        let glyphs = GlyphBitmap::new(filename, 16, &vec!['a' as u32, 'é' as u32]).unwrap();
+
+        let character = glyphs.characters.get(&'é').unwrap();
+        assert!(character.size.x > 0 && character.size.y > 0);
+
+        let mut coverage = 0;
+        for y in 0..character.size.y as usize {
+            for x in 0..character.size.x as usize {
+                coverage += glyphs.bitmap[y * glyphs.width as usize + character.bitmap_x + x] as u32;
+            }
         }
+        assert!(coverage > 0);
     }
 }
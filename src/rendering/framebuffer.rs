@@ -10,17 +10,21 @@ pub struct FrameBuffer {
 }
 
 pub struct FrameBufferBinding<'a> {
-    frame_buffer: &'a FrameBuffer
+    frame_buffer: &'a FrameBuffer,
+    previous_frame_buffer_id: u32
 }
 
 impl<'a> FrameBufferBinding<'a> {
     pub fn new(frame_buffer: &'a FrameBuffer) -> FrameBufferBinding {
+        let previous_frame_buffer_id = current_frame_buffer_id();
+
         unsafe {
             gl::BindFramebuffer(gl::FRAMEBUFFER, frame_buffer.frame_buffer_id);
         }
 
         FrameBufferBinding {
-            frame_buffer
+            frame_buffer,
+            previous_frame_buffer_id
         }
     }
 
@@ -39,16 +43,63 @@ impl<'a> FrameBufferBinding<'a> {
             );
         }
     }
+
+    pub fn read_to_image(&self) -> image::RgbaImage {
+        read_to_image(self.frame_buffer.width, self.frame_buffer.height)
+    }
 }
 
 impl<'a> Drop for FrameBufferBinding<'a> {
     fn drop(&mut self) {
         unsafe {
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.previous_frame_buffer_id);
         }
     }
 }
 
+fn current_frame_buffer_id() -> u32 {
+    let mut frame_buffer_id = 0;
+    unsafe {
+        gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut frame_buffer_id);
+    }
+
+    frame_buffer_id as u32
+}
+
+// Reads the lower left (width, height) pixels of the currently bound framebuffer. The color attachment must be readable as RGBA
+// with 8 bits per channel. The rows are flipped such that the first row of the image is the top row of the framebuffer.
+pub fn read_to_image(width: u32, height: u32) -> image::RgbaImage {
+    let row_size = (width * 4) as usize;
+    let mut buffer = vec![0u8; row_size * height as usize];
+
+    if !buffer.is_empty() {
+        unsafe {
+            let mut pack_alignment = 0;
+            gl::GetIntegerv(gl::PACK_ALIGNMENT, &mut pack_alignment);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+
+            gl::ReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                &mut buffer[0] as *mut u8 as *mut c_void
+            );
+
+            gl::PixelStorei(gl::PACK_ALIGNMENT, pack_alignment);
+        }
+    }
+
+    let mut flipped_buffer = Vec::with_capacity(buffer.len());
+    for row in buffer.chunks(row_size.max(1)).rev() {
+        flipped_buffer.extend_from_slice(row);
+    }
+
+    image::RgbaImage::from_raw(width, height, flipped_buffer).unwrap()
+}
+
 impl FrameBuffer {
     pub fn new(width: u32, height: u32, channels: u32) -> FrameBuffer {
         let mut frame_buffer_id = 0;
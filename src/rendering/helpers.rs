@@ -4,6 +4,12 @@ macro_rules! c_str {
     }
 }
 
+pub fn has_current_context() -> bool {
+    unsafe {
+        gl::GetString::is_loaded() && gl::GetString(gl::VERSION) != std::ptr::null()
+    }
+}
+
 pub fn channels_type(num_channels: u32) -> u32 {
     match num_channels {
         4 => gl::RGBA,
@@ -8,6 +8,7 @@ pub mod helpers;
 pub mod font;
 pub mod shader;
 pub mod texture;
+pub mod texture_atlas;
 pub mod framebuffer;
 pub mod texture_render;
 pub mod text_render;
@@ -5,7 +5,7 @@ use gl::types::*;
 use cgmath::Matrix4;
 
 use crate::rendering::shader::Shader;
-use crate::rendering::prelude::{Rectangle, Color4};
+use crate::rendering::prelude::{Rectangle, Color4, Position};
 
 const FLOATS_PER_VERTEX: i32 = 2 + 4;
 const NUM_VERTICES: i32 = 5;
@@ -89,6 +89,41 @@ impl RectangleRender {
             gl::DrawArrays(gl::LINE_STRIP, 0, NUM_VERTICES);
         }
     }
+
+    pub fn render_line(&self,
+                       shader: &Shader,
+                       transform: &Matrix4<f32>,
+                       start: Position,
+                       end: Position,
+                       color: Color4) {
+        unsafe {
+            shader.activate();
+            shader.set_matrix4(c_str!("transform"), &transform);
+
+            gl::BindVertexArray(self.vertex_array);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vertex_buffer);
+
+            let color = [
+                color.x as f32 / 255.0,
+                color.y as f32 / 255.0,
+                color.z as f32 / 255.0,
+                color.w as f32 / 255.0
+            ];
+
+            let vertices: [f32; 2 * FLOATS_PER_VERTEX as usize] = [
+                start.x,    start.y,    color[0], color[1], color[2], color[3],
+                end.x,      end.y,      color[0], color[1], color[2], color[3]
+            ];
+
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                (vertices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
+                &vertices[0] as *const f32 as *const c_void
+            );
+            gl::DrawArrays(gl::LINE_STRIP, 0, 2);
+        }
+    }
 }
 
 impl Drop for RectangleRender {
@@ -119,6 +119,10 @@ impl TextRender {
 
         let mut line_width = 0.0;
 
+        // Rasterize any missing glyphs up front, as this re-creates the font texture
+        let text = text.collect::<Vec<_>>();
+        font.load_characters(text.iter().map(|(character, _)| *character));
+
         unsafe {
             shader.activate();
             shader.set_matrix4(c_str!("transform"), &transform);
@@ -139,7 +143,10 @@ impl TextRender {
                 character_color.z as f32 / 255.0
             );
 
-            let font_character = font.get(character).unwrap();
+            let font_character = match font.get_only(character) {
+                Some(font_character) => font_character,
+                None => continue
+            };
 
             let x = draw_x + font_character.bearing.x as f32;
             let y = match alignment {
@@ -80,6 +80,31 @@ impl Texture {
             );
         }
     }
+
+    pub fn upload_region(&self, buffer: &[u8], x: u32, y: u32, width: u32, height: u32) {
+        assert!(x + width <= self.width && y + height <= self.height);
+        assert!(buffer.len() >= (self.width * self.height * self.channels) as usize);
+
+        let channel_type = channels_type(self.channels);
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture_id);
+
+            // The rows of the region are strided by the width of the full buffer
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, self.width as i32);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                x as i32,
+                y as i32,
+                width as i32,
+                height as i32,
+                channel_type,
+                gl::UNSIGNED_BYTE,
+                &buffer[self.base_index(x, y)] as *const u8 as *const c_void
+            );
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+        }
+    }
 }
 
 impl Drop for Texture {
@@ -0,0 +1,58 @@
+use std::rc::Rc;
+
+use crate::rendering::texture::Texture;
+use crate::rendering::prelude::Rectangle;
+
+const MAX_ATLAS_WIDTH: u32 = 512;
+const ATLAS_PADDING: u32 = 1;
+
+pub struct TextureAtlas {
+    texture: Rc<Texture>,
+    regions: Vec<Rectangle>
+}
+
+impl TextureAtlas {
+    pub fn new(images: &[image::RgbaImage]) -> TextureAtlas {
+        // Packs the images in rows, starting a new row when the current one is full
+        let mut positions = Vec::new();
+        let (mut x, mut y) = (0, 0);
+        let mut row_height = 0;
+        let mut width = 1;
+        for image in images {
+            if x > 0 && x + image.width() > MAX_ATLAS_WIDTH {
+                x = 0;
+                y += row_height + ATLAS_PADDING;
+                row_height = 0;
+            }
+
+            positions.push((x, y));
+            width = width.max(x + image.width());
+            row_height = row_height.max(image.height());
+            x += image.width() + ATLAS_PADDING;
+        }
+        let height = (y + row_height).max(1);
+
+        let mut atlas_image = image::RgbaImage::new(width, height);
+        let mut regions = Vec::new();
+        for (image, &(x, y)) in images.iter().zip(positions.iter()) {
+            for (image_x, image_y, pixel) in image.enumerate_pixels() {
+                atlas_image.put_pixel(x + image_x, y + image_y, *pixel);
+            }
+
+            regions.push(Rectangle::new(x as f32, y as f32, image.width() as f32, image.height() as f32));
+        }
+
+        TextureAtlas {
+            texture: Rc::new(Texture::from_image(&atlas_image)),
+            regions
+        }
+    }
+
+    pub fn texture(&self) -> &Rc<Texture> {
+        &self.texture
+    }
+
+    pub fn region(&self, index: usize) -> Rectangle {
+        self.regions[index].clone()
+    }
+}
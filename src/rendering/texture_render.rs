@@ -1,12 +1,13 @@
 use std::{mem, ptr};
 use std::os::raw::c_void;
+use std::rc::Rc;
 
 use gl::types::*;
 use cgmath::Matrix4;
 
 use crate::rendering::texture::Texture;
 use crate::rendering::shader::Shader;
-use crate::rendering::prelude::Rectangle;
+use crate::rendering::prelude::{Rectangle, Color4};
 
 const FLOATS_PER_VERTEX: i32 = 4;
 const NUM_VERTICES: i32 = 6;
@@ -60,9 +61,40 @@ impl TextureRender {
                       position: cgmath::Point2<f32>,
                       scale: f32,
                       source_rectangle: Option<Rectangle>) {
+        self.render_sub_with_opacity(shader, transform, texture, position, scale, source_rectangle, 1.0);
+    }
+
+    pub fn render_sub_with_opacity(&self,
+                                   shader: &Shader,
+                                   transform: &Matrix4<f32>,
+                                   texture: &Texture,
+                                   position: cgmath::Point2<f32>,
+                                   scale: f32,
+                                   source_rectangle: Option<Rectangle>,
+                                   opacity: f32) {
+        self.render_sub_tinted(shader, transform, texture, position, scale, source_rectangle, opacity, Color4::new(0, 0, 0, 0));
+    }
+
+    pub fn render_sub_tinted(&self,
+                             shader: &Shader,
+                             transform: &Matrix4<f32>,
+                             texture: &Texture,
+                             position: cgmath::Point2<f32>,
+                             scale: f32,
+                             source_rectangle: Option<Rectangle>,
+                             opacity: f32,
+                             tint: Color4) {
         unsafe {
             shader.activate();
             shader.set_matrix4(c_str!("transform"), &transform);
+            shader.set_float32(c_str!("opacity"), opacity);
+            shader.set_vector4(
+                c_str!("tint"),
+                tint.x as f32 / 255.0,
+                tint.y as f32 / 255.0,
+                tint.z as f32 / 255.0,
+                tint.w as f32 / 255.0
+            );
 
             gl::ActiveTexture(gl::TEXTURE0);
             texture.bind();
@@ -123,6 +155,8 @@ impl TextureRender {
         unsafe {
             shader.activate();
             shader.set_matrix4(c_str!("transform"), &transform);
+            shader.set_float32(c_str!("opacity"), 1.0);
+            shader.set_vector4(c_str!("tint"), 0.0, 0.0, 0.0, 0.0);
 
             gl::ActiveTexture(gl::TEXTURE0);
             texture.bind();
@@ -177,6 +211,88 @@ impl TextureRender {
                   position: cgmath::Point2<f32>) {
         self.render_sub(shader, transform, texture, position, 1.0, None)
     }
+
+    pub fn render_batch(&self,
+                        shader: &Shader,
+                        transform: &Matrix4<f32>,
+                        texture: &Texture,
+                        quads: &[(cgmath::Point2<f32>, Rectangle)]) {
+        if quads.is_empty() {
+            return;
+        }
+
+        let mut vertices = Vec::with_capacity(quads.len() * BUFFER_SIZE);
+        for (position, source_rectangle) in quads {
+            let width = source_rectangle.size.x;
+            let height = source_rectangle.size.y;
+
+            let left = source_rectangle.left() / texture.width() as f32;
+            let top = source_rectangle.top() / texture.height() as f32;
+            let right = source_rectangle.right() / texture.width() as f32;
+            let bottom = source_rectangle.bottom() / texture.height() as f32;
+
+            vertices.extend_from_slice(&[
+                position.x, position.y,                   left, top,        // Top-left
+                position.x + width, position.y,           right, top,       // Top-right
+                position.x + width, position.y + height,  right, bottom,    // Bottom-right
+
+                position.x, position.y + height,          left, bottom,     // Bottom-left
+                position.x + width, position.y + height,  right, bottom,    // Bottom-right
+                position.x, position.y,                   left, top,        // Top-left
+            ]);
+        }
+
+        unsafe {
+            shader.activate();
+            shader.set_matrix4(c_str!("transform"), &transform);
+            shader.set_float32(c_str!("opacity"), 1.0);
+            shader.set_vector4(c_str!("tint"), 0.0, 0.0, 0.0, 0.0);
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            texture.bind();
+
+            gl::BindVertexArray(self.vertex_array);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vertex_buffer);
+
+            // Re-allocates the buffer to fit all quads, it is never smaller than what a single quad needs
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (vertices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
+                vertices.as_ptr() as *const c_void,
+                gl::DYNAMIC_DRAW
+            );
+            gl::DrawArrays(gl::TRIANGLES, 0, (quads.len() as i32) * NUM_VERTICES);
+        }
+    }
+}
+
+pub struct TextureBatch {
+    batches: Vec<(Rc<Texture>, Vec<(cgmath::Point2<f32>, Rectangle)>)>
+}
+
+impl TextureBatch {
+    pub fn new() -> TextureBatch {
+        TextureBatch {
+            batches: Vec::new()
+        }
+    }
+
+    pub fn add(&mut self, texture: &Rc<Texture>, position: cgmath::Point2<f32>, source_rectangle: Rectangle) {
+        match self.batches.iter_mut().find(|(batch_texture, _)| Rc::ptr_eq(batch_texture, texture)) {
+            Some((_, quads)) => {
+                quads.push((position, source_rectangle));
+            }
+            None => {
+                self.batches.push((texture.clone(), vec![(position, source_rectangle)]));
+            }
+        }
+    }
+
+    pub fn render(&self, shader: &Shader, texture_render: &TextureRender, transform: &Matrix4<f32>) {
+        for (texture, quads) in &self.batches {
+            texture_render.render_batch(shader, transform, texture, quads);
+        }
+    }
 }
 
 impl Drop for TextureRender {
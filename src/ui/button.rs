@@ -1,9 +1,12 @@
 use std::ops::DerefMut;
+use std::rc::Rc;
 
 use glfw::{Action};
 use cgmath::{EuclideanSpace, Matrix4};
 
 use crate::rendering::texture::Texture;
+use crate::rendering::texture_atlas::TextureAtlas;
+use crate::rendering::texture_render::TextureBatch;
 use crate::rendering::prelude::{Position, Rectangle, Color4};
 use crate::rendering::prelude::Color as RenderingColor;
 use crate::rendering::prelude::Color4 as RenderingColor4;
@@ -21,10 +24,15 @@ pub trait GenericButton<T> {
     fn process_gui_event(&mut self, window: &dyn EditorWindow, event: &glfw::WindowEvent, argument: &mut T);
     fn process_command(&mut self, command: &Command);
     fn render(&self, renders: &Renders, transform: &Matrix4<f32>);
+
+    fn render_batched(&self, renders: &Renders, transform: &Matrix4<f32>, _batch: &mut TextureBatch) {
+        self.render(renders, transform);
+    }
 }
 
 pub struct TextureButton<T=CommandBuffer> {
-    texture: Texture,
+    texture: Rc<Texture>,
+    source_rectangle: Rectangle,
     background: Option<(Rectangle, Color4, Color4)>,
     position: Position,
     left_click_action: Option<ButtonAction<T>>,
@@ -33,13 +41,15 @@ pub struct TextureButton<T=CommandBuffer> {
 }
 
 impl<T> TextureButton<T> {
-    pub fn new(image: &image::RgbaImage,
-               position: Position,
-               left_click_action: Option<ButtonAction<T>>,
-               right_click_action: Option<ButtonAction<T>>,
-               command_action: Option<CommandAction<Self>>) -> TextureButton<T> {
+    pub fn from_atlas(atlas: &TextureAtlas,
+                      index: usize,
+                      position: Position,
+                      left_click_action: Option<ButtonAction<T>>,
+                      right_click_action: Option<ButtonAction<T>>,
+                      command_action: Option<CommandAction<Self>>) -> TextureButton<T> {
         TextureButton {
-            texture: Texture::from_image(image),
+            texture: atlas.texture().clone(),
+            source_rectangle: atlas.region(index),
             background: None,
             position,
             left_click_action,
@@ -51,6 +61,27 @@ impl<T> TextureButton<T> {
     pub fn background_mut(&mut self) -> &mut Option<(Rectangle, Color4, Color4)> {
         &mut self.background
     }
+
+    fn render_background(&self, renders: &Renders, transform: &Matrix4<f32>) {
+        if let Some((background_rectangle, background_color, border_color)) = self.background.as_ref() {
+            let mut rectangle = background_rectangle.clone();
+            rectangle.position += self.position.to_vec();
+
+            renders.solid_rectangle_render.render(
+                renders.solid_rectangle_render.shader(),
+                &transform,
+                &rectangle,
+                *background_color
+            );
+
+            renders.rectangle_render.render(
+                renders.rectangle_render.shader(),
+                &transform,
+                &rectangle,
+                *border_color
+            );
+        }
+    }
 }
 
 impl<T> GenericButton<T> for TextureButton<T> {
@@ -58,8 +89,8 @@ impl<T> GenericButton<T> for TextureButton<T> {
         let bounding_rectangle = Rectangle::new(
             self.position.x,
             self.position.y,
-            self.texture.width() as f32,
-            self.texture.height() as f32
+            self.source_rectangle.size.x,
+            self.source_rectangle.size.y
         );
 
         match event {
@@ -92,32 +123,22 @@ impl<T> GenericButton<T> for TextureButton<T> {
     }
 
     fn render(&self, renders: &Renders, transform: &Matrix4<f32>) {
-        if let Some((background_rectangle, background_color, border_color)) = self.background.as_ref() {
-            let mut rectangle = background_rectangle.clone();
-            rectangle.position += self.position.to_vec();
-
-            renders.solid_rectangle_render.render(
-                renders.solid_rectangle_render.shader(),
-                &transform,
-                &rectangle,
-                *background_color
-            );
-
-            renders.rectangle_render.render(
-                renders.rectangle_render.shader(),
-                &transform,
-                &rectangle,
-                *border_color
-            );
-        }
+        self.render_background(renders, transform);
 
-        renders.texture_render.render(
+        renders.texture_render.render_sub(
             renders.texture_render.shader(),
             &transform,
             &self.texture,
-            self.position
+            self.position,
+            1.0,
+            Some(self.source_rectangle.clone())
         );
     }
+
+    fn render_batched(&self, renders: &Renders, transform: &Matrix4<f32>, batch: &mut TextureBatch) {
+        self.render_background(renders, transform);
+        batch.add(&self.texture, self.position, self.source_rectangle.clone());
+    }
 }
 
 pub struct SolidColorButton<T=CommandBuffer> {
@@ -195,6 +216,7 @@ impl<T> GenericButton<T> for SolidColorButton<T> {
 pub struct TextButton<T=CommandBuffer> {
     font: FontRef,
     text: String,
+    text_color: RenderingColor,
     position: Position,
     left_click_action: Option<ButtonAction<T>>,
     right_click_action: Option<ButtonAction<T>>,
@@ -211,6 +233,7 @@ impl<T> TextButton<T> {
         TextButton {
             font,
             text,
+            text_color: RenderingColor::new(0, 0, 0),
             position,
             left_click_action,
             right_click_action,
@@ -222,6 +245,10 @@ impl<T> TextButton<T> {
         self.text = text;
     }
 
+    pub fn set_text_color(&mut self, color: RenderingColor) {
+        self.text_color = color;
+    }
+
     fn bounding_rectangle(&self) -> Rectangle {
         let mut font = self.font.borrow_mut();
         let width = font.line_width(&self.text);
@@ -281,7 +308,7 @@ impl<T> GenericButton<T> for TextButton<T> {
             renders.text_render.shader(),
             transform,
             self.font.borrow_mut().deref_mut(),
-            self.text.chars().map(|c| (c, RenderingColor::new(0, 0, 0))),
+            self.text.chars().map(|c| (c, self.text_color)),
             self.position,
             TextAlignment::Top
         );
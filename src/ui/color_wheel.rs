@@ -141,6 +141,7 @@ impl ColorWheel {
 
 impl GenericButton<CommandBuffer> for ColorWheel {
     fn process_gui_event(&mut self, window: &dyn EditorWindow, event: &glfw::WindowEvent, command_buffer: &mut CommandBuffer) {
+        let was_selecting_color = self.started_selecting_color;
         if let Some(color) = self.select_color(window, event) {
             match self.mode {
                 SelectColorMode::PrimaryColor => {
@@ -151,6 +152,11 @@ impl GenericButton<CommandBuffer> for ColorWheel {
                 }
             }
         }
+
+        // Dragging over the wheel only previews colors, the color is recent once the button is released
+        if was_selecting_color && !self.started_selecting_color && self.mode == SelectColorMode::PrimaryColor {
+            command_buffer.push(Command::CommitPrimaryColor);
+        }
     }
 
     fn process_command(&mut self, _command: &Command) {
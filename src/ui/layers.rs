@@ -1,30 +1,118 @@
+use std::ops::DerefMut;
+use std::time::Instant;
+
 use glfw::{Action, Key, Modifiers, MouseButton};
 
 use cgmath::{Matrix4};
 
 use crate::editor::editor::{EditorOperation, LayerState};
-use crate::program::{RIGHT_SIDE_PANEL_WIDTH, LAYER_BUFFER, LAYER_SPACING, Renders, LEFT_SIDE_PANEL_WIDTH, TOP_PANEL_HEIGHT};
+use crate::program::{LAYER_BUFFER, LAYER_SPACING, Renders, ProgramAction, ProgramActionData};
 use crate::rendering::prelude::{Position, Rectangle, Color4, blend, Size};
+use crate::rendering::text_render::TextAlignment;
+use crate::command_buffer::{Command, CommandBuffer};
 use crate::editor::Editor;
 use crate::editor::tools::EditorWindow;
 use crate::editor::image_operation::ImageSource;
 use crate::rendering::texture::Texture;
+use crate::ui::UiTheme;
+use crate::content;
 
-pub struct LayersManager {
+const LAYER_NAME_HEIGHT: f32 = 20.0;
+const LAYER_CONTROLS_HEIGHT: f32 = 16.0;
+const DOUBLE_CLICK_TIME: f32 = 0.4;
+const OPACITY_BAR_HEIGHT: f32 = 8.0;
 
+struct LayerLayout {
+    layer_index: usize,
+    name_rectangle: Rectangle,
+    layer_rectangle: Rectangle,
+    opacity_rectangle: Rectangle,
+    move_up_rectangle: Rectangle,
+    move_down_rectangle: Rectangle
+}
+
+pub struct LayersManager {
+    opacity_drag: Option<(usize, f32)>,
+    last_click: Option<(usize, Instant)>,
+    move_up_texture: Texture,
+    move_down_texture: Texture
 }
 
 impl LayersManager {
     pub fn new() -> LayersManager {
         LayersManager {
+            opacity_drag: None,
+            last_click: None,
+            move_up_texture: Texture::from_image(&image::open(content::get_path("content/ui/layer_up.png")).unwrap().into_rgba()),
+            move_down_texture: Texture::from_image(&image::open(content::get_path("content/ui/layer_down.png")).unwrap().into_rgba())
+        }
+    }
 
+    fn layer_layouts(theme: &UiTheme, view_width: u32, editor: &Editor) -> Vec<LayerLayout> {
+        let mut layer_offset = LAYER_BUFFER;
+        let layer_width = theme.right_side_panel_width as f32 - LAYER_BUFFER;
+
+        let mut layouts = Vec::new();
+        for (layer_index, layer) in editor.image().layers().iter().enumerate() {
+            if layer.state != LayerState::Deleted {
+                let position = Position::new(
+                    view_width as f32 + LAYER_BUFFER + theme.left_side_panel_width as f32,
+                    layer_offset + theme.top_panel_height as f32
+                );
+                let layer_height = layer_width * (layer.image.height() as f32 / layer.image.width() as f32);
+
+                let layer_y = position.y + LAYER_NAME_HEIGHT;
+                let controls_y = layer_y + layer_height + LAYER_BUFFER * 2.0;
+                let buttons_width = (LAYER_CONTROLS_HEIGHT + LAYER_BUFFER) * 2.0;
+
+                layouts.push(LayerLayout {
+                    layer_index,
+                    name_rectangle: Rectangle::new(position.x, position.y, layer_width, LAYER_NAME_HEIGHT),
+                    layer_rectangle: Rectangle::new(position.x, layer_y, layer_width, layer_height),
+                    opacity_rectangle: Rectangle::new(
+                        position.x,
+                        controls_y + (LAYER_CONTROLS_HEIGHT - OPACITY_BAR_HEIGHT) / 2.0,
+                        layer_width - buttons_width,
+                        OPACITY_BAR_HEIGHT
+                    ),
+                    move_up_rectangle: Rectangle::new(
+                        position.x + layer_width - buttons_width + LAYER_BUFFER,
+                        controls_y,
+                        LAYER_CONTROLS_HEIGHT,
+                        LAYER_CONTROLS_HEIGHT
+                    ),
+                    move_down_rectangle: Rectangle::new(
+                        position.x + layer_width - LAYER_CONTROLS_HEIGHT,
+                        controls_y,
+                        LAYER_CONTROLS_HEIGHT,
+                        LAYER_CONTROLS_HEIGHT
+                    )
+                });
+
+                layer_offset += LAYER_NAME_HEIGHT + layer_height + LAYER_BUFFER * 2.0 + LAYER_CONTROLS_HEIGHT + LAYER_SPACING;
+            }
         }
+
+        layouts
+    }
+
+    pub fn last_layer_position_y(theme: &UiTheme, view_width: u32, editor: &Editor) -> f32 {
+        LayersManager::layer_layouts(theme, view_width, editor)
+            .last()
+            .map(|layout| layout.move_up_rectangle.bottom() + LAYER_SPACING)
+            .unwrap_or(LAYER_BUFFER + theme.top_panel_height as f32)
+    }
+
+    fn opacity_at(opacity_rectangle: &Rectangle, mouse_position: &Position) -> f32 {
+        ((mouse_position.x - opacity_rectangle.left()) / opacity_rectangle.size.x).max(0.0).min(1.0)
     }
 
     pub fn process_gui_event(&mut self,
                              window: &mut dyn EditorWindow,
+                             theme: &UiTheme,
                              view_width: u32,
                              event: &glfw::WindowEvent,
+                             command_buffer: &mut CommandBuffer,
                              editor: &mut Editor) {
         match event {
             glfw::WindowEvent::Key(Key::N, _, Action::Press, modifier) => {
@@ -37,38 +125,88 @@ impl LayersManager {
                     editor.delete_active_layer();
                 }
             }
-            glfw::WindowEvent::MouseButton(button, Action::Release, _) => {
+            glfw::WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) => {
                 let mouse_position = window.get_cursor_pos();
                 let mouse_position = Position::new(mouse_position.0 as f32, mouse_position.1 as f32);
 
-                let mut layer_offset = LAYER_BUFFER;
-                let layer_width = RIGHT_SIDE_PANEL_WIDTH as f32 - LAYER_BUFFER;
+                for layout in LayersManager::layer_layouts(theme, view_width, editor) {
+                    if layout.opacity_rectangle.contains(&mouse_position) {
+                        let layer = &mut editor.image_mut().layers_mut()[layout.layer_index];
+                        self.opacity_drag = Some((layout.layer_index, layer.opacity));
+                        layer.opacity = LayersManager::opacity_at(&layout.opacity_rectangle, &mouse_position);
+                    }
+                }
+            }
+            glfw::WindowEvent::CursorPos(mouse_x, mouse_y) => {
+                if let Some((drag_layer_index, _)) = self.opacity_drag {
+                    let mouse_position = Position::new(*mouse_x as f32, *mouse_y as f32);
+                    for layout in LayersManager::layer_layouts(theme, view_width, editor) {
+                        if layout.layer_index == drag_layer_index {
+                            editor.image_mut().layers_mut()[layout.layer_index].opacity = LayersManager::opacity_at(&layout.opacity_rectangle, &mouse_position);
+                        }
+                    }
+                }
+            }
+            glfw::WindowEvent::MouseButton(button, Action::Release, _) => {
+                if let Some((layer_index, original_opacity)) = self.opacity_drag.take() {
+                    // Restore the original opacity so that the undo operation is created correctly
+                    let layer = &mut editor.image_mut().layers_mut()[layer_index];
+                    let opacity = layer.opacity;
+                    layer.opacity = original_opacity;
+
+                    if opacity != original_opacity {
+                        editor.apply_editor_op(EditorOperation::SetLayerOpacity(layer_index, opacity));
+                    }
+
+                    return;
+                }
+
+                let mouse_position = window.get_cursor_pos();
+                let mouse_position = Position::new(mouse_position.0 as f32, mouse_position.1 as f32);
 
                 let mut active_layer_index = None;
+                let mut move_layer = None;
+                let mut rename_layer = false;
                 let mut layer_ops = Vec::new();
-                for (layer_index, (state, image)) in editor.image_mut().layers_mut().iter_mut().enumerate() {
-                    if state != &LayerState::Deleted {
-                        let position = Position::new(view_width as f32 + LAYER_BUFFER + LEFT_SIDE_PANEL_WIDTH as f32, layer_offset + TOP_PANEL_HEIGHT as f32);
-                        let layer_height = layer_width * (image.height() as f32 / image.width() as f32);
-
-                        let bounding_rectangle = Rectangle::new(position.x, position.y, layer_width, layer_height);
-                        if bounding_rectangle.contains(&mouse_position) {
-                            match button {
-                                MouseButton::Button1 => {
-                                    active_layer_index = Some(layer_index);
-                                }
-                                MouseButton::Button2 => {
-                                    if state == &LayerState::Visible {
-                                        layer_ops.push(EditorOperation::SetLayerState(layer_index, LayerState::Hidden));
-                                    } else if state == &LayerState::Hidden {
-                                        layer_ops.push(EditorOperation::SetLayerState(layer_index, LayerState::Visible));
+                for layout in LayersManager::layer_layouts(theme, view_width, editor) {
+                    let layer_index = layout.layer_index;
+                    if button == &MouseButton::Button1 {
+                        if layout.move_up_rectangle.contains(&mouse_position) {
+                            move_layer = Some((layer_index, true));
+                        } else if layout.move_down_rectangle.contains(&mouse_position) {
+                            move_layer = Some((layer_index, false));
+                        }
+                    }
+
+                    if layout.name_rectangle.contains(&mouse_position) || layout.layer_rectangle.contains(&mouse_position) {
+                        let state = &editor.image().layers()[layer_index].state;
+                        match button {
+                            MouseButton::Button1 => {
+                                active_layer_index = Some(layer_index);
+
+                                let is_double_click = match self.last_click {
+                                    Some((last_layer_index, last_click)) => {
+                                        last_layer_index == layer_index && last_click.elapsed().as_secs_f32() < DOUBLE_CLICK_TIME
                                     }
+                                    None => false
+                                };
+
+                                if is_double_click {
+                                    self.last_click = None;
+                                    rename_layer = true;
+                                } else {
+                                    self.last_click = Some((layer_index, Instant::now()));
                                 }
-                                _ => {}
                             }
+                            MouseButton::Button2 => {
+                                if state == &LayerState::Visible {
+                                    layer_ops.push(EditorOperation::SetLayerState(layer_index, LayerState::Hidden));
+                                } else if state == &LayerState::Hidden {
+                                    layer_ops.push(EditorOperation::SetLayerState(layer_index, LayerState::Visible));
+                                }
+                            }
+                            _ => {}
                         }
-
-                        layer_offset += layer_height + LAYER_SPACING;
                     }
                 }
 
@@ -80,6 +218,16 @@ impl LayersManager {
                     editor.apply_editor_op(layer_op);
                 }
 
+                match move_layer {
+                    Some((layer_index, true)) => editor.move_layer_up(layer_index),
+                    Some((layer_index, false)) => editor.move_layer_down(layer_index),
+                    None => {}
+                }
+
+                if rename_layer {
+                    command_buffer.push(Command::TriggerProgramAction(ProgramAction::OpenRenameLayerDialog, ProgramActionData::Triggered));
+                }
+
             }
             _ => {}
         }
@@ -88,80 +236,114 @@ impl LayersManager {
     pub fn render(&self,
                   transform: &Matrix4<f32>,
                   renders: &Renders,
+                  theme: &UiTheme,
                   editor: &Editor,
                   view_width: u32,
-                  background_transparent_texture: &Texture) -> f32 {
-        let mut layer_offset = LAYER_BUFFER;
-        let layer_width = RIGHT_SIDE_PANEL_WIDTH as f32 - LAYER_BUFFER;
-
+                  background_transparent_texture: &Texture) {
         let active_layer_index = editor.active_layer_index();
-        for (layer_index, (state, image)) in editor.image().layers().iter().enumerate() {
-            if state != &LayerState::Deleted {
-                let position = Position::new(
-                    view_width as f32 + LAYER_BUFFER + LEFT_SIDE_PANEL_WIDTH as f32,
-                    layer_offset + TOP_PANEL_HEIGHT as f32
-                );
-                let layer_height = layer_width * (image.height() as f32 / image.width() as f32);
+        for layout in LayersManager::layer_layouts(theme, view_width, editor) {
+            let layer_index = layout.layer_index;
+            let layer = &editor.image().layers()[layer_index];
+            let name_position = layout.name_rectangle.position;
+            let position = layout.layer_rectangle.position;
+            let layer_width = layout.layer_rectangle.size.x;
+            let layer_height = layout.layer_rectangle.size.y;
+            let opacity_rectangle = &layout.opacity_rectangle;
 
-                let mut layer_color = None;
-                if active_layer_index == layer_index {
-                    layer_color = Some(Color4::new(0, 148, 255, 64));
-                }
+            let mut layer_color = None;
+            if active_layer_index == layer_index {
+                layer_color = Some(Color4::new(0, 148, 255, 64));
+            }
 
-                if state == &LayerState::Hidden {
-                    match layer_color {
-                        Some(current_layer_color) => {
-                            layer_color = Some(blend(&current_layer_color, &Color4::new(255, 0, 0, 64)));
-                        }
-                        None => {
-                            layer_color = Some(Color4::new(255, 0, 0, 64));
-                        }
+            if layer.state == LayerState::Hidden {
+                match layer_color {
+                    Some(current_layer_color) => {
+                        layer_color = Some(blend(&current_layer_color, &Color4::new(255, 0, 0, 64)));
+                    }
+                    None => {
+                        layer_color = Some(Color4::new(255, 0, 0, 64));
                     }
                 }
+            }
 
-                if let Some(layer_color) = layer_color {
-                    renders.solid_rectangle_render.render(
-                        renders.solid_rectangle_render.shader(),
-                        transform,
-                        &Rectangle::from_position_and_size(
-                            Position::new(position.x - LAYER_BUFFER, position.y - LAYER_BUFFER),
-                            Size::new(layer_width + LAYER_BUFFER, layer_height + LAYER_BUFFER * 2.0),
-                        ),
-                        layer_color
-                    );
-                }
-
-                renders.texture_render.render_sized(
-                    renders.texture_render.shader(),
+            if let Some(layer_color) = layer_color {
+                renders.solid_rectangle_render.render(
+                    renders.solid_rectangle_render.shader(),
                     transform,
-                    background_transparent_texture,
-                    position,
-                    layer_width,
-                    layer_height,
-                    Some(
-                        Rectangle::new(
-                            0.0,
-                            0.0,
-                            layer_width,
-                            layer_height
-                        )
-                    )
+                    &Rectangle::from_position_and_size(
+                        Position::new(name_position.x - LAYER_BUFFER, name_position.y - LAYER_BUFFER),
+                        Size::new(layer_width + LAYER_BUFFER, LAYER_NAME_HEIGHT + layer_height + LAYER_BUFFER * 3.0 + LAYER_CONTROLS_HEIGHT),
+                    ),
+                    layer_color
                 );
+            }
 
-                renders.texture_render.render_sized(
-                    renders.texture_render.shader(),
-                    transform,
-                    image.get_texture(),
-                    position,
-                    layer_width,
-                    layer_height,
-                    None
-                );
+            renders.text_render.render_line(
+                renders.text_render.shader(),
+                transform,
+                renders.ui_font_small.borrow_mut().deref_mut(),
+                layer.name.chars().map(|c| (c, theme.text_color)),
+                name_position,
+                TextAlignment::Top
+            );
 
-                layer_offset += layer_height + LAYER_SPACING;
-            }
-        }
+            renders.texture_render.render_sized(
+                renders.texture_render.shader(),
+                transform,
+                background_transparent_texture,
+                position,
+                layer_width,
+                layer_height,
+                Some(
+                    Rectangle::new(
+                        0.0,
+                        0.0,
+                        layer_width,
+                        layer_height
+                    )
+                )
+            );
+
+            renders.texture_render.render_sized(
+                renders.texture_render.shader(),
+                transform,
+                layer.image.get_texture(),
+                position,
+                layer_width,
+                layer_height,
+                None
+            );
+
+            renders.solid_rectangle_render.render(
+                renders.solid_rectangle_render.shader(),
+                transform,
+                opacity_rectangle,
+                Color4::new(200, 200, 200, 255)
+            );
 
-        layer_offset + TOP_PANEL_HEIGHT as f32
+            renders.solid_rectangle_render.render(
+                renders.solid_rectangle_render.shader(),
+                transform,
+                &Rectangle::from_position_and_size(
+                    opacity_rectangle.position,
+                    Size::new(opacity_rectangle.size.x * layer.opacity, opacity_rectangle.size.y)
+                ),
+                Color4::new(0, 148, 255, 255)
+            );
+
+            renders.texture_render.render(
+                renders.texture_render.shader(),
+                transform,
+                &self.move_up_texture,
+                layout.move_up_rectangle.position
+            );
+
+            renders.texture_render.render(
+                renders.texture_render.shader(),
+                transform,
+                &self.move_down_texture,
+                layout.move_down_rectangle.position
+            );
+        }
     }
-}
\ No newline at end of file
+}
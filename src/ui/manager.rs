@@ -3,36 +3,69 @@ use cgmath::Matrix4;
 use crate::ui::button::{GenericButton};
 use crate::command_buffer::{CommandBuffer, Command};
 use crate::program::Renders;
+use crate::rendering::texture_render::TextureBatch;
 use crate::editor::tools::EditorWindow;
+use crate::editor;
 
 pub type BoxGenericButton = Box<dyn GenericButton<CommandBuffer>>;
 
 pub struct Manager {
-    buttons: Vec<BoxGenericButton>
+    buttons: Vec<BoxGenericButton>,
+    palette_colors: Vec<editor::Color>,
+    palette_buttons: Vec<BoxGenericButton>,
+    recent_color_buttons: Vec<BoxGenericButton>
 }
 
 impl Manager {
-    pub fn new(buttons: Vec<BoxGenericButton>) -> Manager {
+    pub fn new(buttons: Vec<BoxGenericButton>, palette_colors: Vec<editor::Color>) -> Manager {
+        let palette_buttons = super::create_palette_buttons(&palette_colors);
+
         Manager {
-            buttons
+            buttons,
+            palette_colors,
+            palette_buttons,
+            recent_color_buttons: Vec::new()
         }
     }
 
+    pub fn palette_colors(&self) -> &Vec<editor::Color> {
+        &self.palette_colors
+    }
+
+    pub fn set_palette(&mut self, palette_colors: Vec<editor::Color>) {
+        self.palette_buttons = super::create_palette_buttons(&palette_colors);
+        self.palette_colors = palette_colors;
+    }
+
     pub fn process_gui_event(&mut self, window: &mut dyn EditorWindow, event: &glfw::WindowEvent, command_buffer: &mut CommandBuffer) {
-        for button in &mut self.buttons {
+        for button in self.all_buttons_mut() {
             button.process_gui_event(window, event, command_buffer);
         }
     }
 
     pub fn process_command(&mut self, command: &Command) {
-        for button in &mut self.buttons {
+        if let Command::SetRecentColors(colors) = command {
+            self.recent_color_buttons = super::create_recent_color_buttons(colors);
+        }
+
+        for button in self.all_buttons_mut() {
             button.process_command(command);
         }
     }
 
     pub fn render(&self, renders: &Renders, transform: &Matrix4<f32>) {
-        for button in &self.buttons {
-            button.render(renders, transform);
+        // The texture buttons share atlas textures, so they are drawn with one draw call per atlas
+        let mut batch = TextureBatch::new();
+        for button in self.buttons.iter().chain(self.palette_buttons.iter()).chain(self.recent_color_buttons.iter()) {
+            button.render_batched(renders, transform, &mut batch);
         }
+
+        batch.render(renders.texture_render.shader(), &renders.texture_render, transform);
+    }
+
+    fn all_buttons_mut(&mut self) -> impl Iterator<Item=&mut BoxGenericButton> {
+        self.buttons.iter_mut()
+            .chain(self.palette_buttons.iter_mut())
+            .chain(self.recent_color_buttons.iter_mut())
     }
 }
\ No newline at end of file
@@ -5,40 +5,86 @@ pub mod button;
 pub mod layout;
 pub mod color_wheel;
 pub mod layers;
+pub mod theme;
+pub mod palette;
 
 pub use manager::Manager;
 pub use button::TextureButton;
+pub use theme::UiTheme;
 
 use crate::command_buffer::{Command, CommandBuffer};
 use crate::rendering::prelude::{Position, Rectangle, Color4};
+use crate::rendering::texture_atlas::TextureAtlas;
 use crate::editor::tools::{Tools, SelectionSubTool, SelectColorMode};
 use crate::editor::image_operation_helpers::hsv_to_rgb;
-use crate::ui::button::{SolidColorButton};
+use crate::ui::button::{SolidColorButton, TextButton};
 use crate::ui::manager::BoxGenericButton;
-use crate::program::{LEFT_SIDE_PANEL_WIDTH, TOP_PANEL_HEIGHT, ProgramAction, ProgramActionData};
+use crate::program::{ProgramAction, ProgramActionData, Renders};
 use crate::content;
+use crate::editor;
 
-pub fn create() -> Manager {
+const PALETTE_START_X: f32 = 10.0;
+const PALETTE_START_Y: f32 = 425.0;
+const BRUSH_SIZE_START_Y: f32 = 400.0;
+const SELECTED_COLOR_SIZE: f32 = 32.0;
+const COLOR_CELL_SIZE: u32 = 16;
+const COLOR_COLUMNS_WIDTH: f32 = 48.0;
+const RECENT_COLORS_ROWS: f32 = 2.0;
+
+pub fn create(theme: &UiTheme, renders: &Renders) -> Manager {
     let mut buttons = Vec::<BoxGenericButton>::new();
 
-    generate_draw_tools(&mut buttons);
-    generate_color_palette(&mut buttons);
+    generate_draw_tools(&mut buttons, theme);
+    generate_brush_size(&mut buttons, theme, renders);
+    generate_selected_colors(&mut buttons);
 
-    Manager::new(buttons)
+    Manager::new(buttons, default_palette_colors())
 }
 
-fn generate_draw_tools(buttons: &mut Vec<BoxGenericButton>) {
+fn generate_draw_tools(buttons: &mut Vec<BoxGenericButton>, theme: &UiTheme) {
     let mut layout = layout::adaptive_rows(
-        Position::new(10.0, TOP_PANEL_HEIGHT as f32),
+        Position::new(10.0, theme.top_panel_height as f32),
         (35.0, 35.0),
-        LEFT_SIDE_PANEL_WIDTH as f32,
-        13
+        theme.left_side_panel_width as f32,
+        22
     );
 
-    let mut add_tool_button = |tool: Tools, texture_path: &str| {
+    let tools = vec![
+        (Tools::Pencil, "content/ui/pencil.png"),
+        (Tools::BlockPencil, "content/ui/block_pencil.png"),
+        (Tools::Eraser, "content/ui/eraser.png"),
+        (Tools::StampBrush, "content/ui/stamp.png"),
+        (Tools::Spray, "content/ui/spray.png"),
+        (Tools::Line, "content/ui/line.png"),
+        (Tools::Rectangle, "content/ui/rectangle.png"),
+        (Tools::RoundedRectangle, "content/ui/rounded_rectangle.png"),
+        (Tools::Circle, "content/ui/circle.png"),
+        (Tools::Ellipse, "content/ui/ellipse.png"),
+        (Tools::Text, "content/ui/text.png"),
+        (Tools::BucketFill, "content/ui/fill.png"),
+        (Tools::ColorReplace, "content/ui/color_replace.png"),
+        (Tools::ColorPicker, "content/ui/color_picker.png"),
+        (Tools::ColorGradient, "content/ui/color_gradient.png"),
+        (Tools::Measure, "content/ui/measure.png"),
+        (Tools::Selection(SelectionSubTool::Select), "content/ui/selection.png"),
+        (Tools::Selection(SelectionSubTool::SelectByColor), "content/ui/select_by_color.png"),
+        (Tools::Selection(SelectionSubTool::Polygon), "content/ui/lasso.png"),
+        (Tools::Selection(SelectionSubTool::MovePixels), "content/ui/move.png"),
+        (Tools::Selection(SelectionSubTool::ResizePixels), "content/ui/resize.png"),
+        (Tools::Selection(SelectionSubTool::RotatePixels), "content/ui/rotate.png"),
+    ];
+
+    let images = tools
+        .iter()
+        .map(|(_, texture_path)| image::open(content::get_path(texture_path)).unwrap().into_rgba())
+        .collect::<Vec<_>>();
+    let atlas = TextureAtlas::new(&images);
+
+    for (index, &(tool, _)) in tools.iter().enumerate() {
         buttons.push(
-            Box::new(TextureButton::<CommandBuffer>::new(
-                &image::open(texture_path).unwrap().into_rgba(),
+            Box::new(TextureButton::<CommandBuffer>::from_atlas(
+                &atlas,
+                index,
                 layout.next().unwrap(),
                 Some(Box::new(move |command_buffer| {
                     command_buffer.push(Command::SetTool(tool));
@@ -63,24 +109,31 @@ fn generate_draw_tools(buttons: &mut Vec<BoxGenericButton>) {
                 )
             ))
         );
-    };
-
-    add_tool_button(Tools::Pencil, &content::get_path("content/ui/pencil.png"));
-    add_tool_button(Tools::BlockPencil, &content::get_path("content/ui/block_pencil.png"));
-    add_tool_button(Tools::Eraser, &content::get_path("content/ui/eraser.png"));
-    add_tool_button(Tools::Line, &content::get_path("content/ui/line.png"));
-    add_tool_button(Tools::Rectangle, &content::get_path("content/ui/rectangle.png"));
-    add_tool_button(Tools::Circle, &content::get_path("content/ui/circle.png"));
-    add_tool_button(Tools::BucketFill, &content::get_path("content/ui/fill.png"));
-    add_tool_button(Tools::ColorPicker, &content::get_path("content/ui/color_picker.png"));
-    add_tool_button(Tools::ColorGradient, &content::get_path("content/ui/color_gradient.png"));
-    add_tool_button(Tools::Selection(SelectionSubTool::Select), &content::get_path("content/ui/selection.png"));
-    add_tool_button(Tools::Selection(SelectionSubTool::MovePixels), &content::get_path("content/ui/move.png"));
-    add_tool_button(Tools::Selection(SelectionSubTool::ResizePixels), &content::get_path("content/ui/resize.png"));
-    add_tool_button(Tools::Selection(SelectionSubTool::RotatePixels), &content::get_path("content/ui/rotate.png"));
+    }
+}
+
+fn generate_brush_size(buttons: &mut Vec<BoxGenericButton>, theme: &UiTheme, renders: &Renders) {
+    let mut brush_size_button = TextButton::<CommandBuffer>::new(
+        renders.ui_font_small.clone(),
+        "".to_owned(),
+        Position::new(10.0, BRUSH_SIZE_START_Y),
+        Some(Box::new(|command_buffer| {
+            command_buffer.push(Command::ChangeBrushSize(1));
+        })),
+        Some(Box::new(|command_buffer| {
+            command_buffer.push(Command::ChangeBrushSize(-1));
+        })),
+        Some(Box::new(|button, command| {
+            if let Command::SetBrushSize(size) = command {
+                button.change_text(format!("Size: {}", size * 2 + 1));
+            }
+        }))
+    );
+    brush_size_button.set_text_color(theme.text_color);
+    buttons.push(Box::new(brush_size_button));
 }
 
-fn generate_color_palette(buttons: &mut Vec<BoxGenericButton>) {
+pub fn default_palette_colors() -> Vec<editor::Color> {
     let mut colors = Vec::new();
     colors.push(image::Rgba([255, 255, 255, 255]));
     colors.push(image::Rgba([0, 0, 0, 255]));
@@ -99,10 +152,14 @@ fn generate_color_palette(buttons: &mut Vec<BoxGenericButton>) {
         }
     }
 
-    let start_x = 10.0;
-    let start_y = 300.0;
-    let selected_color_width = 32.0;
-    let selected_color_height = 32.0;
+    colors
+}
+
+fn generate_selected_colors(buttons: &mut Vec<BoxGenericButton>) {
+    let start_x = PALETTE_START_X;
+    let start_y = PALETTE_START_Y;
+    let selected_color_width = SELECTED_COLOR_SIZE;
+    let selected_color_height = SELECTED_COLOR_SIZE;
 
     buttons.push(
         Box::new(SolidColorButton::<CommandBuffer>::new(
@@ -148,30 +205,53 @@ fn generate_color_palette(buttons: &mut Vec<BoxGenericButton>) {
             }))
         ))
     );
+}
 
-    let cell_size = (16, 16);
+pub fn create_recent_color_buttons(colors: &[editor::Color]) -> Vec<BoxGenericButton> {
+    create_color_buttons(
+        colors,
+        Position::new(PALETTE_START_X, PALETTE_START_Y + SELECTED_COLOR_SIZE * 1.5 + 5.0)
+    )
+}
+
+pub fn create_palette_buttons(colors: &[editor::Color]) -> Vec<BoxGenericButton> {
+    create_color_buttons(
+        colors,
+        Position::new(
+            PALETTE_START_X,
+            PALETTE_START_Y + SELECTED_COLOR_SIZE * 1.5 + 5.0 + COLOR_CELL_SIZE as f32 * RECENT_COLORS_ROWS + 5.0
+        )
+    )
+}
+
+fn create_color_buttons(colors: &[editor::Color], origin: Position) -> Vec<BoxGenericButton> {
+    let mut buttons = Vec::<BoxGenericButton>::new();
+    let cell_size = (COLOR_CELL_SIZE, COLOR_CELL_SIZE);
+
+    let images = colors
+        .iter()
+        .map(|color| image::RgbaImage::from_pixel(cell_size.0, cell_size.1, *color))
+        .collect::<Vec<_>>();
+    let atlas = TextureAtlas::new(&images);
 
     let layout = layout::adaptive_rows(
-        Position::new(start_x, start_y + selected_color_height * 1.5 + 5.0),
+        origin,
         (cell_size.0 as f32, cell_size.1 as f32),
-        48.0,
+        COLOR_COLUMNS_WIDTH,
         colors.len()
     );
 
-    for (color, position) in colors.iter().zip_eq(layout) {
-        let mut image = image::RgbaImage::new(cell_size.0, cell_size.1);
+    for (index, (color, position)) in colors.iter().zip_eq(layout).enumerate() {
         let color = *color;
 
-        for pixel in image.pixels_mut() {
-            *pixel = color;
-        }
-
         buttons.push(
-            Box::new(TextureButton::<CommandBuffer>::new(
-                &image,
+            Box::new(TextureButton::<CommandBuffer>::from_atlas(
+                &atlas,
+                index,
                 position,
                 Some(Box::new(move |command_buffer| {
                     command_buffer.push(Command::SetPrimaryColor(color));
+                    command_buffer.push(Command::CommitPrimaryColor);
                 })),
                 Some(Box::new(move |command_buffer| {
                     command_buffer.push(Command::SetSecondaryColor(color));
@@ -180,4 +260,6 @@ fn generate_color_palette(buttons: &mut Vec<BoxGenericButton>) {
             ))
         );
     }
-}
\ No newline at end of file
+
+    buttons
+}
@@ -0,0 +1,56 @@
+use std::path::Path;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::fs::File;
+
+use crate::editor;
+
+pub fn load_palette(path: &Path) -> std::io::Result<Vec<editor::Color>> {
+    let reader = BufReader::new(File::open(path)?);
+
+    let mut colors = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        // Skip the header, attributes such as "Name:" and "Columns:", and comments
+        if line.is_empty() || line.starts_with('#') || line.starts_with("GIMP Palette") || line.contains(':') {
+            continue;
+        }
+
+        let channels = line
+            .split_whitespace()
+            .take(3)
+            .map(|channel| channel.parse::<u8>())
+            .collect::<Result<Vec<_>, _>>();
+
+        match channels {
+            Ok(channels) if channels.len() == 3 => {
+                colors.push(image::Rgba([channels[0], channels[1], channels[2], 255]));
+            }
+            _ => {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Invalid palette line: {}", line)));
+            }
+        }
+    }
+
+    Ok(colors)
+}
+
+pub fn save_palette(path: &Path, colors: &[editor::Color]) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writeln!(writer, "GIMP Palette")?;
+    writeln!(writer, "Name: {}", path.file_stem().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default())?;
+    writeln!(writer, "#")?;
+
+    for color in colors {
+        writeln!(
+            writer,
+            "{:3} {:3} {:3}\t#{:02x}{:02x}{:02x}",
+            color[0], color[1], color[2],
+            color[0], color[1], color[2]
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use crate::rendering::prelude::{Color, Color4};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiTheme {
+    pub left_side_panel_width: u32,
+    pub right_side_panel_width: u32,
+    pub top_panel_height: u32,
+    pub menu_color: Color4,
+    pub background_color: Color4,
+    pub text_color: Color
+}
+
+impl UiTheme {
+    pub fn light() -> UiTheme {
+        UiTheme {
+            left_side_panel_width: 70,
+            right_side_panel_width: 150,
+            top_panel_height: 40,
+            menu_color: Color4::new(255, 255, 255, 255),
+            background_color: Color4::new(214, 214, 214, 255),
+            text_color: Color::new(0, 0, 0)
+        }
+    }
+
+    pub fn dark() -> UiTheme {
+        UiTheme {
+            menu_color: Color4::new(128, 128, 128, 255),
+            background_color: Color4::new(64, 64, 64, 255),
+            text_color: Color::new(240, 240, 240),
+            ..UiTheme::light()
+        }
+    }
+
+    pub fn side_panels_width(&self) -> u32 {
+        self.left_side_panel_width + self.right_side_panel_width
+    }
+
+    pub fn clear_color(&self) -> (f32, f32, f32, f32) {
+        (
+            self.background_color.x as f32 / 255.0,
+            self.background_color.y as f32 / 255.0,
+            self.background_color.z as f32 / 255.0,
+            self.background_color.w as f32 / 255.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_color() {
+        assert_eq!((0.0, 0.0, 0.0, 1.0), UiTheme { background_color: Color4::new(0, 0, 0, 255), ..UiTheme::light() }.clear_color());
+        assert_eq!((1.0, 1.0, 1.0, 1.0), UiTheme { background_color: Color4::new(255, 255, 255, 255), ..UiTheme::light() }.clear_color());
+    }
+
+    #[test]
+    fn test_dark_keeps_layout() {
+        let light = UiTheme::light();
+        let dark = UiTheme::dark();
+        assert_eq!(light.side_panels_width(), dark.side_panels_width());
+        assert_eq!(light.top_panel_height, dark.top_panel_height);
+        assert_ne!(light.text_color, dark.text_color);
+    }
+}